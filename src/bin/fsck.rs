@@ -0,0 +1,70 @@
+//! 离线检查磁盘镜像
+//!
+//! ```text
+//! fsck [-y] [image]
+//! ```
+//!
+//! 不带 `-y` 时以只读方式打开镜像，只报告问题；带 `-y` 时修复问题。
+//! 退出码与 e2fsck 相同：0 表示没有问题，1 表示问题已经修复，4 表示还有没修复的问题，8 表示检查失败
+
+use simulate_unixlike_fs::fs::*;
+use std::process::exit;
+
+fn main() {
+    let mut repair = false;
+    let mut path = DISK_PATH.to_string();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "-y" => repair = true,
+            arg if arg.starts_with('-') => {
+                eprintln!("usage: fsck [-y] [image]");
+                exit(8);
+            }
+            arg => path = arg.into(),
+        }
+    }
+
+    let options = MountOptions {
+        force: true,
+        ..Default::default()
+    };
+    let fs = FileDevice::open(&path).and_then(|device| match repair {
+        true => Fs::mount(device, &options),
+        false => Fs::mount(ReadOnlyDevice::new(device), &options),
+    });
+    let mut fs = match fs {
+        Ok(fs) => fs,
+        Err(e) => {
+            eprintln!("fsck: {}: {}", path, e);
+            exit(8);
+        }
+    };
+
+    let report = match fs.fsck(repair) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("fsck: {}", e);
+            exit(8);
+        }
+    };
+    if let Err(e) = fs.exit() {
+        eprintln!("fsck: {}", e);
+        exit(8);
+    }
+
+    for problem in &report.problems {
+        println!("{}", problem);
+    }
+    let code = match (report.is_clean(), report.repaired) {
+        (true, _) => 0,
+        (false, true) if report.problems.iter().all(FsckProblem::fixable) => 1,
+        _ => 4,
+    };
+    println!(
+        "{}: {} problems found{}",
+        path,
+        report.problems.len(),
+        if report.repaired { ", repaired" } else { "" }
+    );
+    exit(code);
+}
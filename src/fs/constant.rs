@@ -1,61 +1,185 @@
 //! 用来定义文件系统的常量
+//!
+//! 磁盘块大小、索引节点个数等几何参数在格式化时由 [`FormatOptions`](crate::fs::FormatOptions)
+//! 决定，并记录在超级块里，这里只保留与几何参数无关的常量。
 
 use std::mem::size_of;
 
-use super::{Inode, DirEntry};
+use super::Inode;
 
-/// 每个磁盘块的大小：512 字节
-pub const BLOCK_SIZE: usize = 512;
+/// 超级块中的魔数，用来识别磁盘上是否是本文件系统
+pub const FS_MAGIC: u16 = 0xEF53;
 
-/// 数据块的块数为 BLOCK_SIZE * 8
-pub const DATA_BLOCKS: usize = BLOCK_SIZE * 8;
+/// 当前实现的磁盘格式版本
+///
+/// - 版本 1：块号和索引节点号为 16 位
+/// - 版本 2：块号和索引节点号扩展为 32 位
+/// - 版本 3：索引节点增加三级间接索引
+/// - 版本 4：索引节点增加 `i_flags`，支持区段树
+/// - 版本 5：保留 0 号数据块，块指针为 0 表示空洞
+/// - 版本 6：超级块增加日志区
+/// - 版本 7：目录项改为变长记录，文件名最长 255 字节
+/// - 版本 8：时间戳改为 64 位秒加纳秒，索引节点增加访问时间和创建时间，扩展到 128 字节
+/// - 版本 9：权限扩展为拥有者、用户组、其他用户三组共 9 位，超级块增加用户组表
+/// - 版本 10：用户和用户组移到镜像中的 `/etc/passwd`、`/etc/group` 和 `/etc/shadow`，
+///   文件的拥有者和用户组扩展为 16 位的 UID 和 GID；版本 9 的镜像在挂载时自动升级
+pub const FS_VERSION: u16 = 10;
 
-/// 文件系统支持的最大文件数
-const MAX_FILES: usize = DATA_BLOCKS; // 假设每个数据块可以存储一个文件
+/// 挂载时可以自动升级到 [`FS_VERSION`] 的旧版本
+pub const UPGRADABLE_VERSION: u16 = 9;
 
-/// 每个块可以存储的 Inode 数量
-const INODES_PER_BLOCK: usize = BLOCK_SIZE / size_of::<Inode>();
+/// 当前实现支持的兼容特性：不认识的兼容特性可以直接忽略
+pub const FEATURE_COMPAT_SUPP: u32 = FEATURE_COMPAT_HAS_JOURNAL | FEATURE_COMPAT_DIR_INDEX;
 
-/// i 节点需要的块数
-const INODE_BLOCKS: usize = (MAX_FILES + INODES_PER_BLOCK - 1) / INODES_PER_BLOCK;
+/// 当前实现支持的不兼容特性：出现不认识的不兼容特性时拒绝挂载
+pub const FEATURE_INCOMPAT_SUPP: u32 = FEATURE_INCOMPAT_EXTENTS;
 
-/// 数据块在磁盘上的起始位置。
-/// 前三块被用来存储 位图 和 组描述符 [`GroupDesc`](crate::fs::GroupDesc)，
-/// 之后的 [`INODE_BLOCKS`] 被 i 节点占用
-pub const DATA_BEGIN_BLOCK: usize = (3 + INODE_BLOCKS) as usize;
+/// 当前实现支持的只读兼容特性：出现不认识的只读兼容特性时只能以只读方式挂载
+pub const FEATURE_RO_COMPAT_SUPP: u32 = FEATURE_RO_COMPAT_METADATA_CSUM;
 
-/// 磁盘块的总块数
-pub const BLOCKS: usize = DATA_BEGIN_BLOCK + DATA_BLOCKS;
+/// 兼容特性：磁盘上有日志区
+pub const FEATURE_COMPAT_HAS_JOURNAL: u32 = 0x0004;
 
-/// 目录项的大小
-pub const DIR_ENTRY_SIZE: usize = size_of::<DirEntry>();
+/// 兼容特性：超过一个数据块的目录使用哈希索引，见 [`htree`](super::htree)
+///
+/// 索引藏在目录项的空闲空间里，不认识这个特性的实现仍然可以逐项遍历这些目录
+pub const FEATURE_COMPAT_DIR_INDEX: u32 = 0x0020;
 
-/// 每一个数据块可以存储的目录项个数
-pub const BLOCK_ADDR_NUM: usize = BLOCK_SIZE / DIR_ENTRY_SIZE;
+/// 不兼容特性：新建的文件使用区段树映射数据块
+pub const FEATURE_INCOMPAT_EXTENTS: u32 = 0x0040;
 
-/// 虚拟磁盘的路径名
+/// 只读兼容特性：超级块、组描述符、索引节点和目录项带有 CRC32C 校验和，读取时检查
+///
+/// 没有这个特性的镜像照常挂载，只是不做检查；
+/// 不认识这个特性的实现写入时不会更新校验和，所以只能只读挂载
+pub const FEATURE_RO_COMPAT_METADATA_CSUM: u32 = 0x0400;
+
+/// 索引节点标志：使用区段树而不是多级索引映射数据块
+pub const INODE_FLAG_EXTENTS: u16 = 0x0001;
+
+/// 索引节点标志：目录带有哈希索引
+pub const INODE_FLAG_INDEX: u16 = 0x0002;
+
+/// 文件系统状态：已经正常卸载
+pub const FS_STATE_CLEAN: u16 = 1;
+
+/// 文件系统状态：正在使用，或者上次没有正常卸载
+pub const FS_STATE_DIRTY: u16 = 2;
+
+/// 表示空洞的块指针
+///
+/// 0 号数据块在格式化时就被保留，不会分配给任何文件，
+/// 所以块指针为 0 表示对应的逻辑块还没有分配数据块，读取时得到全 0
+pub const HOLE_BLK: u32 = 0;
+
+/// 权限位：设置用户ID（setuid）
+pub const MODE_SETUID: u16 = 0o4000;
+
+/// 权限位：设置用户组ID（setgid），目录设置之后其中新建的文件继承目录的用户组
+pub const MODE_SETGID: u16 = 0o2000;
+
+/// 权限位：粘滞位，目录设置之后只有文件的拥有者、目录的拥有者和 root 可以删除或重命名其中的文件
+pub const MODE_STICKY: u16 = 0o1000;
+
+/// root 用户的ID
+pub const ROOT_UID: u16 = 0;
+
+/// root 用户组的ID，root 用户的主组
+pub const ROOT_GID: u16 = 0;
+
+/// users 用户组的ID，新用户的主组
+pub const USERS_GID: u16 = 100;
+
+/// 新用户和新用户组的ID从这里开始分配
+pub const FIRST_ID: u16 = 1000;
+
+/// 根目录的第一个数据块
+pub const ROOT_DIR_BLK: u32 = 1;
+
+/// 默认的日志区块数
+pub const DEFAULT_JOURNAL_BLOCKS: usize = 256;
+
+/// 默认的块缓存大小（块数）
+pub const DEFAULT_CACHE_BLOCKS: usize = 1024;
+
+/// 默认的磁盘块大小：512 字节
+pub const DEFAULT_BLOCK_SIZE: usize = 512;
+
+/// 格式化时支持的磁盘块大小
+pub const SUPPORTED_BLOCK_SIZES: [usize; 3] = [512, 1024, 4096];
+
+/// 索引节点的大小
+pub const INODE_SIZE: usize = size_of::<Inode>();
+
+/// 数据块索引表里每个地址占用的字节数
+pub const ADDR_SIZE: usize = 4;
+
+/// 目录项在磁盘上的头部大小：索引节点号、记录长度、文件名长度、文件类型和校验和，之后紧跟文件名
+pub const DIR_ENTRY_HEADER_SIZE: usize = 12;
+
+/// 目录数据块末尾的校验和记录的大小，它和目录项的头部布局相同，见 [`DirEntry`]
+pub const DIR_TAIL_SIZE: usize = DIR_ENTRY_HEADER_SIZE;
+
+/// 目录数据块末尾的校验和记录的文件类型
+pub const DIR_TAIL_TYPE: u8 = 0xDE;
+
+/// 文件名的最大长度
+pub const MAX_NAME_LEN: usize = 255;
+
+/// 虚拟磁盘的默认路径名
 pub const DISK_PATH: &str = "disk.bin";
 
+/// 镜像中保存用户信息的文件，见 [`userdb`](crate::fs::userdb)
+pub const PASSWD_PATH: &str = "/etc/passwd";
+
+/// 镜像中保存用户组信息的文件，见 [`userdb`](crate::fs::userdb)
+pub const GROUP_PATH: &str = "/etc/group";
+
+/// 镜像中保存密码散列和账户状态的文件，见 [`shadow`](crate::fs::shadow)
+pub const SHADOW_PATH: &str = "/etc/shadow";
+
+/// 新用户默认的登录 shell
+pub const DEFAULT_SHELL: &str = "/bin/sh";
+
+/// 默认的骨架目录，存在时 [`Fs::useradd`](crate::fs::Fs::useradd) 把其中的内容复制到新用户的主目录
+pub const SKEL_PATH: &str = "/etc/skel";
+
 /// 整个文件系统可以同时打开的文件个数
 pub const FD_LIMIT: usize = 20;
 
 #[test]
 fn test_sizes() {
-    use super::GroupDesc;
+    use super::{GroupDesc, SuperBlock};
 
     /// 检查一个数是否是以2为底的幂
     fn check_log2(mut num: usize) -> bool {
         while num != 1 {
-            if num % 2 != 0 {
+            if !num.is_multiple_of(2) {
                 return false;
             }
             num /= 2
         }
-        return true;
+        true
     }
 
-    assert!(size_of::<GroupDesc>() <= BLOCK_SIZE);
-    assert!(check_log2(BLOCK_SIZE));
-    assert!(check_log2(size_of::<DirEntry>()));
+    for block_size in SUPPORTED_BLOCK_SIZES {
+        assert!(size_of::<SuperBlock>() <= block_size);
+        assert!(check_log2(block_size));
+        // 组描述符中的空闲块数和空闲索引节点数只有 16 位
+        assert!(block_size * 8 <= u16::MAX as usize);
+    }
+    assert!(check_log2(size_of::<GroupDesc>()));
     assert!(check_log2(size_of::<Inode>()));
-}
\ No newline at end of file
+
+    // 用户表移走之后原来的位置保留不用，魔数和版本号的位置在各个版本之间保持不变
+    assert_eq!(size_of::<SuperBlock>(), 512);
+    assert_eq!(std::mem::offset_of!(SuperBlock, mtime), 336);
+    assert_eq!(std::mem::offset_of!(SuperBlock, magic), 408);
+    assert_eq!(std::mem::offset_of!(SuperBlock, checksum), 504);
+    assert_eq!(size_of::<GroupDesc>(), 32);
+    assert_eq!(std::mem::offset_of!(GroupDesc, block_bitmap_csum), 18);
+    assert_eq!(std::mem::offset_of!(GroupDesc, inode_bitmap_csum), 22);
+    assert_eq!(std::mem::offset_of!(GroupDesc, checksum), 28);
+    assert_eq!(size_of::<Inode>(), 128);
+    assert_eq!(std::mem::offset_of!(Inode, i_checksum), 124);
+}
@@ -0,0 +1,276 @@
+//! # 块缓存模块
+//!
+//! 读取索引节点、扫描位图和遍历目录都要反复读取同样的几个磁盘块。`BlockCache` 包装了底层的块设备，
+//! 在内存中按照 LRU（最近最少使用）的顺序缓存最多 [`MountOptions::cache_blocks`] 个磁盘块：
+//!
+//! - 读取时先查缓存，没有命中才读取整个块，并放入缓存
+//! - 写入只修改缓存中的块并把它标记为脏块，脏块在被换出、[`Fs::flush`]、[`Fs::sync`]
+//!   或者卸载时才写回磁盘；没有卸载就丢弃文件系统时，也会尽量把脏块写回
+//!
+//! 缓存位于日志之下，日志在提交事务时按顺序调用 [`BlockDevice::flush`]，
+//! 这时缓存中所有的脏块都会先写回磁盘，所以日志的顺序保证不受影响。
+
+use super::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// 块缓存的统计信息
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// 缓存最多能容纳的块数，为 0 时不使用缓存
+    pub capacity: usize,
+    /// 当前缓存的块数
+    pub cached: usize,
+    /// 修改过、还没有写回磁盘的块数
+    pub dirty: usize,
+    /// 读取时在缓存中找到的块数
+    pub hits: u64,
+    /// 读取时需要从磁盘读取的块数
+    pub misses: u64,
+}
+
+/// 带缓存的块设备
+pub(in crate::fs) struct BlockCache {
+    /// 底层的块设备
+    inner: Box<dyn BlockDevice>,
+    /// 缓存的状态，`read_at` 和 `write_at` 只拿到 `&self`，所以放在锁里
+    state: Mutex<CacheState>,
+}
+
+/// 缓存中的一个块
+struct CachedBlock {
+    /// 块的内容
+    data: Vec<u8>,
+    /// 是否修改过，还没有写回磁盘
+    dirty: bool,
+    /// 最近一次访问的时刻
+    tick: u64,
+}
+
+/// 缓存的状态
+struct CacheState {
+    /// 磁盘块大小
+    block_size: u64,
+    /// 最多缓存的块数
+    capacity: usize,
+    /// 块号 -> 缓存的块
+    blocks: HashMap<u64, CachedBlock>,
+    /// 访问时刻 -> 块号，第一个就是最久没有访问的块
+    lru: BTreeMap<u64, u64>,
+    /// 逻辑时钟，每次访问加一
+    tick: u64,
+    /// 命中次数
+    hits: u64,
+    /// 未命中次数
+    misses: u64,
+}
+
+impl BlockCache {
+    /// 用最多 `capacity` 个大小为 `block_size` 的块缓存块设备 `inner`
+    pub(in crate::fs) fn new(inner: Box<dyn BlockDevice>, block_size: u64, capacity: usize) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(CacheState {
+                block_size,
+                capacity,
+                blocks: HashMap::new(),
+                lru: BTreeMap::new(),
+                tick: 0,
+                hits: 0,
+                misses: 0,
+            }),
+        }
+    }
+
+    /// 缓存的统计信息
+    pub(in crate::fs) fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap();
+        CacheStats {
+            capacity: state.capacity,
+            cached: state.blocks.len(),
+            dirty: state.blocks.values().filter(|b| b.dirty).count(),
+            hits: state.hits,
+            misses: state.misses,
+        }
+    }
+
+    /// 把所有脏块按块号顺序写回磁盘，但是不要求磁盘立即落盘
+    pub(in crate::fs) fn write_back(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let bs = state.block_size;
+        let mut dirty: Vec<_> = state.blocks.iter_mut().filter(|(_, b)| b.dirty).collect();
+        dirty.sort_by_key(|(blk, _)| **blk);
+        for (blk, block) in dirty {
+            self.inner.write_at(&block.data, blk * bs)?;
+            block.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// 取出块号为 `blk` 的块，没有缓存时从磁盘读取，`whole` 为真时调用者会覆盖整个块，不需要读取
+    fn get<'a>(&self, state: &'a mut CacheState, blk: u64, whole: bool) -> Result<&'a mut CachedBlock> {
+        state.tick += 1;
+        let tick = state.tick;
+        match state.blocks.get_mut(&blk) {
+            Some(block) => {
+                state.lru.remove(&block.tick);
+                block.tick = tick;
+                if !whole {
+                    state.hits += 1;
+                }
+            }
+            None => {
+                let mut data = vec![0u8; state.block_size as usize];
+                if !whole {
+                    state.misses += 1;
+                    self.inner.read_at(&mut data, blk * state.block_size)?;
+                }
+                state.blocks.insert(blk, CachedBlock { data, dirty: false, tick });
+            }
+        }
+        state.lru.insert(tick, blk);
+        Ok(state.blocks.get_mut(&blk).unwrap())
+    }
+
+    /// 缓存的块数超过上限时换出最久没有访问的块，脏块先写回磁盘
+    fn evict(&self, state: &mut CacheState) -> Result<()> {
+        while state.blocks.len() > state.capacity {
+            let (_, blk) = state.lru.pop_first().unwrap();
+            let block = state.blocks.remove(&blk).unwrap();
+            if block.dirty {
+                self.inner.write_at(&block.data, blk * state.block_size)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for BlockCache {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.capacity == 0 {
+            return self.inner.read_at(buf, offset);
+        }
+
+        for (blk, range, buf_range) in split_blocks(state.block_size, offset, buf.len()) {
+            let block = self.get(&mut state, blk, false)?;
+            buf[buf_range].copy_from_slice(&block.data[range]);
+        }
+        self.evict(&mut state)?;
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.capacity == 0 || self.inner.is_read_only() {
+            return self.inner.write_at(buf, offset);
+        }
+
+        let bs = state.block_size;
+        for (blk, range, buf_range) in split_blocks(bs, offset, buf.len()) {
+            let whole = range.len() as u64 == bs;
+            let block = self.get(&mut state, blk, whole)?;
+            block.data[range].copy_from_slice(&buf[buf_range]);
+            block.dirty = true;
+        }
+        self.evict(&mut state)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.write_back()?;
+        self.inner.flush()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+}
+
+impl Drop for BlockCache {
+    /// 尽量把脏块写回磁盘，这时已经没有办法报告错误，出错时忽略
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl Fs {
+    /// 把缓存中修改过的块写回磁盘
+    ///
+    /// 写回之后宿主机可能还没有真正把数据落盘，需要保证持久化时使用 [`Fs::sync`]
+    pub fn flush(&mut self) -> Result<()> {
+        self.disk.write_back()
+    }
+
+    /// 把缓存中修改过的块写回磁盘，并等待磁盘把它们真正落盘
+    pub fn sync(&mut self) -> Result<()> {
+        self.disk.flush()
+    }
+
+    /// 块缓存的统计信息
+    pub fn cache_stats(&self) -> CacheStats {
+        self.disk.cache_stats()
+    }
+}
+
+#[test]
+fn test_cache() {
+    let disk = MemDevice::new();
+    let mut fs = Fs::format_on(disk.clone(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.create("a").unwrap();
+
+    // 第二次读取同一个索引节点时命中缓存
+    let before = fs.cache_stats();
+    fs.get_inode(0).unwrap();
+    fs.get_inode(0).unwrap();
+    let after = fs.cache_stats();
+    assert_eq!(after.misses, before.misses);
+    assert!(after.hits >= before.hits + 2);
+    assert!(after.cached <= after.capacity);
+
+    // 不在事务中的写入留在缓存里，写回之后才出现在磁盘上
+    let addr = fs.addr_data_blk(fs.super_block.blocks_count - 1);
+    fs.disk.write_at(b"cached", addr).unwrap();
+    let mut buf = [0u8; 6];
+    disk.read_at(&mut buf, addr).unwrap();
+    assert_eq!(&buf, &[0; 6]);
+    assert!(fs.cache_stats().dirty > 0);
+    fs.flush().unwrap();
+    assert_eq!(fs.cache_stats().dirty, 0);
+    disk.read_at(&mut buf, addr).unwrap();
+    assert_eq!(&buf, b"cached");
+
+    // 打开同一个文件的两个文件描述符看到的文件大小一致
+    let fd1 = fs.open_with("a", OpenOptions::new().read(true).write(true)).unwrap();
+    let fd2 = fs.open_with("a", OpenOptions::new().read(true).write(true)).unwrap();
+    fs.write(fd1, b"hello").unwrap();
+    let mut buf = [0u8; 16];
+    assert_eq!(fs.read(fd2, &mut buf).unwrap(), 5);
+    assert_eq!(&buf[..5], b"hello");
+    fs.close(fd1).unwrap();
+    fs.close(fd2).unwrap();
+
+    // 缓存很小时频繁换出，内容仍然正确；卸载时写回所有脏块
+    fs.exit().unwrap();
+    let options = MountOptions {
+        cache_blocks: 4,
+        ..Default::default()
+    };
+    let mut fs = Fs::mount(disk.clone(), &options).unwrap();
+    fs.mkdir("dir").unwrap();
+    for i in 0..50 {
+        fs.create(&format!("dir/{}", i)).unwrap();
+    }
+    assert!(fs.cache_stats().cached <= 4);
+    fs.exit().unwrap();
+    let mut fs = Fs::init_on(disk.clone()).unwrap();
+    assert!(fs.open("dir/49").is_ok());
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 没有卸载就丢弃文件系统时，缓存中的脏块也会写回
+    fs.disk.write_at(b"dropped", addr).unwrap();
+    drop(fs);
+    let mut buf = [0u8; 7];
+    disk.read_at(&mut buf, addr).unwrap();
+    assert_eq!(&buf, b"dropped");
+}
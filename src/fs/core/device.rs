@@ -0,0 +1,239 @@
+//! # 块设备模块
+//!
+//! 文件系统不再直接操作宿主机上的 `std::fs::File`，而是通过 [`BlockDevice`] 这个 trait
+//! 访问底层存储。这样同一套文件系统代码就可以运行在不同的“磁盘”上：
+//!
+//! - [`FileDevice`]：宿主机上的镜像文件（默认的 `disk.bin`）
+//! - [`MemDevice`]：纯内存磁盘，适合单元测试，多个测试之间互不干扰
+//! - [`ReadOnlyDevice`]：只读包装器，任何写操作都会返回 `PermissionDenied`
+
+use super::*;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// 块设备特征
+///
+/// 提供按字节偏移定位读写的能力，[`Fs`] 通过它访问磁盘上的所有数据结构
+pub trait BlockDevice: Send {
+    /// 在指定位置读取数据
+    ///
+    /// # 参数
+    /// - `buf`: 读取数据的缓冲区
+    /// - `offset`: 读取位置的字节偏移量
+    ///
+    /// # 返回值
+    /// 成功时返回实际读取的字节数，失败时返回IO错误
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize>;
+
+    /// 在指定位置写入数据
+    ///
+    /// # 参数
+    /// - `buf`: 要写入的数据
+    /// - `offset`: 写入位置的字节偏移量
+    ///
+    /// # 返回值
+    /// 成功时返回实际写入的字节数，失败时返回IO错误
+    fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize>;
+
+    /// 将缓冲区中的数据刷新到存储介质上
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// 设备是否只读
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// 从设备上读取元数据时是否检查校验和，见 [`FromDisk`]
+    ///
+    /// 只有知道文件系统特性的日志层会返回真，普通的块设备不做检查
+    fn verify_checksums(&self) -> bool {
+        false
+    }
+}
+
+impl<D: BlockDevice + ?Sized> BlockDevice for Box<D> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+        (**self).write_at(buf, offset)
+    }
+
+    fn flush(&self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn is_read_only(&self) -> bool {
+        (**self).is_read_only()
+    }
+
+    fn verify_checksums(&self) -> bool {
+        (**self).verify_checksums()
+    }
+}
+
+/// 把从 `offset` 开始的 `len` 个字节按磁盘块拆开
+///
+/// 返回每一段所在的块号、在块内的范围和在缓冲区中的范围
+pub(in crate::fs) fn split_blocks(
+    bs: u64,
+    offset: u64,
+    len: usize,
+) -> impl Iterator<Item = (u64, std::ops::Range<usize>, std::ops::Range<usize>)> {
+    let end = offset + len as u64;
+    (offset / bs..end.div_ceil(bs)).map(move |blk| {
+        let from = offset.max(blk * bs);
+        let to = end.min((blk + 1) * bs);
+        (
+            blk,
+            (from - blk * bs) as usize..(to - blk * bs) as usize,
+            (from - offset) as usize..(to - offset) as usize,
+        )
+    })
+}
+
+/// 宿主机文件磁盘
+///
+/// 将宿主机上的一个普通文件当作磁盘使用
+pub struct FileDevice {
+    file: std::fs::File,
+}
+
+impl FileDevice {
+    /// 以读写方式打开已经存在的镜像文件
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::options().read(true).write(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    /// 创建新的镜像文件，如果文件已经存在则清空它
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self { file })
+    }
+}
+
+impl BlockDevice for FileDevice {
+    #[inline(always)]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        FsFileExt::read_at(&self.file, buf, offset)
+    }
+
+    #[inline(always)]
+    fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+        FsFileExt::write_at(&self.file, buf, offset)
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.file.sync_all()
+    }
+}
+
+/// 内存磁盘
+///
+/// 所有数据保存在内存里，写入超出当前大小的位置时自动扩容。
+/// 克隆出来的 `MemDevice` 与原设备共享同一块内存，因此可以在格式化之后
+/// 用克隆的设备重新挂载文件系统
+#[derive(Clone, Default)]
+pub struct MemDevice {
+    data: Arc<Mutex<Vec<u8>>>,
+}
+
+impl MemDevice {
+    /// 创建一个空的内存磁盘
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 当前磁盘的字节数
+    pub fn len(&self) -> usize {
+        self.data.lock().unwrap().len()
+    }
+
+    /// 磁盘是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl BlockDevice for MemDevice {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        let data = self.data.lock().unwrap();
+        let offset = offset as usize;
+        if offset >= data.len() {
+            return Ok(0);
+        }
+        let len = buf.len().min(data.len() - offset);
+        buf[..len].copy_from_slice(&data[offset..offset + len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+        let mut data = self.data.lock().unwrap();
+        let offset = offset as usize;
+        if data.len() < offset + buf.len() {
+            data.resize(offset + buf.len(), 0);
+        }
+        data[offset..offset + buf.len()].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+}
+
+/// 只读磁盘
+///
+/// 包装另一个块设备，读操作直接转发，写操作一律拒绝
+pub struct ReadOnlyDevice<D: BlockDevice> {
+    inner: D,
+}
+
+impl<D: BlockDevice> ReadOnlyDevice<D> {
+    /// 将 `inner` 包装为只读设备
+    pub fn new(inner: D) -> Self {
+        Self { inner }
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for ReadOnlyDevice<D> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        self.inner.read_at(buf, offset)
+    }
+
+    fn write_at(&self, _buf: &[u8], _offset: u64) -> Result<usize> {
+        Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "Read-only device",
+        ))
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn verify_checksums(&self) -> bool {
+        self.inner.verify_checksums()
+    }
+}
+
+#[test]
+fn test_mem_device() {
+    let dev = MemDevice::new();
+    dev.write_at(b"hello", 510).unwrap();
+    assert_eq!(dev.len(), 515);
+
+    // 克隆出来的设备共享同一块内存
+    let mut buf = [0u8; 5];
+    dev.clone().read_at(&mut buf, 510).unwrap();
+    assert_eq!(&buf, b"hello");
+
+    let ro = ReadOnlyDevice::new(dev.clone());
+    assert!(ro.write_at(b"x", 0).is_err());
+    assert_eq!(ro.read_at(&mut buf, 512).unwrap(), 3);
+}
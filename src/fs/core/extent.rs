@@ -0,0 +1,454 @@
+//! # 区段（extent）映射模块
+//!
+//! 传统的索引节点为文件的每一个数据块都保存一个地址，大文件需要大量的索引块。
+//! 设置了 [`INODE_FLAG_EXTENTS`] 标志的索引节点改用区段树描述数据块：
+//! 每个区段记录“从逻辑块号 `logical` 开始的 `len` 个块，依次存放在从 `start`
+//! 开始的数据块里”，物理上连续的数据块只需要一个区段。
+//!
+//! ## 区段树的结构
+//!
+//! - 根结点存放在索引节点的 `i_block` 里：`i_block[0]` 的低 16 位是区段个数，高 16 位是树的深度，
+//!   之后每 3 个 `u32` 组成一个区段，最多 `ROOT_EXTENTS` 个
+//! - 其它结点各占一个数据块：块头依次是区段个数、深度和魔数 `EXTENT_MAGIC`，
+//!   之后是若干个 12 字节的区段
+//! - 深度为 0 的结点是叶子，区段指向数据块；深度大于 0 的结点是索引结点，
+//!   区段的 `start` 指向下一层的结点，`len` 不使用
+
+use super::*;
+
+/// 根结点里最多能存放的区段个数
+pub(in crate::fs) const ROOT_EXTENTS: usize = 2;
+
+/// 区段树结点块的魔数
+pub(in crate::fs) const EXTENT_MAGIC: u32 = 0xF30A;
+
+/// 每个区段在磁盘上占用的字节数
+const EXTENT_SIZE: usize = 12;
+
+/// 结点块的块头大小
+const NODE_HEADER_SIZE: usize = 12;
+
+/// 区段
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(in crate::fs) struct Extent {
+    /// 起始逻辑块号
+    pub logical: u32,
+    /// 区段包含的块数，索引结点中不使用
+    pub len: u32,
+    /// 起始数据块号，索引结点中为下一层结点所在的块号
+    pub start: u32,
+}
+
+/// 区段树的一个结点
+#[derive(Clone, Debug, Default)]
+pub(in crate::fs) struct ExtentNode {
+    /// 结点的深度，叶子结点为 0
+    pub depth: u32,
+    /// 结点中的区段，按逻辑块号升序排列
+    pub entries: Vec<Extent>,
+}
+
+impl ExtentNode {
+    /// 在结点中查找包含逻辑块号 `blk` 的区段（叶子结点），或者应该继续查找的子结点（索引结点）
+    fn find(&self, blk: u32) -> Option<&Extent> {
+        self.entries.iter().rev().find(|e| e.logical <= blk)
+    }
+}
+
+impl Fs {
+    /// 一个结点块最多能存放的区段个数
+    fn extents_per_blk(&self) -> usize {
+        (self.block_size() - NODE_HEADER_SIZE) / EXTENT_SIZE
+    }
+
+    /// 从块号为 `blk` 的数据块里读取区段树结点
+    pub(in crate::fs) fn read_extent_node(&self, blk: u32) -> Result<ExtentNode> {
+        let mut buf = self.empty_blk();
+        self.disk.read_at(&mut buf, self.addr_data_blk(blk))?;
+
+        let word = |i: usize| u32::from_le_bytes(buf[i * 4..i * 4 + 4].try_into().unwrap());
+        let count = word(0) as usize;
+        if word(2) != EXTENT_MAGIC || count > self.extents_per_blk() {
+            return Err(Error::new(ErrorKind::InvalidData, "Bad extent node"));
+        }
+
+        let entries = (0..count)
+            .map(|i| {
+                let base = (NODE_HEADER_SIZE + i * EXTENT_SIZE) / 4;
+                Extent {
+                    logical: word(base),
+                    len: word(base + 1),
+                    start: word(base + 2),
+                }
+            })
+            .collect();
+        Ok(ExtentNode {
+            depth: word(1),
+            entries,
+        })
+    }
+
+    /// 将区段树结点写入块号为 `blk` 的数据块
+    pub(in crate::fs) fn write_extent_node(&mut self, blk: u32, node: &ExtentNode) -> Result<()> {
+        let mut buf = self.empty_blk();
+        let words = [node.entries.len() as u32, node.depth, EXTENT_MAGIC]
+            .into_iter()
+            .chain(node.entries.iter().flat_map(|e| [e.logical, e.len, e.start]));
+        for (i, word) in words.enumerate() {
+            buf[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        self.disk.write_at(&buf, self.addr_data_blk(blk))?;
+        Ok(())
+    }
+}
+
+impl Inode {
+    /// 索引节点是否使用区段树
+    #[inline(always)]
+    pub fn uses_extents(&self) -> bool {
+        self.i_flags & INODE_FLAG_EXTENTS != 0
+    }
+
+    /// 读取存放在 `i_block` 里的根结点
+    pub(in crate::fs) fn extent_root(&self) -> ExtentNode {
+        let count = (self.i_block[0] & 0xffff) as usize;
+        ExtentNode {
+            depth: self.i_block[0] >> 16,
+            entries: (0..count.min(ROOT_EXTENTS))
+                .map(|i| Extent {
+                    logical: self.i_block[1 + i * 3],
+                    len: self.i_block[2 + i * 3],
+                    start: self.i_block[3 + i * 3],
+                })
+                .collect(),
+        }
+    }
+
+    /// 将根结点写回 `i_block`
+    fn set_extent_root(&mut self, root: &ExtentNode) {
+        self.i_block = Default::default();
+        self.i_block[0] = root.entries.len() as u32 | root.depth << 16;
+        for (i, e) in root.entries.iter().enumerate() {
+            self.i_block[1 + i * 3] = e.logical;
+            self.i_block[2 + i * 3] = e.len;
+            self.i_block[3 + i * 3] = e.start;
+        }
+    }
+
+    /// 在区段树中查找逻辑块号 `blk` 对应的数据块号
+    ///
+    /// # 返回值
+    /// 找到时返回数据块号，逻辑块没有映射到数据块时返回 `None`
+    pub(in crate::fs) fn extent_lookup(&self, fs: &Fs, blk: u32) -> Result<Option<u32>> {
+        let mut node = self.extent_root();
+        loop {
+            let Some(&extent) = node.find(blk) else {
+                return Ok(None);
+            };
+            if node.depth == 0 {
+                return Ok((blk < extent.logical + extent.len).then(|| extent.start + blk - extent.logical));
+            }
+            node = fs.read_extent_node(extent.start)?;
+        }
+    }
+
+    /// 遍历区段树，返回所有叶子区段以及所有结点块的块号
+    fn extent_collect(&self, fs: &Fs) -> Result<(Vec<Extent>, Vec<u32>)> {
+        let mut extents = Vec::new();
+        let mut node_blks = Vec::new();
+        let mut stack = vec![self.extent_root()];
+        while let Some(node) = stack.pop() {
+            if node.depth == 0 {
+                extents.extend(node.entries);
+                continue;
+            }
+            // 倒序压栈，保证叶子区段按逻辑块号升序输出
+            for e in node.entries.iter().rev() {
+                node_blks.push(e.start);
+                stack.push(fs.read_extent_node(e.start)?);
+            }
+        }
+        Ok((extents, node_blks))
+    }
+
+    /// 将新的区段插入到区段树的最右侧
+    ///
+    /// # 参数
+    /// - `fs`: 文件系统的可变引用
+    /// - `extent`: 要插入的区段，逻辑块号必须大于树中已有的所有区段
+    ///
+    /// # 算法
+    /// 1. 沿着最右侧的路径找到叶子结点
+    /// 2. 叶子结点还有空位时直接插入
+    /// 3. 否则新建一个兄弟结点存放新区段，再把兄弟结点插入上一层，依此类推
+    /// 4. 根结点也满时，把根结点的内容搬到新的结点块里，树的深度加一
+    fn extent_insert(&mut self, fs: &mut Fs, extent: Extent) -> Result<()> {
+        // 最右侧路径上的结点，第 0 个是根结点
+        let mut path: Vec<(u32, ExtentNode)> = vec![(0, self.extent_root())];
+        while path.last().unwrap().1.depth > 0 {
+            let child = path.last().unwrap().1.entries.last().unwrap().start;
+            path.push((child, fs.read_extent_node(child)?));
+        }
+
+        let mut entry = extent;
+        for level in (0..path.len()).rev() {
+            let capacity = match level {
+                0 => ROOT_EXTENTS,
+                _ => fs.extents_per_blk(),
+            };
+            let (blk, node) = &mut path[level];
+
+            if node.entries.len() < capacity {
+                node.entries.push(entry);
+                match level {
+                    0 => self.set_extent_root(node),
+                    _ => fs.write_extent_node(*blk, node)?,
+                }
+                return Ok(());
+            }
+
+            let new_blk = fs.alloc_near(entry.start)?;
+            if level == 0 {
+                // 根结点已满，整棵树长高一层
+                let mut child = std::mem::take(node);
+                child.entries.push(entry);
+                fs.write_extent_node(new_blk, &child)?;
+
+                let root = ExtentNode {
+                    depth: child.depth + 1,
+                    entries: vec![Extent {
+                        logical: child.entries[0].logical,
+                        len: 0,
+                        start: new_blk,
+                    }],
+                };
+                self.set_extent_root(&root);
+                return Ok(());
+            }
+
+            // 当前结点已满，新建兄弟结点，并在上一层插入指向它的区段
+            let sibling = ExtentNode {
+                depth: node.depth,
+                entries: vec![entry],
+            };
+            fs.write_extent_node(new_blk, &sibling)?;
+            entry = Extent {
+                logical: entry.logical,
+                len: 0,
+                start: new_blk,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// 为使用区段树的索引节点的逻辑块 `logical` 分配数据块
+    ///
+    /// 逻辑块已经有数据块时直接返回它。新的数据块会清零，并尽量紧跟在前一个区段之后分配：
+    /// 在文件末尾追加时，最后一个区段通常只需要长度加一；
+    /// 写入区段之间的空洞时，新区段插入到中间，整棵树重建
+    pub(in crate::fs) fn extent_alloc(&mut self, fs: &mut Fs, inode_i: u32, logical: u32) -> Result<u32> {
+        if let Some(blk) = self.extent_lookup(fs, logical)? {
+            return Ok(blk);
+        }
+
+        // 找到最右侧的叶子区段
+        let mut node = self.extent_root();
+        let mut leaf_blk = 0;
+        while node.depth > 0 {
+            leaf_blk = node.entries.last().unwrap().start;
+            node = fs.read_extent_node(leaf_blk)?;
+        }
+
+        if matches!(node.entries.last(), Some(last) if last.logical > logical) {
+            // 写入的是区段之间的空洞
+            let (mut extents, node_blks) = self.extent_collect(fs)?;
+            let i = extents.partition_point(|e| e.logical < logical);
+            let blk = Self::alloc_extent_blk(fs, inode_i, i.checked_sub(1).map(|i| extents[i]), logical)?;
+            extents.insert(
+                i,
+                Extent {
+                    logical,
+                    len: 1,
+                    start: blk,
+                },
+            );
+            self.extent_rebuild(fs, extents, node_blks)?;
+            self.i_blocks += 1;
+            return Ok(blk);
+        }
+
+        let blk = Self::alloc_extent_blk(fs, inode_i, node.entries.last().copied(), logical)?;
+        match node.entries.last_mut() {
+            // 物理上连续，直接延长最后一个区段
+            Some(last) if last.start + last.len == blk && last.logical + last.len == logical => {
+                last.len += 1;
+                match leaf_blk {
+                    0 => self.set_extent_root(&node),
+                    _ => fs.write_extent_node(leaf_blk, &node)?,
+                }
+            }
+            _ => self.extent_insert(
+                fs,
+                Extent {
+                    logical,
+                    len: 1,
+                    start: blk,
+                },
+            )?,
+        }
+
+        self.i_blocks += 1;
+        Ok(blk)
+    }
+
+    /// 为逻辑块 `logical` 分配一个清零的数据块，尽量让它和前一个区段 `prev` 在物理上连续
+    fn alloc_extent_blk(fs: &mut Fs, inode_i: u32, prev: Option<Extent>, logical: u32) -> Result<u32> {
+        let blk = match prev {
+            Some(prev) => fs.alloc_near(prev.start.saturating_add(logical - prev.logical))?,
+            None => fs.alloc(BlkType::DataBlk, inode_i)?,
+        };
+        fs.clear_data_blk(blk)?;
+        Ok(blk)
+    }
+
+    /// 释放旧的结点块 `node_blks`，用按逻辑块号排好序的 `extents` 重新建树
+    ///
+    /// 逻辑上和物理上都连续的相邻区段会合并成一个
+    fn extent_rebuild(&mut self, fs: &mut Fs, extents: Vec<Extent>, node_blks: Vec<u32>) -> Result<()> {
+        fs.free(BlkType::DataBlk, &node_blks)?;
+
+        let mut merged: Vec<Extent> = Vec::with_capacity(extents.len());
+        for e in extents {
+            match merged.last_mut() {
+                Some(last) if last.logical + last.len == e.logical && last.start + last.len == e.start => {
+                    last.len += e.len
+                }
+                _ => merged.push(e),
+            }
+        }
+
+        self.set_extent_root(&ExtentNode::default());
+        for e in merged {
+            self.extent_insert(fs, e)?;
+        }
+        Ok(())
+    }
+
+    /// 释放使用区段树的索引节点中逻辑块号不小于 `new_blk_counts` 的数据块
+    ///
+    /// 从最右侧的区段开始逐个删除或者截短，变空的结点块一并释放。
+    /// 只修改最右侧路径上的结点，修改的块数和释放的区段个数成正比，不会重建整棵树
+    pub(in crate::fs) fn extent_truncate(&mut self, new_blk_counts: u32, fs: &mut Fs) -> Result<()> {
+        loop {
+            // 最右侧路径上的结点，第 0 个是根结点
+            let mut path: Vec<(u32, ExtentNode)> = vec![(0, self.extent_root())];
+            loop {
+                let node = &path.last().unwrap().1;
+                match node.entries.last() {
+                    Some(e) if node.depth > 0 => {
+                        let child = e.start;
+                        path.push((child, fs.read_extent_node(child)?));
+                    }
+                    _ => break,
+                }
+            }
+
+            let leaf = &mut path.last_mut().unwrap().1;
+            let Some(&last) = leaf.entries.last().filter(|_| leaf.depth == 0) else {
+                // 树已经空了
+                self.set_extent_root(&ExtentNode::default());
+                return Ok(());
+            };
+            if last.logical + last.len <= new_blk_counts {
+                return Ok(());
+            }
+
+            let keep = new_blk_counts.saturating_sub(last.logical);
+            let blks: Vec<u32> = (last.start + keep..last.start + last.len).collect();
+            fs.free(BlkType::DataBlk, &blks)?;
+            self.i_blocks -= last.len - keep;
+            match keep {
+                0 => {
+                    leaf.entries.pop();
+                }
+                _ => leaf.entries.last_mut().unwrap().len = keep,
+            }
+
+            // 变空的结点块释放掉，并从上一层删除指向它的区段
+            while path.len() > 1 && path.last().unwrap().1.entries.is_empty() {
+                let (blk, _) = path.pop().unwrap();
+                fs.free(BlkType::DataBlk, &[blk])?;
+                path.last_mut().unwrap().1.entries.pop();
+            }
+            let (blk, node) = path.last().unwrap();
+            match path.len() {
+                1 if node.entries.is_empty() => self.set_extent_root(&ExtentNode::default()),
+                1 => self.set_extent_root(node),
+                _ => fs.write_extent_node(*blk, node)?,
+            }
+        }
+    }
+
+    /// 区段树中叶子区段的个数
+    pub fn extents_count(&self, fs: &Fs) -> Result<usize> {
+        match self.uses_extents() {
+            true => Ok(self.extent_collect(fs)?.0.len()),
+            false => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn test_extents() {
+    let options = FormatOptions {
+        extents: true,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.mkdir("dir").unwrap();
+    fs.chdir("dir/../dir").unwrap();
+    fs.chdir("..").unwrap();
+    fs.rmdir("dir").unwrap();
+    let free_blocks = fs.super_block().free_blocks_count;
+    let data: Vec<u8> = (0..300 * 512).map(|i| (i % 251) as u8).collect();
+
+    // 连续分配的大文件只需要一个区段
+    fs.create("big").unwrap();
+    let fd = fs.open_with("big", OpenOptions::new().read(true).write(true)).unwrap();
+    fs.write(fd, &data).unwrap();
+    let inode = fs.get_inode(fs.fds[fd].as_ref().unwrap().inode_i).unwrap();
+    assert!(inode.uses_extents());
+    assert_eq!(inode.extents_count(&fs).unwrap(), 1);
+
+    // 交替写入两个文件，数据块不再连续，区段树会长高
+    fs.create("a").unwrap();
+    fs.create("b").unwrap();
+    let fa = fs.open_with("a", OpenOptions::new().read(true).write(true)).unwrap();
+    let fb = fs.open_with("b", OpenOptions::new().read(true).write(true)).unwrap();
+    for chunk in data.chunks(512).take(120) {
+        fs.write(fa, chunk).unwrap();
+        fs.write(fb, chunk).unwrap();
+    }
+    let inode = fs.get_inode(fs.fds[fa].as_ref().unwrap().inode_i).unwrap();
+    assert!(inode.extent_root().depth > 1);
+
+    let fa = fs.open_with("a", OpenOptions::new().read(true).write(true)).unwrap();
+    let mut buf = vec![0u8; 120 * 512];
+    assert_eq!(fs.read(fa, &mut buf).unwrap(), buf.len());
+    assert_eq!(buf, data[..buf.len()]);
+
+    // 裁剪之后剩下的数据不变
+    fs.cut(fa, 1000).unwrap();
+    let fa = fs.open_with("a", OpenOptions::new().read(true).write(true)).unwrap();
+    let mut buf = vec![0u8; 2000];
+    assert_eq!(fs.read(fa, &mut buf).unwrap(), 1000);
+    assert_eq!(buf[..1000], data[..1000]);
+
+    // 删除所有文件之后，数据块和结点块都被释放
+    fs.rm(fd).unwrap();
+    fs.rm(fa).unwrap();
+    fs.rm(fb).unwrap();
+    assert_eq!(fs.super_block().free_blocks_count, free_blocks);
+}
@@ -12,7 +12,7 @@ use super::*;
 /// 文件类型
 /// 
 /// 表示文件系统中的三种基本文件类型
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FileType {
     /// 普通文件
     File,
@@ -22,18 +22,18 @@ pub enum FileType {
     Symlink,
 }
 
-impl Into<u8> for FileType {
+impl From<FileType> for u8 {
     /// 将文件类型转换为数字表示
     /// 
     /// # 返回值
     /// - 1: 普通文件
     /// - 2: 目录
     /// - 3: 符号链接
-    fn into(self) -> u8 {
-        match self {
-            Self::File => 1,
-            Self::Dir => 2,
-            Self::Symlink => 3,
+    fn from(val: FileType) -> Self {
+        match val {
+            FileType::File => 1,
+            FileType::Dir => 2,
+            FileType::Symlink => 3,
         }
     }
 }
@@ -61,22 +61,29 @@ impl From<u8> for FileType {
 
 /// 文件权限模式
 /// 
-/// 存储文件的访问权限和所有者信息
+/// 存储文件的访问权限、拥有者和所属用户组
 #[derive(Default, Clone)]
+#[repr(C)]
 pub struct FileMode {
-    /// 文件的存取权限位图，格式为 [0rwx:0rwx]
-    /// 高3位表示所有者权限，低3位表示其他用户权限
-    pub mode: u8,
-    /// 文件的拥有者ID
-    pub owner: u8,
+    /// 文件的存取权限位图，格式为 [ugt:rwx:rwx:rwx]
+    /// 最高三位是 [`MODE_SETUID`]、[`MODE_SETGID`] 和 [`MODE_STICKY`]，
+    /// 之后从高到低依次是拥有者、所属用户组和其他用户的权限
+    pub mode: u16,
+    /// 文件拥有者的用户ID，即 [`PASSWD_PATH`] 中记录的 UID
+    pub owner: u16,
+    /// 文件所属用户组的ID，即 [`GROUP_PATH`] 中记录的 GID
+    pub group: u16,
 }
 
 /// 用户类型
 /// 
 /// 用于区分不同的用户类型以进行权限检查
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum UserType {
     /// 文件拥有者
     Owner,
+    /// 文件所属用户组的成员
+    Group,
     /// 其他用户
     Other,
 }
@@ -86,81 +93,92 @@ impl FileMode {
     /// 
     /// # 参数
     /// - `owner`: 文件拥有者的用户ID
+    /// - `group`: 文件所属用户组的ID
     /// - `file_type`: 文件类型
     /// 
     /// # 返回值
     /// 根据文件类型设置默认权限的 FileMode 实例
     /// 
     /// # 默认权限
-    /// - 普通文件: rwxr--
-    /// - 目录: rwxr-x
-    /// - 符号链接: rwxr--
-    pub fn new(owner: usize, file_type: FileType) -> Self {
+    /// - 普通文件: rwxr--r--
+    /// - 目录: rwxr-xr-x
+    /// - 符号链接: rwxr--r--
+    pub fn new(owner: u16, group: u16, file_type: FileType) -> Self {
         Self {
             mode: match file_type {
-                FileType::File => 0b00_111_100,
-                FileType::Dir => 0b00_111_101,
-                FileType::Symlink => 0b00_111_100,
+                FileType::File => 0o744,
+                FileType::Dir => 0o755,
+                FileType::Symlink => 0o744,
             },
-            owner: owner as u8,
+            owner,
+            group,
         }
     }
 
-    /// 检查用户是否有读权限
+    /// 判断用户属于哪一类：拥有者、所属用户组的成员还是其他用户
     /// 
     /// # 参数
     /// - `user`: 用户ID
-    /// 
-    /// # 返回值
-    /// 如果用户有读权限则返回 true，否则返回 false
+    /// - `gids`: 用户所在的全部用户组，包括主组和附加组
+    pub fn user_type(&self, user: u16, gids: &[u16]) -> UserType {
+        if self.owner == user {
+            UserType::Owner
+        } else if gids.contains(&self.group) {
+            UserType::Group
+        } else {
+            UserType::Other
+        }
+    }
+
+    /// 检查 `user` 是否有 `bit` 表示的权限，`bit` 是其他用户那一组中的某一位
     /// 
     /// # 规则
-    /// - root用户（ID为0）总是有读权限
     /// - 文件拥有者检查拥有者权限位
+    /// - 所属用户组的成员检查用户组权限位
     /// - 其他用户检查其他用户权限位
-    pub fn can_read(&self, user: usize) -> bool {
-        match self.owner == user as u8 {
-            true => self.mode & 0b00_100_000 != 0,
-            false => self.mode & 0b00_000_100 != 0 || user == 0,
+    /// - root用户（ID为0）不是拥有者时总是有权限
+    fn check(&self, user: u16, gids: &[u16], bit: u16) -> bool {
+        match self.user_type(user, gids) {
+            UserType::Owner => self.mode & bit << 6 != 0,
+            UserType::Group => self.mode & bit << 3 != 0 || user == 0,
+            UserType::Other => self.mode & bit != 0 || user == 0,
         }
     }
 
+    /// 检查用户是否有读权限
+    /// 
+    /// # 参数
+    /// - `user`: 用户ID
+    /// - `gids`: 用户所在的全部用户组
+    /// 
+    /// # 返回值
+    /// 如果用户有读权限则返回 true，否则返回 false
+    pub fn can_read(&self, user: u16, gids: &[u16]) -> bool {
+        self.check(user, gids, 0o4)
+    }
+
     /// 检查用户是否有写权限
     /// 
     /// # 参数
     /// - `user`: 用户ID
+    /// - `gids`: 用户所在的全部用户组
     /// 
     /// # 返回值
     /// 如果用户有写权限则返回 true，否则返回 false
-    /// 
-    /// # 规则
-    /// - root用户（ID为0）总是有写权限
-    /// - 文件拥有者检查拥有者权限位
-    /// - 其他用户检查其他用户权限位
-    pub fn can_write(&self, user: usize) -> bool {
-        match self.owner == user as u8 {
-            true => self.mode & 0b00_010_000 != 0,
-            false => self.mode & 0b00_000_010 != 0 || user == 0,
-        }
+    pub fn can_write(&self, user: u16, gids: &[u16]) -> bool {
+        self.check(user, gids, 0o2)
     }
 
     /// 检查用户是否有执行权限
     /// 
     /// # 参数
     /// - `user`: 用户ID
+    /// - `gids`: 用户所在的全部用户组
     /// 
     /// # 返回值
     /// 如果用户有执行权限则返回 true，否则返回 false
-    /// 
-    /// # 规则
-    /// - root用户（ID为0）总是有执行权限
-    /// - 文件拥有者检查拥有者权限位
-    /// - 其他用户检查其他用户权限位
-    pub fn can_exec(&self, user: usize) -> bool {
-        match self.owner == user as u8 {
-            true => self.mode & 0b00_001_000 != 0,
-            false => self.mode & 0b00_000_001 != 0 || user == 0,
-        }
+    pub fn can_exec(&self, user: u16, gids: &[u16]) -> bool {
+        self.check(user, gids, 0o1)
     }
 
     /// 设置文件权限模式
@@ -174,16 +192,16 @@ impl FileMode {
     /// 
     /// # 错误
     /// - 非文件拥有者且非root用户尝试修改权限
-    /// - 权限模式超出有效范围（0-127）
-    pub fn set_mode(&mut self, user: usize, mode: u8) -> Result<()> {
-        if self.owner != user as u8 && user != 0 {
+    /// - 权限模式超出有效范围（0o0000-0o7777）
+    pub fn set_mode(&mut self, user: u16, mode: u16) -> Result<()> {
+        if self.owner != user && user != 0 {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Permission Denied. Can't set mode to file",
             ));
         }
 
-        if mode > 0b00_111_111 {
+        if mode > 0o7777 {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Permission Denied. Invaild file mode",
@@ -194,34 +212,44 @@ impl FileMode {
 
         Ok(())
     }
+
+    /// 在设置了粘滞位的目录中，用户 `user` 能否删除或重命名拥有者为 `file` 的文件
+    /// 
+    /// 没有设置粘滞位时只检查目录的写权限，这里总是返回 true
+    pub fn can_unlink(&self, file: &FileMode, user: u16) -> bool {
+        self.mode & MODE_STICKY == 0 || user == 0 || file.owner == user || self.owner == user
+    }
 }
 
 impl std::fmt::Display for FileMode {
     /// 格式化文件权限为字符串表示
     /// 
     /// # 返回值
-    /// 格式为 "rwx:rwx" 的权限字符串
-    /// - 第一部分是拥有者权限
-    /// - 第二部分是其他用户权限
+    /// 格式为 "rwxrwxrwx" 的权限字符串
+    /// - 三组依次是拥有者、所属用户组和其他用户的权限
     /// - r表示读权限，w表示写权限，x表示执行权限
     /// - 无权限时用 '-' 表示
+    /// - setuid、setgid 和粘滞位显示在对应的执行权限位置上：有执行权限时为 s、s、t，否则为 S、S、T
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut mask = 0b00_100_000;
+        let special = [(MODE_SETUID, 's'), (MODE_SETGID, 's'), (MODE_STICKY, 't')];
+        let mut mask = 0o400;
         let mut str = String::new();
-        for i in 0..6 {
-            match mask & self.mode {
-                0 => str.push('-'),
-                _ => match i % 3 {
-                    0 => str.push('r'),
-                    1 => str.push('w'),
-                    2 => str.push('x'),
-                    _ => unreachable!(),
-                },
-            };
-            if i == 2 {
-                str.push(':')
+        for i in 0..9 {
+            let set = mask & self.mode != 0;
+            match i % 3 {
+                0 => str.push(if set { 'r' } else { '-' }),
+                1 => str.push(if set { 'w' } else { '-' }),
+                _ => {
+                    let (bit, c) = special[i / 3];
+                    str.push(match (self.mode & bit != 0, set) {
+                        (true, true) => c,
+                        (true, false) => c.to_ascii_uppercase(),
+                        (false, true) => 'x',
+                        (false, false) => '-',
+                    })
+                }
             }
-            mask = mask >> 1;
+            mask >>= 1;
         }
         write!(f, "{}", str)
     }
@@ -231,43 +259,143 @@ impl FileMode {
     /// 将字符串格式的权限转换为数字模式
     /// 
     /// # 参数
-    /// - `str`: 权限字符串，格式为 "rwx:rwx"
+    /// - `str`: 权限字符串，支持三种格式：
+    ///   - 八进制数，例如 `755`、`1777`、`2755`
+    ///   - 完整的权限字符串，例如 `rwxr-xr-x`、`rwxrwxrwt`
+    ///   - 符号形式，例如 `u+x`、`go-w`、`+t`、`g+s`、`u=rwx,o=`，多个子句用逗号分隔
+    /// - `old`: 文件原来的权限，符号形式在它的基础上修改
     /// 
     /// # 返回值
     /// 成功时返回权限的数字表示，失败时返回错误
     /// 
     /// # 错误
-    /// - 字符串长度不为7
-    /// - 字符串格式不正确
-    /// - 包含无效字符
+    /// 字符串不是上面任何一种格式
     /// 
     /// # 示例
     /// ```
-    /// let mode = FileMode::str_to_mode("rwx:r-x").unwrap();
-    /// assert_eq!(mode, 0b00_111_101);
+    /// # use simulate_unixlike_fs::fs::FileMode;
+    /// assert_eq!(FileMode::str_to_mode("rwxr-x---", 0).unwrap(), 0o750);
+    /// assert_eq!(FileMode::str_to_mode("1777", 0).unwrap(), 0o1777);
+    /// assert_eq!(FileMode::str_to_mode("g+s,o-rx", 0o755).unwrap(), 0o2750);
     /// ```
-    pub fn str_to_mode(str: &str) -> Result<u8> {
-        let mut err = str.len() != 7;
+    pub fn str_to_mode(str: &str, old: u16) -> Result<u16> {
+        let err = || Error::new(ErrorKind::InvalidData, "Wrong file mode format");
 
-        let mut str = str.as_bytes().to_vec();
-        str.remove(3);
+        // 八进制数
+        if !str.is_empty() && str.len() <= 4 && str.bytes().all(|c| (b'0'..=b'7').contains(&c)) {
+            return u16::from_str_radix(str, 8).map_err(|_| err());
+        }
+
+        // 完整的权限字符串
+        if let Some(mode) = Self::parse_full(str) {
+            return Ok(mode);
+        }
 
-        let mut mode = 0b00_000_000;
-        let mut mask = 0b00_100_000;
-        let temp = [b'r', b'w', b'x'];
+        // 符号形式：[ugoa]*([-+=][rwxst]*)+，多个子句用逗号分隔
+        let mut mode = old;
+        for clause in str.split(',') {
+            let ops_begin = clause.find(['+', '-', '=']).ok_or_else(err)?;
+            let (who, mut ops) = clause.split_at(ops_begin);
+            let mut who_mask = 0;
+            for c in who.chars() {
+                who_mask |= match c {
+                    'u' => MODE_SETUID | 0o700,
+                    'g' => MODE_SETGID | 0o070,
+                    'o' => MODE_STICKY | 0o007,
+                    'a' => 0o7777,
+                    _ => return Err(err()),
+                };
+            }
+            if who_mask == 0 {
+                who_mask = 0o7777;
+            }
 
-        for i in 0..6 {
-            match str[i] {
-                b'-' => {}
-                a if a == temp[i % 3] => mode = mode | mask,
-                _ => err = true,
-            };
-            mask = mask >> 1;
+            while let Some(op) = ops.chars().next() {
+                let perms_end = ops[1..].find(['+', '-', '=']).map_or(ops.len(), |i| i + 1);
+                let mut bits = 0;
+                for c in ops[1..perms_end].chars() {
+                    bits |= match c {
+                        'r' => 0o444,
+                        'w' => 0o222,
+                        'x' => 0o111,
+                        's' => MODE_SETUID | MODE_SETGID,
+                        't' => MODE_STICKY,
+                        _ => return Err(err()),
+                    };
+                }
+                bits &= who_mask;
+                mode = match op {
+                    '+' => mode | bits,
+                    '-' => mode & !bits,
+                    _ => mode & !who_mask | bits,
+                };
+                ops = &ops[perms_end..];
+            }
+        }
+        Ok(mode)
+    }
+
+    /// 解析 `rwxr-xr-x` 形式的完整权限字符串，执行权限的位置可以是 s、S、t、T
+    fn parse_full(str: &str) -> Option<u16> {
+        let str = str.as_bytes();
+        if str.len() != 9 {
+            return None;
         }
 
-        match err {
-            true => Err(Error::new(ErrorKind::InvalidData, "Wrong file mode format")),
-            false => Ok(mode),
+        let special = [MODE_SETUID, MODE_SETGID, MODE_STICKY];
+        let mut mode = 0;
+        let mut mask = 0o400;
+        for (i, &c) in str.iter().enumerate() {
+            let special_char = if i == 8 { b't' } else { b's' };
+            match (i % 3, c) {
+                (_, b'-') => {}
+                (0, b'r') | (1, b'w') | (2, b'x') => mode |= mask,
+                (2, c) if c == special_char => mode |= mask | special[i / 3],
+                (2, c) if c == special_char.to_ascii_uppercase() => mode |= special[i / 3],
+                _ => return None,
+            }
+            mask >>= 1;
         }
+        Some(mode)
     }
 }
+
+#[test]
+fn test_file_mode() {
+    // 拥有者是 1，所属用户组是 2
+    let mode = FileMode {
+        mode: 0o640,
+        owner: 1,
+        group: 2,
+    };
+    assert_eq!(mode.to_string(), "rw-r-----");
+    assert_eq!(FileMode::str_to_mode(&mode.to_string(), 0).unwrap(), 0o640);
+    assert!(FileMode::str_to_mode("rwx:rwx", 0).is_err());
+
+    // 特殊权限位的显示和解析
+    for (mode, str) in [(0o4755, "rwsr-xr-x"), (0o2745, "rwxr-Sr-x"), (0o1777, "rwxrwxrwt"), (0o1770, "rwxrwx--T")] {
+        let file_mode = FileMode { mode, owner: 0, group: 0 };
+        assert_eq!(file_mode.to_string(), str);
+        assert_eq!(FileMode::str_to_mode(str, 0).unwrap(), mode);
+    }
+
+    // 八进制和符号形式
+    assert_eq!(FileMode::str_to_mode("2755", 0).unwrap(), 0o2755);
+    assert!(FileMode::str_to_mode("8", 0).is_err());
+    assert_eq!(FileMode::str_to_mode("+t", 0o777).unwrap(), 0o1777);
+    assert_eq!(FileMode::str_to_mode("u+s", 0o755).unwrap(), 0o4755);
+    assert_eq!(FileMode::str_to_mode("u=rw,go=r", 0o4777).unwrap(), 0o644);
+    assert_eq!(FileMode::str_to_mode("a-x+w", 0o755).unwrap(), 0o666);
+    assert!(FileMode::str_to_mode("u+q", 0).is_err());
+    assert!(FileMode::str_to_mode("ux", 0).is_err());
+
+    // 拥有者、用户组成员和其他用户分别检查自己的那一组权限
+    assert!(mode.can_write(1, &[1]));
+    assert!(mode.can_read(3, &[1, 2]) && !mode.can_write(3, &[1, 2]));
+    assert!(!mode.can_read(3, &[1]));
+    assert_eq!(mode.user_type(3, &[2]), UserType::Group);
+    assert!(!mode.can_exec(1, &[2]));
+
+    // root 不是拥有者时总是有权限
+    assert!(mode.can_write(0, &[0]));
+}
@@ -1,8 +1,7 @@
 //! # 文件系统核心模块
 //!
 //! 此模块实现了 Ext2 文件系统的核心功能，包括：
-//! - 文件系统描述符管理
-//! - 用户管理
+//! - 超级块和组描述符管理
 //! - 目录项管理
 //! - 磁盘块分配和释放
 //! - 索引节点管理
@@ -13,140 +12,411 @@ use super::*;
 /// 块类型枚举
 /// 
 /// 用于区分不同类型的磁盘块，以便进行相应的分配和释放操作
+#[derive(Clone, Copy)]
 pub(in crate::fs) enum BlkType {
     /// 索引节点块
     INode,
+    /// 目录的索引节点，分配时会尽量分散到不同的块组
+    DirINode,
     /// 数据块
     DataBlk,
 }
 
-/// 用户信息结构体
+/// 超级块结构体
 /// 
-/// 存储用户的基本信息，包括用户名和密码
-#[derive(Default, Debug, Clone, Copy)]
-#[repr(align(32))]
-pub struct User {
-    /// 用户名，最大长度为16字节
-    pub name: [u8; 16],
-    /// 密码，最大长度为16字节
-    pub password: [u8; 16],
+/// 存储在第 0 块，记录整个文件系统的几何参数、空闲块数量等。
+/// 字段按照磁盘上的顺序声明，用 `repr(C)` 固定布局
+#[repr(C, align(32))]
+#[derive(Default)]
+pub struct SuperBlock {
+    /// 保留区域，版本 10 之前存放用户表，用户现在保存在 [`PASSWD_PATH`] 中；
+    /// 保留它使之后的字段，特别是魔数和版本号，在各个版本中的位置不变。
+    ///
+    /// 从版本 9 升级的镜像中，还没有登录过的用户仍然占用原来的位置：
+    /// 前 16 字节是用户名，后 16 字节是明文密码，第一次登录成功后清零
+    pub(in crate::fs) reserved: [[u8; 32]; 10],
+    /// 卷名，最大长度为16字节
+    pub volume_name: [u8; 16],
+    /// 最后一次挂载的时间（Unix时间戳）
+    pub mtime: u64,
+    /// 最后一次写入超级块的时间（Unix时间戳）
+    pub wtime: u64,
+    /// 兼容特性标志
+    pub feature_compat: u32,
+    /// 不兼容特性标志
+    pub feature_incompat: u32,
+    /// 只读兼容特性标志
+    pub feature_ro_compat: u32,
+    /// 磁盘块大小（字节数）
+    pub block_size: u32,
+    /// 索引节点的总数
+    pub inodes_count: u32,
+    /// 数据块的总数
+    pub blocks_count: u32,
+    /// 每个块组的索引节点个数
+    pub inodes_per_group: u32,
+    /// 每个块组的数据块个数（最后一个块组可能不满）
+    pub blocks_per_group: u32,
+    /// 块组的个数
+    pub groups_count: u32,
+    /// 组描述符表的起始块号
+    pub group_table: u32,
+    /// 日志区的块数，日志区紧跟在组描述符表之后，为 0 时没有日志
+    pub journal_blocks: u32,
+    /// 空闲块的个数
+    pub free_blocks_count: u32,
+    /// 空闲索引节点的个数    
+    pub free_inodes_count: u32,
+    /// 目录个数
+    pub used_dirs_count: u32,
+    /// 魔数，必须等于 [`FS_MAGIC`]
+    pub magic: u16,
+    /// 磁盘格式版本
+    pub version: u16,
+    /// 文件系统状态，[`FS_STATE_CLEAN`] 或 [`FS_STATE_DIRTY`]
+    pub state: u16,
+    /// 格式化以来的挂载次数
+    pub mount_count: u16,
+    /// 保留区域，版本 10 之前存放用户组表，用户组现在保存在 [`GROUP_PATH`] 中；
+    /// 从版本 9 升级的过程中记录转换进度，见 [`upgrade`](super::upgrade)
+    pub(in crate::fs) reserved_tail: [u64; 11],
+    /// 超级块的校验和
+    pub checksum: u32,
+}
+
+impl SuperBlock {
+    /// 根据格式化选项创建新的超级块
+    /// 
+    /// # 返回值
+    /// 返回初始化好的超级块实例，用户和用户组由 [`Fs::format_on`] 写入 [`PASSWD_PATH`] 等文件
+    /// 
+    /// # 磁盘布局
+    /// - 超级块: 块0
+    /// - 组描述符表: 块1 开始，每个块组占用一个 [`GroupDesc`]
+    /// - 日志区: 紧跟在组描述符表之后，见 [`journal`](super::journal)
+    /// - 块组: 紧跟在日志区之后依次排列，每个块组由块位图、索引节点位图、
+    ///   索引节点表和数据区组成
+    /// 
+    /// # 错误
+    /// 选项不合法，镜像过大，或者卷名过长时返回错误
+    pub(in crate::fs) fn new(options: &FormatOptions) -> Result<Self> {
+        options.validate()?;
+
+        let blocks_per_group = options.block_size * 8;
+        let groups_count = options.blocks_count.div_ceil(blocks_per_group);
+        let inodes_per_group = options.inodes_count.div_ceil(groups_count);
+
+        // 块号和索引节点号都用 u32 记录
+        let too_large = || Error::new(ErrorKind::InvalidInput, "Image too large");
+        if options.blocks_count > u32::MAX as usize
+            || inodes_per_group * groups_count > u32::MAX as usize
+        {
+            return Err(too_large());
+        }
+
+        let now = utils::now().as_secs();
+        let super_block = Self {
+            magic: FS_MAGIC,
+            version: FS_VERSION,
+            state: FS_STATE_DIRTY,
+            mount_count: 1,
+            feature_compat: match options.journal_blocks {
+                0 => 0,
+                _ => FEATURE_COMPAT_HAS_JOURNAL,
+            } | match options.dir_index {
+                true => FEATURE_COMPAT_DIR_INDEX,
+                false => 0,
+            },
+            feature_incompat: match options.extents {
+                true => FEATURE_INCOMPAT_EXTENTS,
+                false => 0,
+            },
+            feature_ro_compat: match options.checksums {
+                true => FEATURE_RO_COMPAT_METADATA_CSUM,
+                false => 0,
+            },
+            mtime: now,
+            wtime: now,
+            volume_name: options.volume_name.as_str().into_array()?,
+            block_size: options.block_size as u32,
+            inodes_count: (inodes_per_group * groups_count) as u32,
+            blocks_count: options.blocks_count as u32,
+            inodes_per_group: inodes_per_group as u32,
+            blocks_per_group: blocks_per_group as u32,
+            groups_count: groups_count as u32,
+            group_table: 1,
+            journal_blocks: options.journal_blocks as u32,
+            free_blocks_count: options.blocks_count as u32,
+            free_inodes_count: (inodes_per_group * groups_count) as u32,
+            used_dirs_count: 0,
+            reserved: Default::default(),
+            reserved_tail: Default::default(),
+            checksum: 0,
+        };
+
+        if super_block.total_blocks() > u32::MAX as usize {
+            return Err(too_large());
+        }
+
+        Ok(super_block)
+    }
+
+    /// 组描述符表占用的块数
+    pub fn group_table_blocks(&self) -> usize {
+        (self.groups_count as usize * size_of::<GroupDesc>()).div_ceil(self.block_size as usize)
+    }
+
+    /// 每个块组的索引节点表占用的块数
+    pub fn inode_table_blocks(&self) -> usize {
+        (self.inodes_per_group as usize * INODE_SIZE).div_ceil(self.block_size as usize)
+    }
+
+    /// 第 `group` 个块组的数据块个数
+    pub fn group_blocks(&self, group: usize) -> usize {
+        let begin = group * self.blocks_per_group as usize;
+        (self.blocks_count as usize - begin).min(self.blocks_per_group as usize)
+    }
+
+    /// 日志区的起始块号
+    pub fn journal_begin(&self) -> usize {
+        self.group_table as usize + self.group_table_blocks()
+    }
+
+    /// 第 `group` 个块组的起始块号
+    fn group_begin(&self, group: usize) -> usize {
+        let meta_blocks = 2 + self.inode_table_blocks();
+        self.journal_begin()
+            + self.journal_blocks as usize
+            + group * (meta_blocks + self.blocks_per_group as usize)
+    }
+
+    /// 磁盘镜像的总块数
+    pub fn total_blocks(&self) -> usize {
+        let last = self.groups_count as usize - 1;
+        self.group_begin(last) + 2 + self.inode_table_blocks() + self.group_blocks(last)
+    }
+}
+
+/// 挂载错误
+/// 
+/// [`Fs::init_on`] 等挂载函数在检查超级块失败时，返回的 [`Error`] 里包含这个类型，
+/// 可以用 [`MountError::of`] 取出来
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MountError {
+    /// 磁盘上没有本文件系统的超级块
+    NotAFilesystem,
+    /// 磁盘格式版本与当前实现不同
+    UnsupportedVersion(u16),
+    /// 磁盘使用了当前实现不支持的特性
+    UnsupportedFeatures,
+    /// 上次挂载之后没有正常卸载
+    NotCleanlyUnmounted,
+}
+
+impl MountError {
+    /// 如果 `err` 是挂载错误，返回对应的 [`MountError`]
+    pub fn of(err: &Error) -> Option<MountError> {
+        err.get_ref()?.downcast_ref().copied()
+    }
+}
+
+impl std::fmt::Display for MountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotAFilesystem => write!(f, "Not a filesystem"),
+            Self::UnsupportedVersion(v) => write!(f, "Unsupported filesystem version {}", v),
+            Self::UnsupportedFeatures => write!(f, "Unsupported filesystem features"),
+            Self::NotCleanlyUnmounted => write!(f, "Filesystem was not cleanly unmounted"),
+        }
+    }
+}
+
+impl std::error::Error for MountError {}
+
+impl From<MountError> for Error {
+    fn from(err: MountError) -> Self {
+        let kind = match err {
+            MountError::NotAFilesystem => ErrorKind::InvalidData,
+            MountError::UnsupportedVersion(_) | MountError::UnsupportedFeatures => {
+                ErrorKind::Unsupported
+            }
+            MountError::NotCleanlyUnmounted => ErrorKind::Other,
+        };
+        Error::new(kind, err)
+    }
 }
 
 /// 组描述符结构体
 /// 
-/// 存储文件系统的元数据信息，包括位图位置、空闲块数量、用户信息等
-#[repr(align(32))]
-#[derive(Default)]
+/// 每个块组对应一个组描述符，记录该组的位图、索引节点表、数据区的位置和空闲数量。
+/// 所有组描述符依次存放在超级块之后的组描述符表里，布局用 `repr(C)` 固定。
+/// 和 ext4 的 32 字节组描述符一样，空闲块数和空闲索引节点数只有 16 位，
+/// 之后的 16 位分别是两个位图的校验和
+#[repr(C, align(32))]
+#[derive(Default, Clone, Debug)]
 pub struct GroupDesc {
-    /// 卷名，最大长度为16字节
-    pub volume_name: [u8; 16],
     /// 保存块位图所在的块号
-    pub block_bitmap: u16,
+    pub block_bitmap: u32,
     /// 索引节点位图的块号
-    pub inode_bitmap: u16,
+    pub inode_bitmap: u32,
     /// 索引表的起始位置
-    pub inode_table: u16,
+    pub inode_table: u32,
+    /// 数据区的起始块号
+    pub data_begin: u32,
     /// 空闲块的个数
     pub free_blocks_count: u16,
+    /// 块位图的校验和，CRC32C 的低 16 位
+    pub block_bitmap_csum: u16,
     /// 空闲索引节点的个数    
     pub free_inodes_count: u16,
+    /// 索引节点位图的校验和，CRC32C 的低 16 位
+    pub inode_bitmap_csum: u16,
     /// 目录个数
-    pub used_dirs_count: u16,
-    /// 用户表，最多支持10个用户
-    pub users: [User; 10],
-    /// 当前用户数量
-    pub users_len: u16,
+    pub used_dirs_count: u32,
+    /// 组描述符的校验和
+    pub checksum: u32,
 }
 
 impl GroupDesc {
-    /// 创建新的组描述符
-    /// 
-    /// # 返回值
-    /// 返回初始化好的组描述符实例，包含默认的root用户
-    /// 
-    /// # 默认配置
-    /// - 卷名: "Ext2Disk"
-    /// - 块位图位置: 块1
-    /// - 索引节点位图位置: 块2
-    /// - 索引节点表位置: 块3
-    /// - 默认用户: root (密码: 123)
-    pub(in crate::fs) fn new() -> Self {
-        let mut users = [User::default(); 10];
-
-        // 默认用户
-        users[0] = User {
-            name: "root".into_array().unwrap(),
-            password: "123".into_array().unwrap(),
-        };
+    /// 块位图的名字，校验和不匹配时出现在错误信息里
+    pub const BLOCK_BITMAP_NAME: &'static str = "Block bitmap";
+    /// 索引节点位图的名字，校验和不匹配时出现在错误信息里
+    pub const INODE_BITMAP_NAME: &'static str = "Inode bitmap";
 
+    /// 按照超级块里的几何参数，创建第 `group` 个块组的组描述符，两个位图都是空的
+    pub(in crate::fs) fn new(super_block: &SuperBlock, group: usize) -> Self {
+        let begin = super_block.group_begin(group);
+        let empty_map = bitmap_checksum(&vec![0; super_block.block_size as usize]);
         Self {
-            volume_name: "Ext2Disk".into_array().unwrap(),
-            block_bitmap: 1,
-            inode_bitmap: 2,
-            inode_table: 3,
-            free_blocks_count: DATA_BLOCKS as u16,
-            free_inodes_count: DATA_BLOCKS as u16,
+            block_bitmap: begin as u32,
+            inode_bitmap: (begin + 1) as u32,
+            inode_table: (begin + 2) as u32,
+            data_begin: (begin + 2 + super_block.inode_table_blocks()) as u32,
+            free_blocks_count: super_block.group_blocks(group) as u16,
+            block_bitmap_csum: empty_map,
+            free_inodes_count: super_block.inodes_per_group as u16,
+            inode_bitmap_csum: empty_map,
             used_dirs_count: 0,
-            users_len: 1,
-            users,
+            checksum: 0,
         }
     }
+    /// 从磁盘上读取整个组描述符表
+    pub(in crate::fs) fn read_table(disk: &dyn BlockDevice, super_block: &SuperBlock) -> Result<Vec<Self>> {
+        let table = super_block.block_size as u64 * super_block.group_table as u64;
+        (0..super_block.groups_count as u64)
+            .map(|i| Self::from_disk(disk, table + i * size_of::<Self>() as u64))
+            .collect()
+    }
+}
+
+/// 位图的校验和：整个位图块的 CRC32C 的低 16 位
+pub(in crate::fs) fn bitmap_checksum(map: &[u8]) -> u16 {
+    utils::crc32c(map) as u16
 }
 
 /// 目录项结构体
 /// 
-/// 表示目录中的一个条目，包含文件名、索引节点号、文件类型等信息
-#[repr(align(32))]
-#[derive(Default, PartialEq, Debug, Clone)]
+/// 表示目录中的一个条目，包含文件名、索引节点号、文件类型等信息。
+/// 
+/// 和 ext2 一样，目录项在磁盘上是变长记录：[`DIR_ENTRY_HEADER_SIZE`] 字节的头部之后紧跟
+/// `name_len` 字节的文件名，整个记录按 4 字节对齐。`rec_len` 是到下一个目录项的距离，
+/// 可以大于记录本身的长度，多出来的空间留给之后插入的目录项；
+/// 每个数据块的最后一个目录项一直延伸到块末尾的校验和记录之前，目录项不会跨越数据块。
+///
+/// 和 ext4 的 `ext4_dir_entry_tail` 一样，目录数据块的最后 [`DIR_TAIL_SIZE`] 字节是一个校验和记录：
+/// 索引节点号和文件名长度为 0，文件类型为 [`DIR_TAIL_TYPE`]，校验和字段是它之前整个数据块的 CRC32C
+#[derive(PartialEq, Debug, Clone)]
 pub struct DirEntry {
     /// 索引节点号
-    pub i_node: u16,
-    /// 目录项长度
+    pub i_node: u32,
+    /// 到下一个目录项的距离
     pub rec_len: u16,
     /// 文件名长度
     pub name_len: u8,
-    /// 文件类型
+    /// 文件类型，为 0 时表示这个目录项没有使用
     pub file_type: u8,
-    /// 文件名，最大长度为16字节
-    pub name: [u8; 16],
+    /// 目录项的校验和
+    pub checksum: u32,
+    /// 文件名，最大长度为 [`MAX_NAME_LEN`] 字节，之后用 0 填充
+    pub name: [u8; MAX_NAME_LEN + 1],
 }
 
-/// 数据块类型别名
-/// 
-/// 表示一个完整的数据块，大小为 BLOCK_SIZE
-type DataBlk = [u8; BLOCK_SIZE as usize];
+impl Default for DirEntry {
+    fn default() -> Self {
+        Self {
+            i_node: 0,
+            rec_len: 0,
+            name_len: 0,
+            file_type: 0,
+            checksum: 0,
+            name: [0; MAX_NAME_LEN + 1],
+        }
+    }
+}
+
+impl DirEntry {
+    /// 目录数据块的名字，块末尾的校验和不匹配时出现在错误信息里
+    pub const BLOCK_NAME: &'static str = "Directory block";
+
+    /// 文件名长度为 `name_len` 的目录项在磁盘上至少占用的字节数
+    pub const fn rec_size(name_len: usize) -> usize {
+        (DIR_ENTRY_HEADER_SIZE + name_len).next_multiple_of(4)
+    }
+}
+
+/// 目录数据块中能放下新目录项的位置
+#[derive(Clone, Debug)]
+pub(in crate::fs) enum DirSlot {
+    /// 没有使用的记录，新目录项占据它的整个 `rec_len`
+    Unused { addr: u64, rec_len: u16 },
+    /// 位于 `addr` 的目录项 `prev` 的 `rec_len` 中有足够的空闲空间
+    Split { prev: Box<DirEntry>, addr: u64 },
+}
 
 /// 文件结构体
 /// 
 /// 表示一个打开的文件，包含索引节点信息、文件描述符、当前位置等
 #[derive(Clone)]
+/// 
+/// 文件描述符中只记录索引节点号，每次操作时都重新读取索引节点，
+/// 所以打开同一个文件的多个文件描述符总是看到一致的元数据
 pub(in crate::fs) struct File {
-    /// 文件的索引节点
-    pub inode: Inode,
     /// 索引节点号
-    pub inode_i: u16,
+    pub inode_i: u32,
     /// 目录项在磁盘上的地址
     pub dir_entry_addr: u64,
     /// 父目录的索引节点号
-    pub parent_inode_i: u16,
+    pub parent_inode_i: u32,
     /// 当前文件指针位置
     pub current_pos: usize,
+    /// 打开时是否允许读取
+    pub readable: bool,
+    /// 打开时是否允许写入
+    pub writable: bool,
+    /// 是否以追加模式打开，每次写入都在文件末尾
+    pub append: bool,
 }
 
 /// 文件系统主结构体
 /// 
 /// 管理整个文件系统的状态，包括磁盘访问、文件描述符、用户信息等
 pub struct Fs {
-    /// 维护的文件系统描述结构体
-    pub(in crate::fs) fs_desc: GroupDesc,
-    /// 写入/读取 虚拟磁盘的文件
-    pub(in crate::fs) disk: std::fs::File,
+    /// 维护的超级块
+    pub(in crate::fs) super_block: SuperBlock,
+    /// 所有块组的组描述符
+    pub(in crate::fs) groups: Vec<GroupDesc>,
+    /// 写入/读取 虚拟磁盘的块设备
+    pub(in crate::fs) disk: Journal,
     /// 用来记录当前打开的文件
     pub(in crate::fs) fds: [Option<File>; FD_LIMIT],
     /// 当前文件打开的个数
     pub(in crate::fs) opened_len: usize,
-    /// 当前登录用户
-    pub(in crate::fs) user: usize,
+    /// 当前登录用户的ID
+    pub(in crate::fs) user: u16,
+    /// 当前登录用户所在的全部用户组，第一个是主组，登录时确定
+    pub(in crate::fs) gids: Vec<u16>,
+    /// 访问时间的更新策略，挂载时指定
+    pub(in crate::fs) atime_mode: AtimeMode,
     /// 指向当前目录的 DirEntry 节点
     pub cwd: DirEntry,
 }
@@ -160,8 +430,11 @@ impl Fs {
     /// # 返回值
     /// 返回索引节点在磁盘上的字节偏移量
     #[inline(always)]
-    pub(in crate::fs) fn addr_i_node(&self, i_node: u16) -> u64 {
-        BLOCK_SIZE as u64 * (self.fs_desc.inode_table as u64 + i_node as u64)
+    pub(in crate::fs) fn addr_i_node(&self, i_node: u32) -> u64 {
+        let per_group = self.super_block.inodes_per_group;
+        let group = &self.groups[(i_node / per_group) as usize];
+        self.block_size() as u64 * group.inode_table as u64
+            + INODE_SIZE as u64 * (i_node % per_group) as u64
     }
 
     /// 获取数据块在磁盘上的物理地址
@@ -171,26 +444,140 @@ impl Fs {
     /// 
     /// # 返回值
     /// 返回数据块在磁盘上的字节偏移量
-    pub(in crate::fs) fn addr_data_blk(data_blk: u16) -> u64 {
-        BLOCK_SIZE as u64 * (DATA_BEGIN_BLOCK as u64 + data_blk as u64)
+    pub(in crate::fs) fn addr_data_blk(&self, data_blk: u32) -> u64 {
+        let per_group = self.super_block.blocks_per_group;
+        let group = &self.groups[(data_blk / per_group) as usize];
+        self.block_size() as u64 * (group.data_begin as u64 + (data_blk % per_group) as u64)
+    }
+
+    /// 磁盘块的大小（字节数）
+    #[inline(always)]
+    pub fn block_size(&self) -> usize {
+        self.super_block.block_size as usize
+    }
+
+    /// 每个索引块可以存放的数据块地址个数
+    #[inline(always)]
+    pub(in crate::fs) fn addr_per_blk(&self) -> usize {
+        self.block_size() / ADDR_SIZE
+    }
+
+    /// 当前几何参数下单个文件的最大字节数
+    /// 
+    /// 由直接索引、一级、二级、三级间接索引能寻址的数据块个数决定，
+    /// 同时不能超过索引节点中 `i_size` 能表示的范围。
+    /// 启用区段树时，文件大小只受 `i_size` 的限制
+    pub fn max_file_size(&self) -> u64 {
+        if self.new_inode_flags() & INODE_FLAG_EXTENTS != 0 {
+            return u32::MAX as u64;
+        }
+        let addr_num = self.addr_per_blk() as u64;
+        let blocks = 6 + addr_num + addr_num * addr_num + addr_num * addr_num * addr_num;
+        (blocks * self.block_size() as u64).min(u32::MAX as u64)
+    }
+
+    /// 新建索引节点时使用的标志
+    /// 
+    /// 超级块中启用了 [`FEATURE_INCOMPAT_EXTENTS`] 时，新的文件都使用区段树
+    pub(in crate::fs) fn new_inode_flags(&self) -> u16 {
+        match self.super_block.feature_incompat & FEATURE_INCOMPAT_EXTENTS {
+            0 => 0,
+            _ => INODE_FLAG_EXTENTS,
+        }
+    }
+
+    /// 创建一个与磁盘块大小相同的空数据块
+    pub(in crate::fs) fn empty_blk(&self) -> Vec<u8> {
+        vec![0u8; self.block_size()]
+    }
+
+    /// 将块号为 `blk` 的数据块清零
+    pub(in crate::fs) fn clear_blk(&mut self, blk: u32) -> Result<()> {
+        self.disk.write_at(&self.empty_blk(), self.addr_data_blk(blk))?;
+        Ok(())
+    }
+
+    /// 将新分配给文件内容的数据块 `blk` 清零
+    ///
+    /// 和写入文件内容一样，只有数据日志模式下才经过日志
+    pub(in crate::fs) fn clear_data_blk(&mut self, blk: u32) -> Result<()> {
+        self.disk.write_data_at(&self.empty_blk(), self.addr_data_blk(blk))?;
+        Ok(())
     }
 
-    /// 将文件系统描述符写入磁盘
+    /// 将超级块写入磁盘，同时更新最后写入时间
     /// 
     /// # 返回值
     /// 成功时返回 Ok(())，失败时返回错误信息
     /// 
     /// # 错误
     /// 当磁盘写入失败时返回相应的IO错误
-    pub(in crate::fs) fn write_fs_desc(&mut self) -> Result<()> {
-        self.disk.write_at(self.fs_desc.bytes(), 0)?;
+    pub(in crate::fs) fn write_super_block(&mut self) -> Result<()> {
+        self.super_block.wtime = utils::now().as_secs();
+        self.disk.write_at(&self.super_block.bytes(), 0)?;
         Ok(())
     }
 
+    /// 将第 `group` 个块组的组描述符写入组描述符表
+    pub(in crate::fs) fn write_group_desc(&mut self, group: usize) -> Result<()> {
+        self.disk.write_at(&self.groups[group].bytes(), self.addr_group_desc(group))?;
+        Ok(())
+    }
+
+    /// 第 `group` 个块组的数据块位图或索引节点位图的物理地址、名字和组描述符中记录的校验和
+    pub(in crate::fs) fn bitmap_info(&self, group: usize, map_type: BlkType) -> (u64, &'static str, u16) {
+        let desc = &self.groups[group];
+        let (blk, name, csum) = match map_type {
+            BlkType::DataBlk => (desc.block_bitmap, GroupDesc::BLOCK_BITMAP_NAME, desc.block_bitmap_csum),
+            BlkType::INode | BlkType::DirINode => (desc.inode_bitmap, GroupDesc::INODE_BITMAP_NAME, desc.inode_bitmap_csum),
+        };
+        (self.block_size() as u64 * blk as u64, name, csum)
+    }
+
+    /// 读取第 `group` 个块组的数据块位图或索引节点位图
+    ///
+    /// # 错误
+    /// [`BlockDevice::verify_checksums`] 为真并且位图与组描述符中记录的校验和不匹配时返回 `InvalidData` 错误，
+    /// 错误信息里包含位图的名字和位置
+    pub(in crate::fs) fn read_bitmap(&self, group: usize, map_type: BlkType) -> Result<Vec<u8>> {
+        let (addr, name, csum) = self.bitmap_info(group, map_type);
+        let mut map = self.empty_blk();
+        self.disk.read_at(&mut map, addr)?;
+        if self.disk.verify_checksums() && bitmap_checksum(&map) != csum {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} at byte {}: checksum mismatch", name, addr),
+            ));
+        }
+        Ok(map)
+    }
+
+    /// 写入第 `group` 个块组的数据块位图或索引节点位图，并更新组描述符中的校验和
+    ///
+    /// 组描述符由调用者写回磁盘
+    pub(in crate::fs) fn write_bitmap(&mut self, group: usize, map_type: BlkType, map: &[u8]) -> Result<()> {
+        let (addr, _, _) = self.bitmap_info(group, map_type);
+        self.disk.write_at(map, addr)?;
+        let desc = &mut self.groups[group];
+        match map_type {
+            BlkType::DataBlk => desc.block_bitmap_csum = bitmap_checksum(map),
+            BlkType::INode | BlkType::DirINode => desc.inode_bitmap_csum = bitmap_checksum(map),
+        }
+        Ok(())
+    }
+
+    /// 第 `group` 个块组的组描述符在磁盘上的物理地址
+    pub(in crate::fs) fn addr_group_desc(&self, group: usize) -> u64 {
+        self.block_size() as u64 * self.super_block.group_table as u64
+            + (group * size_of::<GroupDesc>()) as u64
+    }
+
     /// 从位图中寻找空闲位并标记为已使用
     /// 
     /// # 参数
     /// - `map`: 位图数据块的可变引用
+    /// - `from`: 从这一位开始寻找，找到末尾后再从头寻找
+    /// - `limit`: 位图中有效位的个数
     /// 
     /// # 返回值
     /// 成功时返回找到的空闲位的位置，失败时返回错误
@@ -199,24 +586,19 @@ impl Fs {
     /// 当位图已满时返回 NotFound 错误
     /// 
     /// # 算法
-    /// 遍历位图的每个字节，检查每一位是否为0（空闲），
+    /// 依次检查每一位是否为0（空闲），
     /// 找到后将该位设置为1（已使用）并返回位置
-    fn find_free_bit(map: &mut DataBlk) -> Result<u16> {
-        let mut blk = 0u16;
-        for i in 0..map.len() as usize {
-            let mut to_match: u8 = 0b1000_0000;
-            for _ in 0..8 {
-                // 找到空闲节点
-                if map[i] & to_match == 0 {
-                    // 将位图里的节点设置为 1
-                    map[i] = map[i] | to_match;
-                    return Ok(blk);
-                }
-                to_match = to_match >> 1;
-                blk += 1;
+    fn find_free_bit(map: &mut [u8], from: usize, limit: usize) -> Result<u32> {
+        for bit in (from.min(limit)..limit).chain(0..from.min(limit)) {
+            let mask = 0b1000_0000 >> (bit % 8);
+            // 找到空闲节点
+            if map[bit / 8] & mask == 0 {
+                // 将位图里的节点设置为 1
+                map[bit / 8] |= mask;
+                return Ok(bit as u32);
             }
         }
-        Err(Error::new(ErrorKind::NotFound, "Inode table is full"))
+        Err(Error::new(ErrorKind::NotFound, "Bitmap is full"))
     }
 
     /// 清除位图中指定位的使用标记
@@ -227,11 +609,11 @@ impl Fs {
     /// 
     /// # 算法
     /// 计算位在字节中的位置，创建掩码并清除对应的位
-    fn clear_used_bit(map: &mut DataBlk, bit_i: u16) {
+    fn clear_used_bit(map: &mut [u8], bit_i: u32) {
         let i = bit_i / 8;
         let bit = bit_i % 8;
         let mask = 0b1000_0000 >> bit;
-        map[i as usize] = map[i as usize] & !mask;
+        map[i as usize] &= !mask;
     }
 
     /// 将索引节点写入磁盘
@@ -246,24 +628,343 @@ impl Fs {
     /// # 错误
     /// - 当索引节点号超出范围时返回 OutOfMemory 错误
     /// - 当磁盘写入失败时返回相应的IO错误
-    pub(in crate::fs) fn write_inode(&mut self, inode_no: u16, inode: Inode) -> Result<()> {
-        let offset = self.addr_i_node(inode_no);
-        match offset >= Fs::addr_data_blk(0) {
+    pub(in crate::fs) fn write_inode(&mut self, inode_no: u32, inode: Inode) -> Result<()> {
+        match inode_no >= self.super_block.inodes_count {
             true => Err(Error::new(
                 ErrorKind::OutOfMemory,
                 "the inode_no out of bounds",
             )),
             false => {
-                self.disk.write_at(inode.bytes(), offset)?;
+                self.disk.write_at(&inode.bytes(), self.addr_i_node(inode_no))?;
                 Ok(())
             }
         }
     }
 
+    /// 在目录 `dir` 中查找文件名为 `name` 的目录项，需要对目录有执行权限
+    /// 
+    /// 有索引的目录只读取文件名哈希值对应的数据块，其它目录逐项比较
+    pub(in crate::fs) fn find_dir_entry(&self, dir: &DirEntry, name: &str) -> Result<Option<Item>> {
+        let name_arr: [u8; MAX_NAME_LEN + 1] = name.into_array()?;
+        let inode = self.get_inode(dir.i_node)?;
+        if inode.is_indexed() {
+            if !inode.i_mode.can_exec(self.user, &self.gids) {
+                return Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "Permission Denied. Need exec permission.",
+                ));
+            }
+            return self.dx_lookup(&inode, name.as_bytes());
+        }
+
+        for item in dir.iter(self)? {
+            match item? {
+                DirEntryIterItem::Using(item) if item.entry.name == name_arr => return Ok(Some(item)),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// 目录数据块中留给目录项的字节数，之后是校验和记录
+    #[inline(always)]
+    pub(in crate::fs) fn dir_block_end(&self) -> usize {
+        self.block_size() - DIR_TAIL_SIZE
+    }
+
+    /// 目录数据块 `block` 末尾应有的校验和记录
+    fn dir_tail(&self, block: &[u8]) -> [u8; DIR_TAIL_SIZE] {
+        let mut tail = [0u8; DIR_TAIL_SIZE];
+        tail[4..6].copy_from_slice(&(DIR_TAIL_SIZE as u16).to_le_bytes());
+        tail[7] = DIR_TAIL_TYPE;
+        tail[8..12].copy_from_slice(&utils::crc32c(&block[..self.dir_block_end()]).to_le_bytes());
+        tail
+    }
+
+    /// 重新计算 `addr` 所在的目录数据块的校验和记录
+    pub(in crate::fs) fn seal_dir_block(&mut self, addr: u64) -> Result<()> {
+        let block_addr = addr - addr % self.block_size() as u64;
+        let mut block = self.empty_blk();
+        self.disk.read_at(&mut block, block_addr)?;
+        let tail = self.dir_tail(&block);
+        self.disk.write_at(&tail, block_addr + self.dir_block_end() as u64)?;
+        Ok(())
+    }
+
+    /// 把目录项 `entry` 写入 `addr`，并更新所在数据块的校验和记录
+    pub(in crate::fs) fn write_dir_entry(&mut self, entry: &DirEntry, addr: u64) -> Result<()> {
+        self.disk.write_at(&entry.bytes(), addr)?;
+        self.seal_dir_block(addr)
+    }
+
+    /// 从 `block_addr` 开始的目录数据块末尾的校验和记录是否正确，不管设备是否要求校验
+    pub(in crate::fs) fn dir_block_ok(&self, block_addr: u64) -> Result<bool> {
+        let mut block = self.empty_blk();
+        self.disk.read_at(&mut block, block_addr)?;
+        Ok(block[self.dir_block_end()..] == self.dir_tail(&block))
+    }
+
+    /// [`BlockDevice::verify_checksums`] 为真时检查从 `block_addr` 开始的目录数据块的校验和记录
+    ///
+    /// # 错误
+    /// 校验和记录不正确时返回 `InvalidData` 错误
+    pub(in crate::fs) fn verify_dir_block(&self, block_addr: u64) -> Result<()> {
+        if self.disk.verify_checksums() && !self.dir_block_ok(block_addr)? {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} at byte {}: checksum mismatch", DirEntry::BLOCK_NAME, block_addr),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 读取目录 `inode` 中逻辑块号为 `blk` 的数据块里的所有记录，包括没有使用的记录
+    pub(in crate::fs) fn dir_block_entries(&self, inode: &Inode, blk: u32) -> Result<Vec<Item>> {
+        let block_size = self.block_size() as u64;
+        let end = self.dir_block_end() as u64;
+        let blk_addr = inode.convert_addr(self, blk as u64 * block_size)?;
+        self.verify_dir_block(blk_addr.addr)?;
+        let mut items = Vec::new();
+        let mut offset = 0;
+        while offset < end {
+            let addr = blk_addr.addr + offset;
+            let entry = DirEntry::from_disk(&self.disk, addr)?;
+            let rec_len = entry.rec_len as u64;
+            if rec_len < DirEntry::rec_size(entry.name_len as usize) as u64 || offset + rec_len > end {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} at byte {}: bad record length", DirEntry::NAME, addr),
+                ));
+            }
+            offset += rec_len;
+            let mut real_addr = blk_addr.clone();
+            real_addr.addr = addr;
+            items.push(Item { entry, real_addr });
+        }
+        Ok(items)
+    }
+
+    /// 在目录 `inode` 的逻辑块 `blk` 中寻找能放下 `needed` 字节的目录项的位置
+    pub(in crate::fs) fn find_dir_slot(&self, inode: &Inode, blk: u32, needed: usize) -> Result<Option<DirSlot>> {
+        Ok(self.dir_block_entries(inode, blk)?.into_iter().find_map(|Item { entry, real_addr }| {
+            match entry.file_type {
+                0 => (entry.rec_len as usize >= needed).then_some(DirSlot::Unused {
+                    addr: real_addr.addr,
+                    rec_len: entry.rec_len,
+                }),
+                _ => {
+                    let used = DirEntry::rec_size(entry.name_len as usize);
+                    (entry.rec_len as usize >= used + needed).then(|| DirSlot::Split {
+                        prev: Box::new(entry),
+                        addr: real_addr.addr,
+                    })
+                }
+            }
+        }))
+    }
+
+    /// 把目录项 `entry` 写入 `slot`，`entry.rec_len` 会被重新设置
+    pub(in crate::fs) fn fill_dir_slot(&mut self, slot: DirSlot, entry: &mut DirEntry) -> Result<()> {
+        let addr = match slot {
+            // 复用没有使用的记录
+            DirSlot::Unused { addr, rec_len } => {
+                entry.rec_len = rec_len;
+                addr
+            }
+            // 把前一个目录项的 rec_len 缩短到它实际占用的长度，剩下的空间给新目录项
+            DirSlot::Split { mut prev, addr } => {
+                let used = DirEntry::rec_size(prev.name_len as usize) as u16;
+                entry.rec_len = prev.rec_len - used;
+                prev.rec_len = used;
+                self.disk.write_at(&prev.bytes(), addr)?;
+                addr + used as u64
+            }
+        };
+        self.write_dir_entry(entry, addr)
+    }
+
+    /// 把 `entries` 依次紧密地写入目录 `inode` 的逻辑块 `blk`，最后一个目录项延伸到块末尾的校验和记录之前
+    /// 
+    /// `entries` 为空时写入一个占据整个块、没有使用的记录
+    ///
+    /// # 返回值
+    /// 每个目录项写入的物理地址
+    pub(in crate::fs) fn write_dir_block(&mut self, inode: &Inode, blk: u32, entries: &mut [DirEntry]) -> Result<Vec<u64>> {
+        let (block_size, end) = (self.block_size(), self.dir_block_end());
+        let addr = inode.convert_addr(self, (blk as usize * block_size) as u64)?.addr;
+        let mut buf = Vec::with_capacity(block_size);
+        let mut addrs = Vec::with_capacity(entries.len());
+        let last = entries.len().saturating_sub(1);
+        for (i, entry) in entries.iter_mut().enumerate() {
+            addrs.push(addr + buf.len() as u64);
+            let size = DirEntry::rec_size(entry.name_len as usize);
+            entry.rec_len = match i == last {
+                true => (end - buf.len()) as u16,
+                false => size as u16,
+            };
+            buf.extend_from_slice(&entry.bytes());
+        }
+        if entries.is_empty() {
+            let unused = DirEntry {
+                rec_len: end as u16,
+                ..Default::default()
+            };
+            buf.extend_from_slice(&unused.bytes());
+        }
+        buf.resize(block_size, 0);
+        let tail = self.dir_tail(&buf);
+        buf[end..].copy_from_slice(&tail);
+
+        self.disk.write_at(&buf, addr)?;
+        Ok(addrs)
+    }
+
+    /// 目录项被搬到了新的位置，更新通过它们打开的文件描述符中记录的目录项地址
+    ///
+    /// `moves` 中的每一项是 `(索引节点号, 原来的地址, 新的地址)`，所有的文件描述符按原来的地址一次更新完，
+    /// 这样搬到了另一个目录项原来位置上的目录项不会被更新两次
+    pub(in crate::fs) fn move_open_entries(&mut self, moves: &[(u32, u64, u64)]) {
+        for file in self.fds.iter_mut().flatten() {
+            let moved = moves.iter().find(|(inode_i, old, _)| file.inode_i == *inode_i && file.dir_entry_addr == *old);
+            if let Some(&(_, _, new)) = moved {
+                file.dir_entry_addr = new;
+            }
+        }
+    }
+
+    /// 在索引节点号为 `dir_i` 的目录中添加目录项 `entry`，`entry.rec_len` 会被重新设置
+    /// 
+    /// 有索引的目录只在文件名哈希值对应的数据块中寻找空间，见 [`Fs::dx_add_entry`]。
+    /// 其它目录优先复用没有使用的记录，其次拆分 `rec_len` 中有足够空闲空间的目录项，
+    /// 都没有时为目录分配一个新的数据块，新目录项占据整个数据块；
+    /// 只有一个数据块的目录放满时先为它建立索引，索引放不下新目录项而被丢弃时在添加之后重建它
+    pub(in crate::fs) fn add_dir_entry(&mut self, dir_i: u32, entry: &DirEntry) -> Result<()> {
+        let mut dir_inode = self.get_inode(dir_i)?;
+        let mut entry = entry.clone();
+
+        let indexed = dir_inode.is_indexed();
+        let mut added = dir_inode.is_indexed() && self.dx_add_entry(dir_i, &mut dir_inode, &mut entry)?;
+        if !added {
+            let needed = DirEntry::rec_size(entry.name_len as usize);
+            let blocks = dir_inode.i_size / self.block_size() as u32;
+            let mut slot = None;
+            for blk in 0..blocks {
+                slot = self.find_dir_slot(&dir_inode, blk, needed)?;
+                if slot.is_some() {
+                    break;
+                }
+            }
+
+            match slot {
+                Some(slot) => self.fill_dir_slot(slot, &mut entry)?,
+                None => {
+                    if blocks == 1 && self.dir_index_enabled() && self.dx_make_indexed(dir_i, &mut dir_inode)? {
+                        added = self.dx_add_entry(dir_i, &mut dir_inode, &mut entry)?;
+                    }
+                    if !added {
+                        let blk = dir_inode.i_size / self.block_size() as u32;
+                        dir_inode.alloc_data_block(self, dir_i)?;
+                        dir_inode.i_size += self.block_size() as u32;
+                        self.write_dir_block(&dir_inode, blk, std::slice::from_mut(&mut entry))?;
+                    }
+                }
+            }
+        }
+        // 重新排列之后叶子更满，两层索引通常又能放下了；事务中放不下重建时留给 fsck
+        if indexed && !dir_inode.is_indexed() {
+            self.dx_make_indexed(dir_i, &mut dir_inode)?;
+        }
+
+        dir_inode.touch_mtime();
+        self.write_inode(dir_i, dir_inode)
+    }
+
+    /// 删除索引节点号为 `dir_i` 的目录中位于 `addr` 的目录项
+    /// 
+    /// 被删除的目录项合并到同一个数据块中的前一个目录项里；它位于数据块开头时只标记为没有使用
+    pub(in crate::fs) fn remove_dir_entry(&mut self, dir_i: u32, addr: u64) -> Result<()> {
+        let mut entry = DirEntry::from_disk(&self.disk, addr)?;
+
+        // 从数据块开头开始寻找前一个目录项
+        let block_size = self.block_size() as u64;
+        let mut prev = None;
+        let mut cur = addr - addr % block_size;
+        while cur < addr {
+            let e = DirEntry::from_disk(&self.disk, cur)?;
+            if e.rec_len == 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} at byte {}: bad record length", DirEntry::NAME, cur),
+                ));
+            }
+            let next = cur + e.rec_len as u64;
+            prev = Some((e, cur));
+            cur = next;
+        }
+
+        match prev {
+            Some((mut prev, prev_addr)) => {
+                prev.rec_len += entry.rec_len;
+                self.write_dir_entry(&prev, prev_addr)?;
+            }
+            None => {
+                entry.i_node = 0;
+                entry.file_type = 0;
+                self.write_dir_entry(&entry, addr)?;
+            }
+        }
+
+        let mut dir_inode = self.get_inode(dir_i)?;
+        dir_inode.touch_mtime();
+        self.write_inode(dir_i, dir_inode)
+    }
+
+    /// 初始化新目录的第一个数据块：写入指向自己的 `.` 和指向父目录的 `..`
+    /// 
+    /// `..` 的 `rec_len` 延伸到数据块末尾的校验和记录之前
+    pub(in crate::fs) fn init_dir_block(&mut self, blk_i: u32, dir_i: u32, parent_i: u32) -> Result<DirEntry> {
+        let addr = self.addr_data_blk(blk_i);
+        let dot = DirEntry {
+            i_node: dir_i,
+            rec_len: DirEntry::rec_size(1) as u16,
+            name_len: 1,
+            file_type: FileType::Dir.into(),
+            name: ".".into_array()?,
+            checksum: 0,
+        };
+        let dot_dot = DirEntry {
+            i_node: parent_i,
+            rec_len: (self.dir_block_end() - DirEntry::rec_size(1)) as u16,
+            name_len: 2,
+            file_type: FileType::Dir.into(),
+            name: "..".into_array()?,
+            checksum: 0,
+        };
+        self.disk.write_at(&dot.bytes(), addr)?;
+        self.write_dir_entry(&dot_dot, addr + dot.rec_len as u64)?;
+        Ok(dot)
+    }
+
+    /// 为新目录挑选块组
+    /// 
+    /// 在还有空闲索引节点的块组里，选择目录最少的那个（目录个数相同时选空闲块最多的），
+    /// 让目录树尽量均匀地分散到各个块组
+    fn find_dir_group(&self) -> usize {
+        self.groups
+            .iter()
+            .enumerate()
+            .filter(|(_, g)| g.free_inodes_count > 0)
+            .min_by_key(|(_, g)| (g.used_dirs_count, std::cmp::Reverse(g.free_blocks_count)))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
     /// 分配空闲的数据块或索引节点
     /// 
     /// # 参数
     /// - `alloc_type`: 分配类型（数据块或索引节点）
+    /// - `goal`: 优先在这个索引节点所在的块组里分配，
+    ///   分配索引节点时传入父目录的索引节点号，分配数据块时传入文件自身的索引节点号
     /// 
     /// # 返回值
     /// 成功时返回分配的块号，失败时返回错误信息
@@ -273,39 +974,82 @@ impl Fs {
     /// - 当位图操作失败时返回相应错误
     /// 
     /// # 算法
-    /// 1. 根据分配类型选择对应的位图和计数器
-    /// 2. 检查是否有空闲空间
-    /// 3. 读取位图，寻找空闲位并标记
-    /// 4. 更新计数器和文件系统描述符
-    pub(in crate::fs) fn alloc(&mut self, alloc_type: BlkType) -> Result<u16> {
-        let (map_blk, free_count) = match alloc_type {
-            BlkType::DataBlk => (
-                self.fs_desc.block_bitmap,
-                &mut self.fs_desc.free_blocks_count,
-            ),
-            BlkType::INode => (
-                self.fs_desc.inode_bitmap,
-                &mut self.fs_desc.free_inodes_count,
-            ),
+    /// 1. 确定起始块组：目录的索引节点由 [`Fs::find_dir_group`] 挑选，其余的使用 `goal` 所在的块组
+    /// 2. 从起始块组开始依次检查每个块组是否有空闲空间
+    /// 3. 读取该块组的位图，寻找空闲位并标记
+    /// 4. 更新组描述符和超级块中的计数器
+    pub(in crate::fs) fn alloc(&mut self, alloc_type: BlkType, goal: u32) -> Result<u32> {
+        let start = match alloc_type {
+            BlkType::DataBlk | BlkType::INode => (goal / self.super_block.inodes_per_group) as usize,
+            BlkType::DirINode => self.find_dir_group(),
         };
+        self.alloc_from(alloc_type, start, 0)
+    }
 
-        if *free_count == 0 {
-            return Err(Error::new(ErrorKind::Other, "No space to alloc"));
+    /// 在数据块 `goal` 附近分配一个空闲的数据块
+    /// 
+    /// 优先分配 `goal` 本身，其次是同一块组中 `goal` 之后的数据块，
+    /// 用来让文件的数据块在物理上尽量连续
+    pub(in crate::fs) fn alloc_near(&mut self, goal: u32) -> Result<u32> {
+        let per_group = self.super_block.blocks_per_group;
+        match (goal / per_group) as usize {
+            group if group < self.groups.len() => {
+                self.alloc_from(BlkType::DataBlk, group, (goal % per_group) as usize)
+            }
+            _ => self.alloc_from(BlkType::DataBlk, 0, 0),
         }
+    }
+
+    /// 从第 `start` 个块组的第 `start_bit` 位开始寻找空闲位，找不到时依次检查之后的块组
+    fn alloc_from(&mut self, alloc_type: BlkType, start: usize, start_bit: usize) -> Result<u32> {
+        let groups_count = self.groups.len();
+        let per_group = match alloc_type {
+            BlkType::DataBlk => self.super_block.blocks_per_group,
+            BlkType::INode | BlkType::DirINode => self.super_block.inodes_per_group,
+        };
+
+        for (i, group) in (0..groups_count).map(|i| (i, (start + i) % groups_count)) {
+            let desc = &self.groups[group];
+            let (limit, free_count) = match alloc_type {
+                BlkType::DataBlk => (self.super_block.group_blocks(group), desc.free_blocks_count),
+                BlkType::INode | BlkType::DirINode => {
+                    (self.super_block.inodes_per_group as usize, desc.free_inodes_count)
+                }
+            };
+
+            if free_count == 0 {
+                continue;
+            }
 
-        // 读取位图
-        let mut bit_map = utils::empty_blk();
-        self.disk
-            .read_at(&mut bit_map, map_blk as u64 * BLOCK_SIZE as u64)?;
+            // 读取位图
+            let mut bit_map = self.read_bitmap(group, alloc_type)?;
 
-        // 寻找空的数据块，将对应的位设置成 1
-        let blk = Self::find_free_bit(&mut bit_map)?;
-        self.disk.write_at(&bit_map, map_blk as u64 * BLOCK_SIZE as u64)?;
+            // 寻找空的数据块，将对应的位设置成 1
+            let from = if i == 0 { start_bit } else { 0 };
+            let bit = Self::find_free_bit(&mut bit_map, from, limit)?;
+            self.write_bitmap(group, alloc_type, &bit_map)?;
+            let desc = &mut self.groups[group];
+            match alloc_type {
+                BlkType::DataBlk => desc.free_blocks_count -= 1,
+                BlkType::INode | BlkType::DirINode => desc.free_inodes_count -= 1,
+            }
 
-        *free_count -= 1;
-        self.write_fs_desc()?;
+            match alloc_type {
+                BlkType::DataBlk => self.super_block.free_blocks_count -= 1,
+                BlkType::INode => self.super_block.free_inodes_count -= 1,
+                BlkType::DirINode => {
+                    self.groups[group].used_dirs_count += 1;
+                    self.super_block.free_inodes_count -= 1;
+                    self.super_block.used_dirs_count += 1;
+                }
+            }
+            self.write_group_desc(group)?;
+            self.write_super_block()?;
 
-        Ok(blk)
+            return Ok(group as u32 * per_group + bit);
+        }
+
+        Err(Error::other("No space to alloc"))
     }
 
     /// 释放数据块或索引节点
@@ -321,34 +1065,51 @@ impl Fs {
     /// 当位图操作或磁盘写入失败时返回相应错误
     /// 
     /// # 算法
-    /// 1. 根据释放类型选择对应的位图和计数器
-    /// 2. 读取位图
+    /// 1. 按照块组将要释放的块号分类
+    /// 2. 读取每个块组对应的位图
     /// 3. 清除指定位的使用标记
-    /// 4. 更新计数器和文件系统描述符
-    pub(in crate::fs) fn free(&mut self, free_type: BlkType, nodes_i: &[u16]) -> Result<()> {
-        let (map_blk, free_count) = match free_type {
-            BlkType::DataBlk => (
-                self.fs_desc.block_bitmap,
-                &mut self.fs_desc.free_blocks_count,
-            ),
-            BlkType::INode => (
-                self.fs_desc.inode_bitmap,
-                &mut self.fs_desc.free_inodes_count,
-            ),
+    /// 4. 更新组描述符和超级块中的计数器
+    pub(in crate::fs) fn free(&mut self, free_type: BlkType, nodes_i: &[u32]) -> Result<()> {
+        let per_group = match free_type {
+            BlkType::DataBlk => self.super_block.blocks_per_group,
+            BlkType::INode | BlkType::DirINode => self.super_block.inodes_per_group,
         };
 
-        let mut bit_map = utils::empty_blk();
-        self.disk
-            .read_at(&mut bit_map, map_blk as u64 * BLOCK_SIZE as u64)?;
-
-        for &bit_i in nodes_i {
-            Self::clear_used_bit(&mut bit_map, bit_i);
+        // 按块组分类
+        let mut by_group = std::collections::BTreeMap::<usize, Vec<u32>>::new();
+        for &node_i in nodes_i {
+            by_group
+                .entry((node_i / per_group) as usize)
+                .or_default()
+                .push(node_i % per_group);
         }
 
-        self.disk.write_at(&bit_map, map_blk as u64 * BLOCK_SIZE as u64)?;
+        for (group, bits) in by_group {
+            let mut bit_map = self.read_bitmap(group, free_type)?;
+            for &bit_i in &bits {
+                Self::clear_used_bit(&mut bit_map, bit_i);
+            }
+            self.write_bitmap(group, free_type, &bit_map)?;
+
+            let desc = &mut self.groups[group];
+            match free_type {
+                BlkType::DataBlk => desc.free_blocks_count += bits.len() as u16,
+                BlkType::INode | BlkType::DirINode => desc.free_inodes_count += bits.len() as u16,
+            }
+
+            match free_type {
+                BlkType::DataBlk => self.super_block.free_blocks_count += bits.len() as u32,
+                BlkType::INode => self.super_block.free_inodes_count += bits.len() as u32,
+                BlkType::DirINode => {
+                    self.groups[group].used_dirs_count -= bits.len() as u32;
+                    self.super_block.free_inodes_count += bits.len() as u32;
+                    self.super_block.used_dirs_count -= bits.len() as u32;
+                }
+            }
+            self.write_group_desc(group)?;
+        }
 
-        *free_count += nodes_i.len() as u16;
-        self.write_fs_desc()?;
+        self.write_super_block()?;
 
         Ok(())
     }
@@ -364,5 +1125,5 @@ pub struct PathParseRes {
     /// 目录项在磁盘上的物理地址
     pub(in crate::fs) dir_entry_addr: u64,
     /// 父目录对应的索引节点号
-    pub(in crate::fs) parent_inode_i: u16,
+    pub(in crate::fs) parent_inode_i: u32,
 }
@@ -0,0 +1,687 @@
+//! # 文件系统检查模块
+//!
+//! [`Fs::fsck`] 检查磁盘上的元数据是否一致，并且可以修复发现的问题：
+//!
+//! 1. 从根目录出发，用 [`DirEntryIterator`] 遍历所有目录，统计每个索引节点被多少个目录项引用
+//! 2. 在索引节点位图中已经分配、但是从根目录访问不到的索引节点是孤立的（orphan），
+//!    修复时把它们放入 `/lost+found`，以 `#索引节点号` 命名
+//! 3. 根据访问到的索引节点重新计算数据块位图和索引节点位图，与磁盘上的位图比较
+//! 4. 检查每个索引节点的 `i_links_count` 是否等于引用它的目录项个数
+//! 5. 检查组描述符和超级块中的空闲块数、空闲索引节点数和目录数
+//! 6. 启用了校验和时，检查访问到的索引节点、目录项、目录数据块、位图以及组描述符和超级块的校验和，
+//!    修复时按照现有内容重新计算校验和
+//! 7. 启用了目录索引时，检查有索引的目录能否通过索引找到每个目录项，以及超过一个数据块的目录是否有索引，
+//!    修复时分步重建索引
+//!
+//! 目录树被当作唯一可信的信息：目录项引用了位图中空闲的索引节点时，修复的是位图。
+//! 块号越界和一个数据块被多个文件使用这两类问题只会报告，不会自动修复。
+
+use super::*;
+use std::fmt;
+
+/// lost+found 目录的路径
+const LOST_FOUND: &str = "/lost+found";
+
+/// 文件系统检查发现的问题
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FsckProblem {
+    /// 目录项引用的索引节点号超出范围，修复时删除这个目录项
+    BadEntry { path: String, inode: u32 },
+    /// 文件使用的块号超出范围
+    BadBlock { inode: u32, block: u32 },
+    /// 数据块已经被其它文件使用
+    DuplicateBlock { inode: u32, block: u32 },
+    /// 块组的数据块位图中有 `count` 位与实际的使用情况不符
+    BlockBitmap { group: usize, count: u32 },
+    /// 块组的索引节点位图中有 `count` 位与实际的使用情况不符
+    InodeBitmap { group: usize, count: u32 },
+    /// 索引节点的硬链接数与引用它的目录项个数不符
+    LinksCount {
+        inode: u32,
+        found: u16,
+        expected: u16,
+    },
+    /// 索引节点已经分配，但是没有任何目录项引用它
+    Orphan { inode: u32 },
+    /// 组描述符中的空闲块数、空闲索引节点数或目录数不正确
+    GroupCounts { group: usize },
+    /// 超级块中的空闲块数、空闲索引节点数或目录数不正确
+    SuperBlockCounts,
+    /// 位于 `addr` 的结构体（[`IntoBytes::NAME`]、[`DirEntry::BLOCK_NAME`] 或者位图的名字）的校验和不正确
+    Checksum { name: &'static str, addr: u64 },
+    /// 目录的哈希索引损坏或者与目录项不一致，修复时重建索引
+    DirIndex { path: String },
+    /// 超过一个数据块的目录没有哈希索引（索引放不下新的目录项时被丢弃了），修复时重建索引
+    MissingDirIndex { path: String },
+}
+
+impl FsckProblem {
+    /// 这个问题能否被自动修复
+    pub fn fixable(&self) -> bool {
+        !matches!(self, Self::BadBlock { .. } | Self::DuplicateBlock { .. })
+    }
+}
+
+impl fmt::Display for FsckProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadEntry { path, inode } => {
+                write!(f, "{}: entry points to invalid inode {}", path, inode)
+            }
+            Self::BadBlock { inode, block } => {
+                write!(f, "inode {}: block {} out of range", inode, block)
+            }
+            Self::DuplicateBlock { inode, block } => {
+                write!(
+                    f,
+                    "inode {}: block {} is shared with another file",
+                    inode, block
+                )
+            }
+            Self::BlockBitmap { group, count } => {
+                write!(f, "group {}: {} wrong bits in block bitmap", group, count)
+            }
+            Self::InodeBitmap { group, count } => {
+                write!(f, "group {}: {} wrong bits in inode bitmap", group, count)
+            }
+            Self::LinksCount {
+                inode,
+                found,
+                expected,
+            } => write!(
+                f,
+                "inode {}: links count is {}, should be {}",
+                inode, found, expected
+            ),
+            Self::Orphan { inode } => write!(f, "inode {}: not referenced by any directory", inode),
+            Self::GroupCounts { group } => {
+                write!(f, "group {}: wrong free or directory counts", group)
+            }
+            Self::SuperBlockCounts => write!(f, "super block: wrong free or directory counts"),
+            Self::Checksum { name, addr } => write!(f, "{} at byte {}: checksum mismatch", name, addr),
+            Self::DirIndex { path } => write!(f, "{}: directory index is corrupt", path),
+            Self::MissingDirIndex { path } => write!(f, "{}: directory is not indexed", path),
+        }
+    }
+}
+
+/// 文件系统检查的结果
+#[derive(Clone, Debug, Default)]
+pub struct FsckReport {
+    /// 发现的所有问题
+    pub problems: Vec<FsckProblem>,
+    /// 是否已经修复了能够修复的问题
+    pub repaired: bool,
+}
+
+impl FsckReport {
+    /// 文件系统是否没有任何问题
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// 检查过程中收集的信息
+struct Checker {
+    /// 每个索引节点被目录项引用的次数，不含 `.` 和 `..`
+    refs: Vec<u16>,
+    /// 从根目录或者孤立的索引节点出发能访问到的索引节点
+    reached: Vec<bool>,
+    /// 索引节点是否是目录
+    dirs: Vec<bool>,
+    /// 被文件使用的数据块
+    blocks: Vec<bool>,
+    /// 引用了非法索引节点的目录项：（所在目录的索引节点号，目录项的地址）
+    bad_entries: Vec<(u32, u64)>,
+    /// 需要放入 lost+found 的孤立索引节点
+    orphans: Vec<u32>,
+    /// 是否检查校验和
+    checksums: bool,
+    /// 校验和不正确的索引节点
+    bad_inode_csums: Vec<u32>,
+    /// 校验和不正确的目录项的地址
+    bad_entry_csums: Vec<u64>,
+    /// 末尾的校验和记录不正确的目录数据块的地址
+    bad_dir_blocks: Vec<u64>,
+    /// 哈希索引损坏、需要先丢弃的目录
+    bad_indexes: Vec<u32>,
+    /// 需要重建哈希索引的目录
+    reindex: Vec<u32>,
+    /// 发现的问题
+    problems: Vec<FsckProblem>,
+}
+
+/// 位图中的第 `bit` 位是否为 1
+pub(in crate::fs) fn test_bit(map: &[u8], bit: usize) -> bool {
+    map[bit / 8] & (0b1000_0000 >> (bit % 8)) != 0
+}
+
+/// 将位图中的第 `bit` 位设置为 `used`
+fn set_bit(map: &mut [u8], bit: usize, used: bool) {
+    let mask = 0b1000_0000 >> (bit % 8);
+    match used {
+        true => map[bit / 8] |= mask,
+        false => map[bit / 8] &= !mask,
+    }
+}
+
+impl Fs {
+    /// 检查文件系统的一致性，`repair` 为真时修复发现的问题
+    ///
+    /// 修复在一个事务中完成，只有 root 才能修复
+    pub fn fsck(&mut self, repair: bool) -> Result<FsckReport> {
+        if repair && self.user != 0 {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Only root can repair",
+            ));
+        }
+
+        // 校验和不正确的结构体由检查自己报告，检查期间读取时不做校验
+        let verify = self.disk.verify_checksums();
+        self.disk.set_checksums(false);
+        let res = match repair {
+            true => self.transaction(|fs| fs._fsck(true)),
+            false => self._fsck(false),
+        };
+        self.disk.set_checksums(verify);
+        res
+    }
+
+    /// 检查并修复文件系统
+    fn _fsck(&mut self, repair: bool) -> Result<FsckReport> {
+        let inodes_count = self.super_block.inodes_count as usize;
+        let mut checker = Checker {
+            refs: vec![0; inodes_count],
+            reached: vec![false; inodes_count],
+            dirs: vec![false; inodes_count],
+            blocks: vec![false; self.super_block.blocks_count as usize],
+            bad_entries: Vec::new(),
+            orphans: Vec::new(),
+            checksums: self.super_block.feature_ro_compat & FEATURE_RO_COMPAT_METADATA_CSUM != 0,
+            bad_inode_csums: Vec::new(),
+            bad_entry_csums: Vec::new(),
+            bad_dir_blocks: Vec::new(),
+            bad_indexes: Vec::new(),
+            reindex: Vec::new(),
+            problems: Vec::new(),
+        };
+
+        // 1. 从根目录出发遍历目录树
+        checker.reached[0] = true;
+        checker.dirs[0] = true;
+        self.fsck_walk(&mut checker, 0, "/".into())?;
+
+        // 2. 位图中已经分配、但是访问不到的索引节点是孤立的，孤立的目录中的文件也要遍历
+        let inode_maps = self.fsck_read_bitmaps(BlkType::INode)?;
+        let per_group = self.super_block.inodes_per_group as usize;
+        let mut unreached = Vec::new();
+        for i in 0..inodes_count {
+            if !checker.reached[i] && test_bit(&inode_maps[i / per_group], i % per_group) {
+                checker.reached[i] = true;
+                checker.dirs[i] = self.fsck_looks_like_dir(i as u32)?;
+                unreached.push(i as u32);
+            }
+        }
+        for &i in &unreached {
+            if checker.dirs[i as usize] {
+                self.fsck_walk(&mut checker, i, format!("<{}>", i))?;
+            }
+        }
+        // 没有被其它孤立目录引用的才需要放入 lost+found
+        for i in unreached {
+            if checker.refs[i as usize] == 0 {
+                checker.problems.push(FsckProblem::Orphan { inode: i });
+                checker.orphans.push(i);
+            }
+        }
+
+        // 3. 统计文件使用的数据块，0 号数据块总是保留
+        checker.blocks[HOLE_BLK as usize] = true;
+        for i in 0..inodes_count {
+            if checker.reached[i] {
+                self.fsck_blocks(&mut checker, i as u32)?;
+
+                let addr = self.addr_i_node(i as u32);
+                if checker.checksums && !checksum_ok::<Inode>(&self.disk, addr)? {
+                    checker.problems.push(FsckProblem::Checksum { name: Inode::NAME, addr });
+                    checker.bad_inode_csums.push(i as u32);
+                }
+            }
+        }
+
+        // 4. 检查位图的校验和，然后重新计算位图和计数
+        let mut block_maps = self.fsck_read_bitmaps(BlkType::DataBlk)?;
+        let mut inode_maps = inode_maps;
+        if checker.checksums {
+            for group in 0..self.groups.len() {
+                for (map_type, map) in [(BlkType::DataBlk, &block_maps[group]), (BlkType::INode, &inode_maps[group])] {
+                    let (addr, name, csum) = self.bitmap_info(group, map_type);
+                    if bitmap_checksum(map) != csum {
+                        checker.problems.push(FsckProblem::Checksum { name, addr });
+                    }
+                }
+            }
+        }
+        let (mut free_blocks, mut free_inodes, mut used_dirs) = (0, 0, 0);
+        let mut groups = self.groups.clone();
+        for (group, desc) in groups.iter_mut().enumerate() {
+            let blocks = self.super_block.group_blocks(group);
+            let block_base = group * self.super_block.blocks_per_group as usize;
+            let block_map = &mut block_maps[group];
+            let mut count = 0;
+            for bit in 0..blocks {
+                let used = checker.blocks[block_base + bit];
+                count += (test_bit(block_map, bit) != used) as u32;
+                set_bit(block_map, bit, used);
+            }
+            if count > 0 {
+                checker
+                    .problems
+                    .push(FsckProblem::BlockBitmap { group, count });
+            }
+
+            let inode_base = group * per_group;
+            let inode_map = &mut inode_maps[group];
+            let mut count = 0;
+            for bit in 0..per_group {
+                let used = checker.reached[inode_base + bit];
+                count += (test_bit(inode_map, bit) != used) as u32;
+                set_bit(inode_map, bit, used);
+            }
+            if count > 0 {
+                checker
+                    .problems
+                    .push(FsckProblem::InodeBitmap { group, count });
+            }
+
+            let used = |map: &[bool], base: usize, len: usize| {
+                map[base..base + len].iter().filter(|&&b| b).count() as u32
+            };
+            let counts = (
+                blocks as u32 - used(&checker.blocks, block_base, blocks),
+                per_group as u32 - used(&checker.reached, inode_base, per_group),
+                (inode_base..inode_base + per_group)
+                    .filter(|&i| checker.reached[i] && checker.dirs[i])
+                    .count() as u32,
+            );
+            let addr = self.addr_group_desc(group);
+            if checker.checksums && !checksum_ok::<GroupDesc>(&self.disk, addr)? {
+                checker.problems.push(FsckProblem::Checksum { name: GroupDesc::NAME, addr });
+            }
+            if counts
+                != (
+                    desc.free_blocks_count as u32,
+                    desc.free_inodes_count as u32,
+                    desc.used_dirs_count,
+                )
+            {
+                checker.problems.push(FsckProblem::GroupCounts { group });
+            }
+            desc.free_blocks_count = counts.0 as u16;
+            desc.free_inodes_count = counts.1 as u16;
+            desc.used_dirs_count = counts.2;
+            free_blocks += counts.0;
+            free_inodes += counts.1;
+            used_dirs += counts.2;
+        }
+        let sb = &self.super_block;
+        if (free_blocks, free_inodes, used_dirs)
+            != (
+                sb.free_blocks_count,
+                sb.free_inodes_count,
+                sb.used_dirs_count,
+            )
+        {
+            checker.problems.push(FsckProblem::SuperBlockCounts);
+        }
+        if checker.checksums && !checksum_ok::<SuperBlock>(&self.disk, 0)? {
+            checker.problems.push(FsckProblem::Checksum { name: SuperBlock::NAME, addr: 0 });
+        }
+
+        // 5. 检查硬链接数：根目录没有父目录，固定为 1；孤立的索引节点修复后由 lost+found 引用
+        let mut links = Vec::new();
+        for i in 0..inodes_count {
+            if !checker.reached[i] || (checker.refs[i] == 0 && i != 0) {
+                continue;
+            }
+            let expected = if i == 0 { 1 } else { checker.refs[i] };
+            let found = self.get_inode(i as u32)?.i_links_count;
+            if found != expected {
+                checker.problems.push(FsckProblem::LinksCount {
+                    inode: i as u32,
+                    found,
+                    expected,
+                });
+                links.push((i as u32, expected));
+            }
+        }
+        for &i in &checker.orphans {
+            links.push((i, 1));
+        }
+
+        let repaired = repair && checker.problems.iter().any(FsckProblem::fixable);
+        if repaired {
+            self.fsck_repair(&checker, (block_maps, inode_maps), groups, links)?;
+            self.super_block.free_blocks_count = free_blocks;
+            self.super_block.free_inodes_count = free_inodes;
+            self.super_block.used_dirs_count = used_dirs;
+            self.write_super_block()?;
+            self.fsck_reconnect(&checker)?;
+            // 重建索引会分配数据块，要在位图修复之后进行
+            for &dir_i in &checker.reindex {
+                self.dx_reindex(dir_i)?;
+            }
+        }
+
+        Ok(FsckReport {
+            problems: checker.problems,
+            repaired,
+        })
+    }
+
+    /// 从索引节点号为 `dir_i`、路径为 `path` 的目录出发遍历目录树
+    fn fsck_walk(&self, checker: &mut Checker, dir_i: u32, path: String) -> Result<()> {
+        let mut stack = vec![(dir_i, path)];
+        while let Some((dir_i, path)) = stack.pop() {
+            let dir = DirEntry {
+                i_node: dir_i,
+                file_type: FileType::Dir.into(),
+                ..Default::default()
+            };
+            for item in dir.iter_without_limit(self)? {
+                let item = item?;
+                let (DirEntryIterItem::Using(Item { real_addr, .. })
+                | DirEntryIterItem::Deleted(Item { real_addr, .. })) = &item;
+                let addr = real_addr.addr;
+                if checker.checksums && addr % self.block_size() as u64 == 0 && !self.dir_block_ok(addr)? {
+                    checker.problems.push(FsckProblem::Checksum { name: DirEntry::BLOCK_NAME, addr });
+                    checker.bad_dir_blocks.push(addr);
+                }
+                if checker.checksums && !checksum_ok::<DirEntry>(&self.disk, addr)? {
+                    checker.problems.push(FsckProblem::Checksum { name: DirEntry::NAME, addr });
+                    checker.bad_entry_csums.push(addr);
+                }
+
+                let DirEntryIterItem::Using(Item { entry, real_addr }) = item else {
+                    continue;
+                };
+                let name = utils::str(&entry.name);
+                if name == "." || name == ".." {
+                    continue;
+                }
+
+                let child_path = format!("{}/{}", path.trim_end_matches('/'), name);
+                let i = entry.i_node as usize;
+                if i >= checker.refs.len() {
+                    checker.problems.push(FsckProblem::BadEntry {
+                        path: child_path,
+                        inode: entry.i_node,
+                    });
+                    checker.bad_entries.push((dir_i, real_addr.addr));
+                    continue;
+                }
+
+                checker.refs[i] = checker.refs[i].saturating_add(1);
+                if checker.reached[i] {
+                    continue;
+                }
+                checker.reached[i] = true;
+                if entry.file_type == u8::from(FileType::Dir) {
+                    checker.dirs[i] = true;
+                    stack.push((entry.i_node, child_path));
+                }
+            }
+
+            let inode = self.get_inode(dir_i)?;
+            if inode.is_indexed() && !self.dx_check(&inode, checker.checksums)? {
+                checker.problems.push(FsckProblem::DirIndex { path });
+                checker.bad_indexes.push(dir_i);
+                checker.reindex.push(dir_i);
+            } else if !inode.is_indexed()
+                && self.dir_index_enabled()
+                && inode.i_size as usize > self.block_size()
+                && self.dx_can_index(&inode)?
+            {
+                checker.problems.push(FsckProblem::MissingDirIndex { path });
+                checker.reindex.push(dir_i);
+            }
+        }
+        Ok(())
+    }
+
+    /// 孤立的索引节点没有目录项记录文件类型，第一个目录项是指向自己的 `.` 时认为它是目录
+    fn fsck_looks_like_dir(&self, inode_i: u32) -> Result<bool> {
+        let inode = self.get_inode(inode_i)?;
+        if inode.i_blocks == 0 || (inode.i_size as usize) < self.block_size() {
+            return Ok(false);
+        }
+        let addr = inode.convert_addr(self, 0)?;
+        if addr.is_hole() {
+            return Ok(false);
+        }
+        let entry = DirEntry::from_disk(&self.disk, addr.addr)?;
+        Ok(entry.name == ".".into_array()? && entry.i_node == inode_i)
+    }
+
+    /// 统计索引节点 `inode_i` 使用的数据块，包括索引块和区段树的结点块
+    fn fsck_blocks(&self, checker: &mut Checker, inode_i: u32) -> Result<()> {
+        // 标记数据块，返回块号是否合法
+        let claim = |checker: &mut Checker, block: u32| match checker.blocks.get_mut(block as usize)
+        {
+            None => {
+                checker.problems.push(FsckProblem::BadBlock {
+                    inode: inode_i,
+                    block,
+                });
+                false
+            }
+            Some(true) => {
+                checker.problems.push(FsckProblem::DuplicateBlock {
+                    inode: inode_i,
+                    block,
+                });
+                true
+            }
+            Some(used) => {
+                *used = true;
+                true
+            }
+        };
+
+        let inode = self.get_inode(inode_i)?;
+        if inode.uses_extents() {
+            let mut stack = vec![inode.extent_root()];
+            while let Some(node) = stack.pop() {
+                for e in &node.entries {
+                    if node.depth == 0 {
+                        for block in e.start..e.start.saturating_add(e.len) {
+                            claim(checker, block);
+                        }
+                    } else if claim(checker, e.start) {
+                        stack.push(self.read_extent_node(e.start)?);
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        // 直接索引，以及一级、二级、三级索引
+        let mut stack: Vec<_> = inode.i_block[..6].iter().map(|&blk| (blk, 0)).collect();
+        stack.extend((1..=3).map(|depth| (inode.i_block[5 + depth], depth)));
+        while let Some((blk, depth)) = stack.pop() {
+            if blk == HOLE_BLK || !claim(checker, blk) || depth == 0 {
+                continue;
+            }
+            stack.extend(
+                self.read_addrs(blk)?
+                    .into_iter()
+                    .map(|addr| (addr, depth - 1)),
+            );
+        }
+        Ok(())
+    }
+
+    /// 读取每个块组的数据块位图或索引节点位图
+    pub(in crate::fs) fn fsck_read_bitmaps(&self, map_type: BlkType) -> Result<Vec<Vec<u8>>> {
+        (0..self.groups.len()).map(|group| self.read_bitmap(group, map_type)).collect()
+    }
+
+    /// 删除非法的目录项，写回重新计算的位图和组描述符，修正硬链接数和校验和
+    ///
+    /// 组描述符和超级块总是会被重新写入，写入时会重新计算它们的校验和
+    fn fsck_repair(
+        &mut self,
+        checker: &Checker,
+        (block_maps, inode_maps): (Vec<Vec<u8>>, Vec<Vec<u8>>),
+        groups: Vec<GroupDesc>,
+        links: Vec<(u32, u16)>,
+    ) -> Result<()> {
+        for &addr in &checker.bad_entry_csums {
+            self.ensure_room(STEP_BLOCKS)?;
+            let entry = DirEntry::from_disk(&self.disk, addr)?;
+            self.write_dir_entry(&entry, addr)?;
+        }
+        for &addr in &checker.bad_dir_blocks {
+            self.ensure_room(STEP_BLOCKS)?;
+            self.seal_dir_block(addr)?;
+        }
+        for &inode_i in &checker.bad_inode_csums {
+            self.ensure_room(STEP_BLOCKS)?;
+            let inode = self.get_inode(inode_i)?;
+            self.write_inode(inode_i, inode)?;
+        }
+
+        for &(dir_i, addr) in &checker.bad_entries {
+            self.ensure_room(STEP_BLOCKS)?;
+            self.remove_dir_entry(dir_i, addr)?;
+        }
+        for &dir_i in &checker.bad_indexes {
+            let mut inode = self.get_inode(dir_i)?;
+            inode.i_flags &= !INODE_FLAG_INDEX;
+            self.write_inode(dir_i, inode)?;
+        }
+
+        // 修复可能修改大量的块，每修复一处之后都可以先提交；位图和记录它们校验和的组描述符一起写入
+        self.groups = groups;
+        for (group, (block_map, inode_map)) in block_maps.iter().zip(&inode_maps).enumerate() {
+            self.ensure_room(STEP_BLOCKS)?;
+            self.write_bitmap(group, BlkType::DataBlk, block_map)?;
+            self.write_bitmap(group, BlkType::INode, inode_map)?;
+            self.write_group_desc(group)?;
+        }
+
+        for (inode_i, links_count) in links {
+            self.ensure_room(STEP_BLOCKS)?;
+            let mut inode = self.get_inode(inode_i)?;
+            inode.i_links_count = links_count;
+            self.write_inode(inode_i, inode)?;
+        }
+        Ok(())
+    }
+
+    /// 把孤立的索引节点放入 lost+found，这个目录不存在时先创建它
+    fn fsck_reconnect(&mut self, checker: &Checker) -> Result<()> {
+        if checker.orphans.is_empty() {
+            return Ok(());
+        }
+
+        let lost_found = match self.path_parse(LOST_FOUND) {
+            Ok(res) => res.dir_entry,
+            Err(_) => {
+                self.mkdir(LOST_FOUND)?;
+                self.path_parse(LOST_FOUND)?.dir_entry
+            }
+        };
+        if !matches!(lost_found.file_type.into(), FileType::Dir) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "lost+found is not a directory",
+            ));
+        }
+
+        for &inode_i in &checker.orphans {
+            self.ensure_room(STEP_BLOCKS)?;
+            let name = format!("#{}", inode_i);
+            let is_dir = checker.dirs[inode_i as usize];
+            let file_type = match is_dir {
+                true => FileType::Dir,
+                false => FileType::File,
+            };
+            let entry = DirEntry {
+                i_node: inode_i,
+                rec_len: 0,
+                name_len: name.len() as u8,
+                file_type: file_type.into(),
+                name: name.as_str().into_array()?,
+                checksum: 0,
+            };
+            self.add_dir_entry(lost_found.i_node, &entry)?;
+
+            // 目录的 .. 改为指向 lost+found
+            if is_dir {
+                let addr = self
+                    .get_inode(inode_i)?
+                    .convert_addr(self, DirEntry::rec_size(1) as u64)?
+                    .addr;
+                let mut parent = DirEntry::from_disk(&self.disk, addr)?;
+                parent.i_node = lost_found.i_node;
+                self.write_dir_entry(&parent, addr)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_fsck() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.mkdir("dir").unwrap();
+    fs.create("dir/a").unwrap();
+    let fd = fs.open_with("dir/a", OpenOptions::new().read(true).write(true)).unwrap();
+    fs.write(fd, &[7u8; 2000]).unwrap();
+    fs.link("dir/a", "b").unwrap();
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 制造几个问题：位图中泄漏一个数据块，错误的硬链接数，
+    // 以及把 dir 的目录项删掉，使得 dir 和其中的文件变成孤立的
+    let dir_i = fs.path_parse("dir").unwrap().dir_entry.i_node;
+    let a_i = fs.path_parse("b").unwrap().dir_entry.i_node;
+    let free_blocks = fs.super_block.free_blocks_count;
+    fs.alloc(BlkType::DataBlk, 0).unwrap();
+    let mut inode = fs.get_inode(a_i).unwrap();
+    inode.i_links_count = 5;
+    fs.write_inode(a_i, inode).unwrap();
+    let res = fs.path_parse("dir").unwrap();
+    fs.remove_dir_entry(0, res.dir_entry_addr).unwrap();
+
+    let report = fs.fsck(false).unwrap();
+    assert!(!report.repaired);
+    assert!(report
+        .problems
+        .contains(&FsckProblem::Orphan { inode: dir_i }));
+    assert!(report
+        .problems
+        .contains(&FsckProblem::BlockBitmap { group: 0, count: 1 }));
+    assert!(report.problems.contains(&FsckProblem::LinksCount {
+        inode: a_i,
+        found: 5,
+        expected: 2,
+    }));
+    assert!(report
+        .problems
+        .contains(&FsckProblem::GroupCounts { group: 0 }));
+
+    // 修复之后 dir 出现在 lost+found 中，再次检查没有任何问题
+    let report = fs.fsck(true).unwrap();
+    assert!(report.repaired);
+    assert!(fs.fsck(false).unwrap().is_clean());
+    let name = format!("/lost+found/#{}/a", dir_i);
+    let fd = fs.open_with(&name, OpenOptions::new().read(true).write(true)).unwrap();
+    let mut buf = [0u8; 2000];
+    assert_eq!(fs.read(fd, &mut buf).unwrap(), 2000);
+    assert!(buf.iter().all(|&b| b == 7));
+    assert_eq!(fs.get_inode(a_i).unwrap().i_links_count, 2);
+    // lost+found 自己占用一个数据块
+    assert_eq!(fs.super_block.free_blocks_count, free_blocks - 1);
+}
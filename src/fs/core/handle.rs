@@ -0,0 +1,184 @@
+//! # 文件句柄模块
+//!
+//! [`FileHandle`] 借用 [`Fs`] 和其中的一个文件描述符，实现了 [`std::io::Read`]、[`std::io::Write`]、
+//! [`std::io::Seek`] 和 [`std::io::BufRead`]，这样普通的 Rust 代码可以像操作宿主机上的文件一样
+//! 操作磁盘镜像中的文件，例如用 [`std::io::copy`] 在宿主机和模拟的文件系统之间复制数据：
+//!
+//! ```
+//! # use simulate_unixlike_fs::fs::*;
+//! use std::io::{BufRead, Write};
+//!
+//! let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+//! let mut file = fs.open_handle("a.txt", OpenOptions::new().write(true).create(true)).unwrap();
+//! writeln!(file, "hello").unwrap();
+//! drop(file);
+//!
+//! let file = fs.open_handle("a.txt", OpenOptions::new().read(true)).unwrap();
+//! let lines: Vec<_> = file.lines().map(|line| line.unwrap()).collect();
+//! assert_eq!(lines, ["hello"]);
+//! ```
+//!
+//! 句柄被丢弃时自动关闭文件描述符。
+
+use super::*;
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+
+/// 读缓冲区的大小
+const HANDLE_BUF_SIZE: usize = 4096;
+
+/// 打开的文件，丢弃时关闭文件描述符
+pub struct FileHandle<'a> {
+    /// 文件所在的文件系统
+    fs: &'a mut Fs,
+    /// 文件描述符
+    fd: usize,
+    /// 读缓冲区，`BufRead` 需要
+    buf: Box<[u8]>,
+    /// 缓冲区中下一个没有读取的字节
+    pos: usize,
+    /// 缓冲区中有效数据的长度
+    filled: usize,
+}
+
+impl<'a> FileHandle<'a> {
+    /// 文件描述符
+    pub fn fd(&self) -> usize {
+        self.fd
+    }
+
+    /// 丢弃读缓冲区中还没有读取的数据，并把文件指针移回到这些数据的开头
+    ///
+    /// 写入和移动文件指针之前都要调用，保证文件指针和调用者看到的位置一致
+    fn discard_buffer(&mut self) {
+        if let Some(file) = self.fs.fds[self.fd].as_mut() {
+            file.current_pos -= self.filled - self.pos;
+        }
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl Fs {
+    /// 按照 `options` 打开路径名为 `path` 的文件，返回实现了 [`std::io`] 读写 trait 的文件句柄
+    pub fn open_handle(&mut self, path: &str, options: &OpenOptions) -> Result<FileHandle<'_>> {
+        let fd = self.open_with(path, options)?;
+        Ok(FileHandle {
+            fs: self,
+            fd,
+            buf: vec![0; HANDLE_BUF_SIZE].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        })
+    }
+}
+
+impl Read for FileHandle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // 缓冲区为空并且要读取的数据比缓冲区还大时直接读取，不经过缓冲区
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return self.fs.read(self.fd, buf);
+        }
+
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for FileHandle<'_> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.pos == self.filled {
+            self.filled = self.fs.read(self.fd, &mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl Write for FileHandle<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.discard_buffer();
+        self.fs.write(self.fd, buf)
+    }
+
+    /// 写入直接交给文件系统，句柄本身不缓存数据，这里把块缓存中的脏块写回磁盘
+    fn flush(&mut self) -> Result<()> {
+        self.fs.flush()
+    }
+}
+
+impl Seek for FileHandle<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        self.discard_buffer();
+        let file = self.fs.fds[self.fd].as_ref().unwrap();
+        let new_pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(offset) => {
+                let size = self.fs.get_inode(file.inode_i)?.i_size as u64;
+                size.checked_add_signed(offset)
+            }
+            SeekFrom::Current(offset) => (file.current_pos as u64).checked_add_signed(offset),
+        };
+
+        let Some(new_pos) = new_pos else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Seek failed. Can't set cursor of file to negative",
+            ));
+        };
+        self.fs.fds[self.fd].as_mut().unwrap().current_pos = new_pos as usize;
+        Ok(new_pos)
+    }
+}
+
+impl Drop for FileHandle<'_> {
+    fn drop(&mut self) {
+        let _ = self.fs.close(self.fd);
+    }
+}
+
+#[test]
+fn test_file_handle() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+
+    // 从宿主机复制到镜像中，再复制回来
+    let data: Vec<u8> = (0..20000u32).map(|i| (i * 7 % 251) as u8).collect();
+    let mut file = fs.open_handle("data", OpenOptions::new().write(true).create(true)).unwrap();
+    assert_eq!(std::io::copy(&mut &data[..], &mut file).unwrap(), data.len() as u64);
+    drop(file);
+    assert_eq!(fs.opened_len, 0);
+
+    let mut file = fs.open_handle("data", OpenOptions::new().read(true)).unwrap();
+    let mut copied = Vec::new();
+    std::io::copy(&mut file, &mut copied).unwrap();
+    assert_eq!(copied, data);
+
+    // 移动文件指针
+    assert_eq!(file.seek(SeekFrom::End(-10)).unwrap(), data.len() as u64 - 10);
+    let mut buf = [0u8; 4];
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, data[data.len() - 10..data.len() - 6]);
+    assert_eq!(file.seek(SeekFrom::Current(-4)).unwrap(), data.len() as u64 - 10);
+    assert_eq!(file.seek(SeekFrom::Start(3)).unwrap(), 3);
+    assert!(file.seek(SeekFrom::Current(-4)).is_err());
+    drop(file);
+
+    // 读缓冲区中预读的数据不会影响写入的位置
+    let mut file = fs.open_handle("lines", OpenOptions::new().read(true).write(true).create(true)).unwrap();
+    file.write_all(b"one\ntwo\nthree\n").unwrap();
+    file.rewind().unwrap();
+    let mut line = String::new();
+    file.read_line(&mut line).unwrap();
+    assert_eq!(line, "one\n");
+    file.write_all(b"TWO").unwrap();
+    file.rewind().unwrap();
+    let mut content = String::new();
+    file.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "one\nTWO\nthree\n");
+}
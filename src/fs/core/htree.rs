@@ -0,0 +1,738 @@
+//! # 目录索引（htree）模块
+//!
+//! 没有索引的目录查找文件时只能逐项比较，目录很大时非常慢。启用了 [`FEATURE_COMPAT_DIR_INDEX`]
+//! 的文件系统在目录超过一个数据块时为它建立类似 ext3 的哈希索引，并设置 [`INODE_FLAG_INDEX`] 标志：
+//! 查找时先计算文件名的哈希值，再沿着索引找到唯一可能存放这个文件名的数据块。
+//!
+//! ## 索引的结构
+//!
+//! - 目录的第 0 个逻辑块是根结点：开头仍然是 `.` 和 `..`，`..` 的 `rec_len` 延伸到块末尾的校验和记录之前，
+//!   索引就放在 `..` 之后的空闲空间里：8 字节的索引信息（哈希算法、层数），之后是索引项
+//! - 中间结点各占一个数据块：块的开头是一个没有使用、占据整个块的目录项，之后是索引项
+//! - 每个索引项 8 字节：哈希值和逻辑块号，表示哈希值不小于它的文件名（直到下一个索引项）
+//!   存放在这个逻辑块里。第一个索引项的哈希值总是 0，这个位置改为存放索引项的上限和个数
+//! - 结点中校验和记录之前的 4 字节是索引部分的 CRC32C 校验和，结点所在的数据块同样以目录数据块的校验和记录结尾
+//! - 叶子就是普通的目录数据块，哈希值相同的文件名总是在同一个叶子里
+//!
+//! 索引都藏在目录项的空闲空间里，所以按顺序遍历有索引的目录仍然能得到所有目录项。
+//! 索引放不下新的叶子时会被丢弃，随后把目录项按哈希值紧凑地重新排列，重建索引。
+//! 事务中放不下重建时目录暂时退回到逐项比较，内容不受影响，[`Fs::fsck`] 修复时会分步重建索引。
+
+use super::*;
+
+/// 文件名哈希算法：CRC32C
+const DX_HASH_CRC32C: u8 = 1;
+
+/// 根结点中索引信息的长度
+const DX_INFO_SIZE: usize = 8;
+
+/// 每个索引项在磁盘上占用的字节数
+const DX_ENTRY_SIZE: usize = 8;
+
+/// 索引部分末尾校验和的长度
+const DX_TAIL_SIZE: usize = 4;
+
+/// 索引树最多的层数（不含叶子）
+const DX_MAX_LEVELS: u8 = 2;
+
+/// 根结点中索引部分的起始位置：紧跟在 `.` 和 `..` 之后
+const DX_ROOT_BASE: usize = DirEntry::rec_size(1) + DirEntry::rec_size(2);
+
+/// 中间结点中索引部分的起始位置：紧跟在没有使用的目录项之后
+const DX_NODE_BASE: usize = DIR_ENTRY_HEADER_SIZE;
+
+/// 重建索引时每一步最多搬动的目录项个数
+const DX_MOVE_STEP: usize = 16;
+
+/// 重建索引时除了目录的数据块之外最多还会修改的块数：索引节点、位图、组描述符、超级块和间接块
+const DX_REBUILD_EXTRA: u64 = 8;
+
+/// 索引项
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(in crate::fs) struct DxEntry {
+    /// 这个逻辑块中文件名哈希值的下界
+    pub hash: u32,
+    /// 目录中的逻辑块号，指向下一层的结点或者叶子
+    pub block: u32,
+}
+
+/// 索引树的一个结点
+#[derive(Clone, Debug, Default)]
+pub(in crate::fs) struct DxNode {
+    /// 结点所在的逻辑块号，根结点为 0
+    pub block: u32,
+    /// 根结点记录的索引层数，1 表示根结点直接指向叶子；中间结点中不使用
+    pub levels: u8,
+    /// 结点中的索引项，按哈希值升序排列
+    pub entries: Vec<DxEntry>,
+}
+
+impl DxNode {
+    /// 是否是根结点
+    fn is_root(&self) -> bool {
+        self.block == 0
+    }
+
+    /// 索引部分在数据块中的起始位置
+    fn base(&self) -> usize {
+        match self.is_root() {
+            true => DX_ROOT_BASE,
+            false => DX_NODE_BASE,
+        }
+    }
+
+    /// 索引项在数据块中的起始位置
+    fn entries_base(&self) -> usize {
+        match self.is_root() {
+            true => DX_ROOT_BASE + DX_INFO_SIZE,
+            false => DX_NODE_BASE,
+        }
+    }
+
+    /// 哈希值 `hash` 应该继续查找的索引项的位置
+    fn find(&self, hash: u32) -> usize {
+        self.entries.partition_point(|e| e.hash <= hash).saturating_sub(1)
+    }
+}
+
+/// 重建索引时要搬动的目录项：（原来所在的逻辑块号，原来的地址，目录项）
+type DxMovedEntry = (u32, u64, DirEntry);
+
+/// 重建索引时目录项的排列方式
+struct DxLayout {
+    /// 每个叶子的第一个哈希值，以及放入这个叶子的目录项
+    leaves: Vec<(u32, Vec<DxMovedEntry>)>,
+    /// 中间结点的个数，为 0 时根结点直接指向叶子
+    nodes: usize,
+}
+
+/// 文件名的哈希值
+pub(in crate::fs) fn dx_hash(name: &[u8]) -> u32 {
+    utils::crc32c(name)
+}
+
+impl Inode {
+    /// 目录是否有哈希索引
+    #[inline(always)]
+    pub fn is_indexed(&self) -> bool {
+        self.i_flags & INODE_FLAG_INDEX != 0
+    }
+}
+
+impl Fs {
+    /// 是否为超过一个数据块的目录建立索引
+    pub(in crate::fs) fn dir_index_enabled(&self) -> bool {
+        self.super_block.feature_compat & FEATURE_COMPAT_DIR_INDEX != 0
+    }
+
+    /// 一个结点最多能存放的索引项个数
+    fn dx_limit(&self, node: &DxNode) -> usize {
+        (self.dir_block_end() - node.entries_base() - DX_TAIL_SIZE) / DX_ENTRY_SIZE
+    }
+
+    /// 读取目录 `inode` 中逻辑块号为 `block` 的索引结点，`verify` 为真时检查校验和
+    pub(in crate::fs) fn dx_read(&self, inode: &Inode, block: u32, verify: bool) -> Result<DxNode> {
+        let block_size = self.block_size();
+        let addr = inode.convert_addr(self, block as u64 * block_size as u64)?.addr;
+        let mut buf = self.empty_blk();
+        self.disk.read_at(&mut buf, addr)?;
+
+        let mut node = DxNode { block, ..Default::default() };
+        let base = node.base();
+        let tail = self.dir_block_end() - DX_TAIL_SIZE;
+        if verify && u32::from_le_bytes(buf[tail..tail + DX_TAIL_SIZE].try_into().unwrap()) != utils::crc32c(&buf[base..tail]) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Directory index at byte {}: checksum mismatch", addr),
+            ));
+        }
+
+        let bad = || Error::new(ErrorKind::InvalidData, "Bad directory index");
+        if node.is_root() {
+            let info = &buf[base..base + DX_INFO_SIZE];
+            if info[4] != DX_HASH_CRC32C || info[5] as usize != DX_INFO_SIZE || !(1..=DX_MAX_LEVELS).contains(&info[6]) {
+                return Err(bad());
+            }
+            node.levels = info[6];
+        }
+
+        let word = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+        let entries_base = node.entries_base();
+        let (limit, count) = (word(entries_base) & 0xFFFF, word(entries_base) >> 16);
+        if limit as usize != self.dx_limit(&node) || count == 0 || count > limit {
+            return Err(bad());
+        }
+        node.entries = (0..count as usize)
+            .map(|i| DxEntry {
+                hash: if i == 0 { 0 } else { word(entries_base + i * DX_ENTRY_SIZE) },
+                block: word(entries_base + i * DX_ENTRY_SIZE + 4),
+            })
+            .collect();
+        let blocks = inode.i_size / block_size as u32;
+        if node.entries.iter().any(|e| e.block == 0 || e.block >= blocks) {
+            return Err(bad());
+        }
+        Ok(node)
+    }
+
+    /// 写入目录 `inode` 的索引结点 `node`
+    ///
+    /// 根结点只改写 `..` 之后的部分，中间结点改写整个数据块，最后更新数据块的校验和记录
+    fn dx_write(&mut self, inode: &Inode, node: &DxNode) -> Result<()> {
+        let (block_size, end) = (self.block_size(), self.dir_block_end());
+        let mut buf = self.empty_blk();
+        if node.is_root() {
+            buf[DX_ROOT_BASE + 4] = DX_HASH_CRC32C;
+            buf[DX_ROOT_BASE + 5] = DX_INFO_SIZE as u8;
+            buf[DX_ROOT_BASE + 6] = node.levels;
+        } else {
+            let fake = DirEntry {
+                rec_len: end as u16,
+                ..Default::default()
+            };
+            buf[..DX_NODE_BASE].copy_from_slice(&fake.bytes());
+        }
+
+        let entries_base = node.entries_base();
+        let head = self.dx_limit(node) as u32 | (node.entries.len() as u32) << 16;
+        for (i, e) in node.entries.iter().enumerate() {
+            let hash = if i == 0 { head } else { e.hash };
+            let pos = entries_base + i * DX_ENTRY_SIZE;
+            buf[pos..pos + 4].copy_from_slice(&hash.to_le_bytes());
+            buf[pos + 4..pos + 8].copy_from_slice(&e.block.to_le_bytes());
+        }
+
+        let base = node.base();
+        let tail = end - DX_TAIL_SIZE;
+        let checksum = utils::crc32c(&buf[base..tail]);
+        buf[tail..end].copy_from_slice(&checksum.to_le_bytes());
+
+        let start = match node.is_root() {
+            true => DX_ROOT_BASE,
+            false => 0,
+        };
+        let addr = inode.convert_addr(self, node.block as u64 * block_size as u64)?.addr;
+        self.disk.write_at(&buf[start..end], addr + start as u64)?;
+        self.seal_dir_block(addr)
+    }
+
+    /// 从根结点出发，找到哈希值 `hash` 所在的叶子
+    ///
+    /// # 返回值
+    /// 经过的每个结点，以及在结点中选中的索引项的位置；最后一个索引项指向叶子
+    fn dx_path(&self, inode: &Inode, hash: u32) -> Result<Vec<(DxNode, usize)>> {
+        let verify = self.disk.verify_checksums();
+        let root = self.dx_read(inode, 0, verify)?;
+        let levels = root.levels as usize;
+        let mut node = root;
+        let mut path = Vec::with_capacity(levels);
+        loop {
+            let pos = node.find(hash);
+            let next = node.entries[pos].block;
+            path.push((node, pos));
+            if path.len() == levels {
+                return Ok(path);
+            }
+            node = self.dx_read(inode, next, verify)?;
+        }
+    }
+
+    /// 在有索引的目录 `inode` 中查找文件名为 `name` 的目录项
+    pub(in crate::fs) fn dx_lookup(&self, inode: &Inode, name: &[u8]) -> Result<Option<Item>> {
+        // `.` 和 `..` 在根结点里，不在索引中
+        let block = match name {
+            b"." | b".." => 0,
+            _ => {
+                let path = self.dx_path(inode, dx_hash(name))?;
+                let (node, pos) = path.last().unwrap();
+                node.entries[*pos].block
+            }
+        };
+        Ok(self
+            .dir_block_entries(inode, block)?
+            .into_iter()
+            .find(|item| item.entry.file_type != 0 && &item.entry.name[..item.entry.name_len as usize] == name))
+    }
+
+    /// 把目录 `inode` 中除了 `.` 和 `..` 之外的目录项按哈希值排序，尽量紧凑地装入叶子
+    ///
+    /// 哈希值相同的目录项一个叶子放不下，或者叶子太多、两层索引放不下时返回 `None`
+    fn dx_layout(&self, inode: &Inode) -> Result<Option<DxLayout>> {
+        let mut entries = Vec::new();
+        for blk in 0..inode.i_size / self.block_size() as u32 {
+            // 第 0 个数据块开头的 `.` 和 `..` 留在根结点里
+            let skip = if blk == 0 { 2 } else { 0 };
+            for Item { entry, real_addr } in self.dir_block_entries(inode, blk)?.into_iter().skip(skip) {
+                if entry.file_type != 0 {
+                    entries.push((dx_hash(&entry.name[..entry.name_len as usize]), blk, real_addr.addr, entry));
+                }
+            }
+        }
+        entries.sort_by_key(|(hash, ..)| *hash);
+
+        let end = self.dir_block_end();
+        let mut leaves = vec![(0, Vec::new())];
+        let mut used = 0;
+        for group in entries.chunk_by(|a, b| a.0 == b.0) {
+            let size: usize = group.iter().map(|(.., e)| DirEntry::rec_size(e.name_len as usize)).sum();
+            if size > end {
+                return Ok(None);
+            }
+            if used + size > end {
+                leaves.push((group[0].0, Vec::new()));
+                used = 0;
+            }
+            used += size;
+            leaves.last_mut().unwrap().1.extend(group.iter().map(|(_, blk, addr, e)| (*blk, *addr, e.clone())));
+        }
+
+        let root_limit = self.dx_limit(&DxNode::default());
+        let node_limit = self.dx_limit(&DxNode { block: 1, ..Default::default() });
+        let nodes = match leaves.len() <= root_limit {
+            true => 0,
+            false => leaves.len().div_ceil(node_limit),
+        };
+        Ok((nodes <= root_limit).then_some(DxLayout { leaves, nodes }))
+    }
+
+    /// 目录 `inode` 中的目录项能否按索引排列
+    pub(in crate::fs) fn dx_can_index(&self, inode: &Inode) -> Result<bool> {
+        Ok(self.dx_layout(inode)?.is_some())
+    }
+
+    /// 在当前事务中为目录 `dir_i` 建立索引，原有的目录项按哈希值重新排列到叶子里
+    ///
+    /// 不会提前提交事务，可以在其它修改的中间调用；目录项没法按索引排列，
+    /// 或者事务中剩余的空间不够时不做任何修改，返回 `false`
+    pub(in crate::fs) fn dx_make_indexed(&mut self, dir_i: u32, inode: &mut Inode) -> Result<bool> {
+        let Some(layout) = self.dx_layout(inode)? else {
+            return Ok(false);
+        };
+        let blocks = (inode.i_size / self.block_size() as u32) as u64;
+        if self.disk.room() < blocks + (layout.leaves.len() + layout.nodes) as u64 + DX_REBUILD_EXTRA {
+            return Ok(false);
+        }
+        self.dx_rebuild(dir_i, inode, layout, false)?;
+        Ok(true)
+    }
+
+    /// 重新为目录 `dir_i` 建立索引，返回目录项能否按索引排列
+    ///
+    /// 事务中的空间不够时分步提交，只能在磁盘上的状态一致的地方调用
+    pub(in crate::fs) fn dx_reindex(&mut self, dir_i: u32) -> Result<bool> {
+        let mut inode = self.get_inode(dir_i)?;
+        let Some(layout) = self.dx_layout(&inode)? else {
+            return Ok(false);
+        };
+        self.dx_rebuild(dir_i, &mut inode, layout, true)?;
+        Ok(true)
+    }
+
+    /// 按照 `layout` 重新排列目录 `dir_i` 并写入索引，`steps` 为真时每一步之前都可以先提交
+    ///
+    /// 写入根结点之前目录都没有索引，每一步只把几个目录项从原来的位置搬到没有目录项的数据块里，
+    /// 所以每一步结束时每个目录项都恰好出现一次，中途提交或者崩溃只会留下一个没有索引的目录
+    fn dx_rebuild(&mut self, dir_i: u32, inode: &mut Inode, layout: DxLayout, steps: bool) -> Result<()> {
+        let blocks = (inode.i_size / self.block_size() as u32) as usize;
+        let mut left = vec![0; blocks];
+        for (blk, ..) in layout.leaves.iter().flat_map(|(_, entries)| entries) {
+            left[*blk as usize] += 1;
+        }
+        let mut free: Vec<u32> = (1..blocks as u32).rev().filter(|&blk| left[blk as usize] == 0).collect();
+        inode.i_flags &= !INODE_FLAG_INDEX;
+        self.write_inode(dir_i, inode.clone())?;
+
+        let mut leaves = Vec::with_capacity(layout.leaves.len());
+        for (hash, entries) in layout.leaves {
+            if steps {
+                self.ensure_room(STEP_BLOCKS)?;
+            }
+            let block = self.dx_take_block(dir_i, inode, &mut free)?;
+            leaves.push(DxEntry { hash, block });
+            let mut placed = Vec::with_capacity(entries.len());
+            for chunk in entries.chunks(DX_MOVE_STEP) {
+                if steps {
+                    self.ensure_room(STEP_BLOCKS)?;
+                }
+                placed.extend(chunk.iter().map(|(.., e)| e.clone()));
+                let new_addrs = self.write_dir_block(inode, block, &mut placed)?;
+                let mut moves = Vec::with_capacity(chunk.len());
+                for ((blk, old, entry), &new) in chunk.iter().zip(&new_addrs[new_addrs.len() - chunk.len()..]) {
+                    self.remove_dir_entry(dir_i, *old)?;
+                    moves.push((entry.i_node, *old, new));
+                    left[*blk as usize] -= 1;
+                    if left[*blk as usize] == 0 && *blk != 0 {
+                        free.push(*blk);
+                    }
+                }
+                self.move_open_entries(&moves);
+            }
+        }
+
+        let mut root = DxNode {
+            block: 0,
+            levels: 1,
+            entries: leaves,
+        };
+        if layout.nodes > 0 {
+            let node_limit = self.dx_limit(&DxNode { block: 1, ..Default::default() });
+            let mut entries = Vec::with_capacity(layout.nodes);
+            for chunk in root.entries.chunks(node_limit) {
+                if steps {
+                    self.ensure_room(STEP_BLOCKS)?;
+                }
+                // 中间结点在按顺序遍历时是没有目录项的数据块，写入根结点之前不会被用到
+                let block = self.dx_take_block(dir_i, inode, &mut free)?;
+                let node = DxNode {
+                    block,
+                    levels: 0,
+                    entries: chunk.to_vec(),
+                };
+                self.dx_write(inode, &node)?;
+                entries.push(DxEntry { hash: chunk[0].hash, block });
+            }
+            root.levels = 2;
+            root.entries = entries;
+        }
+
+        if steps {
+            self.ensure_room(STEP_BLOCKS)?;
+        }
+        // `..` 延伸到块末尾的校验和记录之前，把索引藏在它的空闲空间里
+        let root_addr = inode.convert_addr(self, 0)?.addr;
+        let dot_dot_addr = root_addr + DirEntry::rec_size(1) as u64;
+        let mut dot_dot = DirEntry::from_disk(&self.disk, dot_dot_addr)?;
+        dot_dot.rec_len = (self.dir_block_end() - DirEntry::rec_size(1)) as u16;
+        self.write_dir_entry(&dot_dot, dot_dot_addr)?;
+        self.dx_write(inode, &root)?;
+        inode.i_flags |= INODE_FLAG_INDEX;
+        self.write_inode(dir_i, inode.clone())
+    }
+
+    /// 取出一个没有目录项的数据块，没有时在目录末尾追加一个
+    fn dx_take_block(&mut self, dir_i: u32, inode: &mut Inode, free: &mut Vec<u32>) -> Result<u32> {
+        if let Some(block) = free.pop() {
+            return Ok(block);
+        }
+        let block = self.dx_append_block(dir_i, inode)?;
+        // 之后可能先提交，新的数据块要先写成空的目录数据块并记录到索引节点中
+        self.write_dir_block(inode, block, &mut [])?;
+        self.write_inode(dir_i, inode.clone())?;
+        Ok(block)
+    }
+
+    /// 在有索引的目录 `dir_i` 中添加目录项 `entry`，`entry.rec_len` 会被重新设置
+    ///
+    /// # 返回值
+    /// 返回 `false` 表示没能按索引放下这个目录项，这时索引已经被丢弃，
+    /// 调用者应该按照没有索引的目录添加它，之后重建索引
+    ///
+    /// # 算法
+    /// 1. 按照文件名的哈希值找到叶子，叶子里有足够的空间时直接放入
+    /// 2. 否则把叶子中的目录项连同新目录项按哈希值排序，后一半搬到新分配的叶子里
+    /// 3. 在父结点中为新叶子添加索引项，父结点放满时拆分它，或者为索引树增加一层
+    pub(in crate::fs) fn dx_add_entry(&mut self, dir_i: u32, inode: &mut Inode, entry: &mut DirEntry) -> Result<bool> {
+        let name = &entry.name[..entry.name_len as usize];
+        let path = self.dx_path(inode, dx_hash(name))?;
+        let (node, pos) = path.last().unwrap();
+        let leaf = node.entries[*pos].block;
+
+        let needed = DirEntry::rec_size(entry.name_len as usize);
+        if let Some(slot) = self.find_dir_slot(inode, leaf, needed)? {
+            self.fill_dir_slot(slot, entry)?;
+            return Ok(true);
+        }
+
+        let Some(split) = self.dx_split_leaf(dir_i, inode, leaf, entry)? else {
+            inode.i_flags &= !INODE_FLAG_INDEX;
+            return Ok(false);
+        };
+        if !self.dx_insert(dir_i, inode, path, split)? {
+            // 新目录项已经写入了叶子，只是索引放不下了
+            inode.i_flags &= !INODE_FLAG_INDEX;
+        }
+        Ok(true)
+    }
+
+    /// 为目录 `dir_i` 在末尾追加一个数据块，返回它的逻辑块号
+    fn dx_append_block(&mut self, dir_i: u32, inode: &mut Inode) -> Result<u32> {
+        let block = inode.i_size / self.block_size() as u32;
+        inode.alloc_data_block(self, dir_i)?;
+        inode.i_size += self.block_size() as u32;
+        Ok(block)
+    }
+
+    /// 把叶子 `leaf` 中的目录项和新目录项 `entry` 分到两个叶子里
+    ///
+    /// # 返回值
+    /// 新叶子的索引项；所有目录项的哈希值都相同，或者怎样分都放不下时返回 `None`，这时目录没有被修改
+    fn dx_split_leaf(&mut self, dir_i: u32, inode: &mut Inode, leaf: u32, entry: &DirEntry) -> Result<Option<DxEntry>> {
+        // 新目录项还没有写入过，没有原来的地址
+        let mut entries: Vec<(u32, Option<u64>, DirEntry)> = self
+            .dir_block_entries(inode, leaf)?
+            .into_iter()
+            .filter(|item| item.entry.file_type != 0)
+            .map(|item| (Some(item.real_addr.addr), item.entry))
+            .chain([(None, entry.clone())])
+            .map(|(addr, e)| (dx_hash(&e.name[..e.name_len as usize]), addr, e))
+            .collect();
+        entries.sort_by_key(|(hash, _, _)| *hash);
+
+        // 在哈希值变化的位置中，选择让两个叶子中较满的那个尽量空的位置
+        let sizes: Vec<usize> = entries.iter().map(|(_, _, e)| DirEntry::rec_size(e.name_len as usize)).collect();
+        let total: usize = sizes.iter().sum();
+        let mut lower = 0;
+        let mut best: Option<(usize, usize)> = None;
+        for i in 1..entries.len() {
+            lower += sizes[i - 1];
+            let fuller = lower.max(total - lower);
+            if entries[i].0 != entries[i - 1].0 && fuller <= self.dir_block_end() && best.is_none_or(|(_, f)| fuller < f) {
+                best = Some((i, fuller));
+            }
+        }
+        let Some((split, _)) = best else {
+            return Ok(None);
+        };
+
+        let hash = entries[split].0;
+        let (upper_addrs, mut upper): (Vec<_>, Vec<_>) = entries.split_off(split).into_iter().map(|(_, a, e)| (a, e)).unzip();
+        let (lower_addrs, mut lower): (Vec<_>, Vec<_>) = entries.into_iter().map(|(_, a, e)| (a, e)).unzip();
+        let block = self.dx_append_block(dir_i, inode)?;
+        let mut moves = Vec::new();
+        for (blk, entries, old_addrs) in [(leaf, &mut lower, lower_addrs), (block, &mut upper, upper_addrs)] {
+            let new_addrs = self.write_dir_block(inode, blk, entries)?;
+            for ((entry, old), new) in entries.iter().zip(old_addrs).zip(new_addrs) {
+                if let Some(old) = old {
+                    moves.push((entry.i_node, old, new));
+                }
+            }
+        }
+        self.move_open_entries(&moves);
+        Ok(Some(DxEntry { hash, block }))
+    }
+
+    /// 把新叶子的索引项 `new` 插入到 `path` 最后一个结点中选中的索引项之后
+    ///
+    /// # 返回值
+    /// 索引树已经满了，放不下新的索引项时返回 `false`
+    fn dx_insert(&mut self, dir_i: u32, inode: &mut Inode, mut path: Vec<(DxNode, usize)>, new: DxEntry) -> Result<bool> {
+        let (mut parent, pos) = path.pop().unwrap();
+        parent.entries.insert(pos + 1, new);
+        if parent.entries.len() <= self.dx_limit(&parent) {
+            self.dx_write(inode, &parent)?;
+            return Ok(true);
+        }
+
+        if parent.is_root() {
+            // 根结点放满了：所有索引项搬到一个新的中间结点里，索引树增加一层
+            if parent.levels == DX_MAX_LEVELS {
+                return Ok(false);
+            }
+            let block = self.dx_append_block(dir_i, inode)?;
+            let node = DxNode {
+                block,
+                levels: 0,
+                entries: std::mem::take(&mut parent.entries),
+            };
+            parent.entries = vec![DxEntry { hash: 0, block }];
+            parent.levels += 1;
+            self.dx_write(inode, &node)?;
+            self.dx_write(inode, &parent)?;
+            return Ok(true);
+        }
+
+        // 中间结点放满了：后一半搬到新的中间结点里，在根结点中为它添加索引项
+        let (mut root, root_pos) = path.pop().unwrap();
+        if root.entries.len() >= self.dx_limit(&root) {
+            return Ok(false);
+        }
+        let block = self.dx_append_block(dir_i, inode)?;
+        let upper = DxNode {
+            block,
+            levels: 0,
+            entries: parent.entries.split_off(parent.entries.len() / 2),
+        };
+        root.entries.insert(root_pos + 1, DxEntry { hash: upper.entries[0].hash, block });
+        self.dx_write(inode, &parent)?;
+        self.dx_write(inode, &upper)?;
+        self.dx_write(inode, &root)?;
+        Ok(true)
+    }
+
+    /// 检查有索引的目录 `inode` 中的每个目录项能否通过索引找到，`verify` 为真时检查校验和
+    ///
+    /// 索引损坏或者与目录项不一致时返回 `false`
+    pub(in crate::fs) fn dx_check(&self, inode: &Inode, verify: bool) -> Result<bool> {
+        let res = (|| -> Result<bool> {
+            let root = self.dx_read(inode, 0, verify)?;
+            let mut nodes = vec![root];
+            let mut leaves = vec![false; (inode.i_size / self.block_size() as u32) as usize];
+            while let Some(node) = nodes.pop() {
+                for e in &node.entries {
+                    match node.is_root() && node.levels > 1 {
+                        true => nodes.push(self.dx_read(inode, e.block, verify)?),
+                        false => leaves[e.block as usize] = true,
+                    }
+                }
+            }
+
+            for (block, is_leaf) in leaves.into_iter().enumerate() {
+                for (i, item) in self.dir_block_entries(inode, block as u32)?.into_iter().enumerate() {
+                    let entry = &item.entry;
+                    if entry.file_type == 0 {
+                        continue;
+                    }
+                    // 根结点中只能有 `.` 和 `..`
+                    if block == 0 {
+                        if i >= 2 {
+                            return Ok(false);
+                        }
+                        continue;
+                    }
+                    let path = self.dx_path(inode, dx_hash(&entry.name[..entry.name_len as usize]))?;
+                    let (node, pos) = path.last().unwrap();
+                    if !is_leaf || node.entries[*pos].block != block as u32 {
+                        return Ok(false);
+                    }
+                }
+            }
+            Ok(true)
+        })();
+        match res {
+            Err(e) if e.kind() == ErrorKind::InvalidData => Ok(false),
+            res => res,
+        }
+    }
+}
+
+#[test]
+fn test_htree() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.mkdir("big").unwrap();
+    fs.chdir("big").unwrap();
+    let names: Vec<String> = (0..3000).map(|i| format!("file-{}", i)).collect();
+    for name in &names {
+        fs.create(name).unwrap();
+    }
+
+    let dir = fs.cwd.clone();
+    let inode = fs.get_inode(dir.i_node).unwrap();
+    assert!(inode.is_indexed());
+    assert_eq!(fs.dx_read(&inode, 0, true).unwrap().levels, 2);
+    assert!(fs.dx_check(&inode, true).unwrap());
+    for name in &names {
+        assert!(fs.open(name).is_ok_and(|fd| fs.close(fd).is_ok()));
+    }
+    assert_eq!(
+        fs.create("file-1234").unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+
+    // 删除一半文件之后再建新的，按顺序遍历和按索引查找的结果一致
+    for name in names.iter().step_by(2) {
+        let fd = fs.open(name).unwrap();
+        fs.rm(fd).unwrap();
+    }
+    for i in 0..500 {
+        fs.create(&format!("new-{}", i)).unwrap();
+    }
+    let inode = fs.get_inode(dir.i_node).unwrap();
+    assert!(fs.dx_check(&inode, true).unwrap());
+    let count = dir
+        .iter(&fs)
+        .unwrap()
+        .filter(|item| matches!(item, Ok(DirEntryIterItem::Using(_))))
+        .count();
+    assert_eq!(count, 2 + 1500 + 500);
+    assert!(fs.open("file-0").is_err());
+    assert!(fs.open("file-1").is_ok_and(|fd| fs.close(fd).is_ok()));
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 索引损坏时查找失败，fsck 重建索引之后又能按索引查找
+    let addr = inode.convert_addr(&fs, 0).unwrap().addr + (DX_ROOT_BASE + DX_INFO_SIZE + 4) as u64;
+    fs.disk.write_at(&[0xFF; 4], addr).unwrap();
+    fs.seal_dir_block(addr).unwrap();
+    assert_eq!(fs.open("file-1").unwrap_err().kind(), ErrorKind::InvalidData);
+    let report = fs.fsck(true).unwrap();
+    assert!(matches!(&report.problems[..], [FsckProblem::DirIndex { path }] if path == "/big"));
+    let inode = fs.get_inode(dir.i_node).unwrap();
+    assert!(inode.is_indexed() && fs.dx_check(&inode, true).unwrap());
+    assert!(fs.open("file-1").is_ok_and(|fd| fs.close(fd).is_ok()));
+    fs.create("file-0").unwrap();
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 没有索引的大目录也会被报告，重建要分好几步提交，打开着的文件跟着目录项搬到新的位置
+    let fd = fs.open("file-1").unwrap();
+    let mut inode = fs.get_inode(dir.i_node).unwrap();
+    inode.i_flags &= !INODE_FLAG_INDEX;
+    fs.write_inode(dir.i_node, inode).unwrap();
+    let report = fs.fsck(false).unwrap();
+    assert!(matches!(&report.problems[..], [FsckProblem::MissingDirIndex { path }] if path == "/big"));
+    assert!(fs.fsck(true).unwrap().repaired);
+    let inode = fs.get_inode(dir.i_node).unwrap();
+    assert!(inode.is_indexed() && fs.dx_check(&inode, true).unwrap());
+    fs.rm(fd).unwrap();
+    assert_eq!(fs.open("file-1").unwrap_err().kind(), ErrorKind::NotFound);
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 关闭了目录索引的文件系统照常逐项查找
+    let options = FormatOptions {
+        dir_index: false,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    for i in 0..100 {
+        fs.create(&format!("file-{}", i)).unwrap();
+    }
+    assert!(!fs.get_inode(0).unwrap().is_indexed());
+    assert!(fs.open("file-99").is_ok());
+}
+
+#[test]
+fn test_htree_overflow() {
+    // 一个叶子只能放两个这么长的文件名，拆分之后的叶子常常只剩一个，两层索引很快就会放满
+    let options = FormatOptions {
+        block_size: 512,
+        blocks_count: 16384,
+        inodes_count: 8192,
+        journal_blocks: 0,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.mkdir("big").unwrap();
+    fs.chdir("big").unwrap();
+    let dir_i = fs.cwd.i_node;
+    let name = |i: usize| format!("{:0>200}", i);
+
+    // 一直添加到根结点放满之后中间结点又变少，即索引被丢弃之后重建了
+    let (mut count, mut nodes) = (0, 0);
+    loop {
+        fs.create(&name(count)).unwrap();
+        count += 1;
+        let inode = fs.get_inode(dir_i).unwrap();
+        if inode.i_size as usize == fs.block_size() {
+            continue;
+        }
+        assert!(inode.is_indexed());
+        let root = fs.dx_read(&inode, 0, true).unwrap();
+        if root.levels == 2 && root.entries.len() < nodes {
+            break;
+        }
+        nodes = match root.levels {
+            2 => root.entries.len(),
+            _ => 0,
+        };
+    }
+    assert_eq!(nodes, fs.dx_limit(&DxNode::default()));
+
+    let inode = fs.get_inode(dir_i).unwrap();
+    assert!(fs.dx_check(&inode, true).unwrap());
+    for i in 0..count {
+        assert!(fs.open(&name(i)).is_ok_and(|fd| fs.close(fd).is_ok()));
+    }
+    fs.create(&name(count)).unwrap();
+    assert!(fs.get_inode(dir_i).unwrap().is_indexed());
+    assert!(fs.fsck(false).unwrap().is_clean());
+}
@@ -4,297 +4,430 @@
 //! - 索引节点的数据结构定义
 //! - 逻辑地址到物理地址的转换
 //! - 数据块的分配和释放
-//! - 多级索引的支持（直接索引、一级索引、二级索引）
+//! - 多级索引的支持（直接索引、一级索引、二级索引、三级索引）
+//! - 稀疏文件：没有分配数据块的逻辑块（空洞）读出全 0，写入时才分配
 
 use super::*;
-
-/// 索引块类型枚举
-/// 
-/// 表示不同级别的索引方式，用于文件数据块的寻址
-#[derive(Clone, Debug)]
-enum IndexedBlk {
-    /// 直接索引
-    /// 
-    /// 直接指向数据块，用于小文件的快速访问
-    Directly(u16),
-    /// 一次索引
-    /// 
-    /// 通过一级索引表间接指向数据块
-    OnceIndexed(u16),
-    /// 二次索引
-    /// 
-    /// 通过二级索引表间接指向数据块，支持更大的文件
-    TwiceIndexed(u16, u16),
-}
+use std::time::Duration;
 
 /// 真实地址结构体
 /// 
-/// 记录逻辑地址在磁盘上的实际物理位置和索引方式
+/// 记录逻辑地址在磁盘上的实际物理位置
 #[derive(Clone, Debug)]
 pub struct RealAddr {
-    /// 物理地址（磁盘上的字节偏移量）
+    /// 物理地址（磁盘上的字节偏移量），逻辑地址位于空洞中时为 0
     pub(in crate::fs) addr: u64,
-    /// 在磁盘上的数据块索引方式
-    data_blk: IndexedBlk,
+    /// 逻辑地址所在的数据块号，逻辑地址位于空洞中时为 `None`
+    data_blk: Option<u32>,
+}
+
+impl RealAddr {
+    /// 逻辑地址是否位于空洞中，即对应的逻辑块还没有分配数据块
+    #[inline(always)]
+    pub(in crate::fs) fn is_hole(&self) -> bool {
+        self.data_blk.is_none()
+    }
 }
 
 /// 索引节点结构体
 /// 
-/// 存储文件的元数据信息，包括权限、大小、时间戳、数据块指针等
-#[repr(align(32))]
+/// 存储文件的元数据信息，包括权限、大小、时间戳、数据块指针等。
+/// 字段顺序就是磁盘上的顺序，64 位字段放在前面，中间没有对齐填充，校验和放在最后
+#[repr(C, align(32))]
 #[derive(Clone)]
 pub struct Inode {
-    /// 文件存取权限和所有者信息
-    pub i_mode: FileMode,
-    /// 文件数据块个数
-    pub i_blocks: u16,
+    /// 最后访问时间，从 Unix 纪元开始的秒数
+    pub i_atime: u64,
+    /// 状态改变时间：文件内容、权限、拥有者或者硬链接数改变时更新
+    pub i_ctime: u64,
+    /// 文件内容的修改时间
+    pub i_mtime: u64,
+    /// 创建时间，之后不再改变
+    pub i_crtime: u64,
+    /// 访问时间的纳秒部分
+    pub i_atime_nsec: u32,
+    /// 状态改变时间的纳秒部分
+    pub i_ctime_nsec: u32,
+    /// 修改时间的纳秒部分
+    pub i_mtime_nsec: u32,
+    /// 创建时间的纳秒部分
+    pub i_crtime_nsec: u32,
+    /// 已经分配的数据块个数，不含索引块；稀疏文件的空洞不占用数据块，
+    /// 所以它可能小于 `i_size` 对应的块数
+    pub i_blocks: u32,
     /// 文件大小（字节数）
     pub i_size: u32,
-    /// 创建时间（Unix时间戳）
-    pub i_ctime: u32,
-    /// 修改时间（Unix时间戳）
-    pub i_mtime: u32,
-    /// 硬链接数量（引用计数）
-    pub i_links_count: u16,
     /// 指向数据块的指针数组（使用多级索引）
     /// 
     /// 索引结构：
     /// - i_block[0-5]: 直接索引，直接指向数据块
     /// - i_block[6]: 一级索引，指向包含数据块地址的索引块
     /// - i_block[7]: 二级索引，指向包含一级索引块地址的索引块
-    pub(in crate::fs) i_block: [u16; 8],
+    /// - i_block[8]: 三级索引，指向包含二级索引块地址的索引块
+    /// 
+    /// 值为 [`HOLE_BLK`] 的指针表示空洞。
+    /// 设置了 [`INODE_FLAG_EXTENTS`] 时改为存放区段树的根结点
+    pub(in crate::fs) i_block: [u32; 9],
+    /// 文件存取权限和所有者信息
+    pub i_mode: FileMode,
+    /// 硬链接数量（引用计数）
+    pub i_links_count: u16,
+    /// 索引节点标志，例如 [`INODE_FLAG_EXTENTS`]
+    pub i_flags: u16,
+    /// 保留给以后的字段，填充为 0
+    pub(in crate::fs) i_reserved: [u8; 22],
+    /// 索引节点的校验和
+    pub i_checksum: u32,
 }
 
 impl Default for Inode {
     /// 创建默认的索引节点
     /// 
     /// # 返回值
-    /// 返回初始化的索引节点，设置当前时间为创建和修改时间
+    /// 返回初始化的索引节点，四个时间都设置为当前时间
     fn default() -> Self {
         let now = utils::now();
+        let (sec, nsec) = (now.as_secs(), now.subsec_nanos());
         Self {
+            i_atime: sec,
+            i_ctime: sec,
+            i_mtime: sec,
+            i_crtime: sec,
+            i_atime_nsec: nsec,
+            i_ctime_nsec: nsec,
+            i_mtime_nsec: nsec,
+            i_crtime_nsec: nsec,
             i_mode: Default::default(),
             i_size: 0,
             i_blocks: 0,
             i_block: Default::default(),
-            i_ctime: now,
-            i_mtime: now,
             i_links_count: 1, // 默认为1个引用（创建时）
+            i_flags: 0,
+            i_reserved: [0; 22],
+            i_checksum: 0,
         }
     }
 }
 
+impl Inode {
+    /// 最后访问时间
+    pub fn atime(&self) -> Duration {
+        Duration::new(self.i_atime, self.i_atime_nsec)
+    }
+
+    /// 状态改变时间
+    pub fn ctime(&self) -> Duration {
+        Duration::new(self.i_ctime, self.i_ctime_nsec)
+    }
+
+    /// 修改时间
+    pub fn mtime(&self) -> Duration {
+        Duration::new(self.i_mtime, self.i_mtime_nsec)
+    }
+
+    /// 创建时间
+    pub fn crtime(&self) -> Duration {
+        Duration::new(self.i_crtime, self.i_crtime_nsec)
+    }
+
+    /// 设置访问时间
+    pub(in crate::fs) fn set_atime(&mut self, time: Duration) {
+        (self.i_atime, self.i_atime_nsec) = (time.as_secs(), time.subsec_nanos());
+    }
+
+    /// 设置修改时间，不改变状态改变时间
+    pub(in crate::fs) fn set_mtime(&mut self, time: Duration) {
+        (self.i_mtime, self.i_mtime_nsec) = (time.as_secs(), time.subsec_nanos());
+    }
+
+    /// 元数据改变：更新状态改变时间
+    pub(in crate::fs) fn touch_ctime(&mut self) {
+        let now = utils::now();
+        (self.i_ctime, self.i_ctime_nsec) = (now.as_secs(), now.subsec_nanos());
+    }
+
+    /// 文件内容改变：同时更新修改时间和状态改变时间
+    pub(in crate::fs) fn touch_mtime(&mut self) {
+        self.touch_ctime();
+        (self.i_mtime, self.i_mtime_nsec) = (self.i_ctime, self.i_ctime_nsec);
+    }
+}
+
 impl Inode {
     /// 将逻辑地址转换成物理地址
     /// 
     /// # 参数
-    /// - `disk`: 磁盘文件引用
+    /// - `fs`: 文件系统引用
     /// - `logicaddr`: 文件内的逻辑地址（字节偏移量）
     /// 
     /// # 返回值
-    /// 成功时返回包含物理地址和索引方式的 RealAddr 结构体
+    /// 成功时返回 RealAddr 结构体，逻辑地址位于空洞中时 [`RealAddr::is_hole`] 为真
     /// 
     /// # 错误
-    /// 当磁盘读取失败时返回相应的IO错误
+    /// - 当磁盘读取失败时返回相应的IO错误
+    /// - 当逻辑地址超出三级索引能表示的范围时返回 FileTooLarge 错误
     /// 
     /// # 算法
     /// 1. 计算逻辑地址对应的块号和块内偏移
-    /// 2. 根据块号范围确定索引方式：
+    /// 2. 根据块号范围确定索引方式（n 为每个索引块能存放的地址个数）：
     ///    - 0-5: 直接索引
-    ///    - 6-(6+BLOCK_ADDR_NUM): 一级索引
-    ///    - 其他: 二级索引
-    /// 3. 根据索引方式计算最终的物理地址
+    ///    - 接下来的 n 块: 一级索引
+    ///    - 接下来的 n² 块: 二级索引
+    ///    - 接下来的 n³ 块: 三级索引
+    /// 3. 沿着索引逐级查找，途中遇到 [`HOLE_BLK`] 说明逻辑块位于空洞中
+    /// 4. 根据数据块号计算最终的物理地址
     pub(in crate::fs) fn convert_addr(
         &self,
-        disk: &std::fs::File,
+        fs: &Fs,
         logicaddr: u64,
     ) -> Result<RealAddr> {
-        let blk_i = logicaddr / BLOCK_SIZE as u64;
-        let blk_offset = logicaddr % BLOCK_SIZE as u64;
-
-        let addr = if blk_i <= 5 {
-            // 直接索引：直接从 i_block 数组获取数据块地址
-            RealAddr {
-                addr: Fs::addr_data_blk(self.i_block[blk_i as usize]) + blk_offset as u64,
-                data_blk: IndexedBlk::Directly(self.i_block[blk_i as usize] as u16),
-            }
-        } else if blk_i - 6 < BLOCK_ADDR_NUM as u64 {
-            // 一级索引：通过索引表间接获取数据块地址
-            let mut addr = [0u8; 4];
-            disk.read_at(
-                &mut addr,
-                Fs::addr_data_blk(self.i_block[6]) + (blk_i - 6) * 4,
-            )?;
-            let addr = i32::from_le_bytes(addr) as u16;
-            RealAddr {
-                addr: Fs::addr_data_blk(addr) + blk_offset,
-                data_blk: IndexedBlk::OnceIndexed(addr),
-            }
+        let block_size = fs.block_size() as u64;
+        let blk_i = logicaddr / block_size;
+        let blk_offset = logicaddr % block_size;
+
+        let data_blk = if self.uses_extents() {
+            // 区段树：在树中查找逻辑块对应的数据块
+            self.extent_lookup(fs, Self::extent_blk_i(blk_i)?)?
         } else {
-            // 二级索引：通过两级索引表获取数据块地址
-            let blk_i = blk_i - BLOCK_ADDR_NUM as u64 - 6;
-
-            // 读取一级索引表地址
-            let mut addr = [0u8; 4];
-            disk.read_at(
-                &mut addr,
-                Fs::addr_data_blk(self.i_block[7]) + blk_i / BLOCK_ADDR_NUM as u64 * 4,
-            )?;
-            let addr1 = i32::from_le_bytes(addr) as u16;
-
-            // 读取最终数据块地址
-            addr.fill(0);
-            disk.read_at(
-                &mut addr,
-                Fs::addr_data_blk(addr1) + blk_i % BLOCK_ADDR_NUM as u64 * 4,
-            )?;
-            let addr2 = i32::from_be_bytes(addr) as u16;
-            RealAddr {
-                addr: Fs::addr_data_blk(addr2) + blk_offset,
-                data_blk: IndexedBlk::TwiceIndexed(addr1, addr2),
-            }
+            self.index_lookup(fs, blk_i)?
         };
 
-        Ok(addr)
+        let addr = data_blk.map_or(0, |blk| fs.addr_data_blk(blk) + blk_offset);
+        Ok(RealAddr { addr, data_blk })
+    }
+
+    /// 区段树中的逻辑块号只有 32 位
+    fn extent_blk_i(blk_i: u64) -> Result<u32> {
+        u32::try_from(blk_i).map_err(|_| Error::new(ErrorKind::FileTooLarge, "File too large"))
     }
 
-    /// 为索引节点分配新的数据块
+    /// 确定逻辑块号 `blk_i` 在多级索引中的位置
+    /// 
+    /// # 返回值
+    /// `(slot, depth, offset)`：从 `i_block[slot]` 出发，再经过 `depth` 级索引块找到数据块，
+    /// `offset` 是逻辑块在这棵索引子树里的序号
+    fn locate(fs: &Fs, blk_i: u64) -> Result<(usize, u32, u64)> {
+        if blk_i < 6 {
+            return Ok((blk_i as usize, 0, 0));
+        }
+
+        let addr_num = fs.addr_per_blk() as u64;
+        let mut offset = blk_i - 6;
+        let mut span = 1;
+        for depth in 1..=3 {
+            span *= addr_num;
+            if offset < span {
+                return Ok((5 + depth as usize, depth, offset));
+            }
+            offset -= span;
+        }
+        Err(Error::new(ErrorKind::FileTooLarge, "File too large"))
+    }
+
+    /// 在多级索引中查找逻辑块号 `blk_i` 对应的数据块号，位于空洞中时返回 `None`
+    fn index_lookup(&self, fs: &Fs, blk_i: u64) -> Result<Option<u32>> {
+        let addr_num = fs.addr_per_blk() as u64;
+        let (slot, depth, offset) = Self::locate(fs, blk_i)?;
+
+        let mut blk = self.i_block[slot];
+        for level in (0..depth).rev() {
+            if blk == HOLE_BLK {
+                return Ok(None);
+            }
+            blk = fs.read_addr(blk, offset / addr_num.pow(level) % addr_num)?;
+        }
+        Ok((blk != HOLE_BLK).then_some(blk))
+    }
+
+    /// 为索引节点在文件末尾追加一个数据块
+    /// 
+    /// 只用于目录和软链接这类没有空洞的文件，新数据块的逻辑块号就是 `i_blocks`
+    pub(in crate::fs) fn alloc_data_block(&mut self, fs: &mut Fs, inode_i: u32) -> Result<u32> {
+        self.alloc_data_block_at(fs, inode_i, self.i_blocks as u64)
+    }
+
+    /// 为逻辑块号 `blk_i` 分配数据块
     /// 
     /// # 参数
     /// - `fs`: 文件系统的可变引用
+    /// - `inode_i`: 当前索引节点的编号，新的数据块会尽量分配在同一个块组里
+    /// - `blk_i`: 逻辑块号
     /// 
     /// # 返回值
-    /// 成功时返回新分配的数据块号
+    /// 成功时返回逻辑块对应的数据块号，逻辑块已经有数据块时直接返回它
     /// 
     /// # 错误
-    /// 当磁盘空间不足或磁盘操作失败时返回相应错误
+    /// - 当磁盘空间不足或磁盘操作失败时返回相应错误
+    /// - 当逻辑块超出三级索引的范围时返回 FileTooLarge 错误
     /// 
     /// # 算法
-    /// 根据当前已分配的块数选择合适的索引方式：
-    /// 1. 前6个块使用直接索引
-    /// 2. 接下来的块使用一级索引
-    /// 3. 更多的块使用二级索引
-    pub(in crate::fs) fn alloc_data_block(&mut self, fs: &mut Fs) -> Result<u16> {
-        let blk = if self.i_blocks < 6 {
-            // 直接索引：直接在 i_block 数组中存储数据块地址
-            let addr = fs.alloc(BlkType::DataBlk)?;
-            self.i_block[self.i_blocks as usize] = addr;
-            addr
-        } else if self.i_blocks < 6 + BLOCK_ADDR_NUM as u16 {
-            // 一级索引：需要索引表来存储数据块地址
-            let offset = self.i_blocks - 6;
-            if offset == 0 {
-                // 第一次使用一级索引，需要分配索引表
-                self.i_block[6] = fs.alloc(BlkType::DataBlk)?;
-            }
-            let addr = fs.alloc(BlkType::DataBlk)?;
-            // 将数据块地址写入索引表
-            fs.disk.write_at(
-                &(addr as i32).to_le_bytes(),
-                Fs::addr_data_blk(self.i_block[6]) + offset as u64 * 4,
-            )?;
-            addr
-        } else {
-            // 二级索引：需要两级索引表
-            let offset = self.i_blocks as u64 - 6 - BLOCK_ADDR_NUM as u64;
-            if offset == 0 {
-                // 第一次使用二级索引，需要分配二级索引表
-                self.i_block[7] = fs.alloc(BlkType::DataBlk)?;
-            }
+    /// 沿着索引逐级向下，遇到空洞就分配一个清零的块填上：
+    /// 中间的是索引块，最后一级是数据块。只有新分配的数据块才计入 `i_blocks`
+    pub(in crate::fs) fn alloc_data_block_at(
+        &mut self,
+        fs: &mut Fs,
+        inode_i: u32,
+        blk_i: u64,
+    ) -> Result<u32> {
+        if self.uses_extents() {
+            return self.extent_alloc(fs, inode_i, Self::extent_blk_i(blk_i)?);
+        }
 
-            if offset % BLOCK_ADDR_NUM as u64 == 0 {
-                // 需要新的一级索引表
-                let addr1 = fs.alloc(BlkType::DataBlk)?;
-                // 将一级索引表地址写入二级索引表
-                fs.disk.write_at(
-                    &(addr1 as i32).to_le_bytes(),
-                    Fs::addr_data_blk(self.i_block[7]) + offset / BLOCK_ADDR_NUM as u64 * 4,
-                )?;
-                let addr2 = fs.alloc(BlkType::DataBlk)?;
-                // 将数据块地址写入一级索引表
-                fs.disk
-                    .write_at(&(addr2 as i32).to_le_bytes(), Fs::addr_data_blk(addr1))?;
-                addr2
-            } else {
-                // 使用现有的一级索引表
-                let mut addr = [0u8; 4];
-                fs.disk.read_at(
-                    &mut addr,
-                    Fs::addr_data_blk(self.i_block[7]) + offset / BLOCK_ADDR_NUM as u64 * 4,
-                )?;
-                let addr1 = i32::from_le_bytes(addr) as u16;
-
-                let addr2 = fs.alloc(BlkType::DataBlk)?;
-                // 将数据块地址写入一级索引表
-                fs.disk.write_at(
-                    &(addr2 as i32).to_le_bytes(),
-                    Fs::addr_data_blk(addr1) + offset % BLOCK_ADDR_NUM as u64 * 4,
-                )?;
-                addr2
-            }
-        };
+        let addr_num = fs.addr_per_blk() as u64;
+        let (slot, depth, offset) = Self::locate(fs, blk_i)?;
 
-        self.i_blocks += 1;
+        // 指向当前块的索引块以及在其中的下标，`None` 表示 `i_block[slot]`
+        let mut parent: Option<(u32, u64)> = None;
+        let mut blk = self.i_block[slot];
+        for level in (0..=depth).rev() {
+            if blk == HOLE_BLK {
+                // 新的块要清零：索引块中的 0 表示空洞，数据块中没写过的部分要读出 0
+                blk = fs.alloc(BlkType::DataBlk, inode_i)?;
+                match level {
+                    0 => fs.clear_data_blk(blk)?,
+                    _ => fs.clear_blk(blk)?,
+                }
+                match parent {
+                    None => self.i_block[slot] = blk,
+                    Some((index_blk, i)) => fs.write_addr(index_blk, i, blk)?,
+                }
+                if level == 0 {
+                    self.i_blocks += 1;
+                }
+            }
+            if level == 0 {
+                break;
+            }
 
+            let i = offset / addr_num.pow(level - 1) % addr_num;
+            parent = Some((blk, i));
+            blk = fs.read_addr(blk, i)?;
+        }
         Ok(blk)
     }
 
-    /// 释放索引节点的数据块
+    /// 释放索引节点中逻辑块号不小于 `new_blk_counts` 的数据块
     /// 
     /// # 参数
-    /// - `new_blk_counts`: 新的数据块数量
+    /// - `new_blk_counts`: 保留的逻辑块个数
     /// - `fs`: 文件系统的可变引用
     /// 
-    /// # 返回值
-    /// 成功时返回 Ok(())，失败时返回错误信息
-    /// 
     /// # 行为
-    /// - 如果 `new_blk_counts` >= 当前块数，不执行任何操作
-    /// - 如果 `new_blk_counts` < 当前块数，释放多余的数据块
-    /// 
-    /// # 算法
-    /// 1. 遍历需要释放的数据块
-    /// 2. 根据索引方式确定需要释放的块（包括索引块）
-    /// 3. 批量释放所有相关的数据块
-    /// 4. 更新索引节点的块计数
+    /// - 空洞不占用数据块，直接跳过
+    /// - 不再指向任何数据块的索引块也一并释放
+    /// - `i_blocks` 减去释放的数据块个数
     pub(in crate::fs) fn free_data_block(
         &mut self,
-        new_blk_counts: u16,
+        new_blk_counts: u32,
         fs: &mut Fs,
     ) -> Result<()> {
-        if new_blk_counts >= self.i_blocks {
-            return Ok(());
+        if self.uses_extents() {
+            return self.extent_truncate(new_blk_counts, fs);
         }
 
-        let mut blks_to_clean: Vec<u16> = Vec::new();
-        for i in new_blk_counts..self.i_blocks {
-            // 要删除的数据块号
-            match self.convert_addr(&fs.disk, i as u64 * BLOCK_SIZE as u64)?.data_blk {
-                IndexedBlk::Directly(addr) => blks_to_clean.push(addr),
-                IndexedBlk::OnceIndexed(addr) => {
-                    // 一级索引：需要额外删除索引块
-                    if i == 6 {
-                        blks_to_clean.push(self.i_block[6])
-                    }
-                    blks_to_clean.push(addr)
-                }
-                IndexedBlk::TwiceIndexed(addr1, addr2) => {
-                    // 二级索引：需要额外删除索引块
-                    let offset = i - 6 - BLOCK_ADDR_NUM as u16;
-                    if offset == 0 {
-                        blks_to_clean.push(self.i_block[7]);
-                    }
-                    if offset % BLOCK_ADDR_NUM as u16 == 0 {
-                        blks_to_clean.push(addr1);
-                    }
-                    blks_to_clean.push(addr2);
-                }
+        let keep = new_blk_counts as u64;
+        let mut blks_to_clean: Vec<u32> = Vec::new();
+        let mut freed = 0;
+
+        // 直接索引
+        for slot in keep.min(6) as usize..6 {
+            if self.i_block[slot] != HOLE_BLK {
+                blks_to_clean.push(self.i_block[slot]);
+                self.i_block[slot] = HOLE_BLK;
+                freed += 1;
             }
         }
 
+        // 一级、二级、三级索引
+        let addr_num = fs.addr_per_blk() as u64;
+        let (mut base, mut span) = (6, 1);
+        for depth in 1..=3 {
+            span *= addr_num;
+            let slot = 5 + depth as usize;
+            let args = (self.i_block[slot], depth, base, keep);
+            if Self::truncate_index(fs, args, &mut blks_to_clean, &mut freed)? {
+                self.i_block[slot] = HOLE_BLK;
+            }
+            base += span;
+        }
+
         fs.free(BlkType::DataBlk, &blks_to_clean)?;
 
-        self.i_blocks = new_blk_counts;
+        self.i_blocks -= freed;
+        Ok(())
+    }
+
+    /// 释放一棵索引子树中逻辑块号不小于 `keep` 的数据块
+    /// 
+    /// `(blk, depth, base, keep)`：`blk` 是子树的根，`depth` 是子树中索引块的层数，
+    /// `base` 是子树中第一个逻辑块号。要释放的数据块和不再需要的索引块都放入 `blks`，
+    /// 其中数据块的个数累加到 `freed` 上。返回整棵子树是否都被释放了
+    fn truncate_index(
+        fs: &mut Fs,
+        (blk, depth, base, keep): (u32, u32, u64, u64),
+        blks: &mut Vec<u32>,
+        freed: &mut u32,
+    ) -> Result<bool> {
+        if blk == HOLE_BLK {
+            return Ok(true);
+        }
+
+        let addr_num = fs.addr_per_blk() as u64;
+        if base + addr_num.pow(depth) <= keep {
+            // 整棵子树都保留
+            return Ok(false);
+        }
+        if depth == 0 {
+            blks.push(blk);
+            *freed += 1;
+            return Ok(true);
+        }
+
+        let span = addr_num.pow(depth - 1);
+        let mut empty = true;
+        for (i, child) in fs.read_addrs(blk)?.into_iter().enumerate() {
+            let args = (child, depth - 1, base + i as u64 * span, keep);
+            match Self::truncate_index(fs, args, blks, freed)? {
+                true if child != HOLE_BLK => fs.write_addr(blk, i as u64, HOLE_BLK)?,
+                true => {}
+                false => empty = false,
+            }
+        }
+
+        if empty {
+            blks.push(blk);
+        }
+        Ok(empty)
+    }
+}
+
+impl Fs {
+    /// 读取索引块 `index_blk` 中第 `i` 个数据块地址
+    pub(in crate::fs) fn read_addr(&self, index_blk: u32, i: u64) -> Result<u32> {
+        let mut addr = [0u8; ADDR_SIZE];
+        self.disk.read_at(
+            &mut addr,
+            self.addr_data_blk(index_blk) + i * ADDR_SIZE as u64,
+        )?;
+        Ok(u32::from_le_bytes(addr))
+    }
+
+    /// 将数据块地址 `addr` 写入索引块 `index_blk` 的第 `i` 个位置
+    pub(in crate::fs) fn write_addr(&mut self, index_blk: u32, i: u64, addr: u32) -> Result<()> {
+        self.disk.write_at(
+            &addr.to_le_bytes(),
+            self.addr_data_blk(index_blk) + i * ADDR_SIZE as u64,
+        )?;
         Ok(())
     }
+
+    /// 读取索引块 `index_blk` 中的所有数据块地址
+    pub(in crate::fs) fn read_addrs(&self, index_blk: u32) -> Result<Vec<u32>> {
+        let mut blk = self.empty_blk();
+        self.disk.read_at(&mut blk, self.addr_data_blk(index_blk))?;
+        Ok(blk
+            .chunks_exact(ADDR_SIZE)
+            .map(|addr| u32::from_le_bytes(addr.try_into().unwrap()))
+            .collect())
+    }
 }
@@ -6,17 +6,18 @@
 //! - 已删除目录项的识别和处理
 //! - 权限检查和访问控制
 //! - 目录内容的安全遍历
+//! - [`Fs::read_dir`]：按路径列出目录，返回解码好的 [`DirEntryInfo`]
 
 use super::*;
+use std::cell::OnceCell;
 
 /// 目录项迭代器
 /// 
 /// 用于遍历目录中的所有条目，包括正在使用的和已删除的目录项
+#[derive(Clone)]
 pub struct DirEntryIterator<'a> {
     /// 目录对应的索引节点
     i_node: Inode,
-    /// 已遍历的有效目录项计数器
-    counter: usize,
     /// 当前逻辑地址（文件内偏移量）
     logic_addr: u64,
     /// 文件系统引用
@@ -44,57 +45,80 @@ pub enum DirEntryIterItem {
     Using(Item),
     /// 已删除的目录项
     /// 
-    /// 表示该目录项已被删除，但在磁盘上仍有残留数据
+    /// 表示这个记录没有使用，它的 `rec_len` 都是可以复用的空间。
+    /// 删除目录项时会把它合并到同一个块中的前一个目录项里，
+    /// 所以只有位于数据块开头的目录项被删除时才会留下这种记录
     Deleted(Item),
 }
 
 impl<'a> Iterator for DirEntryIterator<'a> {
-    type Item = DirEntryIterItem;
+    type Item = Result<DirEntryIterItem>;
 
     /// 获取下一个目录项
     /// 
     /// # 返回值
-    /// - `Some(DirEntryIterItem)`: 下一个目录项（可能是使用中的或已删除的）
+    /// - `Some(Ok(DirEntryIterItem))`: 下一个目录项（可能是使用中的或已删除的）
+    /// - `Some(Err(_))`: 读取目录项失败，例如校验和不正确或者 `rec_len` 损坏，之后迭代结束
     /// - `None`: 已遍历完所有目录项
     /// 
     /// # 算法
-    /// 1. 检查是否已遍历完所有数据块或达到目录项数量限制
+    /// 1. 检查是否已遍历完目录的所有数据块
     /// 2. 将当前逻辑地址转换为物理地址
     /// 3. 从磁盘读取目录项数据
     /// 4. 根据目录项的状态判断是否已删除
-    /// 5. 更新迭代器状态并返回结果
+    /// 5. 按照 `rec_len` 移动到下一个目录项并返回结果
     fn next(&mut self) -> Option<Self::Item> {
-        // 检查是否已遍历完所有数据块或目录项
-        if self.logic_addr / BLOCK_SIZE as u64 >= self.i_node.i_blocks as u64
-            || self.counter >= self.i_node.i_size as usize / DIR_ENTRY_SIZE
-        {
-            return None;
+        let res = self.try_next();
+        if res.is_err() {
+            // 损坏的目录项之后的位置不可信，不再继续
+            self.logic_addr = u64::MAX;
+        }
+        res.transpose()
+    }
+}
+
+impl DirEntryIterator<'_> {
+    /// 获取下一个目录项
+    fn try_next(&mut self) -> Result<Option<DirEntryIterItem>> {
+        // 目录的大小总是数据块大小的整数倍
+        if self.logic_addr >= self.i_node.i_size as u64 {
+            return Ok(None);
         }
 
-        // 将逻辑地址转换为物理地址
-        let real_addr = self
-            .i_node
-            .convert_addr(&self.fs.disk, self.logic_addr as u64)
-            .unwrap();
+        // 将逻辑地址转换为物理地址，进入新的数据块时检查它的校验和记录
+        let real_addr = self.i_node.convert_addr(self.fs, self.logic_addr)?;
+        let offset = (self.logic_addr % self.fs.block_size() as u64) as usize;
+        if offset == 0 {
+            self.fs.verify_dir_block(real_addr.addr)?;
+        }
 
         // 从磁盘读取目录项
-        let entry = DirEntry::from_disk(&self.fs.disk, real_addr.addr).unwrap();
+        let entry = DirEntry::from_disk(&self.fs.disk, real_addr.addr)?;
 
-        // 判断目录项是否已删除
-        // 已删除的目录项特征：i_node为0但rec_len不为0
-        let deleted = entry.i_node == 0 && entry.rec_len != 0;
+        // rec_len 不合法时继续遍历可能会陷入死循环，或者越过数据块的边界
+        let rec_len = entry.rec_len as usize;
+        let end = self.fs.dir_block_end();
+        if rec_len < DirEntry::rec_size(entry.name_len as usize) || offset + rec_len > end {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} at byte {}: bad record length", DirEntry::NAME, real_addr.addr),
+            ));
+        }
+
+        // 判断目录项是否已删除，根目录的索引节点号也是 0，所以用文件类型来判断
+        let deleted = entry.file_type == 0;
         let item = Item { entry, real_addr };
 
-        // 移动到下一个目录项
-        self.logic_addr += DIR_ENTRY_SIZE as u64;
+        // 移动到下一个目录项，跳过数据块末尾的校验和记录
+        self.logic_addr += rec_len as u64;
+        if offset + rec_len == end {
+            self.logic_addr += DIR_TAIL_SIZE as u64;
+        }
 
-        return match deleted {
-            true => Some(DirEntryIterItem::Deleted(item)),
-            false => {
-                self.counter += 1;
-                Some(DirEntryIterItem::Using(item))
-            }
-        };
+        match deleted {
+            true => Ok(Some(DirEntryIterItem::Deleted(item))),
+            false => Ok(Some(DirEntryIterItem::Using(item))),
+        }
     }
 }
 
@@ -117,14 +141,14 @@ impl DirEntry {
         let i_node = Inode::from_disk(&fs.disk, fs.addr_i_node(self.i_node))?;
 
         // 检查用户权限，当只有执行权限时才能访问目录项
-        if !i_node.i_mode.can_exec(fs.user) {
+        if !i_node.i_mode.can_exec(fs.user, &fs.gids) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::PermissionDenied,
                 "Permission Denied. Need exec permission.",
             ));
         }
 
-        Self::iter_without_limit(&self, fs)
+        Self::iter_without_limit(self, fs)
     }
 
     /// 创建目录项迭代器（无权限检查）
@@ -136,15 +160,14 @@ impl DirEntry {
     /// 成功时返回目录项迭代器，失败时返回错误
     /// 
     /// # 错误
-    /// 当目录项不是目录类型时返回错误
+    /// - 当目录项不是目录类型时返回错误
     /// 
     /// # 注意
     /// 此方法跳过权限检查，主要用于系统内部操作
     pub (in crate::fs) fn iter_without_limit<'a>(&self, fs: &'a Fs) -> Result<DirEntryIterator<'a>> {
         // 只有当 DirEntry 是目录时才能进行迭代
         if let FileType::File = self.file_type.into() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            return Err(std::io::Error::other(
                 format!("{}: Can't iterate with a file", utils::str(&self.name)),
             ));
         }
@@ -153,16 +176,104 @@ impl DirEntry {
 
         Ok(DirEntryIterator {
             i_node,
-            counter: 0,
             fs,
             logic_addr: 0,
         })
     }
 }
 
+/// [`Fs::read_dir`] 返回的迭代器，依次返回目录中正在使用的目录项
+///
+/// 默认不返回 `.` 和 `..`，需要时用 [`ReadDir::dots`] 打开。读取目录项出错时返回一次错误，之后迭代结束
+pub struct ReadDir<'a> {
+    /// 底层的目录项迭代器
+    iter: DirEntryIterator<'a>,
+    /// 是否返回 `.` 和 `..`
+    dots: bool,
+}
+
+/// [`ReadDir`] 返回的目录项
+pub struct DirEntryInfo<'a> {
+    /// 文件名
+    pub name: String,
+    /// 索引节点号
+    pub ino: u32,
+    /// 文件类型
+    pub file_type: FileType,
+    /// 读取元数据用的文件系统
+    fs: &'a Fs,
+    /// 第一次调用 [`DirEntryInfo::metadata`] 时读取的元数据
+    metadata: OnceCell<Metadata>,
+}
+
+impl ReadDir<'_> {
+    /// 是否返回 `.` 和 `..`
+    pub fn dots(mut self, dots: bool) -> Self {
+        self.dots = dots;
+        self
+    }
+}
+
+impl<'a> Iterator for ReadDir<'a> {
+    type Item = Result<DirEntryInfo<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.iter.next()? {
+                Ok(DirEntryIterItem::Using(Item { entry, .. })) => entry,
+                Ok(DirEntryIterItem::Deleted(_)) => continue,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let name = String::from_utf8_lossy(&entry.name[..entry.name_len as usize]).into_owned();
+            if !self.dots && (name == "." || name == "..") {
+                continue;
+            }
+            return Some(Ok(DirEntryInfo {
+                name,
+                ino: entry.i_node,
+                file_type: entry.file_type.into(),
+                fs: self.iter.fs,
+                metadata: OnceCell::new(),
+            }));
+        }
+    }
+}
+
+impl DirEntryInfo<'_> {
+    /// 目录项的元数据，符号链接返回链接本身的元数据；只在第一次调用时读取索引节点
+    pub fn metadata(&self) -> Result<Metadata> {
+        if let Some(metadata) = self.metadata.get() {
+            return Ok(metadata.clone());
+        }
+        let inode = self.fs.get_inode(self.ino)?;
+        let metadata = Metadata::new(self.ino, self.file_type, &inode);
+        Ok(self.metadata.get_or_init(|| metadata).clone())
+    }
+}
+
+impl Fs {
+    /// 列出路径名为 `path` 的目录中的文件，需要目录的执行权限
+    ///
+    /// ```
+    /// # use simulate_unixlike_fs::fs::*;
+    /// let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    /// fs.create("a").unwrap();
+    /// let names: Vec<_> = fs.read_dir("/").unwrap().map(|e| e.unwrap().name).collect();
+    /// assert!(names.contains(&"a".to_string()));
+    /// ```
+    pub fn read_dir(&self, path: &str) -> Result<ReadDir<'_>> {
+        let iter = self.path_parse(path)?.dir_entry.iter(self)?;
+        Ok(ReadDir {
+            iter,
+            dots: false,
+        })
+    }
+}
+
 #[test]
 fn test_iter() {
-    let mut fs = Fs::format().unwrap();
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
     assert!(fs.mkdir("hello").is_ok());
     assert!(fs.mkdir("world").is_ok());
     assert!(fs.mkdir("test1").is_ok());
@@ -170,7 +281,7 @@ fn test_iter() {
     let iter = fs.cwd.iter(&fs).unwrap();
     let mut i = 0;
     for a in iter {
-        if let DirEntryIterItem::Using(Item { entry, .. }) = a {
+        if let DirEntryIterItem::Using(Item { entry, .. }) = a.unwrap() {
             i += 1;
             println!(
                 "{}",
@@ -178,5 +289,131 @@ fn test_iter() {
             );
         }
     }
-    assert_eq!(i, 8);
-}
\ No newline at end of file
+    assert_eq!(i, 9);
+}
+#[test]
+fn test_variable_entries() {
+    // 检查的是没有索引的目录中记录的复用
+    let options = FormatOptions {
+        dir_index: false,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.mkdir("d").unwrap();
+    fs.chdir("d").unwrap();
+
+    // 每个目录项占用 112 字节，一个 512 字节的数据块只能放下 4 个
+    let names: Vec<_> = (0..10).map(|i| format!("{:0>100}", i)).collect();
+    for name in &names {
+        fs.create(name).unwrap();
+    }
+    let long = "x".repeat(MAX_NAME_LEN);
+    fs.create(&long).unwrap();
+    assert_eq!(utils::str(&fs.path_parse(&long).unwrap().dir_entry.name), long);
+
+    let dir_i = fs.cwd.i_node;
+    let size = fs.get_inode(dir_i).unwrap().i_size;
+    assert_eq!(size as usize % fs.block_size(), 0);
+    assert!(size as usize > fs.block_size());
+    let using = |fs: &Fs| {
+        fs.cwd
+            .iter(fs)
+            .unwrap()
+            .filter(|item| matches!(item, Ok(DirEntryIterItem::Using(_))))
+            .count()
+    };
+    assert_eq!(using(&fs), names.len() + 3);
+
+    // 删除数据块开头的目录项会留下一个没有使用的记录，之后的目录项优先复用它
+    let first = names
+        .iter()
+        .map(|name| fs.path_parse(name).unwrap().dir_entry_addr)
+        .find(|addr| addr % fs.block_size() as u64 == 0)
+        .unwrap();
+    let victim = fs
+        .cwd
+        .iter(&fs)
+        .unwrap()
+        .find_map(|item| match item.unwrap() {
+            DirEntryIterItem::Using(Item { entry, real_addr }) if real_addr.addr == first => {
+                Some(entry)
+            }
+            _ => None,
+        })
+        .unwrap();
+    let fd = fs.open(utils::str(&victim.name)).unwrap();
+    fs.rm(fd).unwrap();
+    let deleted = |item: &DirEntryIterItem| match item {
+        DirEntryIterItem::Deleted(Item { real_addr, .. }) => real_addr.addr == first,
+        DirEntryIterItem::Using(_) => false,
+    };
+    assert!(fs.cwd.iter(&fs).unwrap().any(|item| deleted(&item.unwrap())));
+    // 其它数据块末尾剩下的空间都不够放下 72 字节的目录项
+    let medium = "m".repeat(60);
+    fs.create(&medium).unwrap();
+    assert_eq!(fs.path_parse(&medium).unwrap().dir_entry_addr, first);
+
+    // 删除所有文件之后目录为空，大小不变
+    for name in names.iter().chain([&long, &medium]) {
+        if let Ok(fd) = fs.open(name) {
+            fs.rm(fd).unwrap();
+        }
+    }
+    assert_eq!(using(&fs), 2);
+    assert_eq!(fs.get_inode(dir_i).unwrap().i_size, size);
+    fs.chdir("..").unwrap();
+    fs.rmdir("d").unwrap();
+}
+
+#[test]
+fn test_read_dir() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.mkdir("d").unwrap();
+    fs.mkdir("d/sub").unwrap();
+    let fd = fs.open_with("d/file", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.write(fd, b"hello").unwrap();
+    fs.close(fd).unwrap();
+    fs.symlink("file", "d/link").unwrap();
+
+    let mut entries: Vec<_> = fs.read_dir("d").unwrap().map(|e| e.unwrap()).collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["file", "link", "sub"]);
+    assert_eq!(entries[0].file_type, FileType::File);
+    assert_eq!(entries[1].file_type, FileType::Symlink);
+    assert_eq!(entries[2].file_type, FileType::Dir);
+    assert_eq!(entries[0].ino, fs.stat("d/file").unwrap().ino);
+
+    // 元数据和 lstat 一致
+    assert_eq!(entries[0].metadata().unwrap(), fs.stat("d/file").unwrap());
+    assert_eq!(entries[1].metadata().unwrap(), fs.lstat("d/link").unwrap());
+
+    // 可以选择返回 . 和 ..
+    let dots = fs.read_dir("d").unwrap().dots(true).filter(|e| {
+        let name = &e.as_ref().unwrap().name;
+        name == "." || name == ".."
+    });
+    assert_eq!(dots.count(), 2);
+
+    assert!(fs.read_dir("d/file").is_err());
+    assert_eq!(fs.read_dir("missing").err().unwrap().kind(), ErrorKind::NotFound);
+
+    // 不带校验和的磁盘上 rec_len 损坏时返回一次错误，之后迭代结束，fsck 报错而不是 panic
+    let options = FormatOptions {
+        checksums: false,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.mkdir("d").unwrap();
+    fs.create("d/a").unwrap();
+    fs.create("d/b").unwrap();
+    let addr = fs.path_parse("d/a").unwrap().dir_entry_addr;
+    fs.disk.write_at(&1u16.to_le_bytes(), addr + 4).unwrap();
+    let mut entries = fs.read_dir("d").unwrap();
+    assert_eq!(entries.next().unwrap().err().unwrap().kind(), ErrorKind::InvalidData);
+    assert!(entries.next().is_none());
+    assert_eq!(fs.path_parse("d/b").err().unwrap().kind(), ErrorKind::InvalidData);
+    assert_eq!(fs.fsck(false).unwrap_err().kind(), ErrorKind::InvalidData);
+}
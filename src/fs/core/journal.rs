@@ -0,0 +1,566 @@
+//! # 日志模块
+//!
+//! 创建文件、删除目录这样的操作要分别修改位图、索引节点、目录项和组描述符，
+//! 如果在几次写入之间崩溃，磁盘上就会留下泄漏的数据块或者悬空的目录项。
+//! `Journal` 包装了底层的块设备，为这些操作提供预写式日志（write-ahead journal）：
+//!
+//! 1. 事务进行期间，所有写入都先缓存在内存里，读取时优先返回缓存中的内容
+//! 2. 提交时先把修改过的块完整地写入磁盘上的日志区，再写入日志头，写完日志头就算提交成功
+//! 3. 然后把这些块写回原来的位置（检查点），最后清空日志头
+//! 4. 挂载时如果发现日志头里有已经提交的事务，就重新把它写回一遍（重放）
+//!
+//! 因此每个事务要么全部生效，要么完全没有生效。
+//!
+//! ## 日志区的结构
+//!
+//! 日志区紧跟在组描述符表之后，共 [`SuperBlock::journal_blocks`] 个块：
+//!
+//! - 第 0 块是日志头：依次是魔数 `JOURNAL_MAGIC` 和已提交事务中的块数，块数为 0 表示日志为空
+//! - 之后是若干个描述块，每个描述块记录若干个块号（`u64`）
+//! - 再之后按照描述块中的顺序存放这些块的新内容
+//!
+//! 一个事务修改的块数不能超过日志区一次能提交的块数，超过时写入返回 `StorageFull` 错误，
+//! 整个事务被丢弃，不会拆成几次提交而失去原子性。
+//!
+//! 释放大文件、写入大量数据、递归删除目录和 fsck 的修复要修改的块数没有上限，
+//! 它们分成许多小步，每一步之后磁盘上的状态都是一致的（例如文件被截短了一部分）。
+//! 在两步之间调用 [`Fs::ensure_room`]，事务中剩余的空间不够时先提交已经完成的步骤，
+//! 再在同一个事务中继续，和 ext3 重启句柄的做法一样。这些操作因此不受日志区大小的限制，
+//! 代价是作为整体不再是原子的：中途崩溃或者出错时，已经提交的步骤不会撤销。
+
+use super::*;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// 日志头中的魔数
+const JOURNAL_MAGIC: u32 = 0xC03B_3998;
+
+/// 可以分步完成的操作中，一步最多修改的块数，见 [`Fs::ensure_room`]
+pub(in crate::fs) const STEP_BLOCKS: u64 = 32;
+
+/// 日志模式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JournalMode {
+    /// 只记录元数据：位图、索引节点、目录项、组描述符和超级块。
+    /// 文件内容直接写入磁盘，并且总是先于元数据落盘
+    #[default]
+    Metadata,
+    /// 文件内容也写入日志，更安全，但是每个数据块都要写两次
+    Data,
+}
+
+/// 带日志的块设备
+pub(in crate::fs) struct Journal {
+    /// 底层的块设备，带有块缓存
+    inner: BlockCache,
+    /// 事务的状态，`read_at` 和 `write_at` 只拿到 `&self`，所以放在锁里
+    state: Mutex<JournalState>,
+}
+
+/// 日志的状态
+struct JournalState {
+    /// 磁盘块大小
+    block_size: u64,
+    /// 日志区的起始块号
+    begin: u64,
+    /// 日志区的块数，为 0 时没有日志，事务中的修改在提交时直接写回
+    blocks: u64,
+    /// 磁盘镜像的总块数，重放日志时块号不能超出这个范围
+    total_blocks: u64,
+    /// 日志模式
+    mode: JournalMode,
+    /// 读取元数据时是否检查校验和
+    checksums: bool,
+    /// 事务的嵌套层数，为 0 时不在事务中
+    depth: usize,
+    /// 当前事务修改过的块：块号 -> 块的新内容
+    pending: BTreeMap<u64, Vec<u8>>,
+}
+
+impl JournalState {
+    /// 每个描述块能记录的块号个数
+    fn per_desc(&self) -> u64 {
+        self.block_size / 8
+    }
+
+    /// 一个事务最多能包含的块数：这些块加上描述块，再加上日志头，不能超过日志区的大小
+    fn capacity(&self) -> u64 {
+        (self.blocks.saturating_sub(1) * self.per_desc() / (self.per_desc() + 1)).max(1)
+    }
+}
+
+impl Journal {
+    /// 用磁盘块大小为 `block_size`、从第 `begin` 块开始的 `blocks` 个块作为日志区，包装共有 `total_blocks` 块的块设备 `inner`
+    ///
+    /// `inner` 之上还有一层最多缓存 `cache_blocks` 个块的 [`BlockCache`]
+    pub(in crate::fs) fn new(
+        inner: Box<dyn BlockDevice>,
+        block_size: u64,
+        begin: u64,
+        blocks: u64,
+        total_blocks: u64,
+        cache_blocks: usize,
+    ) -> Self {
+        Self {
+            inner: BlockCache::new(inner, block_size, cache_blocks),
+            state: Mutex::new(JournalState {
+                block_size,
+                begin,
+                blocks,
+                total_blocks,
+                mode: JournalMode::default(),
+                checksums: false,
+                depth: 0,
+                pending: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// 设置日志模式
+    pub(in crate::fs) fn set_mode(&self, mode: JournalMode) {
+        self.state.lock().unwrap().mode = mode;
+    }
+
+    /// 设置读取元数据时是否检查校验和，由超级块中的特性标志决定
+    pub(in crate::fs) fn set_checksums(&self, checksums: bool) {
+        self.state.lock().unwrap().checksums = checksums;
+    }
+
+    /// 块缓存的统计信息
+    pub(in crate::fs) fn cache_stats(&self) -> CacheStats {
+        self.inner.stats()
+    }
+
+    /// 把块缓存中的脏块写回磁盘
+    pub(in crate::fs) fn write_back(&self) -> Result<()> {
+        self.inner.write_back()
+    }
+
+    /// 开始一个事务，事务可以嵌套，只有最外层的事务结束时才会提交
+    pub(in crate::fs) fn begin(&self) {
+        self.state.lock().unwrap().depth += 1;
+    }
+
+    /// 结束一个事务
+    ///
+    /// 最外层的事务结束时，`commit` 为真就提交事务中的修改，否则丢弃它们。
+    /// 返回这次调用是否结束了最外层的事务
+    pub(in crate::fs) fn end(&self, commit: bool) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+        state.depth -= 1;
+        if state.depth > 0 {
+            return Ok(false);
+        }
+        match commit {
+            true => self.commit(&mut state)?,
+            false => state.pending.clear(),
+        }
+        Ok(true)
+    }
+
+    /// 当前事务还能再修改的块数，不在事务中或者没有日志时没有限制
+    pub(in crate::fs) fn room(&self) -> u64 {
+        let state = self.state.lock().unwrap();
+        match state.depth == 0 || state.blocks == 0 {
+            true => u64::MAX,
+            false => state.capacity().saturating_sub(state.pending.len() as u64),
+        }
+    }
+
+    /// 提交当前事务中已经做完的修改，之后的修改仍然属于这个事务，在它结束时提交
+    pub(in crate::fs) fn restart(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match state.depth {
+            0 => Ok(()),
+            _ => self.commit(&mut state),
+        }
+    }
+
+    /// 写入文件内容
+    ///
+    /// 在 [`JournalMode::Metadata`] 模式下，文件内容绕过日志直接写入磁盘，
+    /// 同时更新事务缓存中的副本，避免提交时被旧的内容覆盖
+    pub(in crate::fs) fn write_data_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.depth == 0 || state.mode == JournalMode::Data {
+            drop(state);
+            return self.write_at(buf, offset);
+        }
+
+        let bs = state.block_size;
+        for (blk, range, buf_range) in split_blocks(bs, offset, buf.len()) {
+            if let Some(data) = state.pending.get_mut(&blk) {
+                data[range].copy_from_slice(&buf[buf_range]);
+            }
+        }
+        self.inner.write_at(buf, offset)
+    }
+
+    /// 挂载时重放日志中已经提交的事务
+    ///
+    /// # 返回值
+    /// 是否重放了事务
+    ///
+    /// # 错误
+    /// 日志中有需要重放的事务，但是设备是只读的
+    pub(in crate::fs) fn replay(&self) -> Result<bool> {
+        let state = self.state.lock().unwrap();
+        if state.blocks == 0 {
+            return Ok(false);
+        }
+
+        let bs = state.block_size;
+        let mut header = vec![0u8; bs as usize];
+        self.inner.read_at(&mut header, state.begin * bs)?;
+        let word = |i: usize| u32::from_le_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+        let count = word(1) as u64;
+        if word(0) != JOURNAL_MAGIC || count == 0 {
+            return Ok(false);
+        }
+        if count > state.capacity() {
+            return Err(Error::new(ErrorKind::InvalidData, "Bad journal header"));
+        }
+        if self.inner.is_read_only() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Journal needs recovery on a read-only device",
+            ));
+        }
+
+        // 读出描述块中的块号，先全部检查一遍，损坏的描述块不能让重放写到镜像之外或者日志区里
+        let desc_blocks = count.div_ceil(state.per_desc());
+        let mut desc = vec![0u8; (desc_blocks * bs) as usize];
+        self.inner.read_at(&mut desc, (state.begin + 1) * bs)?;
+        let homes: Vec<u64> = desc
+            .chunks_exact(8)
+            .take(count as usize)
+            .map(|num| u64::from_le_bytes(num.try_into().unwrap()))
+            .collect();
+        let journal = state.begin..state.begin + state.blocks;
+        if homes.iter().any(|home| *home >= state.total_blocks || journal.contains(home)) {
+            return Err(Error::new(ErrorKind::InvalidData, "Bad journal descriptor block"));
+        }
+
+        // 再把日志中的块写回原来的位置
+        let mut buf = vec![0u8; bs as usize];
+        for (i, home) in homes.into_iter().enumerate() {
+            self.inner.read_at(&mut buf, (state.begin + 1 + desc_blocks + i as u64) * bs)?;
+            self.inner.write_at(&buf, home * bs)?;
+        }
+        self.inner.flush()?;
+
+        self.write_header(&state, 0)?;
+        Ok(true)
+    }
+
+    /// 提交事务中的修改，事务中的块数不会超过 [`JournalState::capacity`]
+    ///
+    /// 没有修改任何块的事务什么也不写，只读设备上的只读操作也可以放在事务中
+    fn commit(&self, state: &mut JournalState) -> Result<()> {
+        if state.pending.is_empty() {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut state.pending);
+        let bs = state.block_size;
+
+        // 没有日志时直接写回
+        if state.blocks == 0 {
+            for (blk, data) in &pending {
+                self.inner.write_at(data, blk * bs)?;
+            }
+            return self.inner.flush();
+        }
+
+        // 1. 写入描述块和块的新内容
+        let desc_blocks = (pending.len() as u64).div_ceil(state.per_desc());
+        let mut desc = vec![0u8; (desc_blocks * bs) as usize];
+        for (i, (blk, data)) in pending.iter().enumerate() {
+            desc[i * 8..i * 8 + 8].copy_from_slice(&blk.to_le_bytes());
+            self.inner.write_at(data, (state.begin + 1 + desc_blocks + i as u64) * bs)?;
+        }
+        self.inner.write_at(&desc, (state.begin + 1) * bs)?;
+        self.inner.flush()?;
+
+        // 2. 写入日志头，事务在这里提交
+        self.write_header(state, pending.len() as u32)?;
+
+        // 3. 写回原来的位置
+        for (blk, data) in &pending {
+            self.inner.write_at(data, blk * bs)?;
+        }
+        self.inner.flush()?;
+
+        // 4. 清空日志
+        self.write_header(state, 0)
+    }
+
+    /// 写入日志头，`count` 是已提交事务中的块数
+    fn write_header(&self, state: &JournalState, count: u32) -> Result<()> {
+        let mut header = vec![0u8; state.block_size as usize];
+        header[0..4].copy_from_slice(&JOURNAL_MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&count.to_le_bytes());
+        self.inner.write_at(&header, state.begin * state.block_size)?;
+        self.inner.flush()
+    }
+}
+
+impl BlockDevice for Journal {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+        let state = self.state.lock().unwrap();
+        if state.pending.is_empty() {
+            return self.inner.read_at(buf, offset);
+        }
+
+        for (blk, range, buf_range) in split_blocks(state.block_size, offset, buf.len()) {
+            match state.pending.get(&blk) {
+                Some(data) => buf[buf_range].copy_from_slice(&data[range]),
+                None => {
+                    self.inner.read_at(&mut buf[buf_range], offset.max(blk * state.block_size))?;
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.depth == 0 {
+            return self.inner.write_at(buf, offset);
+        }
+
+        // 事务中的写入先缓存起来，第一次修改某个块时先读出它原来的内容。
+        // 有日志时缓存的块数不能超过日志区一次能提交的块数
+        let bs = state.block_size;
+        for (blk, range, buf_range) in split_blocks(bs, offset, buf.len()) {
+            let full = state.blocks > 0 && state.pending.len() as u64 >= state.capacity();
+            let data = match state.pending.entry(blk) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(_) if full => {
+                    return Err(Error::new(
+                        ErrorKind::StorageFull,
+                        "Transaction is too large for the journal",
+                    ));
+                }
+                Entry::Vacant(entry) => {
+                    let mut data = vec![0u8; bs as usize];
+                    self.inner.read_at(&mut data, blk * bs)?;
+                    entry.insert(data)
+                }
+            };
+            data[range].copy_from_slice(&buf[buf_range]);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&self) -> Result<()> {
+        self.inner.flush()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    fn verify_checksums(&self) -> bool {
+        self.state.lock().unwrap().checksums
+    }
+}
+
+impl Fs {
+    /// 在一个事务中执行 `f`
+    ///
+    /// `f` 返回错误时丢弃事务中的所有修改，内存中的超级块和组描述符也恢复成磁盘上的样子
+    pub(in crate::fs) fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.disk.begin();
+        let res = f(self);
+        if self.disk.end(res.is_ok())? && res.is_err() {
+            self.super_block = SuperBlock::from_disk(&self.disk, 0)?;
+            self.groups = GroupDesc::read_table(&self.disk, &self.super_block)?;
+        }
+        res
+    }
+
+    /// 当前事务中剩余的空间不足 `blocks` 个块时，先提交已经做完的修改，再继续这个事务
+    ///
+    /// 只能在磁盘上的状态一致的地方调用，提交过的修改在之后出错时不会撤销
+    pub(in crate::fs) fn ensure_room(&mut self, blocks: u64) -> Result<()> {
+        match self.disk.room() < blocks {
+            true => self.disk.restart(),
+            false => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_journal() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// 写入 `left` 次之后就出错的设备，用来模拟在任意位置崩溃
+    struct CrashDevice {
+        inner: MemDevice,
+        left: Arc<AtomicUsize>,
+    }
+
+    impl BlockDevice for CrashDevice {
+        fn read_at(&self, buf: &mut [u8], offset: u64) -> Result<usize> {
+            self.inner.read_at(buf, offset)
+        }
+
+        fn write_at(&self, buf: &[u8], offset: u64) -> Result<usize> {
+            match self.left.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)) {
+                Ok(_) => self.inner.write_at(buf, offset),
+                Err(_) => Err(Error::other("crashed")),
+            }
+        }
+    }
+
+    /// 检查组描述符和超级块中的空闲计数是否一致
+    fn check(fs: &Fs) {
+        let free_inodes: u32 = fs.groups.iter().map(|g| g.free_inodes_count as u32).sum();
+        let free_blocks: u32 = fs.groups.iter().map(|g| g.free_blocks_count as u32).sum();
+        assert_eq!(free_inodes, fs.super_block.free_inodes_count);
+        assert_eq!(free_blocks, fs.super_block.free_blocks_count);
+    }
+
+    let force = |journal_mode| MountOptions {
+        force: true,
+        journal_mode,
+        ..Default::default()
+    };
+
+    for mode in [JournalMode::Metadata, JournalMode::Data] {
+        let disk = MemDevice::new();
+        let mut fs = Fs::format_on(disk.clone(), &FormatOptions::with_root_password("root")).unwrap();
+        let free_inodes = fs.super_block.free_inodes_count;
+        fs.exit().unwrap();
+
+        // 在每一次写入处崩溃，重新挂载之后文件要么不存在，要么完整地创建并写入了内容
+        let mut crash_at = 0;
+        loop {
+            let mut data = vec![0u8; disk.len()];
+            disk.read_at(&mut data, 0).unwrap();
+            let image = MemDevice::new();
+            image.write_at(&data, 0).unwrap();
+            let left = Arc::new(AtomicUsize::new(usize::MAX));
+            let device = CrashDevice {
+                inner: image.clone(),
+                left: left.clone(),
+            };
+            let mut fs = Fs::mount(device, &force(mode)).unwrap();
+            left.store(crash_at, Ordering::SeqCst);
+            let done = fs.create("x").is_ok()
+                && fs.open_with("x", OpenOptions::new().write(true)).and_then(|fd| fs.write(fd, b"hello")).is_ok();
+            drop(fs);
+
+            let mut fs = Fs::mount(image, &force(mode)).unwrap();
+            check(&fs);
+            match fs.open("x") {
+                Ok(fd) => {
+                    assert_eq!(fs.super_block.free_inodes_count, free_inodes - 1);
+                    let mut buf = [0u8; 8];
+                    let len = fs.read(fd, &mut buf).unwrap();
+                    assert!(len == 0 || &buf[..len] == b"hello");
+                    assert!(!done || len == 5);
+                }
+                Err(_) => assert_eq!(fs.super_block.free_inodes_count, free_inodes),
+            }
+            if done {
+                break;
+            }
+            crash_at += 1;
+        }
+    }
+
+    // 事务出错时，其中的所有修改都被丢弃
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    let free_inodes = fs.super_block.free_inodes_count;
+    let res = fs.transaction(|fs| {
+        fs.mkdir("dir")?;
+        fs.create("dir/x")?;
+        Err::<(), _>(Error::other("abort"))
+    });
+    assert!(res.is_err());
+    assert!(fs.path_parse("dir").is_err());
+    assert_eq!(fs.super_block.free_inodes_count, free_inodes);
+    check(&fs);
+
+    // 超出日志区容量的事务整个失败，不会拆成几次提交
+    let options = FormatOptions {
+        journal_blocks: 24,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    let res = fs.transaction(|fs| (0..64).try_for_each(|i| fs.mkdir(&format!("d{}", i))));
+    assert_eq!(res.unwrap_err().kind(), ErrorKind::StorageFull);
+    assert!(fs.path_parse("d0").is_err());
+    assert_eq!(fs.super_block.free_inodes_count, free_inodes);
+    check(&fs);
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 写入、截短和删除比日志区大得多的文件，以及递归删除大目录，都分成几次提交
+    for (journal_mode, extents) in [(JournalMode::Metadata, false), (JournalMode::Data, false), (JournalMode::Data, true)] {
+        let disk = MemDevice::new();
+        let options = FormatOptions {
+            blocks_count: 8192,
+            journal_blocks: 24,
+            extents,
+            root_password: "root".into(),
+            ..Default::default()
+        };
+        Fs::format_on(disk.clone(), &options).unwrap().exit().unwrap();
+        let mut fs = Fs::mount(disk, &MountOptions { journal_mode, ..Default::default() }).unwrap();
+        let free_blocks = fs.super_block.free_blocks_count;
+
+        let data: Vec<u8> = (0..2 << 20).map(|i: u32| (i % 251) as u8).collect();
+        let fd = fs.open_with("big", OpenOptions::new().read(true).write(true).create(true)).unwrap();
+        assert_eq!(fs.write(fd, &data).unwrap(), data.len());
+        fs.cut(fd, 1 << 20).unwrap();
+        let fd = fs.open("big").unwrap();
+        let mut buf = vec![0u8; 2 << 20];
+        assert_eq!(fs.read(fd, &mut buf).unwrap(), 1 << 20);
+        assert_eq!(buf[..1 << 20], data[..1 << 20]);
+        fs.rm(fd).unwrap();
+        assert_eq!(fs.super_block.free_blocks_count, free_blocks);
+
+        fs.mkdir("dir").unwrap();
+        for i in 0..100 {
+            fs.create(&format!("dir/{}", i)).unwrap();
+        }
+        fs.rmdir_recursive("dir").unwrap();
+        assert_eq!(fs.super_block.free_blocks_count, free_blocks);
+        check(&fs);
+        assert!(fs.fsck(false).unwrap().is_clean());
+    }
+
+    // 没有修改的事务不写入设备，只读挂载时也能打开和读取文件
+    let disk = MemDevice::new();
+    let mut fs = Fs::format_on(disk.clone(), &FormatOptions::with_root_password("root")).unwrap();
+    let fd = fs.open_with("f", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.write(fd, b"read only").unwrap();
+    fs.exit().unwrap();
+    let mut fs = Fs::init_on(ReadOnlyDevice::new(disk)).unwrap();
+    fs.transaction(|_| Ok(())).unwrap();
+    let fd = fs.open("f").unwrap();
+    let mut buf = [0u8; 16];
+    assert_eq!(fs.read(fd, &mut buf).unwrap(), 9);
+    assert_eq!(&buf[..9], b"read only");
+
+    // 描述块中的块号超出镜像范围时拒绝重放
+    let disk = MemDevice::new();
+    let mut fs = Fs::format_on(disk.clone(), &FormatOptions::with_root_password("root")).unwrap();
+    let (bs, begin) = (fs.block_size() as u64, fs.super_block.journal_begin() as u64);
+    fs.exit().unwrap();
+    let mut header = vec![0u8; bs as usize];
+    header[0..4].copy_from_slice(&JOURNAL_MAGIC.to_le_bytes());
+    header[4..8].copy_from_slice(&1u32.to_le_bytes());
+    disk.write_at(&header, begin * bs).unwrap();
+    disk.write_at(&u64::MAX.to_le_bytes(), (begin + 1) * bs).unwrap();
+    let err = Fs::mount(disk.clone(), &force(JournalMode::Metadata)).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    // 指向日志区本身的块号同样被拒绝
+    disk.write_at(&begin.to_le_bytes(), (begin + 1) * bs).unwrap();
+    assert!(Fs::mount(disk, &force(JournalMode::Metadata)).is_err());
+}
@@ -0,0 +1,88 @@
+//! # 文件元数据模块
+//!
+//! [`Metadata`] 是 [`Fs::stat`]、[`Fs::lstat`] 和 [`Fs::fstat`] 的返回值，
+//! 把索引节点和目录项中的信息整理成调用者需要的形式，调用者不需要再直接读取 [`Inode`] 的字段。
+
+use super::*;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// 文件的元数据
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    /// 索引节点号
+    pub ino: u32,
+    /// 文件类型
+    pub file_type: FileType,
+    /// 存取权限，格式和 [`FileMode::mode`] 一样
+    pub mode: u16,
+    /// 文件拥有者的用户 ID
+    pub owner: u16,
+    /// 文件所属用户组的 ID
+    pub group: u16,
+    /// 文件大小（字节数）
+    pub size: u64,
+    /// 实际分配的数据块个数，不含索引块
+    pub blocks: u32,
+    /// 硬链接数量
+    pub links: u16,
+    /// 状态改变时间
+    pub ctime: DateTime<Utc>,
+    /// 修改时间
+    pub mtime: DateTime<Utc>,
+    /// 访问时间，更新策略见 [`AtimeMode`]
+    pub atime: DateTime<Utc>,
+    /// 创建时间
+    pub btime: DateTime<Utc>,
+}
+
+impl Metadata {
+    /// 由索引节点号、文件类型和索引节点生成元数据
+    pub(in crate::fs) fn new(ino: u32, file_type: FileType, inode: &Inode) -> Self {
+        // 超出 chrono 表示范围的时间戳只可能来自损坏的索引节点，显示为 Unix 纪元
+        let time = |time: Duration| {
+            i64::try_from(time.as_secs())
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, time.subsec_nanos()))
+                .unwrap_or_default()
+        };
+        Self {
+            ino,
+            file_type,
+            mode: inode.i_mode.mode,
+            owner: inode.i_mode.owner,
+            group: inode.i_mode.group,
+            size: inode.i_size as u64,
+            blocks: inode.i_blocks,
+            links: inode.i_links_count,
+            ctime: time(inode.ctime()),
+            mtime: time(inode.mtime()),
+            atime: time(inode.atime()),
+            btime: time(inode.crtime()),
+        }
+    }
+
+    /// 是否是目录
+    pub fn is_dir(&self) -> bool {
+        self.file_type == FileType::Dir
+    }
+
+    /// 是否是普通文件
+    pub fn is_file(&self) -> bool {
+        self.file_type == FileType::File
+    }
+
+    /// 是否是符号链接，只有 [`Fs::lstat`] 会返回符号链接
+    pub fn is_symlink(&self) -> bool {
+        self.file_type == FileType::Symlink
+    }
+
+    /// 存取权限、拥有者和所属用户组，可以用来显示 `rwxrwxrwx` 形式的权限
+    pub fn permissions(&self) -> FileMode {
+        FileMode {
+            mode: self.mode,
+            owner: self.owner,
+            group: self.group,
+        }
+    }
+}
@@ -4,11 +4,23 @@
 //! 
 //! ## 模块结构
 //! 
+//! - [`device`] - 块设备抽象，支持宿主机文件、内存、只读等多种磁盘
 //! - [`inode`] - 索引节点管理，处理文件元数据和数据块索引
+//! - [`extent`] - 区段树，用少量区段描述物理上连续的数据块
+//! - [`options`] - 格式化选项，决定磁盘镜像的几何参数
 //! - [`file`] - 文件类型和权限管理，定义文件权限模式
+//! - [`metadata`] - 文件元数据，`stat` 系列函数的返回值
 //! - [`traits`] - 核心特征定义，提供数据序列化和磁盘IO功能
 //! - [`iter`] - 目录项迭代器，支持目录内容遍历
+//! - [`htree`] - 目录索引，按文件名的哈希值快速查找大目录中的目录项
 //! - [`fs`] - 文件系统主体结构，管理磁盘空间和用户会话
+//! - [`userdb`] - 用户数据库，用户和用户组保存在镜像中的 `/etc/passwd` 和 `/etc/group` 里
+//! - [`shadow`] - 密码存储，保存用户密码的加盐散列和账户状态
+//! - [`handle`] - 文件句柄，为镜像中的文件实现 `std::io` 的读写 trait
+//! - [`cache`] - 块缓存，按 LRU 顺序缓存磁盘块，脏块延迟写回
+//! - [`journal`] - 预写式日志，保证一次操作中的多次写入要么全部生效，要么都不生效
+//! - [`fsck`] - 文件系统检查，重新计算位图和计数，把孤立的文件放入 lost+found
+//! - [`upgrade`] - 磁盘格式升级，挂载时把旧版本的镜像转换为当前版本
 //! - [`utils`] - 工具函数集合，提供常用的辅助功能
 //! 
 //! ## 主要功能
@@ -22,32 +34,56 @@
 //! ## 使用示例
 //! 
 //! ```rust
-//! use crate::fs::core::*;
+//! use simulate_unixlike_fs::fs::*;
 //! 
-//! // 创建文件系统
-//! let mut fs = Fs::format()?;
+//! # fn main() -> Result<()> {
+//! // 在内存磁盘上创建文件系统
+//! let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root"))?;
 //! 
 //! // 创建目录
 //! fs.mkdir("documents")?;
 //! 
-//! // 创建文件
-//! let fd = fs.create("test.txt")?;
+//! // 创建并以写模式打开文件
+//! let fd = fs.open_with("test.txt", OpenOptions::new().write(true).create(true))?;
 //! fs.write(fd, b"Hello, World!")?;
 //! fs.close(fd)?;
+//! # Ok(())
+//! # }
 //! ```
 
+pub mod device;
 pub mod inode;
+pub mod extent;
+pub mod options;
 pub mod file;
+pub mod metadata;
 pub mod traits;
 pub mod iter;
+pub mod htree;
 pub mod fs;
+pub mod userdb;
+pub mod shadow;
+pub mod cache;
+pub mod handle;
+pub mod journal;
+pub mod fsck;
+pub mod upgrade;
 pub mod utils;
 
+pub use device::*;
 pub use inode::*;
+pub use options::*;
 pub use file::*;
+pub use metadata::*;
 pub use traits::*;
 pub use iter::*;
 pub use fs::*;
+pub use userdb::*;
+pub use shadow::*;
+pub use cache::*;
+pub use handle::*;
+pub use journal::*;
+pub use fsck::*;
 pub use super::constant::*;
 
 pub use std::io::Error;
@@ -0,0 +1,279 @@
+//! # 格式化选项模块
+//!
+//! [`FormatOptions`] 描述了新建磁盘镜像时的几何参数，由 [`Fs::format`] 使用；
+//! [`MountOptions`] 控制挂载已有镜像时的行为，由 [`Fs::mount`] 使用，其中 [`AtimeMode`] 决定怎样更新访问时间；
+//! [`OpenOptions`] 决定打开文件时的访问模式，由 [`Fs::open_with`] 使用。
+
+use super::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// 格式化选项
+///
+/// 所有字段都有默认值，可以只修改关心的部分：
+///
+/// ```
+/// # use simulate_unixlike_fs::fs::*;
+/// let options = FormatOptions {
+///     block_size: 1024,
+///     volume_name: "Backup".into(),
+///     root_password: "root".into(),
+///     ..Default::default()
+/// };
+/// let fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+/// assert_eq!(fs.block_size(), 1024);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FormatOptions {
+    /// 镜像文件的路径，只有 [`Fs::format`] 会用到
+    pub path: PathBuf,
+    /// 磁盘块大小，只能是 [`SUPPORTED_BLOCK_SIZES`] 中的一个
+    pub block_size: usize,
+    /// 数据块个数，超过一个块组能容纳的个数（`block_size * 8`）时会划分成多个块组
+    pub blocks_count: usize,
+    /// 索引节点个数，即文件系统最多能容纳的文件个数，会平均分配到每个块组
+    pub inodes_count: usize,
+    /// 卷名
+    pub volume_name: String,
+    /// root 用户的初始密码，长度不限，以散列的形式保存在 [`SHADOW_PATH`] 中，不能为空
+    pub root_password: String,
+    /// 新建的文件是否使用区段树映射数据块，见 [`extent`]
+    pub extents: bool,
+    /// 日志区的块数，为 0 时不使用日志，见 [`journal`]
+    pub journal_blocks: usize,
+    /// 读取元数据时是否检查校验和，见 [`FEATURE_RO_COMPAT_METADATA_CSUM`]
+    pub checksums: bool,
+    /// 是否为超过一个数据块的目录建立哈希索引，见 [`FEATURE_COMPAT_DIR_INDEX`]
+    pub dir_index: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(DISK_PATH),
+            block_size: DEFAULT_BLOCK_SIZE,
+            blocks_count: DEFAULT_BLOCK_SIZE * 8,
+            inodes_count: DEFAULT_BLOCK_SIZE * 8,
+            volume_name: "Ext2Disk".into(),
+            root_password: String::new(),
+            extents: false,
+            journal_blocks: DEFAULT_JOURNAL_BLOCKS,
+            checksums: true,
+            dir_index: true,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// 默认的格式化选项，root 的初始密码为 `root_password`
+    pub fn with_root_password(root_password: &str) -> Self {
+        Self {
+            root_password: root_password.into(),
+            ..Default::default()
+        }
+    }
+
+    /// 检查选项是否合法
+    ///
+    /// # 错误
+    /// - 块大小不受支持
+    /// - 数据块个数少于 2，或者索引节点个数为 0
+    /// - 平均到每个块组的索引节点个数超过了一个位图块能记录的个数
+    /// - 日志区太小，放不下日志头、一个描述块和一个数据块
+    /// - 没有指定 root 的初始密码
+    pub fn validate(&self) -> Result<()> {
+        if !SUPPORTED_BLOCK_SIZES.contains(&self.block_size) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unsupported block size {}, should be one of {:?}",
+                    self.block_size, SUPPORTED_BLOCK_SIZES
+                ),
+            ));
+        }
+
+        // 0 号数据块保留不用，至少还要有一个数据块存放根目录
+        if self.blocks_count < 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Block count should be at least 2",
+            ));
+        }
+
+        let groups_count = self.blocks_count.div_ceil(self.block_size * 8);
+        if self.inodes_count == 0 || self.inodes_count > groups_count * self.block_size * 8 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Inode count should between 1 and {}",
+                    groups_count * self.block_size * 8
+                ),
+            ));
+        }
+
+        if self.journal_blocks != 0 && self.journal_blocks < 3 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Journal should be 0 or at least 3 blocks",
+            ));
+        }
+
+        if self.root_password.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Root password can't be empty",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// 挂载选项
+#[derive(Clone, Debug)]
+pub struct MountOptions {
+    /// 即使上次没有正常卸载，也强制挂载
+    pub force: bool,
+    /// 日志模式，只记录元数据还是连文件内容一起记录
+    pub journal_mode: JournalMode,
+    /// 块缓存最多缓存的块数，为 0 时不使用缓存，见 [`cache`]
+    pub cache_blocks: usize,
+    /// 读取文件时怎样更新访问时间
+    pub atime: AtimeMode,
+}
+
+impl Default for MountOptions {
+    fn default() -> Self {
+        Self {
+            force: false,
+            journal_mode: JournalMode::default(),
+            cache_blocks: DEFAULT_CACHE_BLOCKS,
+            atime: AtimeMode::default(),
+        }
+    }
+}
+
+/// 访问时间的更新策略，对应 Linux 的 `strictatime`、`relatime` 和 `noatime` 挂载选项
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AtimeMode {
+    /// 每次读取都更新访问时间
+    Strict,
+    /// 只有访问时间早于修改时间或状态改变时间，或者距离上次更新超过一天时才更新，
+    /// 避免每次读取都要写索引节点
+    #[default]
+    Relatime,
+    /// 从不更新访问时间
+    Noatime,
+}
+
+impl AtimeMode {
+    /// 读取文件之后是否需要更新它的访问时间
+    pub(in crate::fs) fn need_update(self, inode: &Inode, now: Duration) -> bool {
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+        match self {
+            AtimeMode::Strict => true,
+            AtimeMode::Relatime => {
+                let atime = inode.atime();
+                atime <= inode.mtime() || atime <= inode.ctime() || now.saturating_sub(atime) >= DAY
+            }
+            AtimeMode::Noatime => false,
+        }
+    }
+}
+
+/// 打开文件的选项，用法和 [`std::fs::OpenOptions`] 一样
+///
+/// ```
+/// # use simulate_unixlike_fs::fs::*;
+/// let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+/// let fd = fs.open_with("log", OpenOptions::new().append(true).create(true)).unwrap();
+/// fs.write(fd, b"hello").unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct OpenOptions {
+    pub(in crate::fs) read: bool,
+    pub(in crate::fs) write: bool,
+    pub(in crate::fs) append: bool,
+    pub(in crate::fs) truncate: bool,
+    pub(in crate::fs) create: bool,
+    pub(in crate::fs) create_new: bool,
+}
+
+impl OpenOptions {
+    /// 所有选项都关闭，至少要打开 `read`、`write` 和 `append` 中的一个
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 以读模式打开，需要文件的读权限
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// 以写模式打开，需要文件的写权限
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// 以追加模式打开，每次写入前都先把文件指针移到文件末尾，隐含 `write`
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// 打开时把文件长度截断为 0，需要 `write`
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    /// 文件不存在时创建文件，需要 `write` 或 `append`
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// 总是创建新文件，文件已经存在时打开失败，需要 `write` 或 `append`
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// 是否可以写入文件
+    pub(in crate::fs) fn writable(&self) -> bool {
+        self.write || self.append
+    }
+
+    /// 检查选项的组合是否合法
+    ///
+    /// # 错误
+    /// - `read`、`write` 和 `append` 都没有打开
+    /// - 打开了 `truncate`，但是没有打开 `write`，或者同时打开了 `append`
+    /// - 打开了 `create` 或 `create_new`，但是不能写入文件
+    pub fn validate(&self) -> Result<()> {
+        if !self.read && !self.writable() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Should open for reading, writing or appending",
+            ));
+        }
+
+        if self.truncate && (!self.write || self.append) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Truncate needs write mode and can't be used with append",
+            ));
+        }
+
+        if (self.create || self.create_new) && !self.writable() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Create needs write or append mode",
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+//! # 密码存储模块
+//!
+//! 用户的密码不以明文保存，而是用 Argon2id 计算加盐的散列，和账户状态一起保存在镜像中的 [`SHADOW_PATH`] 里。
+//! 每行一条记录，格式和 Linux 的 `/etc/shadow` 一样：
+//!
+//! ```text
+//! 用户名:散列:最后修改密码的日期:::::过期日期:
+//! ```
+//!
+//! - 散列使用 PHC 字符串格式（`$argon2id$v=19$m=...,t=...,p=...$盐$散列`），算法参数和盐都记录在字符串中，
+//!   以后调整参数也能验证旧的散列；散列前面加 `!` 表示账户被禁用
+//! - 日期都是从 Unix 纪元开始的天数，过期日期为空表示永不过期
+//!
+//! 这个文件的拥有者是 root，权限为 `0o600`，普通用户不能读取，只能通过 [`Fs::login`] 和 [`Fs::passwd`] 间接使用。
+//!
+//! 从版本 9 升级的镜像中，还没有登录过的用户的密码仍然以明文保存在超级块的保留区域里，
+//! 用户第一次登录成功时散列写入这个文件，明文随之清零。
+
+use super::*;
+use argon2::Argon2;
+use password_hash::rand_core::OsRng;
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// 计算密码的加盐散列，返回 PHC 格式的字符串
+///
+/// 每次调用都会生成新的随机盐，同一个密码两次的结果不同
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::other(format!("Hash password failed: {}", e)))
+}
+
+/// 检查密码和 [`hash_password`] 生成的散列是否匹配，散列格式不正确时返回 `false`
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// 今天是从 Unix 纪元开始的第几天
+pub(in crate::fs) fn today() -> u64 {
+    utils::now().as_secs() / (24 * 3600)
+}
+
+/// [`SHADOW_PATH`] 中的一条记录
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shadow {
+    /// 用户名
+    pub name: String,
+    /// 密码散列，不是合法的散列（例如 `*`）时不能用任何密码登录
+    pub hash: String,
+    /// 账户是否被禁用
+    pub locked: bool,
+    /// 最后一次修改密码的日期
+    pub last_change: Option<u64>,
+    /// 账户的过期日期，这一天及以后不能登录
+    pub expire: Option<u64>,
+}
+
+impl Shadow {
+    /// 账户现在是否已经过期
+    pub fn is_expired(&self) -> bool {
+        self.expire.is_some_and(|expire| today() >= expire)
+    }
+}
+
+impl FromStr for Shadow {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let [name, hash, last_change, _, _, _, _, expire, _] = split_fields(line)?;
+        let optional = |field: &str| match field {
+            "" => Ok(None),
+            field => parse_field(field).map(Some),
+        };
+        Ok(Self {
+            name: name.into(),
+            hash: hash.trim_start_matches('!').into(),
+            locked: hash.starts_with('!'),
+            last_change: optional(last_change)?,
+            expire: optional(expire)?,
+        })
+    }
+}
+
+impl Display for Shadow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let optional = |field: Option<u64>| field.map(|day| day.to_string()).unwrap_or_default();
+        write!(
+            f,
+            "{}:{}{}:{}:::::{}:",
+            self.name,
+            if self.locked { "!" } else { "" },
+            self.hash,
+            optional(self.last_change),
+            optional(self.expire)
+        )
+    }
+}
+
+impl Fs {
+    /// 读取 [`SHADOW_PATH`] 中的全部记录
+    pub(in crate::fs) fn read_shadows(&mut self) -> Result<Vec<Shadow>> {
+        self.read_records(SHADOW_PATH)
+    }
+
+    /// 用 `shadows` 覆盖 [`SHADOW_PATH`]
+    pub(in crate::fs) fn write_shadows(&mut self, shadows: &[Shadow]) -> Result<()> {
+        self.write_records(SHADOW_PATH, shadows, 0o600)
+    }
+
+    /// 读取用户 `name` 的记录
+    pub(in crate::fs) fn find_shadow(&mut self, name: &str) -> Result<Shadow> {
+        self.read_shadows()?
+            .into_iter()
+            .find(|shadow| shadow.name == name)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "User not exists."))
+    }
+
+    /// 修改用户 `name` 的记录
+    pub(in crate::fs) fn update_shadow(&mut self, name: &str, f: impl FnOnce(&mut Shadow)) -> Result<()> {
+        let mut shadows = self.read_shadows()?;
+        let Some(shadow) = shadows.iter_mut().find(|shadow| shadow.name == name) else {
+            return Err(Error::new(ErrorKind::NotFound, "User not exists."));
+        };
+        f(shadow);
+        self.write_shadows(&shadows)
+    }
+
+    /// 为新用户 `name` 添加记录，`password` 为空时账户没有密码，不能登录
+    pub(in crate::fs) fn init_shadow(&mut self, name: &str, password: &str) -> Result<()> {
+        let hash = match password {
+            "" => "*".to_string(),
+            password => hash_password(password)?,
+        };
+        let mut shadows = self.read_shadows()?;
+        shadows.retain(|shadow| shadow.name != name);
+        self.forget_legacy_password(name)?;
+        shadows.push(Shadow {
+            name: name.into(),
+            hash,
+            locked: false,
+            last_change: Some(today()),
+            expire: None,
+        });
+        self.write_shadows(&shadows)
+    }
+
+    /// 把用户 `name` 的密码设置为 `password`，保存的是它的散列
+    pub(in crate::fs) fn set_password(&mut self, name: &str, password: &str) -> Result<()> {
+        if password.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Password can't be empty"));
+        }
+        let hash = hash_password(password)?;
+        self.update_shadow(name, |shadow| {
+            shadow.hash = hash;
+            shadow.last_change = Some(today());
+        })?;
+        self.forget_legacy_password(name)
+    }
+
+    /// 删除用户 `name` 的记录
+    pub(in crate::fs) fn remove_shadow(&mut self, name: &str) -> Result<()> {
+        let mut shadows = self.read_shadows()?;
+        shadows.retain(|shadow| shadow.name != name);
+        self.forget_legacy_password(name)?;
+        self.write_shadows(&shadows)
+    }
+
+    /// 用户 `name` 在超级块中保留的明文密码的位置
+    fn legacy_slot(&self, name: &str) -> Option<usize> {
+        self.super_block
+            .reserved
+            .iter()
+            .position(|slot| slot[16] != 0 && utils::str(&slot[..16]) == name)
+    }
+
+    /// 清除用户 `name` 在超级块中保留的明文密码
+    fn forget_legacy_password(&mut self, name: &str) -> Result<()> {
+        match self.legacy_slot(name) {
+            Some(i) => {
+                self.super_block.reserved[i] = [0; 32];
+                self.write_super_block()
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 用升级之前的明文密码检查用户 `name` 的密码 `password`
+    ///
+    /// 密码匹配并且设备可写时把散列写入 [`SHADOW_PATH`]，同时清除明文
+    fn check_legacy_password(&mut self, name: &str, password: &str) -> Result<bool> {
+        let Some(i) = self.legacy_slot(name) else {
+            return Ok(false);
+        };
+        if utils::str(&self.super_block.reserved[i][16..]) != password {
+            return Ok(false);
+        }
+        if !self.disk.is_read_only() {
+            self.transaction(|fs| fs.set_password(name, password))?;
+        }
+        Ok(true)
+    }
+
+    /// 检查用户 `name` 能否用密码 `password` 登录
+    ///
+    /// 密码正确之后才检查账户是否被禁用或者过期，密码错误时不会透露账户的状态
+    pub(in crate::fs) fn check_password(&mut self, name: &str, password: &str) -> Result<()> {
+        let wrong = || Error::new(ErrorKind::PermissionDenied, "Wrong username or password");
+        let shadow = self.find_shadow(name).map_err(|_| wrong())?;
+        if !verify_password(password, &shadow.hash) && !self.check_legacy_password(name, password)? {
+            return Err(wrong());
+        }
+        if shadow.locked {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Account is locked"));
+        }
+        if shadow.is_expired() {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Account has expired"));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_password() {
+    // 同一个密码每次的散列都不同，但都能通过验证
+    let hash = hash_password("secret").unwrap();
+    assert!(hash.starts_with("$argon2id$"));
+    assert_ne!(hash, hash_password("secret").unwrap());
+    assert!(verify_password("secret", &hash));
+    assert!(!verify_password("Secret", &hash));
+    assert!(!verify_password("secret", "*"));
+
+    // 记录和文本互相转换
+    let line = format!("alice:!{}:20000:::::20500:", hash);
+    let shadow: Shadow = line.parse().unwrap();
+    assert!(shadow.locked && shadow.hash == hash);
+    assert_eq!((shadow.last_change, shadow.expire), (Some(20000), Some(20500)));
+    assert_eq!(shadow.to_string(), line);
+    assert!("alice:x:1".parse::<Shadow>().is_err());
+
+    // 必须指定 root 的初始密码
+    let err = Fs::format_on(MemDevice::new(), &Default::default()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    // 格式化时指定的 root 密码只以散列的形式保存，普通用户不能读取
+    let long = "a passphrase much longer than sixteen bytes";
+    let options = FormatOptions {
+        root_password: long.into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    assert_eq!(fs.stat(SHADOW_PATH).unwrap().mode, 0o600);
+    fs.login("root", long).unwrap();
+    assert!(fs.login("root", &long[..16]).is_err());
+
+    fs.useradd("alice", "a").unwrap();
+    fs.login("alice", "a").unwrap();
+    let err = fs.open_with(SHADOW_PATH, OpenOptions::new().read(true)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(fs.passwd("root", "x").unwrap_err().kind(), ErrorKind::PermissionDenied);
+    fs.passwd("alice", "new password").unwrap();
+    assert!(fs.login("alice", "a").is_err());
+    fs.login("alice", "new password").unwrap();
+
+    // 删除用户时一起删除密码记录
+    fs.login("root", long).unwrap();
+    fs.userdel("alice").unwrap();
+    assert!(fs.read_shadows().unwrap().iter().all(|shadow| shadow.name != "alice"));
+}
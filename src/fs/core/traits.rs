@@ -3,30 +3,61 @@
 //! 为文件系统定义了多种 trait，以满足对元素的读写功能：
 //!
 //! 1. [`IntoBytes`] 用来将 Rust 结构体转换成字节数组，这样就可以将
-//!   整个结构写入磁盘了
+//!    整个结构写入磁盘了，转换时会填好结构体中的 CRC32C 校验和
 //! 2. [`ByteArray`] 主要是用来将 `&str` 字符串转换成字符数组
-//! 3. [`FromDisk`] 从磁盘文件里读取对象
+//! 3. [`FromDisk`] 从磁盘文件里读取对象，设备要求校验时检查校验和
 //! 4. [`FsFileExt`] 为标准库的 File 类型提供跨平台的定位读写功能
 
 use super::*;
-use std::mem::size_of;
+use std::mem::{offset_of, size_of};
 
 /// 字节转换特征
 /// 
 /// 为结构体提供转换为字节数组的能力，用于将数据写入磁盘
 pub trait IntoBytes: Sized {
+    /// 结构体的名字，校验和不匹配时出现在错误信息里
+    const NAME: &'static str;
+    /// 校验和字段（`u32`）在结构体中的字节偏移
+    const CHECKSUM_OFFSET: usize;
+
     /// 将结构体转换为字节数组
     /// 
     /// # 返回值
-    /// 返回结构体的字节表示，可直接写入磁盘
+    /// 返回结构体的字节表示，校验和字段已经填好，可直接写入磁盘
     /// 
     /// # 安全性
     /// 此方法使用 unsafe 代码将结构体内存直接转换为字节数组
-    fn bytes(&self) -> &[u8] {
-        convert(self)
+    fn bytes(&self) -> Vec<u8> {
+        with_checksum(convert(self).to_vec(), Self::CHECKSUM_OFFSET)
     }
 }
 
+/// 计算校验和并填入 `bytes` 中偏移为 `offset` 的校验和字段
+fn with_checksum(mut bytes: Vec<u8>, offset: usize) -> Vec<u8> {
+    let checksum = checksum(&bytes, offset);
+    bytes[offset..offset + 4].copy_from_slice(&checksum.to_le_bytes());
+    bytes
+}
+
+/// 计算结构体的校验和，计算时把校验和字段本身当作 0
+fn checksum(bytes: &[u8], offset: usize) -> u32 {
+    let crc = utils::crc32c_update(!0, &bytes[..offset]);
+    let crc = utils::crc32c_update(crc, &[0; 4]);
+    !utils::crc32c_update(crc, &bytes[offset + 4..])
+}
+
+/// 结构体的字节表示中记录的校验和是否正确
+fn checksum_matches(bytes: &[u8], offset: usize) -> bool {
+    let field = &bytes[offset..offset + 4];
+    u32::from_le_bytes(field.try_into().unwrap()) == checksum(bytes, offset)
+}
+
+/// 检查磁盘上 `offset` 处的结构体的校验和，不管设备是否要求校验
+pub(in crate::fs) fn checksum_ok<T: FromDisk>(disk: &dyn BlockDevice, offset: u64) -> Result<bool> {
+    let bytes = T::read_bytes(disk, offset)?;
+    Ok(checksum_matches(&bytes, T::CHECKSUM_OFFSET))
+}
+
 /// 内部转换函数
 /// 
 /// # 参数
@@ -43,9 +74,35 @@ fn convert<'b, 'a: 'b, T>(ref_val: &'a T) -> &'b [u8] {
 }
 
 // 为核心数据结构实现 IntoBytes 特征
-impl IntoBytes for GroupDesc {}
-impl IntoBytes for Inode {}
-impl IntoBytes for DirEntry {}
+impl IntoBytes for SuperBlock {
+    const NAME: &'static str = "Super block";
+    const CHECKSUM_OFFSET: usize = offset_of!(SuperBlock, checksum);
+}
+impl IntoBytes for GroupDesc {
+    const NAME: &'static str = "Group descriptor";
+    const CHECKSUM_OFFSET: usize = offset_of!(GroupDesc, checksum);
+}
+impl IntoBytes for Inode {
+    const NAME: &'static str = "Inode";
+    const CHECKSUM_OFFSET: usize = offset_of!(Inode, i_checksum);
+}
+impl IntoBytes for DirEntry {
+    const NAME: &'static str = "Directory entry";
+    const CHECKSUM_OFFSET: usize = 8;
+
+    /// 目录项在磁盘上是变长记录，只包括头部和文件名，不包括 `rec_len` 中多出来的空间
+    fn bytes(&self) -> Vec<u8> {
+        let name_len = self.name_len as usize;
+        let mut bytes = Vec::with_capacity(DirEntry::rec_size(name_len));
+        bytes.extend_from_slice(&self.i_node.to_le_bytes());
+        bytes.extend_from_slice(&self.rec_len.to_le_bytes());
+        bytes.extend_from_slice(&[self.name_len, self.file_type]);
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&self.name[..name_len]);
+        bytes.resize(DirEntry::rec_size(name_len), 0);
+        with_checksum(bytes, Self::CHECKSUM_OFFSET)
+    }
+}
 
 /// 字节数组转换特征
 /// 
@@ -79,19 +136,17 @@ impl ByteArray for &str {
     /// 使用 unsafe 代码进行内存复制，但已确保边界检查
     fn into_array<const LEN: usize>(self) -> Result<[u8; LEN]> {
         if self.len() >= LEN {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 format!("Too long!! Should less {} bytes", LEN).to_string(),
             ));
         }
 
-        if self.len() == 0 {
-            return Err(Error::new(ErrorKind::Other, "can't receive empty string"));
+        if self.is_empty() {
+            return Err(Error::other("can't receive empty string"));
         }
 
         if self.contains("/") {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 "Can't contains char '/'",
             ));
         }
@@ -107,36 +162,84 @@ impl ByteArray for &str {
 /// 从磁盘读取特征
 /// 
 /// 为结构体提供从磁盘文件中读取数据的能力
-pub trait FromDisk: Sized + Default {
+pub trait FromDisk: IntoBytes + Default {
+    /// 读取磁盘指定位置上结构体的字节表示，默认读取结构体大小的字节
+    fn read_bytes(disk: &dyn BlockDevice, offset: u64) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; size_of::<Self>()];
+        disk.read_at(&mut bytes, offset)?;
+        Ok(bytes)
+    }
+
+    /// 从字节表示还原结构体，默认将这些字节复制到默认的结构体实例中
+    /// 
+    /// # 安全性
+    /// 使用 unsafe 代码直接操作结构体内存
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut obj = Self::default();
+        let slice = unsafe {
+            std::slice::from_raw_parts_mut(&mut obj as *mut Self as *mut u8, size_of::<Self>())
+        };
+        slice.copy_from_slice(bytes);
+        obj
+    }
+
     /// 从磁盘指定位置读取结构体数据
     /// 
     /// # 参数
-    /// - `disk`: 磁盘文件引用
+    /// - `disk`: 块设备引用
     /// - `offset`: 读取位置的字节偏移量
     /// 
     /// # 返回值
     /// 成功时返回读取的结构体实例，失败时返回IO错误
     /// 
-    /// # 算法
-    /// 1. 创建默认的结构体实例
-    /// 2. 将结构体内存作为字节数组
-    /// 3. 从磁盘指定位置读取数据填充结构体
+    /// # 错误
+    /// [`BlockDevice::verify_checksums`] 为真并且校验和不匹配时返回 `InvalidData` 错误，
+    /// 错误信息里包含结构体的名字和位置
     /// 
-    /// # 安全性
-    /// 使用 unsafe 代码直接操作结构体内存
-    fn from_disk(disk: &std::fs::File, offset: u64) -> Result<Self> {
-        let mut obj = Self::default();
-        let slice = unsafe {
-            std::slice::from_raw_parts_mut(&mut obj as *mut Self as *mut u8, size_of::<Self>())
-        };
-        disk.read_at(slice, offset)?;
-        Ok(obj)
+    /// # 算法
+    /// 1. 用 [`FromDisk::read_bytes`] 读取结构体的字节表示
+    /// 2. 需要时检查校验和
+    /// 3. 用 [`FromDisk::from_bytes`] 还原结构体
+    fn from_disk(disk: &dyn BlockDevice, offset: u64) -> Result<Self> {
+        let bytes = Self::read_bytes(disk, offset)?;
+        if disk.verify_checksums() && !checksum_matches(&bytes, Self::CHECKSUM_OFFSET) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} at byte {}: checksum mismatch", Self::NAME, offset),
+            ));
+        }
+
+        Ok(Self::from_bytes(&bytes))
     }
 }
 
 // 为核心数据结构实现 FromDisk 特征
-impl FromDisk for DirEntry {}
+impl FromDisk for DirEntry {
+    /// 先读取头部，再按照头部中的文件名长度读取文件名
+    fn read_bytes(disk: &dyn BlockDevice, offset: u64) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; DIR_ENTRY_HEADER_SIZE];
+        disk.read_at(&mut bytes, offset)?;
+        bytes.resize(DirEntry::rec_size(bytes[6] as usize), 0);
+        disk.read_at(&mut bytes[DIR_ENTRY_HEADER_SIZE..], offset + DIR_ENTRY_HEADER_SIZE as u64)?;
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut entry = DirEntry {
+            i_node: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            rec_len: u16::from_le_bytes(bytes[4..6].try_into().unwrap()),
+            name_len: bytes[6],
+            file_type: bytes[7],
+            checksum: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            ..Default::default()
+        };
+        let name_len = entry.name_len as usize;
+        entry.name[..name_len].copy_from_slice(&bytes[DIR_ENTRY_HEADER_SIZE..][..name_len]);
+        entry
+    }
+}
 impl FromDisk for Inode {}
+impl FromDisk for SuperBlock {}
 impl FromDisk for GroupDesc {}
 
 /// 文件扩展特征
@@ -193,3 +296,100 @@ impl FsFileExt for std::fs::File {
         std::os::windows::fs::FileExt::seek_write(self, buf, offset)
     }
 }
+
+#[test]
+fn test_checksums() {
+    assert_eq!(utils::crc32c(b"123456789"), 0xE306_9283);
+
+    // 翻转磁盘上 `addr` 处的一个字节
+    fn flip(device: &MemDevice, addr: u64) {
+        let mut byte = [0u8];
+        device.read_at(&mut byte, addr).unwrap();
+        byte[0] ^= 0x10;
+        device.write_at(&byte, addr).unwrap();
+    }
+
+    let device = MemDevice::new();
+    let mut fs = Fs::format_on(device.clone(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.create("a").unwrap();
+    let res = fs.path_parse("a").unwrap();
+    let inode_addr = fs.addr_i_node(res.dir_entry.i_node);
+    let entry_addr = res.dir_entry_addr;
+    let desc_addr = fs.addr_group_desc(0);
+    let bs = fs.block_size() as u64;
+    let dir_block_addr = entry_addr - entry_addr % bs;
+    let block_bitmap_addr = fs.groups[0].block_bitmap as u64 * bs;
+    let inode_bitmap_addr = fs.groups[0].inode_bitmap as u64 * bs;
+    fs.exit().unwrap();
+
+    // 超级块和组描述符损坏时无法挂载，索引节点和目录数据块损坏时访问对应的文件出错；
+    // 目录数据块中的任何一个字节损坏，包括没有使用的空间，都由块末尾的校验和记录发现
+    let structures = [
+        (4, 0, SuperBlock::NAME),
+        (desc_addr + 4, desc_addr, GroupDesc::NAME),
+        (inode_addr + 4, inode_addr, Inode::NAME),
+        (entry_addr + 4, dir_block_addr, DirEntry::BLOCK_NAME),
+        (dir_block_addr + bs - DIR_TAIL_SIZE as u64 - 8, dir_block_addr, DirEntry::BLOCK_NAME),
+    ];
+    for (flipped, addr, name) in structures {
+        flip(&device, flipped);
+        let err = match Fs::mount(device.clone(), &Default::default()) {
+            Ok(mut fs) => {
+                let err = fs.open("a").unwrap_err();
+                fs.exit().unwrap();
+                err
+            }
+            Err(err) => err,
+        };
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), format!("{} at byte {}: checksum mismatch", name, addr));
+        flip(&device, flipped);
+    }
+
+    // 位图损坏时分配失败，错误信息里是位图的名字和位置
+    let bitmaps = [(block_bitmap_addr, GroupDesc::BLOCK_BITMAP_NAME), (inode_bitmap_addr, GroupDesc::INODE_BITMAP_NAME)];
+    for (addr, name) in bitmaps {
+        flip(&device, addr + bs - 1);
+        let mut fs = Fs::mount(device.clone(), &Default::default()).unwrap();
+        let err = fs.mkdir("d").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), format!("{} at byte {}: checksum mismatch", name, addr));
+        fs.exit().unwrap();
+        flip(&device, addr + bs - 1);
+    }
+
+    // fsck 报告损坏的校验和，并按照现有内容重新计算
+    flip(&device, inode_addr + 4);
+    flip(&device, dir_block_addr + bs - DIR_TAIL_SIZE as u64 - 8);
+    let mut fs = Fs::mount(device.clone(), &Default::default()).unwrap();
+    fs.groups[0].inode_bitmap_csum ^= 1;
+    fs.write_group_desc(0).unwrap();
+    let report = fs.fsck(false).unwrap();
+    assert_eq!(
+        report.problems,
+        [
+            FsckProblem::Checksum { name: DirEntry::BLOCK_NAME, addr: dir_block_addr },
+            FsckProblem::Checksum { name: Inode::NAME, addr: inode_addr },
+            FsckProblem::Checksum { name: GroupDesc::INODE_BITMAP_NAME, addr: inode_bitmap_addr },
+        ]
+    );
+    assert!(fs.fsck(true).unwrap().repaired);
+    assert!(fs.fsck(false).unwrap().is_clean());
+    fs.open("a").unwrap();
+    fs.mkdir("d").unwrap();
+    fs.exit().unwrap();
+
+    // 没有校验和的磁盘照常挂载，不做检查
+    let device = MemDevice::new();
+    let options = FormatOptions {
+        checksums: false,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(device.clone(), &options).unwrap();
+    fs.create("a").unwrap();
+    fs.exit().unwrap();
+    flip(&device, inode_addr + 4);
+    let mut fs = Fs::mount(device, &Default::default()).unwrap();
+    fs.open("a").unwrap();
+}
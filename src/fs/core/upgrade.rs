@@ -0,0 +1,512 @@
+//! # 磁盘格式升级模块
+//!
+//! 版本 9 的镜像在第一次以可写方式挂载时由 [`Fs::mount`] 升级到 [`FS_VERSION`]：
+//!
+//! 1. 计算每个块组的两个位图的校验和，记录到组描述符中原来空闲计数的高 16 位里
+//! 2. 把每个已分配的索引节点改为新的布局：拥有者和用户组从 8 位扩展为 16 位，
+//!    之后的硬链接数和标志各向后移动 2 字节
+//! 3. 重新排列每个目录的目录项，在每个数据块末尾留出校验和记录，目录的哈希索引被丢弃，
+//!    所有索引节点转换完之后再为超过一个数据块的目录重建索引
+//! 4. 把超级块中的用户表和用户组表写入 [`PASSWD_PATH`]、[`GROUP_PATH`]，
+//!    旧的 `用户名:散列` 格式的 [`SHADOW_PATH`] 改写为新的格式
+//! 5. 清空超级块中不再使用的区域，把版本号改为 [`FS_VERSION`]
+//!
+//! 旧的 UID 和 GID 是用户表和用户组表的下标，按照下面的规则映射为新的ID：
+//!
+//! | 旧ID | 新ID |
+//! |------|------|
+//! | UID 0 | [`ROOT_UID`] |
+//! | UID `i` | [`FIRST_ID`] + `i` - 1 |
+//! | GID 0（root） | [`ROOT_GID`] |
+//! | GID 1（users） | [`USERS_GID`] |
+//! | GID `g` | [`FIRST_ID`] + `g` - 2 |
+//!
+//! 还没有登录过的用户的密码仍然是明文，升级后保留在 [`SuperBlock`] 的保留区域中，
+//! 第一次登录成功时改为散列保存，见 [`shadow`](super::shadow)。
+//!
+//! 索引节点分批转换，每批一个事务，日志放不下时在两个索引节点之间提前提交，
+//! 转换进度记录在超级块中，升级中途断电后再次挂载时从断点继续。
+
+use super::*;
+use std::io::Read;
+
+/// 每个事务转换的索引节点表块数
+const UPGRADE_BATCH_BLOCKS: u32 = 8;
+
+/// 版本 9 超级块末尾区域中各个字段的偏移，相对于 [`SuperBlock::reserved_tail`]
+mod v9 {
+    /// 用户个数，升级开始后改为 [`super::UPGRADE_MARKER`]
+    pub const USERS_LEN: usize = 0;
+    /// 用户组表，4 个用户组，每个是 16 字节的组名加 16 位的成员位图
+    pub const USER_GROUPS: usize = 2;
+    /// 用户组表的项数
+    pub const USER_GROUPS_CAP: usize = 4;
+    /// 用户组表中每一项的大小
+    pub const USER_GROUP_SIZE: usize = 18;
+    /// 每个用户的主组
+    pub const PRIMARY_GIDS: usize = 74;
+    /// 用户组个数和之后的填充，升级开始后记录下一个要转换的索引节点号
+    pub const PROGRESS: usize = 84;
+}
+
+/// 正在升级的标记，版本 9 的用户个数不超过 10，不会与它冲突
+const UPGRADE_MARKER: u16 = 0xffff;
+
+/// 版本 9 的用户ID对应的新ID
+fn upgrade_uid(uid: u8) -> u16 {
+    match uid {
+        0 => ROOT_UID,
+        uid => FIRST_ID + uid as u16 - 1,
+    }
+}
+
+/// 版本 9 的用户组ID对应的新ID
+fn upgrade_gid(gid: u8) -> u16 {
+    match gid {
+        0 => ROOT_GID,
+        1 => USERS_GID,
+        gid => FIRST_ID + gid as u16 - 2,
+    }
+}
+
+/// 把按照新的布局读取的版本 9 的索引节点 `inode` 改为新的布局
+///
+/// 版本 9 在 `i_mode` 之后依次是 8 位的拥有者和用户组、硬链接数和标志，
+/// 按照新的布局读取时，它们分别落在 `owner`、`group` 和 `i_links_count` 中
+fn upgrade_inode_layout(mut inode: Inode) -> Inode {
+    let [owner, group] = inode.i_mode.owner.to_le_bytes();
+    inode.i_flags = inode.i_links_count;
+    inode.i_links_count = inode.i_mode.group;
+    inode.i_mode.owner = upgrade_uid(owner);
+    inode.i_mode.group = upgrade_gid(group);
+    inode
+}
+
+impl SuperBlock {
+    /// 以字节的形式读取 [`SuperBlock::reserved_tail`]
+    fn tail(&self) -> Vec<u8> {
+        self.reserved_tail.iter().flat_map(|word| word.to_ne_bytes()).collect()
+    }
+
+    /// 修改 [`SuperBlock::reserved_tail`] 中从 `offset` 开始的字节
+    fn set_tail(&mut self, offset: usize, bytes: &[u8]) {
+        let mut tail = self.tail();
+        tail[offset..offset + bytes.len()].copy_from_slice(bytes);
+        for (word, chunk) in self.reserved_tail.iter_mut().zip(tail.chunks(8)) {
+            *word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        }
+    }
+}
+
+impl Fs {
+    /// 把版本 [`UPGRADABLE_VERSION`] 的文件系统升级到 [`FS_VERSION`]
+    ///
+    /// # 错误
+    /// 只读设备上不能升级，返回 `PermissionDenied` 错误
+    pub(in crate::fs) fn upgrade(&mut self) -> Result<()> {
+        if self.disk.is_read_only() {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "Filesystem version {} must be upgraded on a writable device",
+                    self.super_block.version
+                ),
+            ));
+        }
+
+        let tail = self.super_block.tail();
+        let mut next = match u16::from_ne_bytes([tail[v9::USERS_LEN], tail[v9::USERS_LEN + 1]]) {
+            UPGRADE_MARKER => u32::from_ne_bytes(tail[v9::PROGRESS..v9::PROGRESS + 4].try_into().unwrap()),
+            _ => 0,
+        };
+        self.super_block.set_tail(v9::USERS_LEN, &UPGRADE_MARKER.to_ne_bytes());
+        self.transaction(|fs| fs.upgrade_bitmap_checksums())?;
+
+        let bitmaps = self.fsck_read_bitmaps(BlkType::INode)?;
+        let dirs = self.upgrade_find_dirs(next)?;
+        let per_group = self.super_block.inodes_per_group;
+        let batch = (self.block_size() / INODE_SIZE) as u32 * UPGRADE_BATCH_BLOCKS;
+        while next < self.super_block.inodes_count {
+            let end = next.saturating_add(batch).min(self.super_block.inodes_count);
+            self.transaction(|fs| {
+                for inode_i in next..end {
+                    let map = &bitmaps[(inode_i / per_group) as usize];
+                    if !fsck::test_bit(map, (inode_i % per_group) as usize) {
+                        continue;
+                    }
+                    if fs.disk.room() < STEP_BLOCKS {
+                        fs.super_block.set_tail(v9::PROGRESS, &inode_i.to_ne_bytes());
+                        fs.write_super_block()?;
+                        fs.ensure_room(STEP_BLOCKS)?;
+                    }
+                    fs.upgrade_inode(inode_i, dirs.contains(&inode_i))?;
+                }
+                fs.super_block.set_tail(v9::PROGRESS, &end.to_ne_bytes());
+                fs.write_super_block()
+            })?;
+            next = end;
+        }
+
+        // 重建被丢弃的索引，中途断电后再次挂载时已经有索引的目录会被跳过
+        if self.dir_index_enabled() {
+            self.transaction(|fs| {
+                for &dir_i in &dirs {
+                    let inode = fs.get_inode(dir_i)?;
+                    if !inode.is_indexed() && inode.i_size as usize > fs.block_size() {
+                        fs.dx_reindex(dir_i)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        self.transaction(|fs| fs.upgrade_userdb())
+    }
+
+    /// 计算每个块组的位图的校验和
+    ///
+    /// 版本 9 的空闲计数不超过 16 位，高 16 位总是 0，按照新的布局读取时就是两个为 0 的校验和
+    fn upgrade_bitmap_checksums(&mut self) -> Result<()> {
+        for group in 0..self.groups.len() {
+            for map_type in [BlkType::DataBlk, BlkType::INode] {
+                let (addr, _, _) = self.bitmap_info(group, map_type);
+                let mut map = self.empty_blk();
+                self.disk.read_at(&mut map, addr)?;
+                self.write_bitmap(group, map_type, &map)?;
+            }
+            self.write_group_desc(group)?;
+        }
+        Ok(())
+    }
+
+    /// 从根目录出发找到所有的目录，索引节点中没有记录文件类型，只能从目录项中得知
+    ///
+    /// 索引节点号小于 `next` 的索引节点已经转换过了
+    fn upgrade_find_dirs(&self, next: u32) -> Result<std::collections::BTreeSet<u32>> {
+        let mut dirs = std::collections::BTreeSet::from([0]);
+        let mut stack = vec![0];
+        while let Some(dir_i) = stack.pop() {
+            let inode = match dir_i < next {
+                true => self.get_inode(dir_i)?,
+                false => upgrade_inode_layout(self.get_inode(dir_i)?),
+            };
+            for entry in self.upgrade_read_dir(&inode)? {
+                let name = &entry.name[..entry.name_len as usize];
+                if entry.file_type == u8::from(FileType::Dir) && name != b"." && name != b".." && dirs.insert(entry.i_node) {
+                    stack.push(entry.i_node);
+                }
+            }
+        }
+        Ok(dirs)
+    }
+
+    /// 读取目录 `inode` 中所有正在使用的目录项
+    ///
+    /// 版本 9 的目录项一直延伸到数据块的末尾，已经转换过的目录的数据块末尾是校验和记录，两种都能读取
+    fn upgrade_read_dir(&self, inode: &Inode) -> Result<Vec<DirEntry>> {
+        let block_size = self.block_size();
+        let mut entries = Vec::new();
+        for blk in 0..inode.i_size as usize / block_size {
+            let addr = inode.convert_addr(self, (blk * block_size) as u64)?.addr;
+            let mut block = self.empty_blk();
+            self.disk.read_at(&mut block, addr)?;
+            let mut offset = 0;
+            while offset < block_size {
+                let rec_len = u16::from_le_bytes([block[offset + 4], block[offset + 5]]) as usize;
+                let size = DirEntry::rec_size(block[offset + 6] as usize);
+                if rec_len < size || offset + rec_len > block_size {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{} at byte {}: bad record length", DirEntry::NAME, addr + offset as u64),
+                    ));
+                }
+                let entry = DirEntry::from_bytes(&block[offset..offset + size]);
+                if entry.file_type != 0 && entry.file_type != DIR_TAIL_TYPE {
+                    entries.push(entry);
+                }
+                offset += rec_len;
+            }
+        }
+        Ok(entries)
+    }
+
+    /// 把按照版本 9 的布局写入的索引节点 `inode_i` 改为新的布局，目录还要重新排列目录项
+    fn upgrade_inode(&mut self, inode_i: u32, is_dir: bool) -> Result<()> {
+        let mut inode = upgrade_inode_layout(self.get_inode(inode_i)?);
+        if is_dir {
+            self.upgrade_dir(inode_i, &mut inode)?;
+        }
+        self.write_inode(inode_i, inode)
+    }
+
+    /// 把目录 `dir_i` 中的目录项重新紧密地写入各个数据块，在每个数据块末尾留出校验和记录
+    ///
+    /// 版本 9 的目录项一直延伸到数据块的末尾，留出校验和记录之后可能放不下，这时为目录追加数据块。
+    /// 哈希索引的结点是没有使用的记录，在重新排列时被丢弃，之后由 [`Fs::upgrade`] 重建
+    fn upgrade_dir(&mut self, dir_i: u32, inode: &mut Inode) -> Result<()> {
+        let (block_size, end) = (self.block_size(), self.dir_block_end());
+        let entries = self.upgrade_read_dir(inode)?;
+
+        // 按顺序装入各个数据块，`.` 和 `..` 仍然在第 0 个数据块的开头
+        let mut packed: Vec<Vec<DirEntry>> = vec![Vec::new()];
+        let mut used = 0;
+        for entry in entries {
+            let size = DirEntry::rec_size(entry.name_len as usize);
+            if used + size > end {
+                packed.push(Vec::new());
+                used = 0;
+            }
+            used += size;
+            packed.last_mut().unwrap().push(entry);
+        }
+        while inode.i_size as usize / block_size < packed.len() {
+            inode.alloc_data_block(self, dir_i)?;
+            inode.i_size += block_size as u32;
+        }
+        packed.resize(inode.i_size as usize / block_size, Vec::new());
+        for (blk, mut entries) in packed.into_iter().enumerate() {
+            self.write_dir_block(inode, blk as u32, &mut entries)?;
+        }
+        inode.i_flags &= !INODE_FLAG_INDEX;
+        Ok(())
+    }
+
+    /// 把超级块中的用户表和用户组表写入用户数据库，改写旧格式的密码文件，最后更新版本号
+    fn upgrade_userdb(&mut self) -> Result<()> {
+        let tail = self.super_block.tail();
+
+        // 旧的密码文件每行是 `用户名:散列`，没有记录的用户还在使用明文密码
+        let hashes = self.as_root(|fs| {
+            let mut content = String::new();
+            match fs.open_handle(SHADOW_PATH, OpenOptions::new().read(true)) {
+                Ok(mut file) => file.read_to_string(&mut content)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => 0,
+                Err(e) => return Err(e),
+            };
+            Ok(content
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, hash)| (name.to_string(), hash.to_string()))
+                .collect::<Vec<_>>())
+        })?;
+
+        let slots: Vec<(usize, String)> = (self.super_block.reserved.iter().enumerate())
+            .filter(|(_, slot)| slot[0] != 0)
+            .map(|(i, slot)| (i, utils::str(&slot[..16]).to_string()))
+            .collect();
+
+        let users: Vec<User> = slots
+            .iter()
+            .map(|(i, name)| {
+                let uid = upgrade_uid(*i as u8);
+                User {
+                    name: name.clone(),
+                    uid,
+                    gid: upgrade_gid(tail[v9::PRIMARY_GIDS + i]),
+                    comment: if uid == ROOT_UID { "root".into() } else { String::new() },
+                    home: if uid == ROOT_UID { "/root".into() } else { format!("/home/{}", name) },
+                    shell: DEFAULT_SHELL.into(),
+                }
+            })
+            .collect();
+
+        let groups: Vec<UserGroup> = (0..v9::USER_GROUPS_CAP)
+            .map(|g| &tail[v9::USER_GROUPS + g * v9::USER_GROUP_SIZE..][..v9::USER_GROUP_SIZE])
+            .enumerate()
+            .filter(|(_, group)| group[0] != 0)
+            .map(|(g, group)| {
+                let members = u16::from_ne_bytes([group[16], group[17]]);
+                UserGroup {
+                    name: utils::str(&group[..16]).into(),
+                    gid: upgrade_gid(g as u8),
+                    members: (slots.iter())
+                        .filter(|(i, _)| members & (1 << i) != 0)
+                        .map(|(_, name)| name.clone())
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let shadows: Vec<Shadow> = slots
+            .iter()
+            .map(|(_, name)| Shadow {
+                name: name.clone(),
+                hash: (hashes.iter())
+                    .find(|(entry, _)| entry == name)
+                    .map_or_else(|| "*".into(), |(_, hash)| hash.clone()),
+                locked: false,
+                last_change: Some(today()),
+                expire: None,
+            })
+            .collect();
+
+        self.write_users(&users)?;
+        self.write_user_groups(&groups)?;
+        self.write_shadows(&shadows)?;
+
+        // 已经有散列的用户不再需要明文密码，只保留还没有迁移的明文密码
+        for (i, name) in &slots {
+            let slot = &mut self.super_block.reserved[*i];
+            if slot[16] == 0 || hashes.iter().any(|(entry, _)| entry == name) {
+                *slot = [0; 32];
+            }
+        }
+        self.super_block.reserved_tail = Default::default();
+        self.super_block.version = FS_VERSION;
+        self.write_super_block()
+    }
+}
+
+#[test]
+fn test_upgrade() {
+    let disk = MemDevice::new();
+    let options = FormatOptions {
+        extents: true,
+        ..FormatOptions::with_root_password("root")
+    };
+    let mut fs = Fs::format_on(disk.clone(), &options).unwrap();
+    fs.useradd("alice", "a").unwrap();
+    fs.useradd("bob", "b").unwrap();
+    fs.groupadd("dev").unwrap();
+    fs.group_add_member("dev", "alice").unwrap();
+    fs.mkdir("/srv").unwrap();
+    fs.chown("/srv", "alice").unwrap();
+    fs.chgrp("/srv", "dev").unwrap();
+    let fd = fs.open_with("/srv/notes", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.write(fd, b"hello").unwrap();
+    fs.close(fd).unwrap();
+    fs.link("/srv/notes", "/srv/notes.bak").unwrap();
+    fs.mkdir("/srv/many").unwrap();
+    for i in 0..200 {
+        fs.create(&format!("/srv/many/file-{}", i)).unwrap();
+    }
+    assert!(fs.get_inode(fs.path_parse("/srv/many").unwrap().dir_entry.i_node).unwrap().is_indexed());
+
+    // 手工改回版本 9 的格式：root 的密码已经是散列，alice 还在用明文密码，bob 已经迁移
+    let root_hash = fs.find_shadow("root").unwrap().hash;
+    let bob_hash = fs.find_shadow("bob").unwrap().hash;
+    for path in [PASSWD_PATH, GROUP_PATH] {
+        let fd = fs.open(path).unwrap();
+        fs.rm(fd).unwrap();
+    }
+    let shadow = format!("root:{}\nbob:{}\n", root_hash, bob_hash);
+    let fd = fs.open_with(SHADOW_PATH, OpenOptions::new().write(true).truncate(true)).unwrap();
+    fs.write(fd, shadow.as_bytes()).unwrap();
+    fs.close(fd).unwrap();
+
+    let downgrade_uid = |uid: u16| match uid {
+        ROOT_UID => 0,
+        uid => (uid - FIRST_ID + 1) as u8,
+    };
+    let downgrade_gid = |gid: u16| match gid {
+        ROOT_GID => 0,
+        USERS_GID => 1,
+        gid => (gid - FIRST_ID + 2) as u8,
+    };
+    // 版本 9 的目录数据块末尾没有校验和记录，最后一个目录项一直延伸到块的末尾
+    let bs = fs.block_size();
+    for dir_i in fs.upgrade_find_dirs(u32::MAX).unwrap() {
+        let inode = fs.get_inode(dir_i).unwrap();
+        for blk in 0..inode.i_size as usize / bs {
+            let addr = inode.convert_addr(&fs, (blk * bs) as u64).unwrap().addr;
+            let mut block = fs.empty_blk();
+            fs.disk.read_at(&mut block, addr).unwrap();
+            let mut offset = 0;
+            let (offset, mut last) = loop {
+                let entry = DirEntry::from_bytes(&block[offset..][..DirEntry::rec_size(block[offset + 6] as usize)]);
+                match offset + entry.rec_len as usize == fs.dir_block_end() {
+                    true => break (offset, entry),
+                    false => offset += entry.rec_len as usize,
+                }
+            };
+            last.rec_len += DIR_TAIL_SIZE as u16;
+            block[offset..][..DirEntry::rec_size(last.name_len as usize)].copy_from_slice(&last.bytes());
+            block[bs - DIR_TAIL_SIZE..].fill(0);
+            fs.disk.write_at(&block, addr).unwrap();
+        }
+    }
+
+    let bitmaps = fs.fsck_read_bitmaps(BlkType::INode).unwrap();
+    let per_group = fs.super_block.inodes_per_group;
+    for inode_i in 0..fs.super_block.inodes_count {
+        if fsck::test_bit(&bitmaps[(inode_i / per_group) as usize], (inode_i % per_group) as usize) {
+            let mut inode = fs.get_inode(inode_i).unwrap();
+            let (owner, group) = (downgrade_uid(inode.i_mode.owner), downgrade_gid(inode.i_mode.group));
+            inode.i_mode.owner = u16::from_le_bytes([owner, group]);
+            inode.i_mode.group = inode.i_links_count;
+            inode.i_links_count = inode.i_flags;
+            inode.i_flags = 0;
+            fs.write_inode(inode_i, inode).unwrap();
+        }
+    }
+
+    let slot = |name: &str, password: &str| {
+        let mut slot = [0; 32];
+        slot[..name.len()].copy_from_slice(name.as_bytes());
+        slot[16..16 + password.len()].copy_from_slice(password.as_bytes());
+        slot
+    };
+    fs.super_block.reserved[0] = slot("root", "");
+    fs.super_block.reserved[1] = slot("alice", "a");
+    fs.super_block.reserved[2] = slot("bob", "");
+    let group = |name: &str, members: u16| {
+        let mut group = name.into_array::<16>().unwrap().to_vec();
+        group.extend_from_slice(&members.to_ne_bytes());
+        group
+    };
+    let groups = [group("root", 0), group("users", 0), group("dev", 0b10)].concat();
+    fs.super_block.set_tail(v9::USERS_LEN, &3u16.to_ne_bytes());
+    fs.super_block.set_tail(v9::USER_GROUPS, &groups);
+    fs.super_block.set_tail(v9::PRIMARY_GIDS, &[0, 1, 1]);
+    fs.super_block.set_tail(v9::PROGRESS, &3u16.to_ne_bytes());
+    fs.super_block.version = UPGRADABLE_VERSION;
+    for group in 0..fs.groups.len() {
+        fs.groups[group].block_bitmap_csum = 0;
+        fs.groups[group].inode_bitmap_csum = 0;
+        fs.write_group_desc(group).unwrap();
+    }
+    fs.exit().unwrap();
+
+    // 只读设备上不能升级
+    let err = Fs::init_on(ReadOnlyDevice::new(disk.clone())).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+
+    let mut fs = Fs::init_on(disk.clone()).unwrap();
+    assert_eq!(fs.super_block().version, FS_VERSION);
+    let names = |users: Vec<User>| users.into_iter().map(|user| (user.name, user.uid, user.gid)).collect::<Vec<_>>();
+    assert_eq!(
+        names(fs.users().unwrap()),
+        [("root".into(), ROOT_UID, ROOT_GID), ("alice".into(), FIRST_ID, USERS_GID), ("bob".into(), FIRST_ID + 1, USERS_GID)]
+    );
+    let dev = fs.find_group("dev").unwrap();
+    assert_eq!((dev.gid, dev.members), (FIRST_ID, vec!["alice".to_string()]));
+
+    // 拥有者、用户组、硬链接数和区段标志都保留下来
+    let srv = fs.stat("/srv").unwrap();
+    assert_eq!((srv.owner, srv.group), (FIRST_ID, FIRST_ID));
+    assert_eq!(fs.stat("/srv/notes").unwrap().links, 2);
+    let notes = fs.path_parse("/srv/notes").unwrap().dir_entry.i_node;
+    assert_ne!(fs.get_inode(notes).unwrap().i_flags & INODE_FLAG_EXTENTS, 0);
+    let mut content = String::new();
+    fs.open_handle("/srv/notes", OpenOptions::new().read(true)).unwrap().read_to_string(&mut content).unwrap();
+    assert_eq!(content, "hello");
+
+    // 目录重新排列之后每个数据块都有校验和记录，索引也重建了，位图也有了校验和
+    let many = fs.get_inode(fs.path_parse("/srv/many").unwrap().dir_entry.i_node).unwrap();
+    assert!(many.is_indexed() && fs.dx_check(&many, true).unwrap());
+    for i in 0..200 {
+        let fd = fs.open(&format!("/srv/many/file-{}", i)).unwrap();
+        fs.close(fd).unwrap();
+    }
+    fs.create("/srv/many/new").unwrap();
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 明文密码在第一次登录时改为散列，已有的散列照常使用
+    fs.login("root", "root").unwrap();
+    fs.login("bob", "b").unwrap();
+    assert!(fs.login("alice", "b").is_err());
+    fs.login("alice", "a").unwrap();
+    assert!(fs.super_block.reserved.iter().all(|slot| *slot == [0; 32]));
+    assert!(verify_password("a", &fs.find_shadow("alice").unwrap().hash));
+    fs.exit().unwrap();
+}
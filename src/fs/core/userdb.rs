@@ -0,0 +1,297 @@
+//! # 用户数据库模块
+//!
+//! 用户和用户组以文本的形式保存在镜像中，格式和 Linux 上的同名文件一样，每行一条记录，字段之间用 `:` 分隔：
+//!
+//! - [`PASSWD_PATH`]：`用户名:x:UID:GID:说明:主目录:登录shell`，所有用户可读
+//! - [`GROUP_PATH`]：`组名:x:GID:成员1,成员2`，所有用户可读
+//! - [`SHADOW_PATH`]：密码散列和账户状态，只有 root 可以读写，见 [`shadow`](super::shadow)
+//!
+//! 用户和用户组的个数只受磁盘空间的限制，索引节点中的 [`FileMode::owner`] 和 [`FileMode::group`]
+//! 记录的就是这里的 UID 和 GID。这些文件由 [`Fs::useradd`]、[`Fs::groupadd`] 等函数维护，
+//! 读写时临时切换为 root，所以普通用户登录、修改自己的密码时也能使用。
+//!
+//! ```
+//! # use simulate_unixlike_fs::fs::*;
+//! let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+//! fs.useradd("alice", "secret").unwrap();
+//!
+//! let alice = fs.find_user("alice").unwrap();
+//! assert_eq!((alice.uid, alice.gid, alice.home.as_str()), (FIRST_ID, USERS_GID, "/home/alice"));
+//! assert_eq!(fs.users().unwrap().len(), 2);
+//! ```
+
+use super::*;
+use std::fmt::{self, Display};
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+/// [`PASSWD_PATH`] 中的一个用户
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct User {
+    /// 用户名
+    pub name: String,
+    /// 用户ID
+    pub uid: u16,
+    /// 主组的ID
+    pub gid: u16,
+    /// 说明，一般是用户的全名
+    pub comment: String,
+    /// 主目录
+    pub home: String,
+    /// 登录 shell
+    pub shell: String,
+}
+
+/// [`GROUP_PATH`] 中的一个用户组
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserGroup {
+    /// 用户组名
+    pub name: String,
+    /// 用户组ID
+    pub gid: u16,
+    /// 附加成员的用户名，以这个组为主组的用户不需要列在这里
+    pub members: Vec<String>,
+}
+
+/// 把一行记录按 `:` 拆分成 `N` 个字段，字段个数不对时返回 `InvalidData` 错误
+pub(in crate::fs) fn split_fields<const N: usize>(line: &str) -> Result<[&str; N]> {
+    let fields: Vec<&str> = line.split(':').collect();
+    fields
+        .try_into()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Bad record: {}", line)))
+}
+
+/// 解析记录中的数字字段
+pub(in crate::fs) fn parse_field<T: FromStr>(field: &str) -> Result<T> {
+    field
+        .parse()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, format!("Bad field: {}", field)))
+}
+
+/// 检查用户名或用户组名：不能为空，不能包含 `:`、`,`、`/` 和空白字符
+pub(in crate::fs) fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains([':', ',', '/']) || name.contains(char::is_whitespace) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid name '{}'", name),
+        ));
+    }
+    Ok(())
+}
+
+/// 在已经使用的ID之后分配一个新的ID，从 [`FIRST_ID`] 开始
+pub(in crate::fs) fn next_id(used: impl Iterator<Item = u16>) -> Result<u16> {
+    match used.filter(|&id| id >= FIRST_ID).max() {
+        None => Ok(FIRST_ID),
+        Some(max) => max.checked_add(1).ok_or_else(|| Error::other("No free ID")),
+    }
+}
+
+impl FromStr for User {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let [name, _, uid, gid, comment, home, shell] = split_fields(line)?;
+        Ok(Self {
+            name: name.into(),
+            uid: parse_field(uid)?,
+            gid: parse_field(gid)?,
+            comment: comment.into(),
+            home: home.into(),
+            shell: shell.into(),
+        })
+    }
+}
+
+impl Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:x:{}:{}:{}:{}:{}",
+            self.name, self.uid, self.gid, self.comment, self.home, self.shell
+        )
+    }
+}
+
+impl FromStr for UserGroup {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let [name, _, gid, members] = split_fields(line)?;
+        Ok(Self {
+            name: name.into(),
+            gid: parse_field(gid)?,
+            members: members
+                .split(',')
+                .filter(|member| !member.is_empty())
+                .map(String::from)
+                .collect(),
+        })
+    }
+}
+
+impl Display for UserGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:x:{}:{}", self.name, self.gid, self.members.join(","))
+    }
+}
+
+impl Fs {
+    /// 以 root 身份执行 `f`，结束后恢复当前用户
+    pub(in crate::fs) fn as_root<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let user = std::mem::replace(&mut self.user, ROOT_UID);
+        let gids = std::mem::replace(&mut self.gids, vec![ROOT_GID]);
+        let res = f(self);
+        self.user = user;
+        self.gids = gids;
+        res
+    }
+
+    /// 读取 `path` 中的全部记录，文件不存在时返回空表
+    pub(in crate::fs) fn read_records<T: FromStr<Err = Error>>(&mut self, path: &str) -> Result<Vec<T>> {
+        self.as_root(|fs| {
+            let mut file = match fs.open_handle(path, OpenOptions::new().read(true)) {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(e),
+            };
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            content.lines().filter(|line| !line.is_empty()).map(str::parse).collect()
+        })
+    }
+
+    /// 用 `records` 覆盖 `path` 并把权限设置为 `mode`，文件或者所在的目录不存在时创建
+    pub(in crate::fs) fn write_records<T: Display>(&mut self, path: &str, records: &[T], mode: u16) -> Result<()> {
+        let content: String = records.iter().map(|record| format!("{}\n", record)).collect();
+
+        self.as_root(|fs| {
+            fs.transaction(|fs| {
+                let (dir, _) = path.rsplit_once('/').unwrap();
+                if fs.stat(dir).is_err() {
+                    fs.mkdir(dir)?;
+                }
+                let mut file = fs.open_handle(
+                    path,
+                    OpenOptions::new().write(true).create(true).truncate(true),
+                )?;
+                file.write_all(content.as_bytes())?;
+                drop(file);
+                fs.chmod(path, mode)
+            })
+        })
+    }
+
+    /// 用 `users` 覆盖 [`PASSWD_PATH`]
+    pub(in crate::fs) fn write_users(&mut self, users: &[User]) -> Result<()> {
+        self.write_records(PASSWD_PATH, users, 0o644)
+    }
+
+    /// 用 `groups` 覆盖 [`GROUP_PATH`]
+    pub(in crate::fs) fn write_user_groups(&mut self, groups: &[UserGroup]) -> Result<()> {
+        self.write_records(GROUP_PATH, groups, 0o644)
+    }
+
+    /// 所有用户，按照它们在 [`PASSWD_PATH`] 中的顺序排列
+    pub fn users(&mut self) -> Result<Vec<User>> {
+        self.read_records(PASSWD_PATH)
+    }
+
+    /// 所有用户组，按照它们在 [`GROUP_PATH`] 中的顺序排列
+    pub fn user_groups(&mut self) -> Result<Vec<UserGroup>> {
+        self.read_records(GROUP_PATH)
+    }
+
+    /// 按用户名查找用户
+    pub fn find_user(&mut self, name: &str) -> Result<User> {
+        self.users()?
+            .into_iter()
+            .find(|user| user.name == name)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "User not exists."))
+    }
+
+    /// 按用户ID查找用户
+    pub fn find_user_by_uid(&mut self, uid: u16) -> Result<User> {
+        self.users()?
+            .into_iter()
+            .find(|user| user.uid == uid)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "User not exists."))
+    }
+
+    /// 按用户组名查找用户组
+    pub fn find_group(&mut self, name: &str) -> Result<UserGroup> {
+        self.user_groups()?
+            .into_iter()
+            .find(|group| group.name == name)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Group not exists."))
+    }
+
+    /// 用户 `user` 所在的全部用户组：主组在前，之后是作为附加成员的用户组
+    pub(in crate::fs) fn user_gids(&mut self, user: &User) -> Result<Vec<u16>> {
+        let supplementary = self.user_groups()?.into_iter().filter_map(|group| {
+            (group.gid != user.gid && group.members.contains(&user.name)).then_some(group.gid)
+        });
+        Ok(std::iter::once(user.gid).chain(supplementary).collect())
+    }
+
+    /// 格式化时写入 root 用户和默认的用户组：root 的主组 root，以及新用户的主组 users
+    pub(in crate::fs) fn init_userdb(&mut self, root_password: &str) -> Result<()> {
+        let root = User {
+            name: "root".into(),
+            uid: ROOT_UID,
+            gid: ROOT_GID,
+            comment: "root".into(),
+            home: "/root".into(),
+            shell: DEFAULT_SHELL.into(),
+        };
+        let groups = [("root", ROOT_GID), ("users", USERS_GID)].map(|(name, gid)| UserGroup {
+            name: name.into(),
+            gid,
+            members: Vec::new(),
+        });
+
+        self.write_users(&[root])?;
+        self.write_user_groups(&groups)?;
+        self.init_shadow("root", root_password)
+    }
+}
+
+#[test]
+fn test_userdb() {
+    // 记录和文本互相转换
+    let line = "alice:x:1000:100:Alice:/home/alice:/bin/sh";
+    let user: User = line.parse().unwrap();
+    assert_eq!((user.uid, user.gid, user.shell.as_str()), (1000, 100, "/bin/sh"));
+    assert_eq!(user.to_string(), line);
+    let group: UserGroup = "dev:x:1001:alice,bob".parse().unwrap();
+    assert_eq!(group.members, ["alice", "bob"]);
+    assert_eq!(group.to_string(), "dev:x:1001:alice,bob");
+    assert!("dev:x:1001".parse::<UserGroup>().is_err());
+    assert!("alice:x:uid:100::/home/alice:/bin/sh".parse::<User>().is_err());
+    assert!(check_name("a:b").is_err() && check_name("").is_err() && check_name("a b").is_err());
+
+    // 用户数不再受超级块的限制，UID 依次分配
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    assert_eq!(fs.stat(PASSWD_PATH).unwrap().mode, 0o644);
+    for i in 0..20 {
+        fs.useradd(&format!("user{}", i), "p").unwrap();
+    }
+    let users = fs.users().unwrap();
+    assert_eq!(users.len(), 21);
+    assert_eq!(users[20].uid, FIRST_ID + 19);
+    assert_eq!(fs.stat("/home/user19").unwrap().owner, FIRST_ID + 19);
+
+    // 新的 UID 总是在现有的最大 UID 之后分配
+    fs.userdel("user19").unwrap();
+    fs.useradd("late", "p").unwrap();
+    assert_eq!(fs.find_user("late").unwrap().uid, FIRST_ID + 19);
+    assert_eq!(fs.find_user("user19").unwrap_err().kind(), ErrorKind::NotFound);
+
+    // 普通用户不能修改用户数据库
+    fs.login("user0", "p").unwrap();
+    assert_eq!(fs.current_user(), FIRST_ID);
+    let err = fs.open_with(PASSWD_PATH, OpenOptions::new().write(true)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert_eq!(fs.useradd("x", "p").unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert_eq!(fs.users().unwrap().len(), 21);
+}
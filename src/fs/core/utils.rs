@@ -2,38 +2,56 @@
 //!
 //! 此模块提供了文件系统操作中常用的工具函数，包括：
 //! - 时间戳获取
-//! - 内存块操作
 //! - 字符串处理
+//! - CRC32C 校验和
 //! - 文件系统状态查询
 
 use super::*;
-use std::io::Write;
 
-/// 获取当前Unix时间戳
+/// 获取当前时间
 /// 
 /// # 返回值
-/// 返回当前时间的Unix时间戳（秒数），类型为u32
+/// 返回从 Unix 纪元开始经过的时长，精确到纳秒
 /// 
 /// # 用途
-/// 主要用于设置文件的创建时间和修改时间
+/// 主要用于设置文件的各种时间戳和超级块的挂载、写入时间
 #[inline(always)]
-pub fn now() -> u32 {
+pub fn now() -> std::time::Duration {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
-        .as_secs() as u32
 }
 
-/// 创建空的数据块
-/// 
-/// # 返回值
-/// 返回大小为 BLOCK_SIZE 的零填充字节数组
-/// 
-/// # 用途
-/// 用于初始化新的数据块或清空现有数据块
-#[inline(always)]
-pub fn empty_blk() -> [u8; BLOCK_SIZE as usize] {
-    [0u8; BLOCK_SIZE as usize]
+/// CRC32C（Castagnoli）多项式的查找表
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0x82F6_3B78,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// 计算 `data` 的 CRC32C 校验和
+pub fn crc32c(data: &[u8]) -> u32 {
+    !crc32c_update(!0, data)
+}
+
+/// 在未取反的中间结果 `crc` 上继续累加 `data`，用来分段计算校验和
+pub(in crate::fs) fn crc32c_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &b| {
+        CRC32C_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
 }
 
 impl Fs {
@@ -47,32 +65,44 @@ impl Fs {
     /// 
     /// # 错误
     /// 当磁盘读取失败时返回相应的IO错误
-    pub fn get_inode(&self, inode_i: u16) -> Result<Inode> {
-        Ok(Inode::from_disk(&self.disk, self.addr_i_node(inode_i))?)
+    pub fn get_inode(&self, inode_i: u32) -> Result<Inode> {
+        Inode::from_disk(&self.disk, self.addr_i_node(inode_i))
     }
 
     /// 安全退出文件系统
     /// 
     /// # 功能
-    /// 1. 将文件系统描述符写入磁盘
+    /// 1. 将文件系统标记为正常卸载，并把超级块写入磁盘
     /// 2. 刷新磁盘缓冲区，确保所有数据都已写入
     /// 
     /// # 注意
-    /// 此方法会强制刷新所有缓冲区，确保数据持久化
-    pub fn exit(&mut self) {
-        self.write_fs_desc().unwrap();
-        self.disk.flush().unwrap()
+    /// 此方法会强制刷新所有缓冲区，确保数据持久化。只读设备上不会写入任何数据
+    ///
+    /// # 错误
+    /// 写入超级块或者刷新磁盘失败时返回相应的IO错误，这时磁盘仍然被标记为没有正常卸载
+    pub fn exit(&mut self) -> Result<()> {
+        if self.disk.is_read_only() {
+            return Ok(());
+        }
+        self.super_block.state = FS_STATE_CLEAN;
+        self.write_super_block()?;
+        self.disk.flush()
     }
 
-    /// 获取文件系统描述符的只读引用
+    /// 获取超级块的只读引用
     /// 
     /// # 返回值
-    /// 返回文件系统描述符的不可变引用
+    /// 返回超级块的不可变引用
     /// 
     /// # 用途
     /// 用于查询文件系统的元数据信息，如空闲块数量、用户信息等
-    pub fn fs_desc(&self) -> &GroupDesc {
-        &self.fs_desc
+    pub fn super_block(&self) -> &SuperBlock {
+        &self.super_block
+    }
+
+    /// 获取所有块组的组描述符
+    pub fn groups(&self) -> &[GroupDesc] {
+        &self.groups
     }
 
     /// 获取当前登录用户的ID
@@ -82,9 +112,27 @@ impl Fs {
     /// 
     /// # 用途
     /// 用于权限检查和文件所有权管理
-    pub fn current_user(&self) -> usize {
+    pub fn current_user(&self) -> u16 {
         self.user
     }
+
+    /// 获取当前用户所在的全部用户组的ID，第一个是主组
+    pub fn current_groups(&self) -> &[u16] {
+        &self.gids
+    }
+
+    /// 目录 `dir_i` 设置了粘滞位时，检查当前用户能否删除或重命名其中索引节点为 `inode_i` 的文件
+    pub(in crate::fs) fn check_sticky(&self, dir_i: u32, inode_i: u32) -> Result<()> {
+        let dir = self.get_inode(dir_i)?;
+        let file = self.get_inode(inode_i)?;
+        match dir.i_mode.can_unlink(&file.i_mode, self.user) {
+            true => Ok(()),
+            false => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Operation not permitted: sticky directory",
+            )),
+        }
+    }
 }
 
 /// 将字节数组转换为UTF-8字符串
@@ -0,0 +1,103 @@
+//! # 账户状态
+//!
+//! 禁用账户和设置过期日期，状态保存在 [`SHADOW_PATH`] 中，[`Fs::login`] 时检查
+
+use crate::fs::core::*;
+use chrono::NaiveDate;
+
+impl Fs {
+    /// 禁用或者启用用户 `name` 的账户，只有 root 可以修改
+    ///
+    /// 禁用的账户不能登录，已经登录的会话不受影响
+    pub fn set_user_locked(&mut self, name: &str, locked: bool) -> Result<()> {
+        self.check_account_admin(name)?;
+        self.update_shadow(name, |shadow| shadow.locked = locked)
+    }
+
+    /// 设置用户 `name` 的账户过期日期，`None` 表示永不过期，只有 root 可以修改
+    ///
+    /// 从过期日期这一天开始不能登录，不能设置早于 1970-01-01 的日期
+    pub fn set_user_expire(&mut self, name: &str, expire: Option<NaiveDate>) -> Result<()> {
+        let expire = match expire {
+            None => None,
+            Some(date) => {
+                let days = (date - NaiveDate::default()).num_days();
+                match u64::try_from(days) {
+                    Ok(days) => Some(days),
+                    Err(_) => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("{}: Date before 1970 is not supported", date),
+                        ))
+                    }
+                }
+            }
+        };
+        self.check_account_admin(name)?;
+        self.update_shadow(name, |shadow| shadow.expire = expire)
+    }
+
+    /// 用户 `name` 的密码和账户状态，root 可以查看所有用户，其他用户只能查看自己的
+    pub fn user_shadow(&mut self, name: &str) -> Result<Shadow> {
+        let user = self.find_user(name)?;
+        if self.user != ROOT_UID && self.user != user.uid {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Permission Denied"));
+        }
+        self.find_shadow(name)
+    }
+
+    /// 只有 root 可以修改账户状态，root 自己的账户不能修改
+    fn check_account_admin(&mut self, name: &str) -> Result<()> {
+        if self.user != ROOT_UID {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Only root can change account status",
+            ));
+        }
+        if self.find_user(name)?.uid == ROOT_UID {
+            return Err(Error::other("Can't change account status of root"));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_account_status() {
+    let options = FormatOptions {
+        root_password: "r".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.useradd("alice", "a").unwrap();
+    fs.useradd("bob", "b").unwrap();
+    assert!(fs.set_user_locked("root", true).is_err());
+
+    // 禁用的账户密码正确也不能登录，启用之后恢复
+    fs.set_user_locked("alice", true).unwrap();
+    assert!(fs.user_shadow("alice").unwrap().locked);
+    assert_eq!(fs.login("alice", "a").unwrap_err().to_string(), "Account is locked");
+    assert_eq!(fs.login("alice", "x").unwrap_err().to_string(), "Wrong username or password");
+    fs.set_user_locked("alice", false).unwrap();
+    fs.login("alice", "a").unwrap();
+
+    // 普通用户不能修改账户状态，只能查看自己的
+    assert_eq!(
+        fs.set_user_locked("bob", true).unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+    assert!(fs.user_shadow("alice").is_ok());
+    assert_eq!(fs.user_shadow("bob").unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    // 过期日期这一天及以后不能登录
+    fs.login("root", "r").unwrap();
+    let today = NaiveDate::default() + chrono::Days::new(today());
+    fs.set_user_expire("bob", Some(today)).unwrap();
+    assert_eq!(fs.login("bob", "b").unwrap_err().to_string(), "Account has expired");
+    fs.set_user_expire("bob", today.succ_opt()).unwrap();
+    fs.login("bob", "b").unwrap();
+    fs.login("root", "r").unwrap();
+    fs.set_user_expire("bob", None).unwrap();
+    assert_eq!(fs.user_shadow("bob").unwrap().expire, None);
+    let before = NaiveDate::from_ymd_opt(1969, 1, 1);
+    assert_eq!(fs.set_user_expire("bob", before).unwrap_err().kind(), ErrorKind::InvalidInput);
+}
@@ -10,7 +10,7 @@ impl Fs {
 
         // 如果用户没有读权限，返回错误
         // chdir 需要对目录的读权限
-        if !inode.i_mode.can_read(self.user) {
+        if !inode.i_mode.can_read(self.user, &self.gids) {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Need read permission of directory",
@@ -22,8 +22,7 @@ impl Fs {
             // 如果文件类型为文件，返回错误
             FileType::File => {
                 // 返回错误
-                return Err(Error::new(
-                    ErrorKind::Other,
+                Err(Error::other(
                     format!("{}: Not a directory", utils::str(&entry.name)),
                 ))
             }
@@ -53,8 +52,7 @@ impl Fs {
             // 如果文件类型为文件，返回错误
             FileType::File => {
                 // 返回错误
-                return Err(Error::new(
-                    ErrorKind::Other,
+                Err(Error::other(
                     format!("{}: Not a directory", utils::str(&entry.name)),
                 ))
             }
@@ -77,7 +75,7 @@ impl Fs {
 
 #[test]
 fn test_chdir() {
-    let mut fs = Fs::format().unwrap();
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
     fs.mkdir("a").unwrap();
     fs.create("1.txt").unwrap();
     assert!(fs.chdir("1.txt").is_err());
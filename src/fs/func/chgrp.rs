@@ -0,0 +1,75 @@
+use crate::fs::core::*;
+
+impl Fs {
+    /// 把文件 `path` 所属的用户组修改为 `group`
+    ///
+    /// 文件的拥有者只能修改为自己所在的用户组，root 可以修改为任意用户组
+    pub fn chgrp(&mut self, path: &str, group: &str) -> Result<()> {
+        let gid = self.find_group(group)?.gid;
+        let inode_i = self.path_parse(path)?.dir_entry.i_node;
+        let mut inode = self.get_inode(inode_i)?;
+
+        if self.user != 0 && (inode.i_mode.owner != self.user || !self.gids.contains(&gid)) {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Permission Denied"));
+        }
+
+        inode.i_mode.group = gid;
+        inode.touch_ctime();
+        self.write_inode(inode_i, inode)
+    }
+}
+
+#[test]
+fn test_groups() {
+    let options = FormatOptions {
+        root_password: "123".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.useradd("alice", "a").unwrap();
+    fs.useradd("bob", "b").unwrap();
+    fs.groupadd("dev").unwrap();
+    assert_eq!(fs.groupadd("dev").unwrap_err().kind(), ErrorKind::AlreadyExists);
+    fs.group_add_member("dev", "bob").unwrap();
+    assert!(fs.group_add_member("dev", "bob").is_err());
+
+    // alice 创建的文件属于她的主组 users，只有 alice 能写
+    fs.login("alice", "a").unwrap();
+    assert_eq!(fs.current_groups(), [USERS_GID]);
+    fs.create("/home/alice/a").unwrap();
+    let meta = fs.stat("/home/alice/a").unwrap();
+    assert_eq!((meta.mode, meta.group), (0o744, USERS_GID));
+
+    // alice 不在 dev 组中，不能把文件改到 dev 组
+    assert_eq!(
+        fs.chgrp("/home/alice/a", "dev").unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+    assert_eq!(fs.groupadd("x").unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    // root 把文件改到 dev 组并允许组成员写入
+    fs.login("root", "123").unwrap();
+    fs.chgrp("/home/alice/a", "dev").unwrap();
+    fs.chmod("/home/alice/a", 0o764).unwrap();
+
+    // bob 是 dev 的附加成员，可以写入
+    fs.login("bob", "b").unwrap();
+    assert_eq!(fs.current_groups(), [USERS_GID, FIRST_ID]);
+    let fd = fs.open_with("/home/alice/a", OpenOptions::new().write(true)).unwrap();
+    fs.close(fd).unwrap();
+
+    // bob 不再是 dev 的成员之后只剩其他用户的读权限
+    fs.login("root", "123").unwrap();
+    fs.group_remove_member("dev", "bob").unwrap();
+    fs.login("bob", "b").unwrap();
+    let err = fs.open_with("/home/alice/a", OpenOptions::new().write(true)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    fs.open("/home/alice/a").and_then(|fd| fs.close(fd)).unwrap();
+
+    // 删除用户组
+    fs.login("root", "123").unwrap();
+    assert!(fs.groupdel("users").is_err());
+    fs.groupdel("dev").unwrap();
+    assert_eq!(fs.groupdel("dev").unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(fs.user_groups().unwrap().len(), 2);
+}
@@ -2,17 +2,79 @@ use crate::fs::*;
 
 impl Fs {
     // 修改文件权限
-    pub fn chmod(&mut self, path: &str, mode: u8) -> Result<()> {
+    pub fn chmod(&mut self, path: &str, mode: u16) -> Result<()> {
         // 解析路径
         let inode_i = self.path_parse(path)?.dir_entry.i_node;
         // 获取inode
         let mut inode = self.get_inode(inode_i)?;
 
+        // 不在文件所属用户组中的普通用户不能设置 setgid 位，和 Linux 一样直接忽略
+        let mut mode = mode;
+        if self.user != 0 && !self.gids.contains(&inode.i_mode.group) {
+            mode &= !MODE_SETGID;
+        }
+
         // 设置文件权限
         inode.i_mode.set_mode(self.user, mode)?;
+        inode.touch_ctime();
 
         // 写入inode
         self.write_inode(inode_i, inode)?;
         Ok(())
     }
 }
+
+#[test]
+fn test_special_bits() {
+    let options = FormatOptions {
+        root_password: "123".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.useradd("alice", "a").unwrap();
+    fs.useradd("bob", "b").unwrap();
+
+    // 所有人都能写入的 /tmp，设置粘滞位之后只能删除和重命名自己的文件
+    fs.mkdir("/tmp").unwrap();
+    fs.chmod("/tmp", 0o1777).unwrap();
+    fs.login("alice", "a").unwrap();
+    let fd = fs.open_with("/tmp/a", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.close(fd).unwrap();
+    fs.chmod("/tmp/a", 0o777).unwrap();
+    fs.login("bob", "b").unwrap();
+    let fd = fs.open_with("/tmp/a", OpenOptions::new().write(true)).unwrap();
+    assert_eq!(fs.rm(fd).unwrap_err().kind(), ErrorKind::PermissionDenied);
+    fs.close(fd).unwrap();
+    assert_eq!(fs.rename("/tmp/a", "/tmp/b").unwrap_err().kind(), ErrorKind::PermissionDenied);
+    fs.mkdir("/tmp/bob").unwrap();
+    fs.chmod("/tmp/bob", 0o777).unwrap();
+    fs.login("alice", "a").unwrap();
+    assert_eq!(fs.rmdir("/tmp/bob").unwrap_err().kind(), ErrorKind::PermissionDenied);
+    fs.rename("/tmp/a", "/tmp/b").unwrap();
+    fs.open("/tmp/b").and_then(|fd| fs.rm(fd)).unwrap();
+
+    // 设置了 setgid 的目录中新建的文件继承目录的用户组，子目录还继承 setgid 位
+    fs.login("root", "123").unwrap();
+    fs.groupadd("dev").unwrap();
+    fs.group_add_member("dev", "alice").unwrap();
+    fs.mkdir("/shared").unwrap();
+    fs.chgrp("/shared", "dev").unwrap();
+    fs.chmod("/shared", 0o2775).unwrap();
+    assert_eq!(fs.stat("/shared").unwrap().permissions().to_string(), "rwxrwsr-x");
+    fs.login("alice", "a").unwrap();
+    let dev = fs.stat("/shared").unwrap().group;
+    fs.create("/shared/f").unwrap();
+    fs.mkdir("/shared/d").unwrap();
+    assert_eq!(fs.stat("/shared/f").unwrap().group, dev);
+    let d = fs.stat("/shared/d").unwrap();
+    assert_eq!((d.group, d.mode), (dev, 0o2755));
+
+    // 不在文件所属用户组中的用户设置 setgid 位会被忽略
+    fs.login("bob", "b").unwrap();
+    fs.create("/home/bob/x").unwrap();
+    fs.login("root", "123").unwrap();
+    fs.chgrp("/home/bob/x", "dev").unwrap();
+    fs.login("bob", "b").unwrap();
+    fs.chmod("/home/bob/x", 0o2755).unwrap();
+    assert_eq!(fs.stat("/home/bob/x").unwrap().mode, 0o755);
+}
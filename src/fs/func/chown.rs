@@ -6,42 +6,26 @@ impl Fs {
         // 解析路径
         let entry = self.path_parse(path)?;
 
-        // 获取用户ID
-        let mut user_id = None;
-        // 遍历用户
-        for (i, u) in self.fs_desc.users.iter().enumerate() {
-            // 如果用户名匹配
-            if u.name == user.into_array()? {
-                // 设置用户ID
-                user_id = Some(i);
-                // 跳出循环
-                break;
-            }
-        }
+        // 获取用户ID，用户不存在时返回错误
+        let uid = match self.find_user(user) {
+            Ok(user) => user.uid,
+            Err(_) => return Err(Error::other(format!("Can't find user {}", user))),
+        };
 
-        // 根据用户ID进行处理
-        match user_id {
-            // 如果用户ID不存在，返回错误
-            None => {
-                // 返回错误
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    format!("Can't find user {}", user),
-                ))
-            }
-            // 如果用户ID存在，修改文件所有者
-            Some(user) => {
-                // 获取inode
-                let mut inode = self.get_inode(entry.dir_entry.i_node)?;
+        // 获取inode
+        let mut inode = self.get_inode(entry.dir_entry.i_node)?;
 
-                // 如果用户不是所有者且不是root，返回错误
-                if inode.i_mode.owner as usize != self.user && self.user != 0 {
-                    return Err(Error::new(ErrorKind::PermissionDenied, "Permission Denied"));
-                }
-                inode.i_mode.owner = user as u8;
-                self.write_inode(entry.dir_entry.i_node, inode)?;
-                Ok(())
-            }
+        // 如果用户不是所有者且不是root，返回错误
+        if inode.i_mode.owner != self.user && self.user != ROOT_UID {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Permission Denied"));
+        }
+        inode.i_mode.owner = uid;
+        // 普通文件换了拥有者之后不再保留 setuid 和 setgid 位
+        if entry.dir_entry.file_type != u8::from(FileType::Dir) {
+            inode.i_mode.mode &= !(MODE_SETUID | MODE_SETGID);
         }
+        inode.touch_ctime();
+        self.write_inode(entry.dir_entry.i_node, inode)?;
+        Ok(())
     }
 }
@@ -6,7 +6,7 @@ impl Fs {
         // 检查文件描述符是否有效
         // 如果文件描述符超出范围，或者对应的文件已经关闭，则返回错误
         if fd >= self.fds.len() || self.fds[fd].is_none() {
-            return Err(Error::new(ErrorKind::Other, "Bad file description"));
+            return Err(Error::other("Bad file description"));
         }
 
         // 将指定的文件描述符位置设置为 `None`，表示关闭文件
@@ -10,29 +10,28 @@ use crate::fs::core::*;
 impl Fs {
     /// 在当前目录下创建目录，`name` 为目录名
     pub fn mkdir(&mut self, name: &str) -> Result<()> {
-        self._create(name, FileType::Dir)
+        self.transaction(|fs| fs._create(name, FileType::Dir))
     }
 
     /// 在当前目录下创建新文件
     pub fn create(&mut self, name: &str) -> Result<()> {
-        self._create(name, FileType::File)
+        self.transaction(|fs| fs._create(name, FileType::File))
     }
 
     /// 创建文件或者目录
-    fn _create(&mut self, path: &str, mode: FileType) -> Result<()> {
-        // 记录被删除的目录项地址
-        let mut deleted_entry_addr = None;
-
+    pub(in crate::fs) fn _create(&mut self, path: &str, mode: FileType) -> Result<()> {
         // 解析路径
         let (path, name) = path.rsplit_once("/").unwrap_or((".", path));
+        // `/name` 的父目录是根目录，不是当前目录
+        let path = if path.is_empty() { "/" } else { path };
         // 获取父目录
         let parent_dir_entry = self.path_parse(path)?.dir_entry;
 
         // 获取父目录inode
         // 检查对父目录的写权限
-        let mut parent_inode = self.get_inode(parent_dir_entry.i_node)?;
+        let parent_inode = self.get_inode(parent_dir_entry.i_node)?;
         // 如果用户没有写权限，返回错误
-        if !parent_inode.i_mode.can_write(self.user) {
+        if !parent_inode.i_mode.can_write(self.user, &self.gids) {
             // 返回错误
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
@@ -40,72 +39,42 @@ impl Fs {
             ));
         }
 
-        // 遍历当前目录项下的所有文件
-        for iter_item in parent_dir_entry.iter(&self)? {
-            match iter_item {
-                DirEntryIterItem::Using(Item { entry, .. }) => {
-                    // 检查同名文件
-                    if entry.name == name.into_array()? {
-                        return Err(Error::new(
-                            ErrorKind::AlreadyExists,
-                            "Files has exists",
-                        ));
-                    }
-                }
-                DirEntryIterItem::Deleted(Item { real_addr, .. }) => {
-                    // 如果找到之前被删除的项，先将它的地址记下来
-                    // 后面直接用新的目录项代替
-                    deleted_entry_addr = Some(real_addr)
-                }
-            }
+        // 检查同名文件
+        if self.find_dir_entry(&parent_dir_entry, name)?.is_some() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "Files has exists",
+            ));
         }
 
-        // 分配索引节点
-        let inode_i = self.alloc(BlkType::INode)?;
+        // 分配索引节点，目录会被分散到不同的块组，其它文件尽量和父目录放在同一个块组
+        let inode_type = match mode {
+            FileType::Dir => BlkType::DirINode,
+            _ => BlkType::INode,
+        };
+        let inode_i = self.alloc(inode_type, parent_dir_entry.i_node)?;
 
         // 根据文件类型分配索引节点
         let inode = match mode {
             // 如果文件类型为文件，分配索引节点
             FileType::File => Inode {
-                i_mode: FileMode::new(self.user, mode),
+                i_mode: self.new_file_mode(&parent_inode, mode),
+                i_flags: self.new_inode_flags(),
                 ..Default::default()
             },
             // 如果文件类型为目录，分配索引节点
             FileType::Dir => {
                 // 填充索引节点的内容
                 let mut inode = Inode {
-                    i_mode: FileMode::new(self.user, mode),
-                    i_size: DIR_ENTRY_SIZE as u32 * 2,
+                    i_mode: self.new_file_mode(&parent_inode, mode),
+                    i_size: self.block_size() as u32,
+                    i_flags: self.new_inode_flags(),
                     ..Default::default()
                 };
 
-                // 分配数据块
-                let data_blk_i = inode.alloc_data_block(self)?;
-
-                // 将 . 和 .. 写入数据块
-                self.disk.write_at(
-                    DirEntry {
-                        i_node: inode_i,
-                        rec_len: 0,
-                        name_len: 1,
-                        file_type: 2,
-                        name: ".".into_array()?,
-                    }
-                    .bytes(),
-                    Fs::addr_data_blk(data_blk_i),
-                )?;
-                // 将 .. 写入数据块
-                self.disk.write_at(
-                    DirEntry {
-                        i_node: parent_dir_entry.i_node,
-                        rec_len: 0,
-                        name_len: 2,
-                        file_type: 2,
-                        name: "..".into_array()?,
-                    }
-                    .bytes(),
-                    Fs::addr_data_blk(data_blk_i) + DIR_ENTRY_SIZE as u64,
-                )?;
+                // 分配数据块，将 . 和 .. 写入数据块
+                let data_blk_i = inode.alloc_data_block(self, inode_i)?;
+                self.init_dir_block(data_blk_i, inode_i, parent_dir_entry.i_node)?;
 
                 inode
             }
@@ -113,8 +82,7 @@ impl Fs {
             FileType::Symlink => {
                 // 软链接的创建在symlink.rs中单独实现
                 // 这里不应该被调用
-                return Err(Error::new(
-                    ErrorKind::Other,
+                return Err(Error::other(
                     "Symlinks should be created using symlink() function",
                 ));
             }
@@ -123,49 +91,37 @@ impl Fs {
         // 保存索引节点
         self.write_inode(inode_i, inode)?;
 
-        // 给当前目录添加 DirEntry
+        // 给当前目录添加 DirEntry，有空位时直接插入空位
         let dir_entry = DirEntry {
             i_node: inode_i,
             name: name.into_array()?,
             name_len: name.len() as u8,
             file_type: mode.into(),
-            rec_len: 1,
+            rec_len: 0,
+            checksum: 0,
         };
+        self.add_dir_entry(parent_dir_entry.i_node, &dir_entry)
+    }
 
-        // 如果已经有空位，那就直接将 DirEntry 插入空位
-        if let Some(addr) = deleted_entry_addr {
-            self.disk.write_at(dir_entry.bytes(), addr.addr)?;
-        } else {
-            // 没有空位，就只能将 DirEntry 写入新的位置
-            let addr = if parent_inode.i_size % BLOCK_SIZE as u32 == 0 {
-                // 需要请求新的数据块
-                let blk = parent_inode.alloc_data_block(self)?;
-                Fs::addr_data_blk(blk)
-            } else {
-                parent_inode
-                    .convert_addr(&mut self.disk, parent_inode.i_size as u64)?
-                    .addr
-            };
-            self.disk.write_at(dir_entry.bytes(), addr)?;
-        }
-
-        // 更新当前目录索引节点信息
-        parent_inode.i_size += DIR_ENTRY_SIZE as u32;
-        if let FileType::Dir = mode {
-            self.fs_desc.used_dirs_count += 1;
+    /// 当前用户在目录 `parent` 中新建文件时使用的权限
+    ///
+    /// 新文件通常属于创建者的主组；父目录设置了 [`MODE_SETGID`] 时改为继承父目录的用户组，
+    /// 新建的目录还会继承 setgid 位，这样整棵目录树中的文件都属于同一个用户组
+    pub(in crate::fs) fn new_file_mode(&self, parent: &Inode, file_type: FileType) -> FileMode {
+        let mut mode = FileMode::new(self.user, self.gids[0], file_type);
+        if parent.i_mode.mode & MODE_SETGID != 0 {
+            mode.group = parent.i_mode.group;
+            if file_type == FileType::Dir {
+                mode.mode |= MODE_SETGID;
+            }
         }
-
-        // 同步磁盘
-        self.write_inode(parent_dir_entry.i_node, parent_inode)?;
-        self.write_fs_desc()?;
-
-        Ok(())
+        mode
     }
 }
 
 #[test]
 fn mkdir_test() {
-    let mut fs = Fs::format().unwrap();
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
     assert!(fs.mkdir(".").is_err());
     assert!(fs.mkdir("..").is_err());
     println!("{:?}", fs.mkdir("hello"));
@@ -173,4 +129,10 @@ fn mkdir_test() {
     println!("{:?}", fs.mkdir("hello"));
     println!("{:?}", fs.create("hello"));
     println!("{:?}", fs.create("new_file"));
+
+    // 以 / 开头的路径总是从根目录开始，和当前目录无关
+    fs.chdir("hello").unwrap();
+    fs.mkdir("/top").unwrap();
+    assert!(fs.stat("/top").unwrap().is_dir());
+    assert!(fs.stat("top").is_err());
 }
@@ -1,89 +1,92 @@
-use std::io::Write;
-
 use crate::fs::core::*;
 
 impl Fs {
-    /// 格式化磁盘
-    pub fn format() -> Result<Self> {
-        let mut disk = std::fs::File::options()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(DISK_PATH)?;
-        let mut fs_desc = GroupDesc::new();
-
-        for _ in 0..BLOCKS {
-            disk.write_all(&utils::empty_blk())?;
-        }
+    /// 按照 `options` 在宿主机上新建（或覆盖）磁盘镜像并格式化
+    pub fn format(options: &FormatOptions) -> Result<Self> {
+        Self::format_on(FileDevice::create(&options.path)?, options)
+    }
 
-        let cwd = Self::init_root(&disk, &mut fs_desc)?;
+    /// 在指定的块设备上格式化文件系统，`options` 中的镜像路径会被忽略
+    pub fn format_on<D: BlockDevice + 'static>(device: D, options: &FormatOptions) -> Result<Self> {
+        let super_block = SuperBlock::new(options)?;
+        let groups = (0..super_block.groups_count as usize)
+            .map(|group| GroupDesc::new(&super_block, group))
+            .collect();
 
+        let disk = Journal::new(
+            Box::new(device),
+            super_block.block_size as u64,
+            super_block.journal_begin() as u64,
+            super_block.journal_blocks as u64,
+            super_block.total_blocks() as u64,
+            DEFAULT_CACHE_BLOCKS,
+        );
+        disk.set_checksums(options.checksums);
         let mut fs = Self {
-            fs_desc,
-            cwd,
+            super_block,
+            groups,
+            cwd: DirEntry::default(),
             disk,
             fds: Default::default(),
             opened_len: 0,
             user: 0,
+            gids: vec![ROOT_GID],
+            atime_mode: AtimeMode::default(),
         };
 
+        let empty_blk = fs.empty_blk();
+        for i in 0..fs.super_block.total_blocks() {
+            fs.disk.write_at(&empty_blk, (i * fs.block_size()) as u64)?;
+        }
+
+        // 写入组描述符表
+        for group in 0..fs.groups.len() {
+            fs.write_group_desc(group)?;
+        }
+
+        fs.cwd = fs.init_root()?;
+
         fs.mkdir("/home")?;
         fs.mkdir("/root")?;
+        fs.init_userdb(&options.root_password)?;
+        fs.disk.flush()?;
 
         Ok(fs)
     }
 
     /// 初始化根目录
-    fn init_root(disk: &std::fs::File, fs_desc: &mut GroupDesc) -> Result<DirEntry> {
-        // 初始化位图
-        let mut blk = utils::empty_blk();
+    fn init_root(&mut self) -> Result<DirEntry> {
+        // 根目录的索引节点和数据块都位于第 0 个块组，初始化该组的位图，
+        // 其中 0 号数据块保留不用，见 [`HOLE_BLK`]
+        let mut blk = self.empty_blk();
         blk[0] = 0b1000_0000;
-        disk.write_at(&blk, fs_desc.inode_bitmap as u64 * BLOCK_SIZE as u64)?;
-        disk.write_at(&blk, fs_desc.block_bitmap as u64 * BLOCK_SIZE as u64)?;
+        self.write_bitmap(0, BlkType::INode, &blk)?;
+        blk[0] = 0b1100_0000;
+        self.write_bitmap(0, BlkType::DataBlk, &blk)?;
 
         // 写入根目录的索引节点
-        let now = utils::now();
         let inode = Inode {
-            i_mode: FileMode::new(0, FileType::Dir),
+            i_mode: FileMode::new(0, ROOT_GID, FileType::Dir),
             i_blocks: 1,
-            i_size: 2 * DIR_ENTRY_SIZE as u32,
-            i_ctime: now,
-            i_mtime: now,
-            i_block: Default::default(),
-            i_links_count: 1,
-        };
-        disk.write_at(
-            (&inode).bytes(),
-            fs_desc.inode_table as u64 * BLOCK_SIZE as u64,
-        )?;
-
-        // 将根目录的目录项写入对应的磁盘块
-        let dir_entry = DirEntry {
-            i_node: 0,
-            rec_len: 0,
-            name_len: 1,
-            file_type: 2,
-            name: ".".into_array()?,
+            i_size: self.block_size() as u32,
+            i_block: [ROOT_DIR_BLK, 0, 0, 0, 0, 0, 0, 0, 0],
+            ..Default::default()
         };
-        disk.write_at(dir_entry.bytes(), Fs::addr_data_blk(0))?;
-        disk.write_at(
-            DirEntry {
-                i_node: 0,
-                rec_len: 0,
-                name_len: 2,
-                file_type: 2,
-                name: "..".into_array()?,
-            }
-            .bytes(),
-            Fs::addr_data_blk(0) + DIR_ENTRY_SIZE as u64,
-        )?;
-
-        fs_desc.free_blocks_count -= 1;
-        fs_desc.free_inodes_count -= 1;
-        fs_desc.used_dirs_count = 1;
-
-        // 将更新后的 fs_desc 写回磁盘
-        disk.write_at(fs_desc.bytes(), 0)?;
+        self.disk.write_at(&inode.bytes(), self.addr_i_node(0))?;
+
+        // 将根目录的目录项写入对应的磁盘块，根目录的父目录是它自己
+        let dir_entry = self.init_dir_block(ROOT_DIR_BLK, 0, 0)?;
+
+        self.groups[0].free_blocks_count -= 2;
+        self.groups[0].free_inodes_count -= 1;
+        self.groups[0].used_dirs_count = 1;
+        self.super_block.free_blocks_count -= 2;
+        self.super_block.free_inodes_count -= 1;
+        self.super_block.used_dirs_count = 1;
+
+        // 将更新后的超级块和组描述符写回磁盘
+        self.write_group_desc(0)?;
+        self.write_super_block()?;
 
         Ok(dir_entry)
     }
@@ -91,5 +94,105 @@ impl Fs {
 
 #[test]
 fn test_format() {
-    assert!(Fs::format().is_ok())
+    assert!(Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).is_ok());
+
+    for block_size in SUPPORTED_BLOCK_SIZES {
+        let options = FormatOptions {
+            block_size,
+            inodes_count: 128,
+            root_password: "root".into(),
+            ..Default::default()
+        };
+        let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+        assert_eq!(fs.block_size(), block_size);
+        assert_eq!(fs.super_block().inodes_count, 128);
+
+        // 文件内容跨越一级和二级索引
+        let content = vec![b'x'; block_size * (8 + block_size / ADDR_SIZE)];
+        fs.create("big").unwrap();
+        let fd = fs.open_with("big", OpenOptions::new().read(true).write(true)).unwrap();
+        fs.write(fd, &content).unwrap();
+        let fd = fs.open_with("big", OpenOptions::new().read(true).write(true)).unwrap();
+        let mut buf = vec![0u8; content.len() + 1];
+        assert_eq!(fs.read(fd, &mut buf).unwrap(), content.len());
+        assert_eq!(&buf[..content.len()], &content[..]);
+    }
+
+    // 多个块组：目录分散到不同的块组，大文件可以跨越块组
+    let disk = MemDevice::new();
+    let options = FormatOptions {
+        blocks_count: 512 * 8 * 3 - 100,
+        inodes_count: 300,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(disk.clone(), &options).unwrap();
+    assert_eq!(fs.super_block().groups_count, 3);
+    assert_eq!(fs.super_block().inodes_count, 300);
+    assert_eq!(disk.len(), fs.super_block().total_blocks() * 512);
+    assert!(fs.groups().iter().all(|g| g.used_dirs_count > 0));
+
+    let content = vec![b'y'; 512 * 512 * 9];
+    fs.create("huge").unwrap();
+    let fd = fs.open_with("huge", OpenOptions::new().read(true).write(true)).unwrap();
+    fs.write(fd, &content).unwrap();
+    assert!(fs.groups().iter().all(|g| g.free_blocks_count < 512 * 8 - 100));
+    fs.exit().unwrap();
+
+    let mut fs = Fs::init_on(disk).unwrap();
+    let fd = fs.open_with("huge", OpenOptions::new().read(true).write(true)).unwrap();
+    let mut buf = vec![0u8; content.len()];
+    assert_eq!(fs.read(fd, &mut buf).unwrap(), content.len());
+    assert_eq!(buf, content);
+    fs.rm(fd).unwrap();
+    assert_eq!(
+        fs.super_block().free_blocks_count as usize,
+        fs.groups().iter().map(|g| g.free_blocks_count as usize).sum::<usize>()
+    );
+
+    let bad = FormatOptions {
+        block_size: 2000,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    assert!(Fs::format_on(MemDevice::new(), &bad).is_err());
+    let bad = FormatOptions {
+        inodes_count: 512 * 8 + 1,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    assert!(Fs::format_on(MemDevice::new(), &bad).is_err());
+
+    // 块号超过 u16 的范围
+    let options = FormatOptions {
+        blocks_count: 70000,
+        inodes_count: 18 * 16,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    assert_eq!(fs.super_block().groups_count, 18);
+    for i in 0..17 {
+        fs.mkdir(&format!("dir{}", i)).unwrap();
+    }
+    let last = fs.groups().len() - 1;
+    assert!(fs.groups()[last].used_dirs_count > 0);
+
+    // 最后一个块组里的目录，其中文件的数据块号超过 65535
+    let dir = (0..17)
+        .map(|i| format!("dir{}", i))
+        .find(|d| {
+            let inode_i = fs.path_parse(d).unwrap().dir_entry.i_node;
+            inode_i / fs.super_block().inodes_per_group == last as u32
+        })
+        .unwrap();
+    fs.chdir(&dir).unwrap();
+    fs.create("far").unwrap();
+    let fd = fs.open_with("far", OpenOptions::new().read(true).write(true)).unwrap();
+    fs.write(fd, b"beyond u16").unwrap();
+    let fd = fs.open_with("far", OpenOptions::new().read(true).write(true)).unwrap();
+    let mut buf = [0u8; 10];
+    fs.read(fd, &mut buf).unwrap();
+    assert_eq!(&buf, b"beyond u16");
+    assert!(fs.groups()[last].free_blocks_count < fs.super_block().group_blocks(last) as u16);
 }
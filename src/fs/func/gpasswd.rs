@@ -0,0 +1,43 @@
+use crate::fs::core::*;
+
+impl Fs {
+    /// 把用户 `user` 添加为用户组 `group` 的附加成员，只有 root 可以修改
+    ///
+    /// 用户下次登录时才会获得新用户组的权限
+    pub fn group_add_member(&mut self, group: &str, user: &str) -> Result<()> {
+        self.set_group_member(group, user, true)
+    }
+
+    /// 把用户 `user` 从用户组 `group` 的附加成员中移除，只有 root 可以修改
+    pub fn group_remove_member(&mut self, group: &str, user: &str) -> Result<()> {
+        self.set_group_member(group, user, false)
+    }
+
+    /// 修改用户组的附加成员
+    fn set_group_member(&mut self, group: &str, user: &str, member: bool) -> Result<()> {
+        if self.user != ROOT_UID {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Only root can change group members",
+            ));
+        }
+
+        let user = self.find_user(user)?;
+        let mut groups = self.user_groups()?;
+        let Some(group) = groups.iter_mut().find(|g| g.name == group) else {
+            return Err(Error::new(ErrorKind::NotFound, "Group not exists."));
+        };
+        if group.members.contains(&user.name) == member {
+            return Err(Error::other(match member {
+                true => "User is already a member of the group",
+                false => "User is not a member of the group",
+            }));
+        }
+        match member {
+            true => group.members.push(user.name),
+            false => group.members.retain(|name| *name != user.name),
+        }
+
+        self.write_user_groups(&groups)
+    }
+}
@@ -0,0 +1,26 @@
+use crate::fs::core::*;
+
+impl Fs {
+    /// 添加名为 `name` 的用户组，只有 root 可以添加
+    ///
+    /// 新用户组的 GID 从 [`FIRST_ID`] 开始依次分配
+    pub fn groupadd(&mut self, name: &str) -> Result<()> {
+        if self.user != ROOT_UID {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Only root can add group"));
+        }
+        check_name(name)?;
+
+        let mut groups = self.user_groups()?;
+        if groups.iter().any(|group| group.name == name) {
+            return Err(Error::new(ErrorKind::AlreadyExists, "Group exists yet."));
+        }
+        let gid = next_id(groups.iter().map(|group| group.gid))?;
+        groups.push(UserGroup {
+            name: name.into(),
+            gid,
+            members: Vec::new(),
+        });
+
+        self.write_user_groups(&groups)
+    }
+}
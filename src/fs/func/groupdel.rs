@@ -0,0 +1,31 @@
+use crate::fs::core::*;
+
+impl Fs {
+    /// 删除名为 `name` 的用户组，只有 root 可以删除
+    ///
+    /// root 和 users 不能删除，还是某个用户的主组时也不能删除。
+    /// 属于这个用户组的文件保留原来的用户组ID
+    pub fn groupdel(&mut self, name: &str) -> Result<()> {
+        if self.user != ROOT_UID {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Only root can delete group"));
+        }
+
+        let mut groups = self.user_groups()?;
+        let Some(i) = groups.iter().position(|group| group.name == name) else {
+            return Err(Error::new(ErrorKind::NotFound, "Group not exists."));
+        };
+        let gid = groups[i].gid;
+        if gid == ROOT_GID || gid == USERS_GID {
+            return Err(Error::other(format!("Can't delete group {}", name)));
+        }
+        if self.users()?.iter().any(|user| user.gid == gid) {
+            return Err(Error::other(format!(
+                "Can't delete group {}: it is the primary group of a user",
+                name
+            )));
+        }
+
+        groups.remove(i);
+        self.write_user_groups(&groups)
+    }
+}
@@ -1,37 +1,161 @@
 use crate::fs::core::*;
 
 impl Fs {
+    /// 加载默认路径 [`DISK_PATH`] 下的磁盘镜像
     pub fn init() -> Result<Fs> {
-        let disk = std::fs::File::options()
-            .read(true)
-            .write(true)
-            .open(DISK_PATH)?;
+        Self::open_image(DISK_PATH)
+    }
 
-        let fs_desc = GroupDesc::from_disk(&disk, 0)?;
+    /// 加载宿主机上路径为 `path` 的磁盘镜像
+    pub fn open_image<P: AsRef<std::path::Path>>(path: P) -> Result<Fs> {
+        Self::open_image_with(path, &Default::default())
+    }
 
-        let cwd_inode = Inode::from_disk(&disk, fs_desc.inode_table as u64 * BLOCK_SIZE as u64)?;
-        let cwd = DirEntry::from_disk(&disk, DATA_BEGIN_BLOCK as u64 * BLOCK_SIZE as u64)?;
+    /// 按照挂载选项 `options` 加载宿主机上路径为 `path` 的磁盘镜像
+    pub fn open_image_with<P: AsRef<std::path::Path>>(
+        path: P,
+        options: &MountOptions,
+    ) -> Result<Fs> {
+        Self::mount(FileDevice::open(path)?, options)
+    }
 
-        if cwd.name != ".".into_array()?
-            || cwd.i_node != 0
-            || cwd_inode.i_size < 2 * DIR_ENTRY_SIZE as u32
+    /// 从指定的块设备上加载已经格式化过的文件系统
+    pub fn init_on<D: BlockDevice + 'static>(device: D) -> Result<Fs> {
+        Self::mount(device, &Default::default())
+    }
+
+    /// 按照挂载选项 `options` 从指定的块设备上加载文件系统
+    ///
+    /// # 错误
+    /// 超级块检查失败时返回的错误里包含 [`MountError`]：
+    /// - 魔数不对，或者几何参数不合法：[`MountError::NotAFilesystem`]
+    /// - 磁盘格式版本与当前实现不同，并且不能升级：[`MountError::UnsupportedVersion`]
+    /// - 使用了不支持的不兼容特性，或者在可写设备上使用了不支持的只读兼容特性：
+    ///   [`MountError::UnsupportedFeatures`]
+    /// - 上次没有正常卸载，并且没有指定 `force`：[`MountError::NotCleanlyUnmounted`]
+    ///
+    /// 版本 9 的镜像在挂载时升级到当前版本，见 [`upgrade`](crate::fs::upgrade)，
+    /// 只读设备上不能升级，返回 `PermissionDenied` 错误
+    pub fn mount<D: BlockDevice + 'static>(device: D, options: &MountOptions) -> Result<Fs> {
+        let mut super_block = SuperBlock::from_disk(&device, 0)?;
+        if super_block.magic != FS_MAGIC
+            || !SUPPORTED_BLOCK_SIZES.contains(&(super_block.block_size as usize))
+            || super_block.groups_count == 0
+            || super_block.inodes_per_group == 0
         {
-            return Err(Error::new(ErrorKind::Other, "Bad filesystem"));
+            return Err(MountError::NotAFilesystem.into());
         }
+        if super_block.version != FS_VERSION && super_block.version != UPGRADABLE_VERSION {
+            return Err(MountError::UnsupportedVersion(super_block.version).into());
+        }
+        if super_block.feature_incompat & !FEATURE_INCOMPAT_SUPP != 0
+            || (super_block.feature_ro_compat & !FEATURE_RO_COMPAT_SUPP != 0
+                && !device.is_read_only())
+        {
+            return Err(MountError::UnsupportedFeatures.into());
+        }
+
+        // 重放日志中已经提交的事务，超级块也可能被修改了，需要重新读取，
+        // 重新读取时顺便校验超级块的校验和
+        let disk = Journal::new(
+            Box::new(device),
+            super_block.block_size as u64,
+            super_block.journal_begin() as u64,
+            super_block.journal_blocks as u64,
+            super_block.total_blocks() as u64,
+            options.cache_blocks,
+        );
+        disk.set_checksums(super_block.feature_ro_compat & FEATURE_RO_COMPAT_METADATA_CSUM != 0);
+        disk.replay()?;
+        super_block = SuperBlock::from_disk(&disk, 0)?;
+        disk.set_mode(options.journal_mode);
+
+        if super_block.state != FS_STATE_CLEAN && !options.force {
+            return Err(MountError::NotCleanlyUnmounted.into());
+        }
+
+        // 读取组描述符表
+        let groups = GroupDesc::read_table(&disk, &super_block)?;
 
-        Ok(Fs {
-            fs_desc,
-            cwd,
+        let mut fs = Fs {
+            super_block,
+            groups,
+            cwd: DirEntry::default(),
             disk,
             fds: Default::default(),
             opened_len: 0,
             user: 0,
-        })
+            gids: vec![ROOT_GID],
+            atime_mode: options.atime,
+        };
+
+        let cwd_inode = fs.get_inode(0)?;
+        let cwd = DirEntry::from_disk(&fs.disk, fs.addr_data_blk(ROOT_DIR_BLK))?;
+
+        if cwd.name != ".".into_array()?
+            || cwd.i_node != 0
+            || (cwd_inode.i_size as usize) < fs.block_size()
+        {
+            return Err(MountError::NotAFilesystem.into());
+        }
+
+        // 标记为正在使用，直到调用 exit 正常卸载
+        if !fs.disk.is_read_only() {
+            fs.super_block.state = FS_STATE_DIRTY;
+            fs.super_block.mount_count += 1;
+            fs.super_block.mtime = utils::now().as_secs();
+            fs.write_super_block()?;
+            fs.disk.flush()?;
+        }
+
+        fs.cwd = cwd;
+        if fs.super_block.version != FS_VERSION {
+            fs.upgrade()?;
+        }
+        Ok(fs)
     }
 }
 
 #[test]
 fn test_init() {
-    Fs::format().unwrap();
-    Fs::init().unwrap();
+    let disk = MemDevice::new();
+    let options = FormatOptions {
+        block_size: 1024,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    Fs::format_on(disk.clone(), &options).unwrap().exit().unwrap();
+    let mut fs = Fs::init_on(disk.clone()).unwrap();
+    assert_eq!(fs.block_size(), 1024);
+    assert_eq!(fs.super_block().mount_count, 2);
+
+    // 没有正常卸载时拒绝挂载，除非强制挂载
+    let err = Fs::init_on(disk.clone()).err().unwrap();
+    assert_eq!(MountError::of(&err), Some(MountError::NotCleanlyUnmounted));
+    Fs::mount(
+        disk.clone(),
+        &MountOptions {
+            force: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    fs.exit().unwrap();
+
+    // 只读设备上也可以挂载
+    Fs::init_on(ReadOnlyDevice::new(disk.clone())).unwrap().exit().unwrap();
+
+    // 版本过新
+    let mut super_block = SuperBlock::from_disk(&disk, 0).unwrap();
+    super_block.version = FS_VERSION + 1;
+    disk.write_at(&super_block.bytes(), 0).unwrap();
+    let err = Fs::init_on(disk.clone()).err().unwrap();
+    assert_eq!(MountError::of(&err), Some(MountError::UnsupportedVersion(FS_VERSION + 1)));
+
+    // 不是文件系统
+    let err = Fs::init_on(MemDevice::new()).err().unwrap();
+    assert_eq!(MountError::of(&err), Some(MountError::NotAFilesystem));
+    disk.write_at(&[0xff; 512], 0).unwrap();
+    let err = Fs::init_on(disk).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
 }
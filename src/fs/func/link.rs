@@ -16,6 +16,11 @@ impl Fs {
     /// 返回:
     /// - `Result<()>`: 操作结果
     pub fn link(&mut self, target: &str, link_name: &str) -> Result<()> {
+        self.transaction(|fs| fs._link(target, link_name))
+    }
+
+    /// 创建硬链接
+    fn _link(&mut self, target: &str, link_name: &str) -> Result<()> {
         // 解析目标文件路径
         let target_path_res = self.path_parse(target)?;
         let target_dir_entry = target_path_res.dir_entry.clone();
@@ -30,12 +35,14 @@ impl Fs {
         
         // 解析硬链接路径
         let (link_dir_path, link_file_name) = link_name.rsplit_once("/").unwrap_or((".", link_name));
+        // `/name` 的父目录是根目录，不是当前目录
+        let link_dir_path = if link_dir_path.is_empty() { "/" } else { link_dir_path };
         let link_dir_res = self.path_parse(link_dir_path)?;
         let link_dir_entry = link_dir_res.dir_entry.clone();
         
         // 检查对链接目录的写权限
         let link_dir_inode = self.get_inode(link_dir_entry.i_node)?;
-        if !link_dir_inode.i_mode.can_write(self.user) {
+        if !link_dir_inode.i_mode.can_write(self.user, &self.gids) {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Need write permission to directory",
@@ -43,24 +50,11 @@ impl Fs {
         }
         
         // 检查链接名是否已存在
-        for iter_item in link_dir_entry.iter(self)? {
-            if let DirEntryIterItem::Using(Item { entry, .. }) = iter_item {
-                if entry.name == link_file_name.into_array()? {
-                    return Err(Error::new(
-                        ErrorKind::AlreadyExists,
-                        "Link name already exists",
-                    ));
-                }
-            }
-        }
-        
-        // 查找可能被删除的目录项位置
-        let mut deleted_entry_addr = None;
-        for iter_item in link_dir_entry.iter_without_limit(self)? {
-            if let DirEntryIterItem::Deleted(Item { real_addr, .. }) = iter_item {
-                deleted_entry_addr = Some(real_addr);
-                break;
-            }
+        if self.find_dir_entry(&link_dir_entry, link_file_name)?.is_some() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "Link name already exists",
+            ));
         }
         
         // 创建新的目录项，指向同一个inode
@@ -70,41 +64,17 @@ impl Fs {
             name_len: link_file_name.len() as u8,
             file_type: target_dir_entry.file_type, // 保持相同的文件类型
             rec_len: 1,
+            checksum: 0,
         };
         
         // 增加目标文件的硬链接计数
         let mut target_inode = self.get_inode(target_dir_entry.i_node)?;
         target_inode.i_links_count += 1;
+        target_inode.touch_ctime();
         self.write_inode(target_dir_entry.i_node, target_inode)?;
         
-        // 将新的目录项写入磁盘
-        if let Some(addr) = deleted_entry_addr {
-            // 使用之前删除的目录项的位置
-            self.disk.write_at(dir_entry.bytes(), addr.addr)?;
-        } else {
-            // 在目录末尾添加新的目录项
-            let mut link_dir_inode = self.get_inode(link_dir_entry.i_node)?;
-            
-            // 检查是否需要分配新的数据块
-            let logic_addr = link_dir_inode.i_size as u64;
-            let blk_i = logic_addr / BLOCK_SIZE as u64;
-            
-            if blk_i >= link_dir_inode.i_blocks as u64 {
-                // 需要分配新的数据块
-                link_dir_inode.alloc_data_block(self)?;
-            }
-            
-            // 计算新目录项的物理地址
-            let real_addr = link_dir_inode.convert_addr(&self.disk, logic_addr)?;
-            
-            // 写入新的目录项
-            self.disk.write_at(dir_entry.bytes(), real_addr.addr)?;
-            
-            // 更新目录的大小
-            link_dir_inode.i_size += DIR_ENTRY_SIZE as u32;
-            link_dir_inode.i_mtime = utils::now();
-            self.write_inode(link_dir_entry.i_node, link_dir_inode)?;
-        }
+        // 将新的目录项写入目录
+        self.add_dir_entry(link_dir_entry.i_node, &dir_entry)?;
         
         Ok(())
     }
@@ -117,11 +87,11 @@ fn test_link() {
     let modified = "Modified content!";
     
     // 创建文件系统
-    let mut fs = Fs::format().unwrap();
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
     
     // 创建测试文件并写入内容
     fs.create("test_file.txt").unwrap();
-    let fd = fs.open("test_file.txt").unwrap();
+    let fd = fs.open_with("test_file.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.write(fd, content.as_bytes()).unwrap();
     fs.close(fd).unwrap();
     
@@ -134,7 +104,7 @@ fn test_link() {
     assert_eq!(original_path.dir_entry.i_node, link_path.dir_entry.i_node);
     
     // 通过硬链接读取内容
-    let fd = fs.open("hard_link.txt").unwrap();
+    let fd = fs.open_with("hard_link.txt", OpenOptions::new().read(true).write(true)).unwrap();
     let mut buffer = Vec::new();
     let mut buf = [0u8; 10];
     while fs.read(fd, &mut buf).unwrap() != 0 {
@@ -148,12 +118,12 @@ fn test_link() {
     assert_eq!(read_content, content);
     
     // 通过硬链接修改内容
-    let fd = fs.open("hard_link.txt").unwrap();
+    let fd = fs.open_with("hard_link.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.write(fd, modified.as_bytes()).unwrap();
     fs.close(fd).unwrap();
     
     // 通过原始文件读取修改后的内容
-    let fd = fs.open("test_file.txt").unwrap();
+    let fd = fs.open_with("test_file.txt", OpenOptions::new().read(true).write(true)).unwrap();
     let mut buffer = Vec::new();
     let mut buf = [0u8; 10];
     while fs.read(fd, &mut buf).unwrap() != 0 {
@@ -167,14 +137,14 @@ fn test_link() {
     assert_eq!(read_content, modified);
     
     // 测试删除原始文件后，硬链接仍然可以访问内容
-    let fd = fs.open("test_file.txt").unwrap();
+    let fd = fs.open_with("test_file.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.rm(fd).unwrap();
     
     // 尝试打开原文件应该失败
     assert!(fs.open("test_file.txt").is_err());
     
     // 通过硬链接仍然可以访问内容
-    let fd = fs.open("hard_link.txt").unwrap();
+    let fd = fs.open_with("hard_link.txt", OpenOptions::new().read(true).write(true)).unwrap();
     let mut buffer = Vec::new();
     let mut buf = [0u8; 10];
     while fs.read(fd, &mut buf).unwrap() != 0 {
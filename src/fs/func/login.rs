@@ -1,24 +1,26 @@
 use crate::fs::core::*;
 
 impl Fs {
+    /// 以用户 `username` 的身份登录
+    ///
+    /// 密码的检查见 [`shadow`](crate::fs::shadow)，账户被禁用或者已经过期时不能登录。
+    /// 登录之后切换到用户的主目录，主目录不存在或者不能进入时切换到 `/`
     pub fn login(&mut self, username: &str, password: &str) -> Result<()> {
+        let wrong = || Error::new(ErrorKind::PermissionDenied, "Wrong username or password");
         if username.is_empty() || password.is_empty() {
-            return Err(Error::new(
-                ErrorKind::PermissionDenied,
-                "Wrong username or password",
-            ));
+            return Err(wrong());
         }
 
-        for (i, user) in self.fs_desc.users.iter().enumerate() {
-            if user.name == username.into_array()? && user.password == password.into_array()? {
-                self.user = i;
-                return Ok(());
-            }
-        }
+        let Ok(user) = self.find_user(username) else {
+            return Err(wrong());
+        };
+        self.check_password(username, password)?;
 
-        Err(Error::new(
-            ErrorKind::PermissionDenied,
-            "Wrong username or password",
-        ))
+        self.gids = self.user_gids(&user)?;
+        self.user = user.uid;
+        if self.chdir(&user.home).is_err() {
+            self.chdir("/")?;
+        }
+        Ok(())
     }
 }
@@ -1,9 +1,14 @@
+mod chage;
 mod chdir;
+mod chgrp;
 mod chmod;
 mod chown;
 mod close;
 mod create;
 mod format;
+mod gpasswd;
+mod groupadd;
+mod groupdel;
 mod init;
 mod link;
 mod login;
@@ -12,10 +17,13 @@ mod passwd;
 mod path;
 mod pwd;
 mod read;
+mod rename;
 mod rm;
 mod rmdir;
 mod seek;
+mod stat;
 mod symlink;
 mod useradd;
 mod userdel;
+mod utime;
 mod write;
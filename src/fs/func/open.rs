@@ -1,67 +1,102 @@
 use crate::fs::core::*; // 引入文件系统核心模块
 
 impl Fs {
-    /// 用来打开路径名为 `path` 的文件
+    /// 以只读模式打开路径名为 `path` 的文件，相当于 `open_with(path, OpenOptions::new().read(true))`
     ///
     /// 当文件不存在、或者同时打开的文件数超过 [`FD_LIMIT`]、或者尝试打开一个目录时，文件打开
     /// 失败
     pub fn open(&mut self, path: &str) -> Result<usize> {
+        self.open_with(path, OpenOptions::new().read(true))
+    }
+
+    /// 按照 `options` 打开路径名为 `path` 的文件
+    ///
+    /// 读模式需要文件的读权限，写模式和追加模式需要文件的写权限，
+    /// 返回的文件描述符只能做打开时允许的操作
+    pub fn open_with(&mut self, path: &str, options: &OpenOptions) -> Result<usize> {
+        // 只有创建和截断会修改磁盘，其余情况不需要事务
+        if options.create || options.create_new || options.truncate {
+            self.transaction(|fs| fs._open_with(path, options))
+        } else {
+            self._open_with(path, options)
+        }
+    }
+
+    /// 打开文件
+    fn _open_with(&mut self, path: &str, options: &OpenOptions) -> Result<usize> {
+        options.validate()?;
+
         // 当前打开的文件已经到达上限
         if self.opened_len == FD_LIMIT {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 "Files descriptions up to limits",
             )); // 如果打开的文件数达到上限，返回错误
         }
 
-        // 使用path_parse_with_options函数解析路径，并设置follow_symlinks为true
-        let path = self.path_parse(path)?; // 解析路径，返回路径解析结果
+        // 解析路径，文件不存在时按照选项创建文件
+        let path = match self.path_parse(path) {
+            Ok(_) if options.create_new => {
+                return Err(Error::new(ErrorKind::AlreadyExists, "Files has exists"));
+            }
+            Ok(res) => res,
+            Err(e) if e.kind() == ErrorKind::NotFound && (options.create || options.create_new) => {
+                self._create(path, FileType::File)?;
+                self.path_parse(path)?
+            }
+            Err(e) => return Err(e),
+        };
 
         // 不能打开目录
         if let FileType::Dir = path.dir_entry.file_type.into() {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 "Can't open directory as file",
             )); // 如果路径指向的是目录，返回错误
         }
 
         // 文件的索引节点
-        let inode = Inode::from_disk(&self.disk, self.addr_i_node(path.dir_entry.i_node))?; // 从磁盘加载文件的索引节点
+        let inode = self.get_inode(path.dir_entry.i_node)?;
 
-        // open 需要文件的读权限
-        if !inode.i_mode.can_read(self.user) {
+        // 读模式需要文件的读权限
+        if options.read && !inode.i_mode.can_read(self.user, &self.gids) {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Permission Denied. Need read permission.",
-            )); // 检查当前用户是否有读权限，如果没有则返回错误
+            ));
         }
 
-        // 分配文件描述符
-        let mut fd = 0; // 初始化文件描述符变量
-        for i in 0..self.fds.len() {
-            if self.fds[i].is_none() {
-                fd = i; // 找到一个空闲的文件描述符位置
-                break;
-            }
+        // 写模式和追加模式需要文件的写权限
+        if options.writable() && !inode.i_mode.can_write(self.user, &self.gids) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "Permission Denied. Need write permission.",
+            ));
         }
 
+        if options.truncate {
+            self.cut_inode(path.dir_entry.i_node, 0)?;
+        }
+
+        // 分配文件描述符
+        let fd = self.fds.iter().position(|f| f.is_none()).unwrap();
         self.fds[fd] = Some(File {
-            inode, // 文件的索引节点
             inode_i: path.dir_entry.i_node, // 索引节点编号
             dir_entry_addr: path.dir_entry_addr, // 目录项地址
             parent_inode_i: path.parent_inode_i, // 父目录的索引节点编号
             current_pos: 0, // 文件的当前读写位置
+            readable: options.read,
+            writable: options.writable(),
+            append: options.append,
         });
 
         self.opened_len += 1; // 增加打开的文件计数
 
-        return Ok(fd); // 返回分配的文件描述符
+        Ok(fd)// 返回分配的文件描述符
     }
 }
 
 #[test]
 fn open_test() {
-    let mut fs = Fs::format().unwrap(); // 格式化文件系统
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap(); // 格式化文件系统
 
     // 测试打开无效路径
     assert!(fs.open(".").is_err()); // 尝试打开当前目录，应该失败
@@ -70,7 +105,9 @@ fn open_test() {
 
     // 测试创建和打开目录
     assert!(fs.mkdir("dir_a").is_ok()); // 创建目录 "dir_a"，应该成功
-    assert!(fs.mkdir("dirrrrrrrrrrrrrrrrrrrrr").is_err()); // 创建过长的目录名，应该失败
+    assert!(fs.mkdir("dirrrrrrrrrrrrrrrrrrrrr").is_ok()); // 目录名可以超过 16 字节
+    assert!(fs.mkdir(&"d".repeat(MAX_NAME_LEN)).is_ok()); // 最长 255 字节
+    assert!(fs.mkdir(&"d".repeat(MAX_NAME_LEN + 1)).is_err()); // 创建过长的目录名，应该失败
 
     // 测试打开目录
     assert!(fs.open("dir_a").is_err()); // 尝试打开目录 "dir_a"，应该失败
@@ -83,5 +120,62 @@ fn open_test() {
             .open(&format!("./../../../../.././/////file_{}", i))
             .is_ok()); // 打开文件，即使路径包含冗余部分，也应该成功
     }
-    assert!(fs.open(&format!("file_0")).is_err()); // 尝试打开超出文件描述符限制的文件，应该失败
-}
\ No newline at end of file
+    assert!(fs.open("file_0").is_err()); // 尝试打开超出文件描述符限制的文件，应该失败
+}
+
+#[test]
+fn test_open_options() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+
+    // 非法的选项组合
+    assert_eq!(fs.open_with("a", &OpenOptions::new()).unwrap_err().kind(), ErrorKind::InvalidInput);
+    let err = fs.open_with("a", OpenOptions::new().read(true).create(true)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = fs.open_with("a", OpenOptions::new().append(true).truncate(true)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    // create 在文件不存在时创建文件，create_new 要求文件不存在
+    assert_eq!(fs.open_with("a", OpenOptions::new().write(true)).unwrap_err().kind(), ErrorKind::NotFound);
+    let fd = fs.open_with("a", OpenOptions::new().write(true).create_new(true)).unwrap();
+    let err = fs.open_with("a", OpenOptions::new().write(true).create_new(true)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+    // 只写的文件描述符不能读，只读的文件描述符不能写
+    fs.write(fd, b"hello").unwrap();
+    assert!(fs.read(fd, &mut [0u8; 5]).is_err());
+    let ro = fs.open("a").unwrap();
+    assert!(fs.write(ro, b"x").is_err());
+
+    // 追加模式总是写在文件末尾，即使另一个文件描述符先把文件写长了
+    let ap = fs.open_with("a", OpenOptions::new().append(true).create(true)).unwrap();
+    fs.write(fd, b" world").unwrap();
+    fs.write(ap, b"!").unwrap();
+    let mut buf = [0u8; 16];
+    assert_eq!(fs.read(ro, &mut buf).unwrap(), 12);
+    assert_eq!(&buf[..12], b"hello world!");
+
+    // truncate 打开时把文件长度截断为 0，并释放数据块
+    let free_blocks = fs.super_block().free_blocks_count;
+    let fd = fs.open_with("a", OpenOptions::new().write(true).truncate(true)).unwrap();
+    assert_eq!(fs.get_inode(fs.fds[fd].as_ref().unwrap().inode_i).unwrap().i_size, 0);
+    assert_eq!(fs.super_block().free_blocks_count, free_blocks + 1);
+
+    // 打开时检查权限
+    fs.create("b").unwrap();
+    fs.chmod("b", 0o400).unwrap();
+    assert_eq!(
+        fs.open_with("b", OpenOptions::new().write(true)).unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+    assert!(fs.open("b").is_ok());
+
+    // 不创建也不截断的打开不写磁盘，只读挂载时同样可以打开
+    let disk = MemDevice::new();
+    let mut fs = Fs::format_on(disk.clone(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.create("c").unwrap();
+    fs.exit().unwrap();
+    let mut fs = Fs::init_on(ReadOnlyDevice::new(disk)).unwrap();
+    assert!(fs.open_with("c", OpenOptions::new().read(true)).is_ok());
+    let err = fs.open_with("d", OpenOptions::new().write(true).create(true)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+}
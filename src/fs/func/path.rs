@@ -25,7 +25,7 @@ impl Fs {
         let mut dir_entry = if path.starts_with("/") {
             // 返回根节点的 DirEntry
             path.remove(0);
-            DirEntry::from_disk(&self.disk, Self::addr_data_blk(0))?
+            DirEntry::from_disk(&self.disk, self.addr_data_blk(ROOT_DIR_BLK))?
         } else {
             // 返回工作目录的 DirEntry
             self.cwd.clone()
@@ -33,10 +33,10 @@ impl Fs {
 
         let inode = self.get_inode(dir_entry.i_node)?;
 
-        let mut dir_entry_addr = Self::addr_data_blk(inode.i_block[0]);
+        let mut dir_entry_addr = inode.convert_addr(self, 0)?.addr;
         let mut parent_inode_i = DirEntry::from_disk(
             &self.disk,
-            Self::addr_data_blk(inode.i_block[0]) + DIR_ENTRY_SIZE as u64,
+            dir_entry_addr + DirEntry::rec_size(1) as u64,
         )?
         .i_node;
 
@@ -51,19 +51,19 @@ impl Fs {
                 ".." => {
                     // 返回父目录
                     let parent_inode = self.get_inode(parent_inode_i)?;
-                    dir_entry_addr = Self::addr_data_blk(parent_inode.i_block[0]);
+                    dir_entry_addr = parent_inode.convert_addr(self, 0)?.addr;
                     dir_entry = DirEntry::from_disk(&self.disk, dir_entry_addr)?;
 
                     parent_inode_i = DirEntry::from_disk(
                         &self.disk,
-                        Self::addr_data_blk(parent_inode.i_block[0]) + DIR_ENTRY_SIZE as u64,
+                        dir_entry_addr + DirEntry::rec_size(1) as u64,
                     )?
                     .i_node;
                 }
                 _ => {
                     // 检查当前目录的执行权限
                     let inode = self.get_inode(dir_entry.i_node)?;
-                    if !inode.i_mode.can_exec(self.user) {
+                    if !inode.i_mode.can_exec(self.user, &self.gids) {
                         return Err(Error::new(
                             ErrorKind::PermissionDenied,
                             "Permission Denied. Need exec permission.",
@@ -71,29 +71,14 @@ impl Fs {
                     }
 
                     // 在当前目录下查找名为 name 的文件
-                    let mut found = false;
-                    let mut found_entry = dir_entry.clone();
-                    let mut found_entry_addr = dir_entry_addr;
-                    let mut found_parent_inode_i = parent_inode_i;
-
-                    for item in dir_entry.iter(self)? {
-                        if let DirEntryIterItem::Using(Item { entry, real_addr }) = item {
-                            if entry.name == name.into_array()? {
-                                found = true;
-                                found_entry = entry;
-                                found_entry_addr = real_addr.addr;
-                                found_parent_inode_i = dir_entry.i_node;
-                                break;
-                            }
-                        }
-                    }
-
-                    if !found {
+                    let Some(Item { entry: found_entry, real_addr }) = self.find_dir_entry(&dir_entry, name)? else {
                         return Err(Error::new(
                             ErrorKind::NotFound,
                             format!("{}: No such file or directory", name),
                         ));
-                    }
+                    };
+                    let found_entry_addr = real_addr.addr;
+                    let found_parent_inode_i = dir_entry.i_node;
 
                     // 处理软链接
                     let symlink_type: u8 = FileType::Symlink.into();
@@ -104,8 +89,8 @@ impl Fs {
                         
                         // 读取软链接数据块中存储的目标路径
                         if symlink_inode.i_blocks > 0 {
-                            let data_blk = symlink_inode.i_block[0];
-                            self.disk.read_at(&mut target_path, Self::addr_data_blk(data_blk))?;
+                            let addr = symlink_inode.convert_addr(self, 0)?.addr;
+                            self.disk.read_at(&mut target_path, addr)?;
                         }
                         
                         let target = String::from_utf8(target_path)
@@ -161,7 +146,7 @@ impl Fs {
 
 #[test]
 fn test_path_parse() -> Result<()> {
-    let mut fs = Fs::format()?;
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root"))?;
 
     fs.mkdir("a")?;
     fs.mkdir("b")?;
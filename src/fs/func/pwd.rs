@@ -18,7 +18,7 @@ impl Fs {
     }
 
     /// 根据inode号获取文件路径
-    fn get_path_by_inode(&self, inode_i: u16) -> String {
+    fn get_path_by_inode(&self, inode_i: u32) -> String {
         if inode_i == 0 {
             return "/".to_string(); // 如果inode号为0，表示根目录
         }
@@ -48,33 +48,33 @@ impl Fs {
     }
 
     /// 获取指定inode的父目录inode号
-    fn get_parent_inode(&self, inode_i: u16) -> Result<u16> {
+    fn get_parent_inode(&self, inode_i: u32) -> Result<u32> {
         // 获取inode
         let inode = self.get_inode(inode_i)?; // 获取指定inode号的inode信息
 
         // 只有目录才有父目录
-        if inode.i_blocks == 0 || inode.i_mode.mode & 0b00_000_001 == 0 {
-            return Err(Error::new(ErrorKind::Other, "Not a directory")); // 如果不是目录，返回错误
+        if inode.i_blocks == 0 || inode.i_mode.mode & 0o001 == 0 {
+            return Err(Error::other("Not a directory")); // 如果不是目录，返回错误
         }
 
-        // 读取目录的第二个目录项（".."）
+        // 读取目录的第二个目录项（".."），它紧跟在 "." 之后
         let parent_entry = DirEntry::from_disk(
             &self.disk,
-            Self::addr_data_blk(inode.i_block[0]) + DIR_ENTRY_SIZE as u64,
+            inode.convert_addr(self, 0)?.addr + DirEntry::rec_size(1) as u64,
         )?;
 
         Ok(parent_entry.i_node) // 返回父目录的inode号
     }
 
     /// 根据inode号获取目录项
-    fn get_entry_by_inode(&self, inode_i: u16) -> Result<DirEntry> {
+    fn get_entry_by_inode(&self, inode_i: u32) -> Result<DirEntry> {
         // 从根目录开始查找
-        let root_entry = DirEntry::from_disk(&self.disk, Self::addr_data_blk(0))?; // 获取根目录的目录项
+        let root_entry = DirEntry::from_disk(&self.disk, self.addr_data_blk(ROOT_DIR_BLK))?; // 获取根目录的目录项
         self.find_entry_by_inode(root_entry, inode_i) // 从根目录开始递归查找指定inode的目录项
     }
 
     /// 在指定目录中查找inode对应的目录项
-    fn find_entry_by_inode(&self, dir_entry: DirEntry, inode_i: u16) -> Result<DirEntry> {
+    fn find_entry_by_inode(&self, dir_entry: DirEntry, inode_i: u32) -> Result<DirEntry> {
         // 如果当前目录就是要找的inode
         if dir_entry.i_node == inode_i {
             return Ok(dir_entry); // 直接返回当前目录项
@@ -82,7 +82,7 @@ impl Fs {
 
         // 遍历目录下的所有项
         for item in dir_entry.iter(self)? {
-            if let DirEntryIterItem::Using(Item { entry, .. }) = item {
+            if let DirEntryIterItem::Using(Item { entry, .. }) = item? {
                 // 跳过. 和 ..
                 let name = utils::str(&entry.name);
                 if name == "." || name == ".." {
@@ -111,7 +111,7 @@ impl Fs {
 
 #[test]
 fn test_pwd() {
-    let mut fs = Fs::format().unwrap(); // 格式化文件系统
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap(); // 格式化文件系统
     fs.mkdir("a").unwrap(); // 创建目录 "a"
     fs.chdir("a").unwrap(); // 切换到目录 "a"
     fs.mkdir("b").unwrap(); // 创建目录 "b"
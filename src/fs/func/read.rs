@@ -8,47 +8,66 @@ impl Fs {
         // 如果文件描述符无效，返回错误
         if fd >= self.fds.len() || self.fds[fd].is_none() {
             // 返回错误
-            return Err(Error::new(ErrorKind::Other, "Bad file description"));
+            return Err(Error::other("Bad file description"));
         }
 
-        // 获取文件
-        let file = self.fds[fd].as_mut().unwrap();
+        // 获取文件的索引节点和当前位置
+        let file = self.fds[fd].as_ref().unwrap();
+        let inode = self.get_inode(file.inode_i)?;
+        let mut current_pos = file.current_pos;
 
-        // 如果用户没有读权限，返回错误
-        if !file.inode.i_mode.can_read(self.user) {
+        // 权限在打开时已经检查过，这里只检查打开时是否允许读取
+        if !file.readable {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
-                "Permission Denied.",
+                "Bad file descriptor: not opened for reading",
             ));
         }
 
+        // 本次能读取的字节数，不能超过文件末尾
+        let block_size = self.block_size();
+        let end = buf.len().min((inode.i_size as usize).saturating_sub(current_pos));
+
         // 计数器
         let mut counter = 0;
 
-        // 循环读取
-        loop {
-            if file.current_pos >= file.inode.i_size as usize || counter == buf.len() {
-                break;
+        // 按数据块循环读取
+        while counter < end {
+            // 每次最多读到当前数据块的末尾
+            let len = (block_size - current_pos % block_size).min(end - counter);
+
+            // 获取文件地址，空洞直接读出 0
+            let addr = inode.convert_addr(self, current_pos as u64)?;
+            match addr.is_hole() {
+                true => buf[counter..counter + len].fill(0),
+                false => {
+                    self.disk.read_at(&mut buf[counter..counter + len], addr.addr)?;
+                }
             }
 
-            // 创建一个缓冲区用于存储读取的字符
-            let mut c = [0u8; 1];
-            // 获取文件地址
-            let addr = file
-                .inode
-                .convert_addr(&self.disk, file.current_pos as u64)?;
-            self.disk.read_at(&mut c, addr.addr)?;
-
-            // 将读取的字符写入缓冲区
-            buf[counter] = c[0];
-
-            // 计数器加 1
-            counter += 1;
-            // 当前位置加 1
-            file.current_pos += 1;
+            counter += len;
+            current_pos += len;
+        }
+
+        // 更新文件的当前位置
+        let inode_i = self.fds[fd].as_ref().unwrap().inode_i;
+        self.fds[fd].as_mut().unwrap().current_pos = current_pos;
+
+        if counter > 0 {
+            self.update_atime(inode_i, inode)?;
         }
 
         // 返回已经读取的字符个数
         Ok(counter)
     }
+
+    /// 按照挂载时指定的 [`AtimeMode`] 更新索引节点的访问时间，只读设备上不更新
+    fn update_atime(&mut self, inode_i: u32, mut inode: Inode) -> Result<()> {
+        let now = utils::now();
+        if self.disk.is_read_only() || !self.atime_mode.need_update(&inode, now) {
+            return Ok(());
+        }
+        inode.set_atime(now);
+        self.transaction(|fs| fs.write_inode(inode_i, inode))
+    }
 }
@@ -0,0 +1,263 @@
+//! # 重命名
+//!
+//! [`Fs::rename`] 把目录项从一个目录移动到另一个目录，不复制文件内容。
+//! 所有修改都在同一个事务中完成，崩溃之后磁盘上要么是移动之前的状态，要么是移动之后的状态。
+
+use crate::fs::core::*;
+
+impl Fs {
+    /// 把 `from` 重命名为 `to`
+    ///
+    /// - `from` 是符号链接时移动的是链接本身
+    /// - `to` 已经存在时会被替换：文件只能替换文件，目录只能替换空目录
+    /// - 需要对两个父目录的写权限，不能把目录移动到它自己的子目录中
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<()> {
+        self.transaction(|fs| fs._rename(from, to))
+    }
+
+    /// 重命名
+    fn _rename(&mut self, from: &str, to: &str) -> Result<()> {
+        // 源文件和它所在的目录
+        let src = self.path_parse_with_options(from, false)?;
+        let src_name = utils::str(&src.dir_entry.name).to_string();
+        if src.dir_entry.i_node == 0 || src_name == "." || src_name == ".." {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Can't rename {}", from),
+            ));
+        }
+        let src_is_dir = src.dir_entry.file_type == u8::from(FileType::Dir);
+
+        // 目标所在的目录
+        let (dst_path, dst_name) = to.rsplit_once("/").unwrap_or((".", to));
+        let dst_dir = match dst_path {
+            "" => self.path_parse("/")?.dir_entry,
+            path => self.path_parse(path)?.dir_entry,
+        };
+        if dst_dir.file_type != u8::from(FileType::Dir) {
+            return Err(Error::new(ErrorKind::NotADirectory, format!("{}: Not a directory", dst_path)));
+        }
+        if dst_name.is_empty() || dst_name == "." || dst_name == ".." {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Can't rename to {}", to),
+            ));
+        }
+
+        // 需要对两个父目录的写权限
+        for dir_i in [src.parent_inode_i, dst_dir.i_node] {
+            if !self.get_inode(dir_i)?.i_mode.can_write(self.user, &self.gids) {
+                return Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "Need write permission to directory",
+                ));
+            }
+        }
+
+        // 设置了粘滞位的目录中只能移动自己的文件
+        self.check_sticky(src.parent_inode_i, src.dir_entry.i_node)?;
+
+        // 目录不能移动到它自己或者它的子目录中
+        if src_is_dir && dst_dir.i_node != src.parent_inode_i {
+            let mut dir_i = dst_dir.i_node;
+            while dir_i != 0 {
+                if dir_i == src.dir_entry.i_node {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "Cannot move directory into itself",
+                    ));
+                }
+                dir_i = self.dir_parent(dir_i)?;
+            }
+        }
+
+        // 检查已经存在的目标
+        let target = self.find_dir_entry(&dst_dir, dst_name)?;
+        if let Some(Item { entry, .. }) = &target {
+            // 源和目标是同一个文件，什么也不做
+            if entry.i_node == src.dir_entry.i_node {
+                return Ok(());
+            }
+            self.check_sticky(dst_dir.i_node, entry.i_node)?;
+            let target_is_dir = entry.file_type == u8::from(FileType::Dir);
+            match (src_is_dir, target_is_dir) {
+                (true, false) => {
+                    return Err(Error::new(
+                        ErrorKind::NotADirectory,
+                        format!("{}: Not a directory", to),
+                    ));
+                }
+                (false, true) => {
+                    return Err(Error::new(
+                        ErrorKind::IsADirectory,
+                        format!("{}: Is a directory", to),
+                    ));
+                }
+                (true, true) if !self.dir_is_empty(entry)? => {
+                    return Err(Error::new(
+                        ErrorKind::DirectoryNotEmpty,
+                        format!("{}: Directory not empty", to),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        // 先删除源目录项：在目标目录中添加目录项时可能拆分目录索引的叶子块，目录项的地址会变
+        self.remove_dir_entry(src.parent_inode_i, src.dir_entry_addr)?;
+
+        let new_addr = match target {
+            // 原地替换目标目录项指向的索引节点，文件名不变，目录索引也不用修改
+            Some(Item { mut entry, real_addr }) => {
+                let old_i = entry.i_node;
+                let old_is_dir = entry.file_type == u8::from(FileType::Dir);
+                entry = DirEntry::from_disk(&self.disk, real_addr.addr)?;
+                entry.i_node = src.dir_entry.i_node;
+                entry.file_type = src.dir_entry.file_type;
+                self.write_dir_entry(&entry, real_addr.addr)?;
+                self.unlink_inode(old_i, old_is_dir)?;
+                real_addr.addr
+            }
+            None => {
+                let entry = DirEntry {
+                    i_node: src.dir_entry.i_node,
+                    name: dst_name.into_array()?,
+                    name_len: dst_name.len() as u8,
+                    file_type: src.dir_entry.file_type,
+                    rec_len: 0,
+                    checksum: 0,
+                };
+                self.add_dir_entry(dst_dir.i_node, &entry)?;
+                self.find_dir_entry(&dst_dir, dst_name)?.unwrap().real_addr.addr
+            }
+        };
+
+        // 移动目录时更新它的 ..
+        if src_is_dir && dst_dir.i_node != src.parent_inode_i {
+            let inode = self.get_inode(src.dir_entry.i_node)?;
+            let addr = inode.convert_addr(self, DirEntry::rec_size(1) as u64)?.addr;
+            let mut dot_dot = DirEntry::from_disk(&self.disk, addr)?;
+            dot_dot.i_node = dst_dir.i_node;
+            self.write_dir_entry(&dot_dot, addr)?;
+        }
+
+        // 打开了这个文件的文件描述符记录的是目录项的位置，跟着一起更新
+        for file in self.fds.iter_mut().flatten() {
+            if file.dir_entry_addr == src.dir_entry_addr && file.inode_i == src.dir_entry.i_node {
+                file.dir_entry_addr = new_addr;
+                file.parent_inode_i = dst_dir.i_node;
+            }
+        }
+        Ok(())
+    }
+
+    /// 目录 `dir_i` 的父目录，即它的 `..` 指向的索引节点
+    fn dir_parent(&self, dir_i: u32) -> Result<u32> {
+        let inode = self.get_inode(dir_i)?;
+        let addr = inode.convert_addr(self, DirEntry::rec_size(1) as u64)?.addr;
+        Ok(DirEntry::from_disk(&self.disk, addr)?.i_node)
+    }
+
+    /// 目录中是否只有 `.` 和 `..`
+    pub(super) fn dir_is_empty(&self, dir: &DirEntry) -> Result<bool> {
+        for item in dir.iter_without_limit(self)? {
+            if let DirEntryIterItem::Using(Item { entry, .. }) = item? {
+                if !matches!(utils::str(&entry.name), "." | "..") {
+                    return Ok(false);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// 被替换的目标少了一个目录项引用，没有其它硬链接时释放它的数据块和索引节点
+    ///
+    /// 打开了这个文件的文件描述符随之失效
+    fn unlink_inode(&mut self, inode_i: u32, is_dir: bool) -> Result<()> {
+        let mut inode = self.get_inode(inode_i)?;
+        inode.i_links_count = inode.i_links_count.saturating_sub(1);
+        inode.touch_ctime();
+        if inode.i_links_count > 0 && !is_dir {
+            return self.write_inode(inode_i, inode);
+        }
+
+        inode.free_data_block(0, self)?;
+        let blk_type = match is_dir {
+            true => BlkType::DirINode,
+            false => BlkType::INode,
+        };
+        self.free(blk_type, &[inode_i])?;
+
+        for fd in self.fds.iter_mut() {
+            if fd.as_ref().is_some_and(|file| file.inode_i == inode_i) {
+                *fd = None;
+                self.opened_len -= 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_rename() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    let free_blocks = fs.super_block().free_blocks_count;
+
+    // 同一个目录中重命名，打开的文件描述符仍然有效
+    let fd = fs.open_with("a", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.write(fd, b"hello").unwrap();
+    let ino = fs.stat("a").unwrap().ino;
+    fs.rename("a", "b").unwrap();
+    assert_eq!(fs.stat("a").unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(fs.stat("b").unwrap().ino, ino);
+
+    // 移动到另一个目录
+    fs.mkdir("d").unwrap();
+    fs.rename("b", "d/c").unwrap();
+    assert_eq!(fs.stat("d/c").unwrap().ino, ino);
+    fs.rm(fd).unwrap();
+    assert!(fs.stat("d/c").is_err());
+
+    // 移动目录时更新 ..，pwd 跟着变化
+    fs.mkdir("d/e").unwrap();
+    fs.mkdir("x").unwrap();
+    fs.chdir("d/e").unwrap();
+    fs.rename("/d", "/x/d").unwrap();
+    assert_eq!(fs.pwd(), "/x/d/e");
+    fs.chdir("/").unwrap();
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 不能把目录移动到它自己的子目录中
+    let err = fs.rename("x", "x/d/e/x").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert!(fs.rename("x/d", "x/d/y").is_err());
+
+    // 替换已有的目标：文件替换文件，目录替换空目录
+    fs.create("f1").unwrap();
+    fs.create("f2").unwrap();
+    let f1 = fs.stat("f1").unwrap().ino;
+    fs.rename("f1", "f2").unwrap();
+    assert_eq!(fs.stat("f2").unwrap().ino, f1);
+    assert_eq!(fs.rename("f2", "x").unwrap_err().kind(), ErrorKind::IsADirectory);
+    assert_eq!(fs.rename("x", "f2").unwrap_err().kind(), ErrorKind::NotADirectory);
+    fs.mkdir("empty").unwrap();
+    let dirs = fs.super_block().used_dirs_count;
+    assert_eq!(fs.rename("empty", "x").unwrap_err().kind(), ErrorKind::DirectoryNotEmpty);
+    fs.rename("x", "empty").unwrap();
+    assert_eq!(fs.super_block().used_dirs_count, dirs - 1);
+    assert_eq!(fs.stat("empty/d/e").unwrap().file_type, FileType::Dir);
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 需要父目录的写权限
+    fs.chmod("empty", 0o500).unwrap();
+    assert_eq!(
+        fs.rename("empty/d", "moved").unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+    fs.chmod("empty", 0o700).unwrap();
+
+    // 清理之后所有数据块都被释放
+    fs.rmdir_recursive("empty").unwrap();
+    fs.open("f2").and_then(|fd| fs.rm(fd)).unwrap();
+    assert_eq!(fs.super_block().free_blocks_count, free_blocks);
+}
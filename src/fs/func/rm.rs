@@ -2,75 +2,103 @@ use crate::fs::core::*;
 
 impl Fs {
     /// 将文件的长度裁剪到 `new_len` 字节，并清空 `new_len` 之后的数据块
-    /// 如果 `new_len` 等于文件长度，则这个函数不起作用
+    /// 如果 `new_len` 不小于文件长度，则这个函数不起作用
     pub fn cut(&mut self, fd: usize, new_len: u64) -> Result<()> {
+        self.transaction(|fs| fs._cut(fd, new_len))
+    }
+
+    /// 裁剪文件
+    fn _cut(&mut self, fd: usize, new_len: u64) -> Result<()> {
         if fd >= self.fds.len() || self.fds[fd].is_none() {
-            return Err(Error::new(ErrorKind::Other, "Bad file description"));
+            return Err(Error::other("Bad file description"));
         }
 
-        // 计算 new_len 字节占用的块数
-        let new_blk_counts = match new_len % BLOCK_SIZE as u64 {
-            0 => new_len / BLOCK_SIZE as u64,
-            _ => new_len / BLOCK_SIZE as u64 + 1,
-        } as u16;
-
-        let mut file = self.fds[fd].clone().unwrap();
+        let inode_i = self.fds[fd].as_ref().unwrap().inode_i;
+        let inode = self.get_inode(inode_i)?;
 
         // 需要写权限
-        if !file.inode.i_mode.can_write(self.user) {
+        if !inode.i_mode.can_write(self.user, &self.gids) {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Need write permission",
             ));
         }
 
-        if new_blk_counts >= file.inode.i_blocks {
+        self.cut_inode(inode_i, new_len)
+    }
+
+    /// 将索引节点为 `inode_i` 的文件裁剪到 `new_len` 字节，不检查权限
+    pub(in crate::fs) fn cut_inode(&mut self, inode_i: u32, new_len: u64) -> Result<()> {
+        // 计算 new_len 字节占用的块数
+        let block_size = self.block_size() as u64;
+        let new_blk_counts = new_len.div_ceil(block_size) as u32;
+
+        let mut inode = self.get_inode(inode_i)?;
+        if new_len >= inode.i_size as u64 {
             return Ok(());
         }
 
-        // 删除多余的数据块
-        file.inode.free_data_block(new_blk_counts, self)?;
+        // 从后往前分步删除多余的数据块，空洞不占用数据块，会被直接跳过。
+        // 每一步之后文件都是完整的，只是短了一些，日志区不够大时可以先提交
+        let step = self.addr_per_blk() as u32;
+        let mut blk_counts = (inode.i_size as u64).div_ceil(block_size) as u32;
+        loop {
+            if self.disk.room() < STEP_BLOCKS {
+                self.write_inode(inode_i, inode.clone())?;
+                self.ensure_room(STEP_BLOCKS)?;
+            }
+            blk_counts = blk_counts.saturating_sub(step).max(new_blk_counts);
+            inode.free_data_block(blk_counts, self)?;
+            inode.i_size = (inode.i_size as u64).min(blk_counts as u64 * block_size) as u32;
+            if blk_counts == new_blk_counts {
+                break;
+            }
+        }
 
-        // 完成文件大小的剪裁，更新索引节点
-        file.inode.i_mtime = utils::now();
-        file.inode.i_size = new_len as u32;
+        // 保留下来的最后一个数据块中 new_len 之后的部分清零，以后把文件写长时这部分要读出 0
+        let tail = inode.convert_addr(self, new_len)?;
+        let offset = (new_len % self.block_size() as u64) as usize;
+        if offset != 0 && !tail.is_hole() {
+            self.disk.write_data_at(&self.empty_blk()[offset..], tail.addr)?;
+        }
 
-        self.fds[fd] = Some(file);
-        Ok(())
+        // 完成文件大小的剪裁，更新索引节点
+        inode.touch_mtime();
+        inode.i_size = new_len as u32;
+        self.write_inode(inode_i, inode)
     }
 
     // 删除文件
     pub fn rm(&mut self, fd: usize) -> Result<()> {
+        self.transaction(|fs| fs._rm(fd))
+    }
+
+    /// 删除文件的目录项，没有其他硬链接时释放索引节点和数据块
+    fn _rm(&mut self, fd: usize) -> Result<()> {
         if fd >= self.fds.len() || self.fds[fd].is_none() {
-            return Err(Error::new(ErrorKind::Other, "Bad file description"));
+            return Err(Error::other("Bad file description"));
         }
 
-        let mut file = self.fds[fd].clone().unwrap();
+        let file = self.fds[fd].clone().unwrap();
+        let mut inode = self.get_inode(file.inode_i)?;
 
         // rm 需要对目录的写权限
-        if !file.inode.i_mode.can_write(self.user) {
+        if !inode.i_mode.can_write(self.user, &self.gids) {
             return Err(Error::new(ErrorKind::PermissionDenied, "Permission Denied"));
         }
+        self.check_sticky(file.parent_inode_i, file.inode_i)?;
 
         // 减少硬链接计数
-        file.inode.i_links_count -= 1;
+        inode.i_links_count -= 1;
+        inode.touch_ctime();
         
         // 如果还有其他硬链接引用，只更新inode并删除当前目录项
-        if file.inode.i_links_count > 0 {
+        if inode.i_links_count > 0 {
             // 更新inode
-            self.write_inode(file.inode_i, file.inode)?;
+            self.write_inode(file.inode_i, inode)?;
             
             // 删除当前目录项
-            let mut dir_entry = DirEntry::from_disk(&self.disk, file.dir_entry_addr)?;
-            dir_entry.i_node = 0;
-            dir_entry.rec_len = 1;
-            self.disk.write_at(dir_entry.bytes(), file.dir_entry_addr)?;
-            
-            // 更新父目录inode
-            let mut cwd_inode = Inode::from_disk(&self.disk, self.addr_i_node(file.parent_inode_i))?;
-            cwd_inode.i_size -= DIR_ENTRY_SIZE as u32;
-            cwd_inode.i_mtime = utils::now();
-            self.write_inode(file.parent_inode_i, cwd_inode)?;
+            self.remove_dir_entry(file.parent_inode_i, file.dir_entry_addr)?;
             
             // 回收文件描述符
             self.fds[fd] = None;
@@ -80,21 +108,13 @@ impl Fs {
         }
 
         // 如果没有其他硬链接引用，清空文件存储的数据块
-        self.cut(fd, 0)?;
+        self._cut(fd, 0)?;
 
         // 删除文件的索引节点
         self.free(BlkType::INode, &[file.inode_i])?;
 
-        // 编辑目录项
-        let mut dir_entry = DirEntry::from_disk(&self.disk, file.dir_entry_addr)?;
-        dir_entry.i_node = 0;
-        dir_entry.rec_len = 1;
-        self.disk.write_at(dir_entry.bytes(), file.dir_entry_addr)?;
-
-        let mut cwd_inode = Inode::from_disk(&self.disk, self.addr_i_node(file.parent_inode_i))?;
-        cwd_inode.i_size -= DIR_ENTRY_SIZE as u32;
-        cwd_inode.i_mtime = utils::now();
-        self.write_inode(file.parent_inode_i, cwd_inode)?;
+        // 删除目录项
+        self.remove_dir_entry(file.parent_inode_i, file.dir_entry_addr)?;
 
         // 回收文件描述符
         self.fds[fd] = None;
@@ -111,6 +131,11 @@ impl Fs {
     /// 返回:
     /// - `Result<()>`: 操作结果
     pub fn rm_symlink(&mut self, path: &str) -> Result<()> {
+        self.transaction(|fs| fs._rm_symlink(path))
+    }
+
+    /// 删除符号链接
+    fn _rm_symlink(&mut self, path: &str) -> Result<()> {
         // 使用path_parse_with_options并设置follow_symlinks为false，这样不会尝试解析符号链接
         let path_res = self.path_parse_with_options(path, false)?;
         let dir_entry = path_res.dir_entry;
@@ -124,20 +149,11 @@ impl Fs {
             ));
         }
         
-        // 获取父目录的inode
-        let mut parent_inode = self.get_inode(path_res.parent_inode_i)?;
-        
+        self.check_sticky(path_res.parent_inode_i, dir_entry.i_node)?;
+
         // 删除目录项
-        let mut dir_entry = DirEntry::from_disk(&self.disk, path_res.dir_entry_addr)?;
         let symlink_inode_i = dir_entry.i_node; // 保存要释放的inode编号
-        dir_entry.i_node = 0;
-        dir_entry.rec_len = 1;
-        self.disk.write_at(dir_entry.bytes(), path_res.dir_entry_addr)?;
-        
-        // 更新父目录inode
-        parent_inode.i_size -= DIR_ENTRY_SIZE as u32;
-        parent_inode.i_mtime = utils::now();
-        self.write_inode(path_res.parent_inode_i, parent_inode)?;
+        self.remove_dir_entry(path_res.parent_inode_i, path_res.dir_entry_addr)?;
         
         // 释放符号链接的inode
         self.free(BlkType::INode, &[symlink_inode_i])?;
@@ -150,22 +166,25 @@ impl Fs {
 fn test_rm_file() {
     use crate::fs::core::Item;
 
-    let mut fs = Fs::format().unwrap();
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
     fs.create("1.txt").unwrap();
     fs.create("2.txt").unwrap();
     fs.mkdir("dir").unwrap();
 
-    let fd = fs.open("1.txt").unwrap();
+    let addr = fs.path_parse("1.txt").unwrap().dir_entry_addr;
+    let fd = fs.open_with("1.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.write(fd, b"hello world").unwrap();
     fs.rm(fd).unwrap();
-    let fd = fs.open("2.txt").unwrap();
+    let fd = fs.open_with("2.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.rm(fd).unwrap();
+    // 两个被删除的目录项都合并到了前一个目录项里，新目录项拆分出这段空间
     fs.mkdir("hello").unwrap();
+    assert_eq!(fs.path_parse("hello").unwrap().dir_entry_addr, addr);
 
     let mut len = 0;
     for entry in fs.cwd.iter(&fs).unwrap() {
         len += 1;
-        match entry {
+        match entry.unwrap() {
             iter::DirEntryIterItem::Using(Item { entry, .. }) => {
                 println!("{}", std::str::from_utf8(&entry.name).unwrap())
             }
@@ -175,5 +194,26 @@ fn test_rm_file() {
         }
     }
 
+    // ., .., home, root, etc, hello, dir
     assert_eq!(len, 7);
+
+    // 打开文件之后目录建立了索引、叶子又被拆分，目录项搬了位置，仍然能通过文件描述符删除
+    for checksums in [true, false] {
+        let options = FormatOptions {
+            checksums,
+            root_password: "root".into(),
+            ..Default::default()
+        };
+        let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+        fs.mkdir("d").unwrap();
+        fs.create("d/victim").unwrap();
+        let fd = fs.open("d/victim").unwrap();
+        for i in 0..200 {
+            fs.create(&format!("d/file-{}", i)).unwrap();
+        }
+        fs.rm(fd).unwrap();
+        assert!(fs.open("d/victim").is_err());
+        assert_eq!(fs.read_dir("d").unwrap().count(), 200);
+        assert!(fs.fsck(false).unwrap().is_clean());
+    }
 }
@@ -4,12 +4,12 @@ use crate::fs::core::*;
 impl Fs {
     // 删除当前目录下的空文件夹 `dir_name`
     pub fn rmdir(&mut self, path: &str) -> Result<()> {
-        self.rmdir_internal(path, false)
+        self.transaction(|fs| fs.rmdir_internal(path, false))
     }
 
     // 递归删除目录（包括非空目录）
     pub fn rmdir_recursive(&mut self, path: &str) -> Result<()> {
-        self.rmdir_internal(path, true)
+        self.transaction(|fs| fs.rmdir_internal(path, true))
     }
 
     // 内部实现，支持递归删除
@@ -22,7 +22,7 @@ impl Fs {
         let inode = Inode::from_disk(&self.disk, self.addr_i_node(dir_entry.i_node))?;
 
         // rm 需要对目录的写权限
-        if !inode.i_mode.can_write(self.user) {
+        if !inode.i_mode.can_write(self.user, &self.gids) {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Permission Denied",
@@ -41,18 +41,17 @@ impl Fs {
         }
 
         if let FileType::File = dir_entry.file_type.into() {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 "Not a directory",
             ));
         }
+        self.check_sticky(item_to_delete.parent_inode_i, dir_entry.i_node)?;
 
         // 检查目录是否为空（只包含 . 和 ..）
-        let is_empty = inode.i_size as usize == 2 * DIR_ENTRY_SIZE;
+        let is_empty = self.dir_is_empty(&dir_entry)?;
         
         if !is_empty && !recursive {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 "Directory is not empty",
             ));
         }
@@ -68,17 +67,11 @@ impl Fs {
 
     // 清空目录中的所有内容（递归删除子目录和文件）
     fn clear_directory_contents(&mut self, dir_entry: &DirEntry) -> Result<()> {
-        // 保存当前工作目录
-        let original_cwd = self.cwd.clone();
-        
-        // 切换到要清空的目录
-        self.cwd = dir_entry.clone();
-
         // 收集所有需要删除的项目（避免在迭代时修改）
         let mut items_to_delete = Vec::new();
         
         for entry_item in dir_entry.iter_without_limit(self)? {
-            if let iter::DirEntryIterItem::Using(item) = entry_item {
+            if let iter::DirEntryIterItem::Using(item) = entry_item? {
                 let entry_name = str(&item.entry.name);
                 // 跳过 "." 和 ".."
                 if entry_name != "." && entry_name != ".." {
@@ -87,22 +80,30 @@ impl Fs {
             }
         }
 
+        // 保存当前工作目录
+        let original_cwd = self.cwd.clone();
+        
+        // 切换到要清空的目录
+        self.cwd = dir_entry.clone();
+
         // 删除收集到的所有项目
+        // 每删除一项之后状态都是一致的，日志区不够大时可以先提交
         for item in items_to_delete {
+            self.ensure_room(STEP_BLOCKS)?;
             let entry_name = str(&item.entry.name);
             match item.entry.file_type.into() {
                 FileType::File => {
                     // 递归删除文件
-                    let fd = self.open(&entry_name)?;
+                    let fd = self.open(entry_name)?;
                     self.rm(fd)?;
                 }
                 FileType::Dir => {
                     // 递归删除目录
-                    self.rmdir_recursive(&entry_name)?;
+                    self.rmdir_recursive(entry_name)?;
                 }
                 FileType::Symlink => {
                     // 删除软链接
-                    let fd = self.open(&entry_name)?;
+                    let fd = self.open(entry_name)?;
                     self.rm(fd)?;
                 }
             }
@@ -116,36 +117,24 @@ impl Fs {
 
     // 删除空目录的具体实现
     fn delete_empty_directory(&mut self, item_to_delete: PathParseRes) -> Result<()> {
-        let mut dir_entry = item_to_delete.dir_entry.clone();
+        let dir_entry = item_to_delete.dir_entry.clone();
         let mut inode = Inode::from_disk(&self.disk, self.addr_i_node(dir_entry.i_node))?;
 
         // 删除所有数据块
         inode.free_data_block(0, self)?;
 
         // 删除索引节点
-        self.free(BlkType::INode, &[dir_entry.i_node])?;
-
-        // 从当前目录下的目录项里删除目录信息
-        dir_entry.i_node = 0;
-        self.disk
-            .write_at(dir_entry.bytes(), item_to_delete.dir_entry_addr)?;
+        self.free(BlkType::DirINode, &[dir_entry.i_node])?;
 
-        // 更新父目录的信息
-        let mut inode = self.get_inode(item_to_delete.parent_inode_i)?;
-        inode.i_size -= DIR_ENTRY_SIZE as u32;
-        self.write_inode(item_to_delete.parent_inode_i, inode)?;
-
-        // 可用目录 + 1
-        self.fs_desc.used_dirs_count -= 1;
-        self.write_fs_desc()?;
-        Ok(())
+        // 从父目录的目录项里删除目录信息
+        self.remove_dir_entry(item_to_delete.parent_inode_i, item_to_delete.dir_entry_addr)
     }
 }
 
 #[test]
 fn test_rmdir() {
     use iter::DirEntryIterItem;
-    let mut fs = Fs::format().unwrap();
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
     fs.rmdir(".").expect_err("rmdir can't delete .");
     fs.rmdir(".").expect_err("rmdir can't delete ..");
 
@@ -162,7 +151,7 @@ fn test_rmdir() {
     fs.rmdir("dir_b").unwrap();
 
     fs.chdir("dir_a").unwrap();
-    let fd = fs.open("a.txt").unwrap();
+    let fd = fs.open_with("a.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.chdir("..").unwrap();
     fs.rm(fd).unwrap();
     fs.read(fd, &mut [0u8; 1])
@@ -171,7 +160,7 @@ fn test_rmdir() {
     fs.rmdir("dir_a").unwrap();
 
     for entry in fs.cwd.iter(&fs).unwrap() {
-        match entry {
+        match entry.unwrap() {
             DirEntryIterItem::Deleted(Item { entry, .. }) => {
                 println!("- {}", std::str::from_utf8(&entry.name).unwrap())
             }
@@ -7,33 +7,30 @@ pub enum Seek {
 }
 
 impl Fs {
+    /// 移动文件描述符 `fd` 的读写位置，返回新的位置
+    ///
+    /// 和 [`FileHandle`] 的 `seek` 一样，移动位置不需要任何访问权限，只读打开的文件也可以移动
     pub fn seek(&mut self, fd: usize, seek: Seek) -> Result<usize> {
         if fd >= self.fds.len() || self.fds[fd].is_none() {
-            return Err(Error::new(ErrorKind::Other, "Bad file description"));
+            return Err(Error::other("Bad file description"));
         }
 
+        let inode = self.get_inode(self.fds[fd].as_ref().unwrap().inode_i)?;
         let file = self.fds[fd].as_mut().unwrap();
 
-        // seek 需要写权限
-        if !file.inode.i_mode.can_write(self.user) {
-            return Err(Error::new(ErrorKind::PermissionDenied, "Permission Denied"));
-        }
-
         match seek {
             Seek::FromStart(pos) => file.current_pos = pos,
             Seek::FromEnd(size) => {
-                if size > file.inode.i_size as usize {
-                    return Err(Error::new(
-                        ErrorKind::Other,
+                if size > inode.i_size as usize {
+                    return Err(Error::other(
                         "Seek failed. Can't set cursor of file to negative",
                     ));
                 }
-                file.current_pos -= size;
+                file.current_pos = inode.i_size as usize - size;
             }
             Seek::Current(offset) => {
-                if file.inode.i_size as i64 + offset < 0 {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
+                if file.current_pos as i64 + offset < 0 {
+                    return Err(std::io::Error::other(
                         "Seek failed. Can't set cursor of file to negative",
                     ));
                 }
@@ -44,3 +41,65 @@ impl Fs {
         Ok(file.current_pos)
     }
 }
+
+#[test]
+fn test_sparse() {
+    for extents in [false, true] {
+        let options = FormatOptions {
+            extents,
+            root_password: "root".into(),
+            ..Default::default()
+        };
+        let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+        let free_blocks = fs.super_block().free_blocks_count;
+
+        // 跳过 100 个数据块之后再写入，中间的部分成为空洞
+        fs.create("sparse").unwrap();
+        let fd = fs.open_with("sparse", OpenOptions::new().read(true).write(true)).unwrap();
+        fs.seek(fd, Seek::FromStart(100 * 512 + 10)).unwrap();
+        fs.write(fd, b"end").unwrap();
+        let inode_of = |fs: &Fs, fd: usize| fs.get_inode(fs.fds[fd].as_ref().unwrap().inode_i).unwrap();
+        let inode = inode_of(&fs, fd);
+        assert_eq!(inode.i_size, 100 * 512 + 13);
+        assert_eq!(inode.i_blocks, 1);
+
+        // 再写入空洞中间的一个数据块
+        fs.seek(fd, Seek::FromStart(50 * 512)).unwrap();
+        fs.write(fd, b"mid").unwrap();
+        assert_eq!(inode_of(&fs, fd).i_blocks, 2);
+
+        // 空洞读出 0
+        let fd = fs.open_with("sparse", OpenOptions::new().read(true).write(true)).unwrap();
+        let mut buf = vec![0xffu8; 101 * 512];
+        assert_eq!(fs.read(fd, &mut buf).unwrap(), 100 * 512 + 13);
+        assert!(buf[..50 * 512].iter().all(|&b| b == 0));
+        assert_eq!(&buf[50 * 512..50 * 512 + 3], b"mid");
+        assert!(buf[50 * 512 + 3..100 * 512 + 10].iter().all(|&b| b == 0));
+        assert_eq!(&buf[100 * 512 + 10..100 * 512 + 13], b"end");
+
+        // 裁剪之后再写长，被裁掉的部分读出 0
+        fs.cut(fd, 50 * 512 + 1).unwrap();
+        assert_eq!(inode_of(&fs, fd).i_blocks, 1);
+        fs.seek(fd, Seek::FromStart(50 * 512 + 5)).unwrap();
+        fs.write(fd, b"x").unwrap();
+        let fd = fs.open_with("sparse", OpenOptions::new().read(true).write(true)).unwrap();
+        fs.seek(fd, Seek::FromStart(50 * 512)).unwrap();
+        let mut buf = [0xffu8; 6];
+        fs.read(fd, &mut buf).unwrap();
+        assert_eq!(&buf, b"m\0\0\0\0x");
+
+        fs.rm(fd).unwrap();
+        assert_eq!(fs.super_block().free_blocks_count, free_blocks);
+    }
+
+    // 移动位置不需要写权限，普通用户只读打开 /etc/passwd 之后也能移动
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.useradd("alice", "a").unwrap();
+    fs.login("alice", "a").unwrap();
+    let fd = fs.open(PASSWD_PATH).unwrap();
+    let size = fs.stat(PASSWD_PATH).unwrap().size as usize;
+    assert_eq!(fs.seek(fd, Seek::FromEnd(5)).unwrap(), size - 5);
+    assert_eq!(fs.seek(fd, Seek::Current(-3)).unwrap(), size - 8);
+    assert!(fs.seek(fd, Seek::Current(-(size as i64))).is_err());
+    assert_eq!(fs.seek(fd, Seek::FromStart(1)).unwrap(), 1);
+}
@@ -0,0 +1,66 @@
+//! # 获取文件的元数据
+
+use crate::fs::core::*;
+
+impl Fs {
+    /// 获取路径名为 `path` 的文件的元数据，路径指向符号链接时返回链接目标的元数据
+    pub fn stat(&self, path: &str) -> Result<Metadata> {
+        self.stat_with(path, true)
+    }
+
+    /// 获取路径名为 `path` 的文件的元数据，路径指向符号链接时返回符号链接本身的元数据
+    pub fn lstat(&self, path: &str) -> Result<Metadata> {
+        self.stat_with(path, false)
+    }
+
+    /// 获取文件描述符 `fd` 对应的文件的元数据
+    pub fn fstat(&self, fd: usize) -> Result<Metadata> {
+        let Some(Some(file)) = self.fds.get(fd) else {
+            return Err(Error::other("Bad file description"));
+        };
+
+        // 只能打开普通文件，打开符号链接时打开的是链接目标
+        let inode = self.get_inode(file.inode_i)?;
+        Ok(Metadata::new(file.inode_i, FileType::File, &inode))
+    }
+
+    /// 解析路径并读取索引节点，`follow_symlinks` 决定是否跟随最后一级的符号链接
+    fn stat_with(&self, path: &str, follow_symlinks: bool) -> Result<Metadata> {
+        let entry = self.path_parse_with_options(path, follow_symlinks)?.dir_entry;
+        let inode = self.get_inode(entry.i_node)?;
+        Ok(Metadata::new(entry.i_node, entry.file_type.into(), &inode))
+    }
+}
+
+#[test]
+fn test_stat() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    fs.mkdir("dir").unwrap();
+    let fd = fs.open_with("dir/a", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.write(fd, &[1; 1000]).unwrap();
+    fs.symlink("dir/a", "link").unwrap();
+
+    let meta = fs.stat("dir/a").unwrap();
+    assert!(meta.is_file());
+    assert_eq!(meta.size, 1000);
+    assert_eq!(meta.blocks, 2);
+    assert_eq!(meta.links, 1);
+    assert_eq!(meta.owner, fs.current_user());
+    assert!(meta.mtime >= meta.btime);
+    assert_eq!(meta.ctime, meta.mtime);
+
+    // fstat 和 stat 看到同一个索引节点
+    assert_eq!(fs.fstat(fd).unwrap(), meta);
+
+    // stat 跟随符号链接，lstat 不跟随
+    assert_eq!(fs.stat("link").unwrap(), meta);
+    let link = fs.lstat("link").unwrap();
+    assert!(link.is_symlink());
+    assert_ne!(link.ino, meta.ino);
+    assert_eq!(link.size, "dir/a".len() as u64);
+
+    assert!(fs.stat("dir").unwrap().is_dir());
+    assert_eq!(fs.stat("missing").unwrap_err().kind(), ErrorKind::NotFound);
+    fs.close(fd).unwrap();
+    assert!(fs.fstat(fd).is_err());
+}
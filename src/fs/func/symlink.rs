@@ -16,14 +16,21 @@ impl Fs {
     /// 返回:
     /// - `Result<()>`: 操作结果
     pub fn symlink(&mut self, target: &str, link_name: &str) -> Result<()> {
+        self.transaction(|fs| fs._symlink(target, link_name))
+    }
+
+    /// 创建软链接
+    fn _symlink(&mut self, target: &str, link_name: &str) -> Result<()> {
         // 解析软链接路径
         let (link_dir_path, link_file_name) = link_name.rsplit_once("/").unwrap_or((".", link_name));
+        // `/name` 的父目录是根目录，不是当前目录
+        let link_dir_path = if link_dir_path.is_empty() { "/" } else { link_dir_path };
         let link_dir_res = self.path_parse(link_dir_path)?;
         let link_dir_entry = link_dir_res.dir_entry.clone();
         
         // 检查对链接目录的写权限
         let link_dir_inode = self.get_inode(link_dir_entry.i_node)?;
-        if !link_dir_inode.i_mode.can_write(self.user) {
+        if !link_dir_inode.i_mode.can_write(self.user, &self.gids) {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
                 "Need write permission to directory",
@@ -31,39 +38,27 @@ impl Fs {
         }
         
         // 检查链接名是否已存在
-        for iter_item in link_dir_entry.iter(self)? {
-            if let DirEntryIterItem::Using(Item { entry, .. }) = iter_item {
-                if entry.name == link_file_name.into_array()? {
-                    return Err(Error::new(
-                        ErrorKind::AlreadyExists,
-                        "Link name already exists",
-                    ));
-                }
-            }
-        }
-        
-        // 查找可能被删除的目录项位置
-        let mut deleted_entry_addr = None;
-        for iter_item in link_dir_entry.iter_without_limit(self)? {
-            if let DirEntryIterItem::Deleted(Item { real_addr, .. }) = iter_item {
-                deleted_entry_addr = Some(real_addr);
-                break;
-            }
+        if self.find_dir_entry(&link_dir_entry, link_file_name)?.is_some() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "Link name already exists",
+            ));
         }
         
         // 分配索引节点
-        let inode_i = self.alloc(BlkType::INode)?;
+        let inode_i = self.alloc(BlkType::INode, link_dir_entry.i_node)?;
         
         // 创建软链接的inode
         let mut inode = Inode {
-            i_mode: FileMode::new(self.user, FileType::Symlink),
+            i_mode: self.new_file_mode(&link_dir_inode, FileType::Symlink),
+            i_flags: self.new_inode_flags(),
             ..Default::default()
         };
         
         // 将目标路径写入软链接的数据块
-        let data_blk_i = inode.alloc_data_block(self)?;
+        let data_blk_i = inode.alloc_data_block(self, inode_i)?;
         let target_bytes = target.as_bytes();
-        self.disk.write_at(target_bytes, Fs::addr_data_blk(data_blk_i))?;
+        self.disk.write_at(target_bytes, self.addr_data_blk(data_blk_i))?;
         
         // 更新inode大小为目标路径的长度
         inode.i_size = target_bytes.len() as u32;
@@ -76,36 +71,11 @@ impl Fs {
             name_len: link_file_name.len() as u8,
             file_type: FileType::Symlink.into(),
             rec_len: 1,
+            checksum: 0,
         };
         
-        // 将新的目录项写入磁盘
-        if let Some(addr) = deleted_entry_addr {
-            // 使用之前删除的目录项的位置
-            self.disk.write_at(dir_entry.bytes(), addr.addr)?;
-        } else {
-            // 在目录末尾添加新的目录项
-            let mut link_dir_inode = self.get_inode(link_dir_entry.i_node)?;
-            
-            // 检查是否需要分配新的数据块
-            let logic_addr = link_dir_inode.i_size as u64;
-            let blk_i = logic_addr / BLOCK_SIZE as u64;
-            
-            if blk_i >= link_dir_inode.i_blocks as u64 {
-                // 需要分配新的数据块
-                link_dir_inode.alloc_data_block(self)?;
-            }
-            
-            // 计算新目录项的物理地址
-            let real_addr = link_dir_inode.convert_addr(&self.disk, logic_addr)?;
-            
-            // 写入新的目录项
-            self.disk.write_at(dir_entry.bytes(), real_addr.addr)?;
-            
-            // 更新目录的大小
-            link_dir_inode.i_size += DIR_ENTRY_SIZE as u32;
-            link_dir_inode.i_mtime = utils::now();
-            self.write_inode(link_dir_entry.i_node, link_dir_inode)?;
-        }
+        // 将新的目录项写入目录
+        self.add_dir_entry(link_dir_entry.i_node, &dir_entry)?;
         
         Ok(())
     }
@@ -131,8 +101,8 @@ impl Fs {
         
         // 读取软链接数据块中存储的目标路径
         if symlink_inode.i_blocks > 0 {
-            let data_blk = symlink_inode.i_block[0];
-            self.disk.read_at(&mut target_path, Self::addr_data_blk(data_blk))?;
+            let addr = symlink_inode.convert_addr(self, 0)?.addr;
+            self.disk.read_at(&mut target_path, addr)?;
         }
         
         String::from_utf8(target_path)
@@ -146,12 +116,12 @@ fn test_symlink() {
     let content = "Hello, symlink!";
     
     // 创建文件系统
-    let mut fs = Fs::format().unwrap();
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
     
     // 创建测试目录和文件
     fs.mkdir("test_dir").unwrap();
     fs.create("test_file.txt").unwrap();
-    let fd = fs.open("test_file.txt").unwrap();
+    let fd = fs.open_with("test_file.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.write(fd, content.as_bytes()).unwrap();
     fs.close(fd).unwrap();
     
@@ -162,7 +132,7 @@ fn test_symlink() {
     fs.symlink("test_dir", "dir_link").unwrap();
     
     // 通过软链接读取文件内容
-    let fd = fs.open("file_link").unwrap();
+    let fd = fs.open_with("file_link", OpenOptions::new().read(true).write(true)).unwrap();
     let mut buffer = Vec::new();
     let mut buf = [0u8; 20];
     let n = fs.read(fd, &mut buf).unwrap();
@@ -174,7 +144,7 @@ fn test_symlink() {
     assert_eq!(read_content, content);
     
     // 测试删除原始文件后，软链接失效
-    let fd = fs.open("test_file.txt").unwrap();
+    let fd = fs.open_with("test_file.txt", OpenOptions::new().read(true).write(true)).unwrap();
     fs.rm(fd).unwrap();
     
     // 尝试打开软链接应该失败（因为目标不存在）
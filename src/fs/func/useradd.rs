@@ -1,31 +1,147 @@
 use crate::fs::core::*;
+use std::io::{Read, Write};
 
 impl Fs {
+    /// 添加用户 `name`，只有 root 可以添加
+    ///
+    /// 新用户的 UID 从 [`FIRST_ID`] 开始依次分配，主组是 users，主目录是 `/home/<name>`，
+    /// [`SKEL_PATH`] 存在时把其中的内容复制到主目录
     pub fn useradd(&mut self, name: &str, passwd: &str) -> Result<()> {
-        if self.fs_desc.users_len as usize >= self.fs_desc.users.len() {
-            return Err(Error::new(ErrorKind::Other, "Can't add more user"));
+        self.transaction(|fs| fs._useradd(name, passwd, None))
+    }
+
+    /// 添加用户 `name`，主目录中的内容从骨架目录 `skel` 复制
+    pub fn useradd_with_skel(&mut self, name: &str, passwd: &str, skel: &str) -> Result<()> {
+        self.transaction(|fs| fs._useradd(name, passwd, Some(skel)))
+    }
+
+    /// 添加用户
+    fn _useradd(&mut self, name: &str, passwd: &str, skel: Option<&str>) -> Result<()> {
+        if self.user != ROOT_UID {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Only root can add user"));
+        }
+        check_name(name)?;
+        if passwd.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "Password can't be empty"));
         }
 
-        for (_, user) in self.fs_desc.users.iter_mut().enumerate() {
-            if user.name == name.into_array()? {
-                return Err(Error::new(ErrorKind::Other, "User exists yet."));
+        // 指定的骨架目录必须存在，默认的骨架目录不存在时主目录为空
+        let skel = match skel {
+            Some(skel) if !self.stat(skel)?.is_dir() => {
+                return Err(Error::new(ErrorKind::NotADirectory, format!("{}: Not a directory", skel)));
             }
+            Some(skel) => Some(skel),
+            None => self.stat(SKEL_PATH).is_ok_and(|meta| meta.is_dir()).then_some(SKEL_PATH),
+        };
 
-            if user.name[0] == 0 {
-                user.name = name.into_array()?;
-                user.password = passwd.into_array()?;
-                self.fs_desc.users_len += 1;
-                break;
-            }
+        let mut users = self.users()?;
+        if users.iter().any(|user| user.name == name) {
+            return Err(Error::new(ErrorKind::AlreadyExists, "User exists yet."));
         }
 
-        let path = format!("/home/{}", name);
+        let user = User {
+            name: name.into(),
+            uid: next_id(users.iter().map(|user| user.uid))?,
+            gid: USERS_GID,
+            comment: String::new(),
+            home: format!("/home/{}", name),
+            shell: DEFAULT_SHELL.into(),
+        };
+        let (home, uid, gid) = (user.home.clone(), user.uid, user.gid);
+        users.push(user);
+        self.write_users(&users)?;
+        self.init_shadow(name, passwd)?;
 
-        self.mkdir(&path)?;
-        self.chown(&path, name)?;
+        self.mkdir(&home)?;
+        self.give_to(&home, uid, gid, None)?;
+        match skel {
+            Some(skel) => self.copy_skel(skel, &home, uid, gid),
+            None => Ok(()),
+        }
+    }
 
-        self.write_fs_desc()?;
+    /// 把骨架目录 `from` 中的内容递归复制到 `to`，复制出来的文件属于新用户，权限和原来的一样
+    fn copy_skel(&mut self, from: &str, to: &str, uid: u16, gid: u16) -> Result<()> {
+        let entries = self
+            .read_dir(from)?
+            .map(|entry| entry.map(|entry| (entry.name, entry.file_type)))
+            .collect::<Result<Vec<_>>>()?;
 
+        for (name, file_type) in entries {
+            let (src, dst) = (format!("{}/{}", from, name), format!("{}/{}", to, name));
+            let mode = self.lstat(&src)?.mode;
+            match file_type {
+                FileType::Dir => {
+                    self.mkdir(&dst)?;
+                    self.copy_skel(&src, &dst, uid, gid)?;
+                }
+                FileType::File => {
+                    let mut content = Vec::new();
+                    self.open_handle(&src, OpenOptions::new().read(true))?
+                        .read_to_end(&mut content)?;
+                    self.open_handle(&dst, OpenOptions::new().write(true).create_new(true))?
+                        .write_all(&content)?;
+                }
+                FileType::Symlink => {
+                    let target = self.read_symlink_target(&src)?;
+                    self.symlink(&target, &dst)?;
+                }
+            }
+            // 符号链接不跟随，权限也保持默认
+            let mode = (file_type != FileType::Symlink).then_some(mode);
+            self.give_to(&dst, uid, gid, mode)?;
+        }
         Ok(())
     }
+
+    /// 把 `path` 本身（不跟随符号链接）的拥有者和用户组改为 `uid` 和 `gid`，`mode` 不为空时同时修改权限
+    fn give_to(&mut self, path: &str, uid: u16, gid: u16, mode: Option<u16>) -> Result<()> {
+        let inode_i = self.path_parse_with_options(path, false)?.dir_entry.i_node;
+        let mut inode = self.get_inode(inode_i)?;
+        inode.i_mode.owner = uid;
+        inode.i_mode.group = gid;
+        if let Some(mode) = mode {
+            inode.i_mode.mode = mode;
+        }
+        inode.touch_ctime();
+        self.write_inode(inode_i, inode)
+    }
+}
+
+#[test]
+fn test_useradd_skel() {
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+
+    // 默认的骨架目录不存在时主目录为空
+    fs.useradd("alice", "a").unwrap();
+    let home = fs.stat("/home/alice").unwrap();
+    assert_eq!((home.owner, home.group, home.mode), (FIRST_ID, USERS_GID, 0o755));
+    assert_eq!(fs.read_dir("/home/alice").unwrap().count(), 0);
+
+    // 骨架目录中的文件、子目录和符号链接都复制到主目录，归新用户所有
+    fs.mkdir(SKEL_PATH).unwrap();
+    fs.mkdir("/etc/skel/.config").unwrap();
+    let fd = fs.open_with("/etc/skel/.profile", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.write(fd, b"export EDITOR=vi\n").unwrap();
+    fs.close(fd).unwrap();
+    fs.chmod("/etc/skel/.profile", 0o600).unwrap();
+    fs.symlink(".profile", "/etc/skel/.config/profile").unwrap();
+    fs.useradd("bob", "b").unwrap();
+
+    let profile = fs.stat("/home/bob/.profile").unwrap();
+    assert_eq!((profile.owner, profile.mode, profile.size), (FIRST_ID + 1, 0o600, 17));
+    assert_eq!(fs.lstat("/home/bob/.config").unwrap().owner, FIRST_ID + 1);
+    let link = fs.lstat("/home/bob/.config/profile").unwrap();
+    assert!(link.is_symlink() && link.owner == FIRST_ID + 1);
+    assert_eq!(fs.read_symlink_target("/home/bob/.config/profile").unwrap(), ".profile");
+    assert_eq!(fs.stat("/etc/skel/.profile").unwrap().owner, ROOT_UID);
+
+    // 指定的骨架目录必须存在，失败时不留下新用户
+    fs.mkdir("/srv").unwrap();
+    fs.create("/srv/README").unwrap();
+    fs.useradd_with_skel("carol", "c", "/srv").unwrap();
+    assert!(fs.stat("/home/carol/README").is_ok() && fs.stat("/home/carol/.profile").is_err());
+    assert!(fs.useradd_with_skel("dave", "d", "/nowhere").is_err());
+    assert!(fs.find_user("dave").is_err());
+    assert!(fs.fsck(false).unwrap().is_clean());
 }
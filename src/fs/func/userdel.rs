@@ -1,31 +1,85 @@
 use crate::fs::core::*;
 
 impl Fs {
+    /// 删除用户 `name`，只有 root 可以删除
+    ///
+    /// 用户同时从所有用户组的附加成员中移除，属于这个用户的文件保留原来的 UID
     pub fn userdel(&mut self, name: &str) -> Result<()> {
-        if name == "root" {
-            return Err(Error::new(ErrorKind::Other, "Can't delete root user"));
-        }
-        if name.into_array()? == self.fs_desc.users[self.user].name {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "Can't delete yourself, please login with other account",
-            ));
+        self.transaction(|fs| fs._userdel(name, false))
+    }
+
+    /// 删除用户 `name`，同时删除用户的主目录
+    pub fn userdel_remove_home(&mut self, name: &str) -> Result<()> {
+        self.transaction(|fs| fs._userdel(name, true))
+    }
+
+    /// 删除用户，`remove_home` 为真时同时删除主目录
+    fn _userdel(&mut self, name: &str, remove_home: bool) -> Result<()> {
+        if self.user != ROOT_UID {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Only root can delete user"));
         }
 
-        let mut ok = false;
-        for user in self.fs_desc.users.iter_mut() {
-            if user.name == name.into_array()? {
-                *user = User::default();
-                self.fs_desc.used_dirs_count -= 1;
-                ok = true;
-            }
+        let mut users = self.users()?;
+        let Some(i) = users.iter().position(|user| user.name == name) else {
+            return Err(Error::new(ErrorKind::NotFound, "User not exists."));
+        };
+        if users[i].uid == ROOT_UID {
+            return Err(Error::other("Can't delete root user"));
         }
+        let user = users.remove(i);
+        self.write_users(&users)?;
 
-        if !ok {
-            return Err(Error::new(ErrorKind::Other, "User not exists."));
+        let mut groups = self.user_groups()?;
+        for group in groups.iter_mut() {
+            group.members.retain(|member| member != name);
         }
+        self.write_user_groups(&groups)?;
+
+        self.remove_shadow(name)?;
 
-        self.write_fs_desc()?;
+        // 主目录已经不存在时忽略，当前目录在主目录中时回到根目录
+        if remove_home && self.stat(&user.home).is_ok() {
+            let pwd = self.pwd();
+            if pwd == user.home || pwd.starts_with(&format!("{}/", user.home)) {
+                self.chdir("/")?;
+            }
+            self.rmdir_recursive(&user.home)?;
+        }
         Ok(())
     }
 }
+
+#[test]
+fn test_userdel_remove_home() {
+    let options = FormatOptions {
+        root_password: "123".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    fs.useradd("alice", "a").unwrap();
+    fs.useradd("alice2", "a").unwrap();
+
+    // 登录之后进入主目录
+    fs.login("alice", "a").unwrap();
+    assert_eq!(fs.pwd(), "/home/alice");
+    fs.mkdir("docs").unwrap();
+    fs.create("docs/notes").unwrap();
+    fs.chdir("docs").unwrap();
+
+    // 不删除主目录时文件保留，删除主目录时连同其中的文件一起删除
+    fs.login("root", "123").unwrap();
+    assert_eq!(fs.pwd(), "/root");
+    fs.userdel("alice2").unwrap();
+    assert!(fs.stat("/home/alice2").is_ok());
+    fs.chdir("/home/alice/docs").unwrap();
+    fs.userdel_remove_home("alice").unwrap();
+    assert_eq!(fs.pwd(), "/");
+    assert!(fs.stat("/home/alice").is_err() && fs.stat("/home/alice2").is_ok());
+    assert!(fs.fsck(false).unwrap().is_clean());
+
+    // 主目录被删除的用户登录时进入根目录
+    fs.useradd("bob", "b").unwrap();
+    fs.rmdir("/home/bob").unwrap();
+    fs.login("bob", "b").unwrap();
+    assert_eq!(fs.pwd(), "/");
+}
@@ -0,0 +1,83 @@
+//! # 修改文件的时间戳
+
+use crate::fs::core::*;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+impl Fs {
+    /// 把路径名为 `path` 的文件的访问时间和修改时间设置为 `atime` 和 `mtime`
+    ///
+    /// - 路径指向符号链接时修改的是链接目标
+    /// - 只有文件的拥有者和 root 可以修改，状态改变时间随之更新为当前时间
+    /// - 不能设置早于 Unix 纪元的时间
+    pub fn set_times(&mut self, path: &str, atime: DateTime<Utc>, mtime: DateTime<Utc>) -> Result<()> {
+        self.transaction(|fs| fs._set_times(path, atime, mtime))
+    }
+
+    /// 修改时间戳
+    fn _set_times(&mut self, path: &str, atime: DateTime<Utc>, mtime: DateTime<Utc>) -> Result<()> {
+        let to_duration = |time: DateTime<Utc>| match u64::try_from(time.timestamp()) {
+            Ok(secs) => Ok(Duration::new(secs, time.timestamp_subsec_nanos())),
+            Err(_) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{}: Time before 1970 is not supported", time),
+            )),
+        };
+        let (atime, mtime) = (to_duration(atime)?, to_duration(mtime)?);
+
+        let inode_i = self.path_parse(path)?.dir_entry.i_node;
+        let mut inode = self.get_inode(inode_i)?;
+        if inode.i_mode.owner != self.user && self.user != ROOT_UID {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Permission Denied"));
+        }
+
+        inode.set_atime(atime);
+        inode.set_mtime(mtime);
+        inode.touch_ctime();
+        self.write_inode(inode_i, inode)
+    }
+}
+
+#[test]
+fn test_set_times() {
+    use chrono::TimeZone;
+
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap();
+    let fd = fs.open_with("a", OpenOptions::new().write(true).create(true)).unwrap();
+    fs.write(fd, b"hello").unwrap();
+    fs.close(fd).unwrap();
+    let btime = fs.stat("a").unwrap().btime;
+
+    // 纳秒和 2106 年之后的时间都能保存下来
+    let atime = Utc.with_ymd_and_hms(2001, 2, 3, 4, 5, 6).unwrap() + chrono::Duration::nanoseconds(789);
+    let mtime = Utc.with_ymd_and_hms(2200, 1, 1, 0, 0, 0).unwrap();
+    fs.set_times("a", atime, mtime).unwrap();
+    let meta = fs.stat("a").unwrap();
+    assert_eq!((meta.atime, meta.mtime, meta.btime), (atime, mtime, btime));
+    assert!(meta.ctime >= btime);
+
+    // relatime：访问时间早于修改时间时读取会更新访问时间，之后一天内不再更新
+    fs.set_times("a", atime, Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap()).unwrap();
+    let fd = fs.open("a").unwrap();
+    let mut buf = [0; 5];
+    fs.read(fd, &mut buf).unwrap();
+    let first = fs.stat("a").unwrap().atime;
+    assert!(first >= meta.ctime);
+    fs.seek(fd, super::seek::Seek::FromStart(0)).unwrap();
+    fs.read(fd, &mut buf).unwrap();
+    assert_eq!(fs.stat("a").unwrap().atime, first);
+    fs.close(fd).unwrap();
+
+    // noatime：读取不更新访问时间
+    fs.atime_mode = AtimeMode::Noatime;
+    fs.set_times("a", atime, mtime).unwrap();
+    let fd = fs.open("a").unwrap();
+    fs.read(fd, &mut buf).unwrap();
+    assert_eq!(fs.stat("a").unwrap().atime, atime);
+    fs.close(fd).unwrap();
+
+    // 早于 1970 年的时间和没有权限的用户都会被拒绝
+    let before = Utc.with_ymd_and_hms(1969, 12, 31, 0, 0, 0).unwrap();
+    assert_eq!(fs.set_times("a", before, mtime).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(fs.set_times("missing", atime, mtime).unwrap_err().kind(), ErrorKind::NotFound);
+}
@@ -1,59 +1,79 @@
 use crate::fs::core::*; // 引入文件系统核心模块
 
 impl Fs {
+    /// 向文件写入 `buf`，返回写入的字节数
+    ///
+    /// 一次写入的数据超出日志区的容量时分几次提交，中途出错时已经提交的部分保留在文件中
     pub fn write(&mut self, fd: usize, buf: &[u8]) -> Result<usize> {
+        self.transaction(|fs| fs._write(fd, buf))
+    }
+
+    /// 写入文件
+    fn _write(&mut self, fd: usize, buf: &[u8]) -> Result<usize> {
         // 定义文件系统的写入函数，接收文件描述符和要写入的数据缓冲区
         if fd >= self.fds.len() || self.fds[fd].is_none() {
             // 检查文件描述符是否有效
-            return Err(Error::new(ErrorKind::Other, "Bad file descriptor"));
+            return Err(Error::other("Bad file descriptor"));
         }
 
         let mut file = self.fds[fd].clone().unwrap(); // 获取文件描述符对应的文件对象
+        let mut inode = self.get_inode(file.inode_i)?; // 读取文件的索引节点
 
-        if !file.inode.i_mode.can_write(self.user) {
-            // 检查当前用户是否有写权限
+        // 权限在打开时已经检查过，这里只检查打开时是否允许写入
+        if !file.writable {
             return Err(Error::new(
                 ErrorKind::PermissionDenied,
-                "Permission Denied.",
+                "Bad file descriptor: not opened for writing",
             ));
         }
 
-        let mut counter = 0; // 初始化写入计数器
+        // 追加模式在同一个事务中把文件指针移到文件末尾，其它文件描述符的写入不会被覆盖
+        if file.append {
+            file.current_pos = inode.i_size as usize;
+        }
 
-        loop {
-            if counter == buf.len() {
-                // 如果已经写完所有数据，退出循环
-                break;
-            }
+        // 写入之后的文件大小不能超过文件系统的限制
+        if (file.current_pos + buf.len()) as u64 > self.max_file_size() {
+            return Err(Error::new(ErrorKind::FileTooLarge, "File too large"));
+        }
+
+        let block_size = self.block_size();
+        let mut counter = 0; // 初始化写入计数器
 
-            if file.current_pos >= file.inode.i_size as usize {
-                // 如果当前写入位置超过文件大小
-                file.inode.i_size = (file.current_pos + 1) as u32; // 更新文件大小
+        while counter < buf.len() {
+            // 每次最多写到当前数据块的末尾
+            let pos = file.current_pos;
+            let len = (block_size - pos % block_size).min(buf.len() - counter);
 
-                let mut new_blocks = (file.inode.i_size / BLOCK_SIZE as u32) as u16; // 计算所需的新块数
-                if file.inode.i_size % BLOCK_SIZE as u32 != 0 {
-                    new_blocks += 1; // 如果有剩余部分，分配一个额外的块
-                }
+            // 日志区不够大时先提交已经写入的部分，之前先写回索引节点，使磁盘上的文件是完整的
+            if self.disk.room() < STEP_BLOCKS {
+                inode.i_size = inode.i_size.max(pos as u32);
+                self.write_inode(file.inode_i, inode.clone())?;
+                self.ensure_room(STEP_BLOCKS)?;
+            }
 
-                let block_to_alloc = new_blocks - file.inode.i_blocks; // 计算需要分配的新块数
-                for _ in 0..block_to_alloc {
-                    file.inode.alloc_data_block(self)?; // 分配数据块
-                }
+            let mut addr = inode.convert_addr(self, pos as u64)?; // 获取当前写入位置的磁盘地址
+            if addr.is_hole() {
+                // 写入空洞时才真正分配数据块
+                let blk_i = (pos / block_size) as u64;
+                inode.alloc_data_block_at(self, file.inode_i, blk_i)?;
+                addr = inode.convert_addr(self, pos as u64)?;
             }
+            self.disk.write_data_at(&buf[counter..counter + len], addr.addr)?; // 将数据写入磁盘
 
-            let addr = file
-                .inode
-                .convert_addr(&self.disk, file.current_pos as u64)?; // 获取当前写入位置的磁盘地址
-            self.disk.write_at(&[buf[counter]], addr.addr)?; // 将数据写入磁盘
+            counter += len; // 更新写入计数器
+            file.current_pos += len; // 更新文件的当前写入位置
+        }
 
-            counter += 1; // 更新写入计数器
-            file.current_pos += 1; // 更新文件的当前写入位置
+        if file.current_pos > inode.i_size as usize {
+            // 如果写入位置超过了文件大小，更新文件大小，跳过的部分成为空洞
+            inode.i_size = file.current_pos as u32;
         }
 
-        file.inode.i_mtime = utils::now(); // 更新文件的修改时间
+        inode.touch_mtime(); // 更新文件的修改时间和状态改变时间
 
         // 写入更新后的索引节点
-        self.write_inode(file.inode_i, file.inode.clone())?;
+        self.write_inode(file.inode_i, inode)?;
 
         self.fds[fd] = Some(file); // 更新文件描述符中的文件对象
 
@@ -65,12 +85,12 @@ impl Fs {
 fn test_read_write() {
     let part1 = r#"ghjgky;;...fygeyrgfierwygw"#; // 定义测试用的字符串
 
-    let mut fs = Fs::format().unwrap(); // 格式化文件系统
+    let mut fs = Fs::format_on(MemDevice::new(), &FormatOptions::with_root_password("root")).unwrap(); // 格式化文件系统
     fs.create("test.txt").unwrap(); // 创建一个测试文件
-    let fd = fs.open("test.txt").unwrap(); // 打开文件并获取文件描述符
+    let fd = fs.open_with("test.txt", OpenOptions::new().read(true).write(true)).unwrap(); // 打开文件并获取文件描述符
     fs.write(fd, part1.as_bytes()).unwrap(); // 向文件写入测试字符串
     fs.write(fd, part1.as_bytes()).unwrap(); // 再次写入相同的字符串
-    let fd2 = fs.open("test.txt").unwrap(); // 重新打开文件以进行读取
+    let fd2 = fs.open_with("test.txt", OpenOptions::new().read(true).write(true)).unwrap(); // 重新打开文件以进行读取
 
     let mut str: Vec<u8> = Vec::new(); // 初始化一个字符串缓冲区
     let mut buf = [0u8; 13]; // 定义一个读取缓冲区
@@ -82,4 +102,53 @@ fn test_read_write() {
     let str = std::str::from_utf8(&str).unwrap_or("invalid utf-8 string"); // 将字节数组转换为字符串
     println!("{}", str); // 打印读取到的内容
     assert_eq!(str.trim_matches('\0'), format!("{}{}", part1, part1)); // 断言读取的内容是否正确
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_triple_indirect() {
+    use super::seek::Seek;
+
+    let options = FormatOptions {
+        blocks_count: 20000,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let mut fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    let free_blocks = fs.super_block().free_blocks_count;
+    let addr_num = fs.addr_per_blk();
+    assert_eq!(
+        fs.max_file_size(),
+        (6 + addr_num + addr_num.pow(2) + addr_num.pow(3)) as u64 * 512
+    );
+
+    // 写入位置位于三级索引的范围内
+    let pos = (6 + addr_num + addr_num.pow(2) + 3) * 512 + 7;
+    fs.create("big").unwrap();
+    let fd = fs.open_with("big", OpenOptions::new().read(true).write(true)).unwrap();
+    fs.seek(fd, Seek::FromStart(pos)).unwrap();
+    fs.write(fd, b"triple").unwrap();
+
+    let fd = fs.open_with("big", OpenOptions::new().read(true).write(true)).unwrap();
+    fs.seek(fd, Seek::FromStart(pos)).unwrap();
+    let mut buf = [0u8; 6];
+    fs.read(fd, &mut buf).unwrap();
+    assert_eq!(&buf, b"triple");
+
+    // 超过最大文件大小
+    let max = fs.max_file_size() as usize;
+    fs.seek(fd, Seek::FromStart(max)).unwrap();
+    let err = fs.write(fd, b"x").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FileTooLarge);
+
+    // 删除文件后所有数据块和索引块都被释放
+    fs.rm(fd).unwrap();
+    assert_eq!(fs.super_block().free_blocks_count, free_blocks);
+
+    let options = FormatOptions {
+        block_size: 4096,
+        root_password: "root".into(),
+        ..Default::default()
+    };
+    let fs = Fs::format_on(MemDevice::new(), &options).unwrap();
+    assert_eq!(fs.max_file_size(), u32::MAX as u64);
+}
@@ -5,8 +5,7 @@ use actix_web::{web, App, HttpResponse, HttpServer, Responder, Result};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use log::info;
-use chrono::TimeZone;
-use std::io::{self, Write};
+use std::io::Write;
 
 use crate::shell::Shell;
 
@@ -49,7 +48,7 @@ pub struct CommandResponse {
 
 /// 获取当前目录内容
 async fn get_current_directory(shell: web::Data<SharedShell>) -> Result<impl Responder> {
-    let shell = shell.lock().unwrap();
+    let mut shell = shell.lock().unwrap();
     
     // 获取当前路径
     let path = shell.fs.pwd();
@@ -58,79 +57,71 @@ async fn get_current_directory(shell: web::Data<SharedShell>) -> Result<impl Res
     let mut items = Vec::new();
     
     // 使用fs的API直接获取目录内容
-    match shell.fs.path_parse("") {
-        Ok(parsed_path) => {
-            let users = &shell.fs.fs_desc().users;
-            
-            // 处理目录项迭代器，如果出错则使用空迭代器
-            if let Ok(dir_entries) = parsed_path.dir_entry.iter(&shell.fs) {
-                for item in dir_entries {
-                if let crate::fs::DirEntryIterItem::Using(crate::fs::Item { entry, .. }) = item {
-                    let filename = crate::fs::utils::str(&entry.name).to_string();
-                    
-                    // 跳过重复的父目录引用
-                    if filename == ".." && items.iter().any(|item: &FileItem| item.name == "..") {
-                        continue;
-                    }
-                    
-                    let file_type: crate::fs::FileType = entry.file_type.into();
-                    let is_symlink = matches!(file_type, crate::fs::FileType::Symlink);
-                    
-                    let mut is_dir = matches!(file_type, crate::fs::FileType::Dir);
-                    
-                    // 如果是软链接，需要检查目标类型
-                    if is_symlink {
-                        // 尝试解析软链接目标
-                        let full_path = if path == "/" {
-                            format!("/{}", filename)
-                        } else {
-                            format!("{}/{}", path, filename)
-                        };
-                        
-                        if let Ok(target_res) = shell.fs.path_parse_with_options(&full_path, true) {
-                            let target_type: crate::fs::FileType = target_res.dir_entry.file_type.into();
-                            is_dir = matches!(target_type, crate::fs::FileType::Dir);
-                        }
-                    }
-                    
-                    // 获取inode信息
-                    if let Ok(i_node) = shell.fs.get_inode(entry.i_node) {
-                        let file_type_char = if is_symlink {
-                            "l"
-                        } else if is_dir {
-                            "d"
-                        } else {
-                            "f"
-                        };
-                        let mode = format!("[{}].{}", file_type_char, i_node.i_mode);
-                        
-                        let owner = users
-                            .get(i_node.i_mode.owner as usize)
-                            .map(|s| crate::fs::utils::str(&s.name))
-                            .unwrap_or("???")
-                            .to_string();
-                        
-                        let size = crate::utils::pretty_byte(i_node.i_size);
-                        
-                        let create_time = chrono::Utc.timestamp_opt(i_node.i_ctime as i64, 0).unwrap().to_string();
-                        let edit_time = chrono::Utc.timestamp_opt(i_node.i_mtime as i64, 0).unwrap().to_string();
-                        
-                        items.push(FileItem {
-                            name: filename,
-                            is_dir,
-                            is_symlink,
-                            size,
-                            owner,
-                            mode,
-                            create_time,
-                            edit_time,
-                        });
-                    }
-                }
-                }
-            }
-        },
+    // 文件的拥有者记录的是 UID，显示时换成用户名
+    let users: std::collections::HashMap<u16, String> = shell
+        .fs
+        .users()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|user| (user.uid, user.name))
+        .collect();
+    let dir_entries = match shell.fs.read_dir("") {
+        Ok(dir_entries) => dir_entries.dots(true),
         Err(e) => return Ok(HttpResponse::InternalServerError().json(format!("解析路径失败: {}", e))),
+    };
+
+    // 读取目录项出错时跳过它
+    for entry in dir_entries.flatten() {
+        // 跳过重复的父目录引用
+        if entry.name == ".." && items.iter().any(|item: &FileItem| item.name == "..") {
+            continue;
+        }
+
+        // 获取文件的元数据，软链接需要检查目标类型
+        if let Ok(meta) = entry.metadata() {
+            let is_symlink = meta.is_symlink();
+            let is_dir = match is_symlink {
+                true => {
+                    let full_path = if path == "/" {
+                        format!("/{}", entry.name)
+                    } else {
+                        format!("{}/{}", path, entry.name)
+                    };
+                    shell.fs.stat(&full_path).is_ok_and(|target| target.is_dir())
+                }
+                false => meta.is_dir(),
+            };
+
+            let file_type_char = if is_symlink {
+                "l"
+            } else if is_dir {
+                "d"
+            } else {
+                "f"
+            };
+            let mode = format!("[{}].{}", file_type_char, meta.permissions());
+            
+            let owner = users
+                .get(&meta.owner)
+                .cloned()
+                .unwrap_or_else(|| meta.owner.to_string());
+            
+            let size = crate::utils::pretty_byte(meta.size as u32);
+            
+            let create_time = meta.btime.to_string();
+            let edit_time = meta.mtime.to_string();
+            
+            items.push(FileItem {
+                name: entry.name.clone(),
+                is_dir,
+                is_symlink,
+                size,
+                owner,
+                mode,
+                create_time,
+                edit_time,
+            });
+        }
     }
     
     Ok(HttpResponse::Ok().json(DirectoryContent {
@@ -143,7 +134,7 @@ async fn get_current_directory(shell: web::Data<SharedShell>) -> Result<impl Res
 async fn change_directory(shell: web::Data<SharedShell>, path: web::Json<String>) -> Result<impl Responder> {
     let mut shell = shell.lock().unwrap();
     
-    match shell.fs.chdir(&path) {
+    match shell.chdir(&path) {
         Ok(_) => {
             let current_path = shell.fs.pwd();
             Ok(HttpResponse::Ok().json(CommandResponse {
@@ -194,7 +185,7 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
                 // 获取目录内容
                 if let Ok(parsed_path) = shell.fs.path_parse("") {
                     if let Ok(dir_entries) = parsed_path.dir_entry.iter(&shell.fs) {
-                        for item in dir_entries {
+                        for item in dir_entries.flatten() {
                             if let crate::fs::DirEntryIterItem::Using(crate::fs::Item { entry, .. }) = item {
                                 let filename = crate::fs::utils::str(&entry.name).to_string();
                                 
@@ -219,36 +210,13 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
                 let mut has_any_error = false;
                 
                 for arg in &args {
-                    match shell.fs.open(arg) {
-                        Ok(fd) => {
-                            let mut file_content = String::new();
-                            let mut has_error = false;
-                            
-                            loop {
-                                let mut buf = [0u8; 512];
-                                match shell.fs.read(fd, &mut buf) {
-                                    Ok(bytes) => {
-                                        if bytes == 0 {
-                                            break;
-                                        } else {
-                                            file_content.push_str(crate::utils::str(&buf[0..bytes]));
-                                        }
-                                    }
-                                    Err(e) => {
-                                        cat_output.push_str(&format!("{}: {}\n", arg, e));
-                                        has_error = true;
-                                        has_any_error = true;
-                                        break;
-                                    }
-                                }
-                            }
-                            
-                            if !has_error {
-                                cat_output.push_str(&file_content);
-                            }
-                            
-                            shell.fs.close(fd).unwrap_or_default();
-                        }
+                    let mut content = Vec::new();
+                    let res = shell
+                        .fs
+                        .open_handle(arg, crate::fs::OpenOptions::new().read(true))
+                        .and_then(|mut file| std::io::Read::read_to_end(&mut file, &mut content));
+                    match res {
+                        Ok(_) => cat_output.push_str(crate::utils::str(&content)),
                         Err(e) => {
                             cat_output.push_str(&format!("{}: {}\n", arg, e));
                             has_any_error = true;
@@ -261,22 +229,17 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
             } else if cmd_req.cmd == "whoami" {
                 // 获取当前用户
                 handled = true;
-                let fs_desc = shell.fs.fs_desc();
-                let user_index = shell.fs.current_user();
-                if user_index < fs_desc.users.len() {
-                    let name = &fs_desc.users[user_index].name;
-                    output_text = crate::fs::utils::str(name).to_string();
-                } else {
-                    output_text = "未登录".to_string();
-                }
+                let uid = shell.fs.current_user();
+                output_text = match shell.fs.find_user_by_uid(uid) {
+                    Ok(user) => user.name,
+                    Err(_) => uid.to_string(),
+                };
             } else if cmd_req.cmd == "users" {
                 // 列出所有用户
                 handled = true;
                 let mut users_output = String::new();
-                for user in &shell.fs.fs_desc().users {
-                    users_output.push_str(&format!("{} {}\n", 
-                        crate::fs::utils::str(&user.name),
-                        crate::fs::utils::str(&user.password)));
+                for user in shell.fs.users().unwrap_or_default() {
+                    users_output.push_str(&format!("{}\n", user.name));
                 }
                 output_text = users_output;
             } else if cmd_req.cmd == "write" {
@@ -295,7 +258,7 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
                         let content = args[1]; // 第二个参数是内容
                         
                         // 打开文件
-                        match shell.fs.open(filename) {
+                        match shell.fs.open_with(filename, crate::fs::OpenOptions::new().write(true)) {
                             Ok(fd) => {
                                 // 清空文件
                                 if let Err(e) = shell.fs.cut(fd, 0) {
@@ -324,17 +287,19 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
                         }
                     } else {
                         // 如果没有提供内容，返回提示信息
-                        output_text = format!("请在Web界面使用格式: write 文件名 内容");
+                        output_text = "请在Web界面使用格式: write 文件名 内容".to_string();
                         success = false;
                     }
                 }
             } else if cmd_req.cmd == "rmdir" || cmd_req.cmd == "rm" || cmd_req.cmd == "mkdir" || 
                     cmd_req.cmd == "touch" || cmd_req.cmd == "chmod" || cmd_req.cmd == "chown" || 
                     cmd_req.cmd == "mv" || cmd_req.cmd == "cp" || cmd_req.cmd == "ln" ||
-                    cmd_req.cmd == "useradd" || cmd_req.cmd == "userdel" || cmd_req.cmd == "passwd" {
+                    cmd_req.cmd == "useradd" || cmd_req.cmd == "userdel" || cmd_req.cmd == "passwd" ||
+                    cmd_req.cmd == "chgrp" || cmd_req.cmd == "groupadd" || cmd_req.cmd == "groupdel" ||
+                    cmd_req.cmd == "gpasswd" {
                 // 执行文件系统修改命令
                 let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                    cmd.run(&mut *shell, &args);
+                    cmd.run(&mut shell, &args);
                 }));
                 
                 if result.is_ok() {
@@ -358,7 +323,7 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
                     Err(_) => {
                         return Ok(HttpResponse::InternalServerError().json(CommandResponse {
                             success: false,
-                            output: format!("无法创建临时文件捕获输出"),
+                            output: "无法创建临时文件捕获输出".to_string(),
                         }));
                     }
                 };
@@ -368,18 +333,12 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
                 
                 // 执行命令
                 let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                    // 备份标准输出
-                    let backup_stdout = io::stdout();
-                    
                     // 临时重定向标准输出
                     let _result = writeln!(output_handle, "执行命令: {} {:?}", cmd_req.cmd, args);
                     output_handle.flush().unwrap_or_default();
                     
                     // 执行命令
-                    cmd.run(&mut *shell, &args);
-                    
-                    // 恢复标准输出
-                    drop(backup_stdout);
+                    cmd.run(&mut shell, &args);
                 }));
                 
                 // 关闭输出文件
@@ -419,14 +378,17 @@ async fn execute_command(shell: web::Data<SharedShell>, cmd_req: web::Json<Comma
 }
 
 /// 启动Web服务器
-pub async fn start_server(shell: Shell) -> std::io::Result<()> {
+pub async fn start_server(mut shell: Shell) -> std::io::Result<()> {
+    // Web界面不需要登录，以当前用户的身份设置会话变量
+    shell.start_session();
     let shared_shell = Arc::new(Mutex::new(shell));
+    let server_shell = shared_shell.clone();
     
     info!("启动Web服务器，监听 127.0.0.1:8080");
     
-    HttpServer::new(move || {
+    let result = HttpServer::new(move || {
         App::new()
-            .app_data(web::Data::new(shared_shell.clone()))
+            .app_data(web::Data::new(server_shell.clone()))
             .service(web::resource("/api/directory").route(web::get().to(get_current_directory)))
             .service(web::resource("/api/cd").route(web::post().to(change_directory)))
             .service(web::resource("/api/command").route(web::post().to(execute_command)))
@@ -434,5 +396,11 @@ pub async fn start_server(shell: Shell) -> std::io::Result<()> {
     })
     .bind("127.0.0.1:8080")?
     .run()
-    .await
+    .await;
+
+    // 服务器停止后正常卸载文件系统
+    if let Err(e) = shared_shell.lock().unwrap().fs.exit() {
+        log::error!("Failed to unmount: {}", e);
+    }
+    result
 }
\ No newline at end of file
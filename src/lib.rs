@@ -29,7 +29,8 @@
 //! 
 //! Linux启动时 super block 0 的内容读入内存，某个组损坏可用来恢复。
 //! 
-//! 我们模拟的类 Unix 文件系统**剔除**了引导块和超级块，整个文件系统**仅由一个组块**构成：
+//! 我们模拟的类 Unix 文件系统**剔除**了引导块，并且只在开头保存一份超级块和组描述符表，
+//! 后面跟着一个或多个块组。每个块组的结构如下：
 //! 
 //! <img src="data:image/png;base64,
 #![doc = include_str!("../img/disk_struct.b64")]
@@ -37,47 +38,93 @@
 //! 
 //! 现在我们的文件系统结构如下：
 //! 
-//! - **组描述符 [`GroupDesc`](fs::GroupDesc)**
+//! - **超级块 [`SuperBlock`](fs::SuperBlock)**
 //! 
-//!   存储在第一个磁盘块，这个数据结构存储了用户名和密码，磁盘布局，空闲索引结点（i结点）个数，空闲数据块的个数
-//!   等信息。
+//!   存储在第一个磁盘块，这个数据结构存储了块大小、块组个数等磁盘布局，空闲索引结点（i结点）个数，
+//!   空闲数据块的个数等全局信息。用户和用户组不在超级块中，而是保存在镜像里的 `/etc/passwd`、
+//!   `/etc/group` 和 `/etc/shadow` 文件中，见 [`userdb`](fs::userdb)。
+//! 
+//! - **组描述符表**
+//! 
+//!   从第二个磁盘块开始存储，每个块组对应一个组描述符 [`GroupDesc`](fs::GroupDesc)，记录该组的位图、
+//!   i 结点表、数据区的位置以及组内的空闲块个数。
+//! 
+//! - **日志区**
+//! 
+//!   紧跟在组描述符表之后，共 [`SuperBlock::journal_blocks`](fs::SuperBlock::journal_blocks) 个块。
+//!   创建、删除、写入等操作都在一个事务中完成，修改过的块先写入日志区再写回原处，
+//!   挂载时重放已经提交的事务，崩溃之后磁盘上不会留下只做了一半的操作，详见 [`fs::core::journal`]。
+//!   日志之下还有一层块缓存，最近访问的磁盘块留在内存里，详见 [`fs::core::cache`]。
 //! 
 //! - **数据块位图**
 //! 
-//!   存储在第二个磁盘块。
+//!   每个块组的第一块。
 //! 
 //!   这是ext2管理存储空间的方法，即位图法。每个位对应一个数据块，位值为 0 表示空闲，1 表示已经分配。
 //!   数据块位图定义为一个块大小。于是，一个组中的数据块个数就决定了。假设块大小为 b 字节。可以区别的块数为 b*8 个。
 //! 
-//!   在我们模拟的文件系统里，将磁盘块的大小（[`fs::BLOCK_SIZE`]）为 512 字节，那么这个位图只能记录 512 * 8 = 4096
-//!   个数据块的使用情况。 
+//!   在我们模拟的文件系统里，磁盘块的默认大小（[`fs::DEFAULT_BLOCK_SIZE`]）为 512 字节，那么这个位图只能记录 512 * 8 = 4096
+//!   个数据块的使用情况，一个块组的数据区大小也就确定为 4096 * 512 = 2097152 字节 = 2MB。
 //! 
-//!   因此，我们模拟的磁盘（只有一个组块）数据区的大小也就确定为 4096 * 512 = 2097152 字节 = 2MB
+//!   需要更大的磁盘时，格式化（[`FormatOptions`](fs::FormatOptions)）会创建多个块组。块大小、数据块个数、
+//!   i 结点个数等参数都写入超级块，挂载时从磁盘上读出。
 //! 
 //! - **i 结点（索引结点）位图**
 //! 
-//!   存储在磁盘上的第三块磁盘块。我们已经知道这个磁盘块最多能记录 4096 个 i 结点的使用情况，在我们的文件系统里，
-//!   每个 i 结点（[`Inode`](fs::Inode)）占用 32 个字节，在磁盘上需要 4096 * 32 = 131072 字节的空间来存储 i 结点。
-//! 
-//!   同时也能算出需要给 i 结点分配 131072 / 512 = 256 个磁盘块
+//!   每个块组的第二块，记录本组 i 结点的使用情况。i 结点会平均分配到每个块组里，
+//!   第 g 个块组保存编号为 g * 每组 i 结点数 开始的那些 i 结点。
 //! 
 //! - **i 结点 [`Inode`](fs::Inode)**
 //! 
-//!   存储在第 4 个 到 第 4 + 256 个磁盘块里。
+//!   紧跟在 i 结点位图之后，占用的块数由每组的 i 结点个数决定。
 //! 
 //!   也被称为索引结点，这个数据结构存储了关于文件的所有信息，如创建时间、存取权限、数据块索引、文件大小等信息。
 //!   **但不存储文件名**，文件名被存储到目录项 [`DirEntry`](fs::DirEntry) 里，后者存储在数据块里。
 //! 
 //! - **数据块**
 //! 
-//!   存储在第 4 + 256 + 1 到 4 + 256 + 1 + 4096 个磁盘块上。
-//! 
-//!   这里是磁盘的最后一块区域，也是存储文件内容的地方，里面包含了文件的内容，文件夹（目录）的相关信息也会存储在数据块里。
+//!   每个块组的最后一块区域，也是存储文件内容的地方，里面包含了文件的内容，文件夹（目录）的相关信息也会存储在数据块里。
 //!   即使文件的大小小于数据块的大小，文件在也会占用一整块数据块。
 //! 
+//!   分配空间时，新目录会被分散到目录最少的块组里，文件的 i 结点和数据块则尽量与父目录放在同一个块组里。
+//! 
 //! # 主要的数据结构
 //! 
-//! ## 组描述符 - [GroupDest](fs::GroupDesc)
+//! ## 超级块 - [SuperBlock](fs::SuperBlock)
+//! 
+//! 定义了块大小，块组个数，每组的 i 结点个数和数据块个数，整个文件系统的空闲块个数等全局信息。
+//! 
+//! 超级块以魔数 [`FS_MAGIC`](fs::FS_MAGIC) 开头，并记录了磁盘格式版本、特性标志、挂载次数和
+//! 是否正常卸载。挂载（[`Fs::init()`](fs::Fs::init())）时会检查这些字段，不认识的磁盘会被拒绝，
+//! 而不是被当成文件系统继续写入。
+//! 
+//! ```ignore
+//! // /src/fs/core/fs.rs
+//! pub struct SuperBlock {
+//!     pub magic: u16,
+//!     pub version: u16,
+//!     pub state: u16,
+//!     pub mount_count: u16,
+//!     pub feature_compat: u32,
+//!     pub feature_incompat: u32,
+//!     pub feature_ro_compat: u32,
+//!     pub mtime: u64,
+//!     pub wtime: u64,
+//!     pub volume_name: [u8; 16],
+//!     pub block_size: u32,
+//!     pub inodes_count: u32,
+//!     pub blocks_count: u32,
+//!     pub inodes_per_group: u32,
+//!     pub blocks_per_group: u32,
+//!     pub groups_count: u32,
+//!     pub group_table: u32,
+//!     pub free_blocks_count: u32,
+//!     pub free_inodes_count: u32,
+//!     pub used_dirs_count: u32,
+//! }
+//! ```
+//! 
+//! ## 组描述符 - [GroupDesc](fs::GroupDesc)
 //!  
 //! 定义了块位图的块号，索引结点位图的块号、索引结点表的起始块号，本组空闲块的个数等组内信息。
 //! 文件系统根据这些信息来查找数据块位图，索引结点位图，索引结点表的位置。
@@ -85,82 +132,78 @@
 //! ```ignore
 //! // /src/fs/core/fs.rs
 //! pub struct GroupDesc {
-//!    /// 卷名
-//!    pub volume_name: [u8; 16],
 //!    /// 块位图所在的块号
-//!    pub block_bitmap: u16,
+//!    pub block_bitmap: u32,
 //!    /// 索引结点位图的块号
-//!    pub inode_bitmap: u16,
+//!    pub inode_bitmap: u32,
 //!    /// 索引表的起始块号
-//!    pub inode_table: u16,
+//!    pub inode_table: u32,
+//!    /// 数据区的起始块号
+//!    pub data_begin: u32,
 //!    /// 空闲块的个数
 //!    pub free_blocks_count: u16,
+//!    /// 块位图的校验和，CRC32C 的低 16 位
+//!    pub block_bitmap_csum: u16,
 //!    /// 空闲索引节点的个数    
 //!    pub free_inodes_count: u16,
+//!    /// 索引节点位图的校验和，CRC32C 的低 16 位
+//!    pub inode_bitmap_csum: u16,
 //!    /// 目录个数
-//!    pub used_dirs_count: u16,
-//!    /// 用户表
-//!    pub users: [User; 10],
-//!    pub users_len: u16,
+//!    pub used_dirs_count: u32,
+//!    /// 组描述符的校验和
+//!    pub checksum: u32,
 //!}
 //! ```
 //! 
-//! 组描述符的默认数据如下，在格式化（[`Fs::format()`](fs::Fs::format())）时会新建一个组描述符，然后将其
-//! 写入第一块磁盘块：
-//! 
-//! ```ignore
-//! // /src/fs/core/fs.rs
-//! impl GroupDesc {
-//!     pub(in crate::fs) fn new() -> Self {
-//!         let mut users = [User::default(); 10];
-//!     
-//!         // 默认用户
-//!         users[0] = User {
-//!             name: "root".into_array().unwrap(),
-//!             password: "123".into_array().unwrap(),
-//!         };
-//!     
-//!         Self {
-//!             volume_name: "Ext2Disk".into_array().unwrap(),
-//!             block_bitmap: 1,
-//!             inode_bitmap: 2,
-//!             inode_table: 3,
-//!             free_blocks_count: DATA_BLOCKS as u16,
-//!             free_inodes_count: DATA_BLOCKS as u16,
-//!             used_dirs_count: 0,
-//!             users_len: 1,
-//!             users,
-//!         }
-//!     }
-//! }
-//! ```
+//! 在格式化（[`Fs::format()`](fs::Fs::format())）时会根据 [`FormatOptions`](fs::FormatOptions) 新建超级块，
+//! 再为每个块组计算出组描述符，然后将它们写入磁盘开头的几个磁盘块。
+//!
+//! 超级块、组描述符、索引结点和目录项的末尾都有一个 CRC32C 校验和，写入时由
+//! [`IntoBytes`](fs::IntoBytes) 填好，设置了 [`FEATURE_RO_COMPAT_METADATA_CSUM`](fs::FEATURE_RO_COMPAT_METADATA_CSUM)
+//! 时由 [`FromDisk`](fs::FromDisk) 在读取时检查，不匹配时返回 `InvalidData` 错误并指出损坏的结构。
+//! 和 ext4 一样，目录的每个数据块末尾还有一个校验和记录，覆盖整个数据块，组描述符中记录了两个位图的校验和，
+//! 读取目录数据块和位图时同样检查。
 //!
 //! ## 索引结点 - [`Inode`](fs::Inode)
 //! 
-//! 每个索引结点数据结构大小为 32 字节。每个索引结点即对应一个文件或是目录。是对其除文件名（目录名）以外的所有属性的描述。
+//! 每个索引结点数据结构大小为 128 字节。每个索引结点即对应一个文件或是目录。是对其除文件名（目录名）以外的所有属性的描述。
 //! 例如：文件类型，文件创建时间，访问时间，修改时间，文件所占数据块的个数，指向数据块的指针。
+//!
+//! 时间戳是 64 位的秒数加上 32 位的纳秒数，2106 年之后也不会溢出。读取文件时按照挂载选项
+//! [`AtimeMode`](fs::AtimeMode) 更新访问时间，[`Fs::set_times()`](fs::Fs::set_times()) 可以直接设置访问时间和修改时间。
 //! 
 //! ```ignore
 //! pub struct Inode {
 //!     /// 文件权限
 //!     pub i_mode: FileMode,
 //!     /// 文件数据块个数
-//!     pub i_blocks: u16,
+//!     pub i_blocks: u32,
 //!     /// 文件大小
 //!     pub i_size: u32,
-//!     /// 创建时间
-//!     pub i_ctime: u32,
+//!     /// 访问时间
+//!     pub i_atime: u64,
+//!     /// 状态改变时间
+//!     pub i_ctime: u64,
 //!     /// 修改时间
-//!     pub i_mtime: u32,
-//!     /// 指向数据块的指针数组（使用二级索引）
-//!     pub(in crate::fs) i_block: [u16; 8],
+//!     pub i_mtime: u64,
+//!     /// 创建时间
+//!     pub i_crtime: u64,
+//!     /// 四个时间的纳秒部分
+//!     pub i_atime_nsec: u32,
+//!     pub i_ctime_nsec: u32,
+//!     pub i_mtime_nsec: u32,
+//!     pub i_crtime_nsec: u32,
+//!     /// 索引节点标志
+//!     pub i_flags: u16,
+//!     /// 指向数据块的指针数组（使用三级索引）
+//!     pub(in crate::fs) i_block: [u32; 9],
 //! }
 //! ```
-//! 数据块指针 [`i_block`](fs::Inode::i_block) 是由 8个元组的数据组成：
+//! 数据块指针 [`i_block`](fs::Inode::i_block) 是由 9个元组的数据组成：
 //! 
 //! - i_block[0]~i_block[5] 直接指向数据块（直接索引）
 //! - i_block[6] 是一个1级子索引。指向的不是数据块，而是存放数据块指针的块。
-//! - 类似的，i_block[7] 是一个2级子索引。
+//! - 类似的，i_block[7] 是一个2级子索引，i_block[8] 是一个3级子索引。
 //! 
 //! 我们这里 i_block 存放的是数据块的块号。
 //! 
@@ -169,7 +212,23 @@
 //! 
 //! 1. 则当文件长度小于 b*6 时，只要用 i_block[0]~i_block[5] 来指向其数据块
 //! 2. 当文件长度在 b*6 到 b*(6 + b / 4) 时，使用 i_block[6] 指向的数据块作为索引
-//! 3. 当文件长度在 b*(6+b/4) 到 b*(6 + b/4 + (b/4)^2 时，使用 i_block[7] 指向的两级索引
+//! 3. 当文件长度在 b*(6+b/4) 到 b*(6 + b/4 + (b/4)^2) 时，使用 i_block[7] 指向的两级索引
+//! 4. 当文件长度在 b*(6 + b/4 + (b/4)^2) 到 b*(6 + b/4 + (b/4)^2 + (b/4)^3) 时，使用 i_block[8] 指向的三级索引
+//! 
+//! 文件长度同时受 `i_size` 的 32 位限制，当前几何参数下的上限可以通过
+//! [`Fs::max_file_size()`](fs::Fs::max_file_size()) 查询，超过上限的写入会返回
+//! [`FileTooLarge`](std::io::ErrorKind::FileTooLarge) 错误。
+//! 
+//! 格式化时设置 [`FormatOptions::extents`](fs::FormatOptions::extents)（shell 中为 `format -e`）后，
+//! 新建的文件会带上 [`INODE_FLAG_EXTENTS`](fs::INODE_FLAG_EXTENTS) 标志，改用类似 ext4 的区段树：
+//! `i_block` 里存放区段树的根结点，每个区段描述一段物理上连续的数据块。
+//! 分配数据块时会优先选择紧跟在上一个区段之后的块，因此连续写入的大文件通常只需要很少的区段。
+//! 
+//! 文件可以是稀疏的：用 `seek` 跳过文件末尾再写入时，跳过的逻辑块不会分配数据块，
+//! 对应的块指针为 [`HOLE_BLK`](fs::HOLE_BLK)（区段树中则是没有区段覆盖），读取时得到全 0。
+//! 0 号数据块在格式化时就被保留，根目录从 [`ROOT_DIR_BLK`](fs::ROOT_DIR_BLK) 开始存放，
+//! 所以块指针 0 不会和真正的数据块混淆。`i_size` 是文件的逻辑大小，`i_blocks` 是实际分配的数据块个数，
+//! shell 中的 `du` 按后者统计空间占用，`du --apparent-size` 按前者统计。
 //! 
 //! <img src="data:image/png;base64,
 #![doc = include_str!("../img/i_block.b64")]
@@ -188,26 +247,35 @@
 //! // src/fs/core/fs.rs
 //! pub struct DirEntry {
 //!    /// 索引节点号
-//!    pub i_node: u16,
-//!    /// 目录项长度
+//!    pub i_node: u32,
+//!    /// 到下一个目录项的距离
 //!    pub rec_len: u16,
 //!    /// 文件名长度
 //!    pub name_len: u8,
 //!    /// 文件类型
 //!    pub file_type: u8,
-//!    /// 文件名
-//!    pub name: [u8; 16],
+//!    /// 目录项的校验和
+//!    pub checksum: u32,
+//!    /// 文件名，最长 255 字节
+//!    pub name: [u8; 256],
 //!}
 //! ```
 //! 
+//! 目录项在磁盘上是变长的，只占用 12 字节的头部加上文件名的长度（按 4 字节对齐），
+//! `rec_len` 可以比这更长，多出来的空间留给以后新建的文件。
+//! 
 //! 目录文件的数据块：
 //! 
 //! <img src="data:image/png;base64,
 #![doc = include_str!("../img/dir_entry.b64")]
 //! "/>
 //! 
-//! inode 标识的是目录项下文件的 i节点，当文件被删除时，inode
-//! 设置为 0
+//! inode 标识的是目录项下文件的 i节点。删除文件时，它的目录项被合并到前一个目录项的 `rec_len` 里；
+//! 位于数据块开头的目录项没有前一个目录项，文件类型被设置为 0，表示这段空间没有使用
+//! 
+//! 超过一个数据块的目录会带上 [`INODE_FLAG_INDEX`](fs::INODE_FLAG_INDEX) 标志和哈希索引：
+//! 第 0 个数据块中 `..` 之后的空闲空间存放索引的根结点，按文件名的哈希值指向存放它的数据块，
+//! 查找时不用再逐项比较，详见 `src/fs/core/htree.rs`。
 //! 
 #[doc = "fs::Inode"]
 pub mod shell;
@@ -11,17 +11,15 @@ impl Cmd for Cd {
     // 实现命令的运行逻辑
     fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
         // 获取第一个参数
-        match argv.get(0) {
-            // 如果参数为空，打印错误信息
-            None => println!("Need a filename"),
-            // 如果参数不为空，尝试切换工作目录
-            Some(path) => {
-                // 尝试切换工作目录
-                if let Err(msg) = shell.fs.chdir(path) {
-                    // 如果切换失败，打印错误信息
-                    println!("{}", msg.to_string())
-                }
-            }
+        // 没有参数时回到主目录
+        let path = match argv.first() {
+            Some(&path) => path.to_string(),
+            None => shell.var("HOME").unwrap_or("/").to_string(),
+        };
+        // 尝试切换工作目录，同时更新 PWD
+        if let Err(msg) = shell.chdir(&path) {
+            // 如果切换失败，打印错误信息
+            println!("{}", msg)
         }
     }
 
@@ -32,7 +30,9 @@ impl Cmd for Cd {
             // 返回命令的帮助信息
             + r"#
             // 返回命令的帮助信息
-        cd <dir>
+        cd [dir]
+
+        Without dir, change to $HOME.
 
         -h show help message#"
     }
@@ -0,0 +1,54 @@
+use chrono::{Days, NaiveDate};
+
+use super::*;
+
+pub struct Chage;
+
+impl Chage {
+    /// 从 Unix 纪元开始的天数转换为日期
+    fn date(days: Option<u64>) -> String {
+        days.and_then(|days| NaiveDate::default().checked_add_days(Days::new(days)))
+            .map(|date| date.to_string())
+            .unwrap_or("never".into())
+    }
+}
+
+impl Cmd for Chage {
+    fn description(&self) -> String {
+        "Change or show account expiry information".into()
+    }
+
+    fn run(&self, shell: &mut Shell, argv: &[&str]) {
+        let res = match argv {
+            ["-l", name] => shell.fs.user_shadow(name).map(|shadow| {
+                println!("Last password change: {}", Self::date(shadow.last_change));
+                println!("Account expires:      {}", Self::date(shadow.expire));
+                println!("Account locked:       {}", if shadow.locked { "yes" } else { "no" });
+            }),
+            ["-E", "-1", name] => shell.fs.set_user_expire(name, None),
+            ["-E", date, name] => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(date) => shell.fs.set_user_expire(name, Some(date)),
+                Err(_) => {
+                    println!("chage: invalid date '{}'", date);
+                    return;
+                }
+            },
+            _ => {
+                println!("{}", self.help());
+                return;
+            }
+        };
+
+        if let Err(e) = res {
+            println!("{}", e);
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description()
+            + "\n chage -l username"
+            + "\n chage -E YYYY-MM-DD|-1 username"
+            + "\n -l show last password change, expiry date and lock status."
+            + "\n -E set the day the account expires, -1 means never."
+    }
+}
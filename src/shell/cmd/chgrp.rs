@@ -0,0 +1,26 @@
+use super::Cmd;
+
+pub struct Chgrp;
+
+impl Cmd for Chgrp {
+    fn description(&self) -> String {
+        "Change group of files.".into()
+    }
+
+    fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
+        let Some((group, paths)) = argv.split_first() else {
+            println!("{}", self.help());
+            return;
+        };
+
+        for path in paths {
+            if let Err(e) = shell.fs.chgrp(path, group) {
+                println!("{}: {}", path, e);
+            }
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description() + "\n chgrp [group] [files...]"
+    }
+}
@@ -5,14 +5,20 @@ use utils::str;
 pub struct Chmod;
 
 impl Chmod {
-    fn chmod_recursively(fs: &mut Fs, path: &str, mode: u8) {       
+    /// 按照权限字符串修改一个文件的权限，符号形式在文件原来的权限上修改
+    fn chmod(fs: &mut Fs, path: &str, mode: &str) -> Result<()> {
+        let old = fs.stat(path)?.mode;
+        fs.chmod(path, FileMode::str_to_mode(mode, old)?)
+    }
+
+    fn chmod_recursively(fs: &mut Fs, path: &str, mode: &str) {       
         // 尝试打开文件
         match fs.open(path) {
             // 文件打开成功
             Ok(fd) => {
                 // 尝试修改文件权限
-                if let Err(e) = fs.chmod(path, mode) {
-                    println!("{}: {}", path, e.to_string())
+                if let Err(e) = Self::chmod(fs, path, mode) {
+                    println!("{}: {}", path, e)
                 }
                 // 关闭文件
                 fs.close(fd).unwrap();
@@ -24,7 +30,7 @@ impl Chmod {
                 // 其他错误
                 std::io::ErrorKind::Other => {}
                 // 其他错误
-                _ => println!("{}: {}", path, e.to_string()),
+                _ => println!("{}: {}", path, e),
             },
         }
 
@@ -37,23 +43,29 @@ impl Chmod {
                 Ok(iter) => {
                     // 遍历目录项
                     for item in iter {
-                        // 如果目录项是使用中的
-                        if let DirEntryIterItem::Using(Item { entry, .. }) = item {
-                            // 如果目录项是当前目录或父目录
-                            if entry.name == ".".into_array().unwrap()
-                                || entry.name == "..".into_array().unwrap()
-                            {
-                                // 跳过
-                                continue;
+                        // 只处理使用中的目录项，读取出错时停止遍历
+                        let entry = match item {
+                            Ok(DirEntryIterItem::Using(Item { entry, .. })) => entry,
+                            Ok(DirEntryIterItem::Deleted(_)) => continue,
+                            Err(e) => {
+                                println!("{}: {}", path, e);
+                                break;
                             }
-                            // 添加文件名到数组
-                            names.push(str(&entry.name).to_string());
+                        };
+                        // 如果目录项是当前目录或父目录
+                        if entry.name == ".".into_array().unwrap()
+                            || entry.name == "..".into_array().unwrap()
+                        {
+                            // 跳过
+                            continue;
                         }
+                        // 添加文件名到数组
+                        names.push(str(&entry.name).to_string());
                     }
                 }
-                Err(e) => println!("{}: {}", path, e.to_string()),
+                Err(e) => println!("{}: {}", path, e),
             },
-            Err(e) => println!("{}: {}", path, e.to_string()),
+            Err(e) => println!("{}: {}", path, e),
         }
 
         // 递归修改文件权限
@@ -63,7 +75,7 @@ impl Chmod {
         }
 
         // 尝试修改文件权限
-        if let Err(e) = fs.chmod(path, mode) {
+        if let Err(e) = Self::chmod(fs, path, mode) {
             println!("{}: {}", path, e);
         }
     }
@@ -82,24 +94,24 @@ impl Cmd for Chmod {
         // 是否递归
         let mut recursively = false;
         // 文件权限
-        let mut mode: u8 = 0xff;
+        let mut mode = "";
 
         // 如果参数为空，返回
-        if argv.len() == 0 {
+        if argv.is_empty() {
             return;
         }
 
         // 如果参数为 -r，设置递归
-        if let Some(&"-r") = argv.get(0) {
+        if let Some(&"-r") = argv.first() {
             recursively = true;
             argv.remove(0);
         }
 
         // 如果参数为文件权限，设置文件权限
-        if let Some(m) = argv.get(0) {
-            match FileMode::str_to_mode(m) {
-                // 如果文件权限转换成功，设置文件权限
-                Ok(m) => mode = m,
+        if let Some(m) = argv.first() {
+            match FileMode::str_to_mode(m, 0) {
+                // 如果文件权限格式正确，记录下来，符号形式要在每个文件原来的权限上修改
+                Ok(_) => mode = m,
                 // 如果文件权限转换失败，打印错误信息并返回
                 Err(e) => {
                     println!("{}", e);
@@ -118,7 +130,7 @@ impl Cmd for Chmod {
                 // 如果非递归，修改文件权限
                 false => {
                     // 如果修改文件权限失败，打印错误信息
-                    if let Err(e) = shell.fs.chmod(path, mode) {
+                    if let Err(e) = Chmod::chmod(&mut shell.fs, path, mode) {
                         println!("{}: {}", path, e);
                     }
                 }
@@ -135,6 +147,9 @@ impl Cmd for Chmod {
             // 返回命令的帮助信息
             + "\n -r change permission recursively."
             // 返回命令的帮助信息
-            + "\n\n [mode] is a string like this rwx:rwx"
+            + "\n\n [mode] can be:"
+            + "\n   octal number, like 755, 1777, 2755"
+            + "\n   full string, like rwxr-xr-x, rwxrwxrwt"
+            + "\n   symbolic, like u+x, go-w, +t, g+s, u=rwx,o="
     }
 }
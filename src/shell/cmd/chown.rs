@@ -9,14 +9,14 @@ impl Chown {
         match fs.open(path) {
             Ok(fd) => {
                 if let Err(e) = fs.chown(path, user) {
-                    println!("{}: {}", path, e.to_string())
+                    println!("{}: {}", path, e)
                 }
                 fs.close(fd).unwrap();
                 return;
             }
             Err(e) => match e.kind() {
                 std::io::ErrorKind::Other => {}
-                _ => println!("{}: {}", path, e.to_string()),
+                _ => println!("{}: {}", path, e),
             },
         }
 
@@ -26,19 +26,25 @@ impl Chown {
             Ok(path_res) => match path_res.dir_entry.iter(fs) {
                 Ok(iter) => {
                     for item in iter {
-                        if let DirEntryIterItem::Using(Item { entry, .. }) = item {
-                            if entry.name == ".".into_array().unwrap()
-                                || entry.name == "..".into_array().unwrap()
-                            {
-                                continue;
+                        let entry = match item {
+                            Ok(DirEntryIterItem::Using(Item { entry, .. })) => entry,
+                            Ok(DirEntryIterItem::Deleted(_)) => continue,
+                            Err(e) => {
+                                println!("{}: {}", path, e);
+                                break;
                             }
-                            names.push(str(&entry.name).to_string());
+                        };
+                        if entry.name == ".".into_array().unwrap()
+                            || entry.name == "..".into_array().unwrap()
+                        {
+                            continue;
                         }
+                        names.push(str(&entry.name).to_string());
                     }
                 }
-                Err(e) => println!("{}: {}", path, e.to_string()),
+                Err(e) => println!("{}: {}", path, e),
             },
-            Err(e) => println!("{}: {}", path, e.to_string()),
+            Err(e) => println!("{}: {}", path, e),
         }
 
         for to_chown in names {
@@ -60,17 +66,17 @@ impl Cmd for Chown {
         let mut argv = argv.to_vec();
         let mut recursively = false;
 
-        if argv.len() == 0 {
+        if argv.is_empty() {
             return;
         }
 
-        if let Some(&"-r") = argv.get(0) {
+        if let Some(&"-r") = argv.first() {
             recursively = true;
             argv.remove(0);
         }
 
         let mut user = None;
-        if let Some(&u) = argv.get(0) {
+        if let Some(&u) = argv.first() {
             argv.remove(0);
             user = Some(u);
         }
@@ -28,7 +28,7 @@ impl Cp {
             let mut copy_tasks = Vec::new();
             
             if let Ok(entries) = src_path.dir_entry.iter(fs) {
-                for entry in entries {
+                for entry in entries.flatten() {
                     if let DirEntryIterItem::Using(Item { entry, .. }) = entry {
                         let filename = utils::str(&entry.name).to_string();
                         
@@ -73,7 +73,7 @@ impl Cp {
         };
 
         let fd_src = fs.open(src)?;
-        let fd_dest = fs.open(dest)?;
+        let fd_dest = fs.open_with(dest, OpenOptions::new().write(true))?;
 
         loop {
             let mut buf = [0u8; 512]; // 使用更大的缓冲区提高效率
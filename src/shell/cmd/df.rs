@@ -1,6 +1,5 @@
 use super::*;
 use crate::shell::Shell;
-use crate::fs::{BLOCKS, BLOCK_SIZE, DATA_BLOCKS};
 
 pub struct Df;
 
@@ -27,17 +26,29 @@ impl Df {
         }
     }
 
+    /// 将 Unix 时间戳格式化为东八区时间
+    fn format_time(timestamp: u64) -> String {
+        use chrono::FixedOffset;
+
+        let china_tz = FixedOffset::east_opt(8 * 3600).unwrap();
+        chrono::DateTime::from_timestamp(timestamp.try_into().unwrap_or(i64::MAX), 0)
+            .unwrap_or_default()
+            .with_timezone(&china_tz)
+            .format("%Y-%m-%d %H:%M:%S CST")
+            .to_string()
+    }
+
     /// 显示文件系统信息
     fn show_filesystem_info(shell: &Shell, human_readable: bool, show_inodes: bool) {
-        let fs_desc = shell.fs.fs_desc();
+        let super_block = shell.fs.super_block();
         
         // 计算块使用情况
-        let total_blocks = BLOCKS as u64;
-        let free_blocks = fs_desc.free_blocks_count as u64;
+        let total_blocks = super_block.total_blocks() as u64;
+        let free_blocks = super_block.free_blocks_count as u64;
         let used_blocks = total_blocks - free_blocks;
         
         // 计算字节数
-        let block_size = BLOCK_SIZE as u64;
+        let block_size = shell.fs.block_size() as u64;
         let total_bytes = total_blocks * block_size;
         let used_bytes = used_blocks * block_size;
         let free_bytes = free_blocks * block_size;
@@ -51,8 +62,8 @@ impl Df {
 
         if show_inodes {
             // 显示inode信息
-            let total_inodes = DATA_BLOCKS as u64;
-            let free_inodes = fs_desc.free_inodes_count as u64;
+            let total_inodes = super_block.inodes_count as u64;
+            let free_inodes = super_block.free_inodes_count as u64;
             let used_inodes = total_inodes - free_inodes;
             let inode_usage_percent = if total_inodes > 0 {
                 (used_inodes as f64 / total_inodes as f64 * 100.0) as u32
@@ -60,58 +71,68 @@ impl Df {
                 0
             };
 
-            println!("{:<15} {:>10} {:>10} {:>10} {:>6} {}", 
-                     "Filesystem", "Inodes", "IUsed", "IFree", "IUse%", "Mounted on");
-            println!("{:<15} {:>10} {:>10} {:>10} {:>5}% {}", 
+            println!("{:<15} {:>10} {:>10} {:>10} {:>6} Mounted on", 
+                     "Filesystem", "Inodes", "IUsed", "IFree", "IUse%");
+            println!("{:<15} {:>10} {:>10} {:>10} {:>5}% /", 
                      "ext2fs", 
                      total_inodes,
                      used_inodes,
                      free_inodes,
-                     inode_usage_percent,
-                     "/");
+                     inode_usage_percent);
         } else {
             // 显示块/字节信息
             if human_readable {
-                println!("{:<15} {:>10} {:>10} {:>10} {:>6} {}", 
-                         "Filesystem", "Size", "Used", "Avail", "Use%", "Mounted on");
-                println!("{:<15} {:>10} {:>10} {:>10} {:>5}% {}", 
+                println!("{:<15} {:>10} {:>10} {:>10} {:>6} Mounted on", 
+                         "Filesystem", "Size", "Used", "Avail", "Use%");
+                println!("{:<15} {:>10} {:>10} {:>10} {:>5}% /", 
                          "ext2fs",
                          Self::format_bytes(total_bytes, true),
                          Self::format_bytes(used_bytes, true),
                          Self::format_bytes(free_bytes, true),
-                         usage_percent,
-                         "/");
+                         usage_percent);
             } else {
-                println!("{:<15} {:>10} {:>10} {:>10} {:>6} {}", 
-                         "Filesystem", "512B-blocks", "Used", "Available", "Use%", "Mounted on");
-                println!("{:<15} {:>10} {:>10} {:>10} {:>5}% {}", 
+                println!("{:<15} {:>10} {:>10} {:>10} {:>6} Mounted on", 
+                         "Filesystem", "512B-blocks", "Used", "Available", "Use%");
+                println!("{:<15} {:>10} {:>10} {:>10} {:>5}% /", 
                          "ext2fs",
                          total_bytes / 512,
                          used_bytes / 512,
                          free_bytes / 512,
-                         usage_percent,
-                         "/");
+                         usage_percent);
             }
         }
     }
 
     /// 显示详细信息
-    fn show_detailed_info(shell: &Shell) {
-        let fs_desc = shell.fs.fs_desc();
+    fn show_detailed_info(shell: &mut Shell) {
+        let super_block = shell.fs.super_block();
+        let block_size = shell.fs.block_size();
+        let total_blocks = super_block.total_blocks();
         
         println!("Filesystem Information:");
         println!("======================");
-        println!("Volume Name: {}", crate::fs::utils::str(&fs_desc.volume_name));
-        println!("Block Size: {} bytes", BLOCK_SIZE);
-        println!("Total Blocks: {}", BLOCKS);
-        println!("Data Blocks: {}", DATA_BLOCKS);
-        println!("Free Blocks: {}", fs_desc.free_blocks_count);
-        println!("Used Blocks: {}", BLOCKS - fs_desc.free_blocks_count as usize);
+        println!("Volume Name: {}", crate::fs::utils::str(&super_block.volume_name));
+        println!("Format Version: {}", super_block.version);
+        println!(
+            "State: {}",
+            match super_block.state {
+                crate::fs::FS_STATE_CLEAN => "clean",
+                _ => "not clean",
+            }
+        );
+        println!("Mount Count: {}", super_block.mount_count);
+        println!("Last Mount Time: {}", Self::format_time(super_block.mtime));
+        println!("Last Write Time: {}", Self::format_time(super_block.wtime));
+        println!("Block Size: {} bytes", block_size);
+        println!("Total Blocks: {}", total_blocks);
+        println!("Data Blocks: {}", super_block.blocks_count);
+        println!("Free Blocks: {}", super_block.free_blocks_count);
+        println!("Used Blocks: {}", total_blocks - super_block.free_blocks_count as usize);
         println!();
         
-        let total_bytes = BLOCKS * BLOCK_SIZE;
-        let used_bytes = (BLOCKS - fs_desc.free_blocks_count as usize) * BLOCK_SIZE;
-        let free_bytes = fs_desc.free_blocks_count as usize * BLOCK_SIZE;
+        let total_bytes = total_blocks * block_size;
+        let used_bytes = (total_blocks - super_block.free_blocks_count as usize) * block_size;
+        let free_bytes = super_block.free_blocks_count as usize * block_size;
         
         println!("Space Information:");
         println!("-----------------");
@@ -122,16 +143,46 @@ impl Df {
         
         println!("Inode Information:");
         println!("-----------------");
-        println!("Total Inodes: {}", DATA_BLOCKS);
-        println!("Free Inodes: {}", fs_desc.free_inodes_count);
-        println!("Used Inodes: {}", DATA_BLOCKS - fs_desc.free_inodes_count as usize);
-        println!("Directories: {}", fs_desc.used_dirs_count);
+        println!("Total Inodes: {}", super_block.inodes_count);
+        println!("Free Inodes: {}", super_block.free_inodes_count);
+        println!("Used Inodes: {}", (super_block.inodes_count - super_block.free_inodes_count));
+        println!("Directories: {}", super_block.used_dirs_count);
         println!();
         
+        println!("Block Groups: {}", super_block.groups_count);
+        println!("-----------------");
+        for (i, group) in shell.fs.groups().iter().enumerate() {
+            println!(
+                "Group {}: free blocks {}/{}, free inodes {}/{}, directories {}",
+                i,
+                group.free_blocks_count,
+                super_block.group_blocks(i),
+                group.free_inodes_count,
+                super_block.inodes_per_group,
+                group.used_dirs_count
+            );
+        }
+        println!();
+
+        let cache = shell.fs.cache_stats();
+        println!("Cache Information:");
+        println!("-----------------");
+        println!("Capacity: {} blocks", cache.capacity);
+        println!("Cached Blocks: {}", cache.cached);
+        println!("Dirty Blocks: {}", cache.dirty);
+        println!("Hits: {}", cache.hits);
+        println!("Misses: {}", cache.misses);
+        println!();
+
         println!("User Information:");
         println!("----------------");
-        println!("Registered Users: {}", fs_desc.users_len);
-        println!("Max Users: {}", fs_desc.users.len());
+        match (shell.fs.users(), shell.fs.user_groups()) {
+            (Ok(users), Ok(groups)) => {
+                println!("Registered Users: {}", users.len());
+                println!("Groups: {}", groups.len());
+            }
+            (Err(e), _) | (_, Err(e)) => println!("Can't read user database: {}", e),
+        }
     }
 }
 
@@ -217,7 +268,8 @@ Options:
 Fields explanation:
   Filesystem    - filesystem name
   Size/1K-blocks - total size (human readable format or 1K blocks)
-  Used          - used space
+  Used          - used space, counted in allocated blocks; holes in sparse
+                  files take no space, see 'du --apparent-size'
   Avail         - available space
   Use%          - percentage of space used
   Mounted on    - mount point
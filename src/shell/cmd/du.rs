@@ -0,0 +1,99 @@
+use super::*;
+use crate::fs::*;
+
+pub struct Du;
+
+/// du 的选项
+struct DuOptions {
+    /// 同时显示普通文件
+    all: bool,
+    /// 只显示总计
+    summarize: bool,
+    /// 显示文件的逻辑大小（`i_size`），而不是实际占用的数据块
+    apparent_size: bool,
+}
+
+impl Du {
+    /// 统计 `entry` 占用的字节数，目录会递归统计其中的所有文件
+    fn usage(fs: &Fs, entry: &DirEntry, path: &str, options: &DuOptions, depth: usize) -> Result<u64> {
+        let inode = fs.get_inode(entry.i_node)?;
+        let mut size = match options.apparent_size {
+            true => inode.i_size as u64,
+            // 空洞不占用数据块，稀疏文件的实际占用会小于逻辑大小
+            false => inode.i_blocks as u64 * fs.block_size() as u64,
+        };
+
+        let is_dir = matches!(entry.file_type.into(), FileType::Dir);
+        if is_dir {
+            for item in entry.iter(fs)? {
+                if let DirEntryIterItem::Using(Item { entry: child, .. }) = item? {
+                    let name = utils::str(&child.name);
+                    if name == "." || name == ".." {
+                        continue;
+                    }
+                    let child_path = format!("{}/{}", path.trim_end_matches('/'), name);
+                    size += Self::usage(fs, &child, &child_path, options, depth + 1)?;
+                }
+            }
+        }
+
+        // 目录总是显示，普通文件只在 -a 时显示；-s 时只显示最外层
+        if (is_dir || options.all || depth == 0) && (!options.summarize || depth == 0) {
+            println!("{:<10} {}", size, path);
+        }
+        Ok(size)
+    }
+}
+
+impl Cmd for Du {
+    fn description(&self) -> String {
+        "Estimate file space usage".into()
+    }
+
+    fn run(&self, shell: &mut Shell, argv: &[&str]) {
+        let mut options = DuOptions {
+            all: false,
+            summarize: false,
+            apparent_size: false,
+        };
+        let mut paths = Vec::new();
+
+        for &arg in argv {
+            match arg {
+                "-a" | "--all" => options.all = true,
+                "-s" | "--summarize" => options.summarize = true,
+                "--apparent-size" => options.apparent_size = true,
+                arg if arg.starts_with('-') => {
+                    println!("du: unknown option '{}'", arg);
+                    return;
+                }
+                path => paths.push(path),
+            }
+        }
+        if paths.is_empty() {
+            paths.push(".");
+        }
+
+        for path in paths {
+            let res = shell
+                .fs
+                .path_parse(path)
+                .and_then(|res| Self::usage(&shell.fs, &res.dir_entry, path, &options, 0));
+            if let Err(e) = res {
+                println!("du: {}: {}", path, e);
+            }
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description()
+            + r#"
+
+du [-a] [-s] [--apparent-size] [path]...
+Show the space used by each directory in bytes.
+ -a, --all          show files as well as directories
+ -s, --summarize    only show a total for each argument
+ --apparent-size    show file sizes instead of allocated blocks,
+                    holes in sparse files are counted"#
+    }
+}
@@ -0,0 +1,35 @@
+use super::Cmd;
+
+pub struct Env;
+
+impl Cmd for Env {
+    fn description(&self) -> String {
+        "Print session variables".into()
+    }
+
+    fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
+        // 指定了变量名时只打印这些变量
+        match argv.is_empty() {
+            true => {
+                for (name, value) in shell.env.iter() {
+                    println!("{}={}", name, value);
+                }
+            }
+            false => {
+                for &name in argv {
+                    match shell.var(name) {
+                        Some(value) => println!("{}={}", name, value),
+                        None => println!("env: {}: not set", name),
+                    }
+                }
+            }
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description()
+            + "\n env [NAME...]"
+            + "\n HOME, USER and PWD are set after login."
+            + "\n Arguments of any command can use $NAME, ${NAME} and a leading ~."
+    }
+}
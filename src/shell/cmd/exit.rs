@@ -10,7 +10,9 @@ impl Cmd for Exit {
     }
 
     fn run(&self, shell: &mut crate::shell::Shell, _argv: &[&str]) {
-        shell.fs.exit();
+        if let Err(e) = shell.fs.exit() {
+            eprintln!("Failed to unmount: {}", e);
+        }
         println!("Bye.");
         exit(0);
     }
@@ -1,15 +1,113 @@
 use std::io::Write;
 use super::*;
+use crate::fs::FormatOptions;
 
 pub struct Format;
 
+impl Format {
+    /// 提示输入两次 root 的初始密码，两次不一致或者为空时返回 `None`
+    pub fn read_root_password() -> Option<String> {
+        let read = |prompt: &str| {
+            print!("{}", prompt);
+            std::io::stdout().flush().unwrap();
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password).unwrap();
+            password.trim().to_string()
+        };
+
+        let password = read("Root password: ");
+        if password.is_empty() {
+            println!("Password can't be empty");
+            return None;
+        }
+        if read("Press again:   ") != password {
+            println!("Confirm failed");
+            return None;
+        }
+        Some(password)
+    }
+
+    /// 解析命令行参数，生成格式化选项
+    ///
+    /// root 的初始密码不从命令行读取，避免它留在历史记录里，调用者需要另外提示输入
+    fn parse_options(argv: &[&str]) -> Result<FormatOptions, String> {
+        let mut options = FormatOptions::default();
+        let mut inodes_count = None;
+        let mut blocks_count = None;
+        let mut iter = argv.iter();
+
+        while let Some(&arg) = iter.next() {
+            match arg {
+                "-b" | "-c" | "-i" | "-j" | "-n" => {
+                    let value = *iter
+                        .next()
+                        .ok_or(format!("{}: option requires an argument", arg))?;
+                    match arg {
+                        "-b" => {
+                            options.block_size = value
+                                .parse()
+                                .map_err(|_| format!("{}: invalid block size", value))?
+                        }
+                        "-c" => {
+                            blocks_count = Some(
+                                value
+                                    .parse()
+                                    .map_err(|_| format!("{}: invalid block count", value))?,
+                            )
+                        }
+                        "-i" => {
+                            inodes_count = Some(
+                                value
+                                    .parse()
+                                    .map_err(|_| format!("{}: invalid inode count", value))?,
+                            )
+                        }
+                        "-j" => {
+                            options.journal_blocks = value
+                                .parse()
+                                .map_err(|_| format!("{}: invalid journal size", value))?
+                        }
+                        _ => options.volume_name = value.into(),
+                    }
+                }
+                "-e" => options.extents = true,
+                "-C" => options.checksums = false,
+                "-H" => options.dir_index = false,
+                path if !path.starts_with('-') => options.path = path.into(),
+                _ => return Err(format!("format: unknown option '{}'", arg)),
+            }
+        }
+
+        // 没有指定数据块个数时，只创建一个块组；没有指定索引节点个数时，让其与数据块个数相同
+        options.blocks_count = blocks_count.unwrap_or(options.block_size * 8);
+        options.inodes_count = inodes_count.unwrap_or(options.blocks_count);
+        Ok(options)
+    }
+}
+
 impl Cmd for Format {
     fn description(&self) -> String {
         "Format your disk".into()
     }
 
-    fn run(&self, crate::shell::Shell { fs, .. }: &mut Shell, _argv: &[&str]) {
-        println!("!!! This opretion will wipe all data in this disk");
+    fn run(&self, crate::shell::Shell { fs, .. }: &mut Shell, argv: &[&str]) {
+        let mut options = match Self::parse_options(argv) {
+            Ok(options) => options,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        options.root_password = match Self::read_root_password() {
+            Some(password) => password,
+            None => return,
+        };
+        if let Err(e) = options.validate() {
+            println!("{}", e);
+            return;
+        }
+
+        println!("!!! This opretion will wipe all data in {}", options.path.display());
         print!("!!! Continue ? [Y/N]   ");
         std::io::stdout().flush().unwrap();
 
@@ -17,10 +115,31 @@ impl Cmd for Format {
         std::io::stdin().read_line(&mut i).unwrap();
 
         if i.trim() == "Y" || i.trim() == "y" {
-            match crate::fs::Fs::format() {
+            // 先卸载当前的文件系统，避免之后覆盖新磁盘上的超级块
+            if let Err(e) = fs.exit() {
+                println!("{}", e);
+                return;
+            }
+            match crate::fs::Fs::format(&options) {
                 Ok(f) => *fs = f,
-                Err(e) => println!("{}", e.to_string()),
+                Err(e) => println!("{}", e),
             }
         }
     }
+
+    fn help(&self) -> String {
+        self.description()
+            + r#"
+
+format [-e] [-C] [-H] [-b block_size] [-c blocks] [-i inodes] [-j journal_blocks] [-n volume_name] [image]
+ -e  map data blocks of new files with extent trees
+ -C  do not checksum metadata
+ -H  do not build hashed indexes for large directories
+ -b  block size in bytes: 512, 1024 or 4096 (default 512)
+ -c  number of data blocks, split into groups of block_size * 8 (default block_size * 8)
+ -i  number of inodes (default same as the number of data blocks)
+ -j  number of blocks in the journal, 0 disables journaling (default 256)
+ -n  volume name (default Ext2Disk)
+ image  path of the disk image on host (default disk.bin)"#
+    }
 }
@@ -0,0 +1,56 @@
+use super::*;
+
+pub struct Fsck;
+
+impl Cmd for Fsck {
+    fn description(&self) -> String {
+        "Check and repair the file system".into()
+    }
+
+    fn run(&self, shell: &mut Shell, argv: &[&str]) {
+        let repair = match argv {
+            [] => false,
+            ["-y"] => true,
+            _ => {
+                println!("fsck: unknown option '{}'", argv.join(" "));
+                return;
+            }
+        };
+
+        let report = match shell.fs.fsck(repair) {
+            Ok(report) => report,
+            Err(e) => {
+                println!("fsck: {}", e);
+                return;
+            }
+        };
+        for problem in &report.problems {
+            let note = match (report.repaired, problem.fixable()) {
+                (true, true) => " (fixed)",
+                (_, false) => " (not fixable)",
+                _ => "",
+            };
+            println!("{}{}", problem, note);
+        }
+        match (report.is_clean(), report.repaired) {
+            (true, _) => println!("fsck: file system is clean"),
+            (false, true) => println!(
+                "fsck: {} problems found and repaired",
+                report.problems.len()
+            ),
+            (false, false) => println!(
+                "fsck: {} problems found, run 'fsck -y' to repair",
+                report.problems.len()
+            ),
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description()
+            + r#"
+
+fsck [-y]
+Walk the directory tree and check bitmaps, free counts and link counts.
+ -y  repair the problems, orphaned files are moved to /lost+found (root only)"#
+    }
+}
@@ -0,0 +1,38 @@
+use super::*;
+
+pub struct Gpasswd;
+
+impl Cmd for Gpasswd {
+    fn description(&self) -> String {
+        "Administer group members".into()
+    }
+
+    fn run(&self, shell: &mut Shell, argv: &[&str]) {
+        let res = match argv {
+            ["-a", user, group] => shell.fs.group_add_member(group, user),
+            ["-d", user, group] => shell.fs.group_remove_member(group, user),
+            [group] => {
+                // 列出用户组的附加成员
+                shell.fs.find_group(group).map(|group| println!("{}", group.members.join(",")))
+            }
+            _ => {
+                println!("{}", self.help());
+                return;
+            }
+        };
+
+        if let Err(e) = res {
+            println!("{}", e);
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description()
+            + "\n gpasswd -a [username] [group]"
+            + "\n gpasswd -d [username] [group]"
+            + "\n gpasswd [group]"
+            + "\n -a add user to group."
+            + "\n -d remove user from group."
+            + "\n without option list members of group."
+    }
+}
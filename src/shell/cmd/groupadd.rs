@@ -0,0 +1,24 @@
+use super::Cmd;
+
+pub struct Groupadd;
+
+impl Cmd for Groupadd {
+    fn description(&self) -> String {
+        "Add new group".into()
+    }
+
+    fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
+        if argv.len() != 1 {
+            println!("Need one argument as group name");
+            return;
+        }
+
+        if let Err(e) = shell.fs.groupadd(argv[0]) {
+            println!("{}", e);
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description() + "\n groupadd [group]"
+    }
+}
@@ -0,0 +1,24 @@
+use super::Cmd;
+
+pub struct Groupdel;
+
+impl Cmd for Groupdel {
+    fn description(&self) -> String {
+        "Delete group".into()
+    }
+
+    fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
+        if argv.len() != 1 {
+            println!("Need one argument as group name");
+            return;
+        }
+
+        if let Err(e) = shell.fs.groupdel(argv[0]) {
+            println!("{}", e);
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description() + "\n groupdel [group]"
+    }
+}
@@ -3,19 +3,19 @@ use std::io::Write;
 use crossterm::style::Stylize;
 
 use super::Cmd;
-use crate::fs::Fs;
+use crate::shell::Shell;
 
 pub struct Login;
 
 impl Login {
     fn username() -> String {
-        println!("{}", ":: default user: root, password: 123".green());
+        println!("{}", ":: root's password is the one given when formatting".green());
 
         print!("username: ");
         std::io::stdout().flush().unwrap();
         let mut username = String::new();
         std::io::stdin().read_line(&mut username).unwrap();
-        return username.trim().into();
+        username.trim().into()
     }
 
     fn passwd() -> String {
@@ -23,31 +23,28 @@ impl Login {
         std::io::stdout().flush().unwrap();
         let mut password = String::new();
         std::io::stdin().read_line(&mut password).unwrap();
-        return password.trim().into();
+        password.trim().into()
     }
 
-    pub fn login_with_name(fs: &mut Fs, username: &str) -> bool {
+    /// 登录成功之后已经在用户的主目录中，并设置好了 `HOME`、`USER` 和 `PWD`
+    pub fn login_with_name(shell: &mut Shell, username: &str) -> bool {
         let password = Self::passwd();
 
-        if let Err(e) = fs.login(&username.trim(), &password.trim()) {
+        if let Err(e) = shell.fs.login(username.trim(), password.trim()) {
             println!("{}", e);
             return false;
         }
 
-        let username = username.trim();
-        let path_to_switch = match username == "root" {
-            true => "/root".into(),
-            false => format!("/home/{}", username),
-        };
-
-        if let Err(e) = fs.chdir(&path_to_switch) {
-            println!("Can't chdir to {}: {}", path_to_switch, e);
+        shell.start_session();
+        if shell.var("HOME") != shell.var("PWD") {
+            println!("No directory, logging in with HOME=/");
+            shell.set_var("HOME", "/");
         }
-        return true;
+        true
     }
 
-    pub fn login(fs: &mut Fs) -> bool {
-        Self::login_with_name(fs, &Self::username())
+    pub fn login(shell: &mut Shell) -> bool {
+        Self::login_with_name(shell, &Self::username())
     }
 }
 
@@ -58,11 +55,11 @@ impl Cmd for Login {
 
     fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
         match argv.len() {
-            0 => Self::login(&mut shell.fs),
-            1 => Self::login_with_name(&mut shell.fs, argv[0]),
+            0 => Self::login(shell),
+            1 => Self::login_with_name(shell, argv[0]),
             _ => {
                 println!("Too many argument");
-                return;
+                false
             }
         };
     }
@@ -17,7 +17,7 @@ impl Cmd for Mkdir {
         for dir in argv {
             // 如果创建目录失败，打印错误信息
             if let Err(e) = fs.mkdir(dir) {
-                println!("{}: {}", dir, e.to_string())
+                println!("{}: {}", dir, e)
             }
         }
     }
@@ -4,14 +4,21 @@ use super::Shell;
 
 mod cat;
 mod cd;
+mod chage;
+mod chgrp;
 mod chmod;
 mod chown;
 mod clear;
 mod cp;
 mod df;
+mod du;
+mod env;
 mod exit;
-mod format;
+mod fsck;
+mod gpasswd;
 mod grep;
+mod groupadd;
+mod groupdel;
 mod help;
 mod history;
 mod ln;
@@ -22,6 +29,7 @@ mod passwd;
 mod pwd;
 mod rm;
 mod rmdir;
+mod sync;
 mod touch;
 mod useradd;
 mod userdel;
@@ -31,12 +39,13 @@ mod write;
 mod zip;
 mod unzip;
 
+pub mod format;
 pub mod login;
 
 use {
-    cat::Cat, cd::Cd, chmod::Chmod, chown::Chown, clear::Clear, cp::Cp, df::Df, exit::Exit, format::Format,
-    grep::Grep, help::Help, history::History, ln::Ln, login::Login, ls::Ls, mkdir::Mkdir, mv::Mv, passwd::Passwd, pwd::Pwd, rm::Rm, rmdir::RmDir,
-    touch::Touch, useradd::Useradd, userdel::UserDel, users::Users, whoami::Whoami, write::Write,
+    cat::Cat, cd::Cd, chage::Chage, chgrp::Chgrp, chmod::Chmod, chown::Chown, clear::Clear, cp::Cp, df::Df, du::Du, env::Env, exit::Exit, format::Format, fsck::Fsck,
+    gpasswd::Gpasswd, grep::Grep, groupadd::Groupadd, groupdel::Groupdel, help::Help, history::History, ln::Ln, login::Login, ls::Ls, mkdir::Mkdir, mv::Mv, passwd::Passwd, pwd::Pwd, rm::Rm, rmdir::RmDir,
+    sync::SyncFs, touch::Touch, useradd::Useradd, userdel::UserDel, users::Users, whoami::Whoami, write::Write,
     zip::Zip, unzip::Unzip,
 };
 
@@ -61,7 +70,10 @@ pub fn cmds() -> Cmds {
         ("pwd", Box::new(Pwd) as Box<dyn Cmd + Send + Sync>),
         ("rm", Box::new(Rm) as Box<dyn Cmd + Send + Sync>),
         ("rmdir", Box::new(RmDir) as Box<dyn Cmd + Send + Sync>),
+        ("sync", Box::new(SyncFs) as Box<dyn Cmd + Send + Sync>),
+        ("du", Box::new(Du) as Box<dyn Cmd + Send + Sync>),
         ("format", Box::new(Format) as Box<dyn Cmd + Send + Sync>),
+        ("fsck", Box::new(Fsck) as Box<dyn Cmd + Send + Sync>),
         ("touch", Box::new(Touch) as Box<dyn Cmd + Send + Sync>),
         ("write", Box::new(Write) as Box<dyn Cmd + Send + Sync>),
         ("cat", Box::new(Cat) as Box<dyn Cmd + Send + Sync>),
@@ -70,11 +82,17 @@ pub fn cmds() -> Cmds {
         ("ln", Box::new(Ln) as Box<dyn Cmd + Send + Sync>),
         ("login", Box::new(Login) as Box<dyn Cmd + Send + Sync>),
         ("whoami", Box::new(Whoami) as Box<dyn Cmd + Send + Sync>),
+        ("env", Box::new(Env) as Box<dyn Cmd + Send + Sync>),
         ("passwd", Box::new(Passwd) as Box<dyn Cmd + Send + Sync>),
         ("useradd", Box::new(Useradd) as Box<dyn Cmd + Send + Sync>),
         ("userdel", Box::new(UserDel) as Box<dyn Cmd + Send + Sync>),
+        ("chage", Box::new(Chage) as Box<dyn Cmd + Send + Sync>),
         ("chmod", Box::new(Chmod) as Box<dyn Cmd + Send + Sync>),
         ("chown", Box::new(Chown) as Box<dyn Cmd + Send + Sync>),
+        ("chgrp", Box::new(Chgrp) as Box<dyn Cmd + Send + Sync>),
+        ("groupadd", Box::new(Groupadd) as Box<dyn Cmd + Send + Sync>),
+        ("groupdel", Box::new(Groupdel) as Box<dyn Cmd + Send + Sync>),
+        ("gpasswd", Box::new(Gpasswd) as Box<dyn Cmd + Send + Sync>),
         ("users", Box::new(Users) as Box<dyn Cmd + Send + Sync>),
         ("clear", Box::new(Clear) as Box<dyn Cmd + Send + Sync>),
         ("history", Box::new(History) as Box<dyn Cmd + Send + Sync>),
@@ -1,7 +1,5 @@
 use std::io::{stdin, stdout, Write}; // 引入标准库中的输入输出相关模块
 
-use crate::fs::ByteArray; // 引入文件系统模块中的 ByteArray 类型
-
 use super::*; // 引入上一级模块中的所有内容，包括 Cmd 特性和 Shell 结构体等
 
 pub struct Passwd; // 定义 Passwd 结构体，用于实现更改用户密码的功能
@@ -14,27 +12,19 @@ impl Cmd for Passwd {
 
     // 定义命令的运行逻辑
     fn run(&self, shell: &mut Shell, argv: &[&str]) {
-        // 获取当前用户
-        let mut user = shell.fs.current_user();
-
-        // 如果提供了用户名参数
-        if argv.len() == 1 {
-            let mut to_search = None; // 初始化一个变量，用于存储找到的用户索引
-            // 遍历用户列表，查找指定用户名
-            for (i, u) in shell.fs.fs_desc().users.iter().enumerate() {
-                if argv[0].into_array().unwrap() == u.name { // 将用户名参数转换为数组并与用户列表中的名字进行比较
-                    to_search = Some(i); // 如果找到匹配的用户，记录其索引
-                    break;
+        // 禁用或者启用账户
+        match argv {
+            ["-l" | "-u", name] => {
+                if let Err(e) = shell.fs.set_user_locked(name, argv[0] == "-l") {
+                    println!("{}", e);
                 }
+                return;
             }
-            // 根据查找结果更新用户变量
-            match to_search {
-                Some(i) => user = i, // 如果找到了用户，更新 user 为找到的用户索引
-                None => {
-                    println!("User {} is not exists.", argv[0]); // 如果未找到用户，打印错误信息并退出
-                    return;
-                }
+            [option, ..] if option.starts_with('-') => {
+                println!("{}", self.help());
+                return;
             }
+            _ => {}
         }
         // 如果参数数量过多
         if argv.len() > 1 {
@@ -42,6 +32,23 @@ impl Cmd for Passwd {
             return;
         }
 
+        // 没有提供用户名参数时修改当前用户的密码
+        let name = match argv.first() {
+            Some(name) => name.to_string(),
+            None => match shell.fs.find_user_by_uid(shell.fs.current_user()) {
+                Ok(user) => user.name,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            },
+        };
+        // 检查用户是否存在
+        if shell.fs.find_user(&name).is_err() {
+            println!("User {} is not exists.", name); // 如果未找到用户，打印错误信息并退出
+            return;
+        }
+
         // 初始化两个字符串变量，用于存储用户输入的新密码
         let mut password1 = String::new();
         let mut password2 = String::new();
@@ -63,13 +70,18 @@ impl Cmd for Passwd {
         }
 
         // 调用文件系统的 passwd 方法更改用户密码
-        if let Err(e) = shell.fs.passwd(user, &password1.trim()) {
+        if let Err(e) = shell.fs.passwd(&name, password1.trim()) {
             println!("{}", e); // 如果更改密码时发生错误，打印错误信息
         };
     }
 
     // 返回命令的帮助信息
     fn help(&self) -> String {
-        self.description() + "\n passwd [username]" // 帮助信息：描述命令的用法
+        // 帮助信息：描述命令的用法
+        self.description()
+            + "\n passwd [username]"
+            + "\n passwd -l|-u username"
+            + "\n -l lock the account, the user can't login until unlocked."
+            + "\n -u unlock the account."
     }
 }
\ No newline at end of file
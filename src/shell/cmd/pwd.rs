@@ -11,7 +11,7 @@ impl Cmd for Pwd {
     // 定义命令的运行逻辑
     fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
         // 检查命令行参数
-        match argv.get(0) {
+        match argv.first() {
             Some(&"-h") => println!("{}", self.help()), // 如果参数是 "-h"，打印帮助信息
             _ => {
                 // 否则，打印当前工作目录
@@ -13,13 +13,13 @@ impl Rm {
         match fs.open(path) {
             Ok(fd) => {
                 if let Err(e) = fs.rm(fd) {
-                    println!("{}: {}", path, e.to_string())
+                    println!("{}: {}", path, e)
                 }
                 return;
             }
             Err(e) => match e.kind() {
                 std::io::ErrorKind::Other => {}
-                _ => println!("{}: {}", path, e.to_string()),
+                _ => println!("{}: {}", path, e),
             },
         }
 
@@ -29,19 +29,25 @@ impl Rm {
             Ok(path_res) => match path_res.dir_entry.iter(fs) {
                 Ok(iter) => {
                     for item in iter {
-                        if let DirEntryIterItem::Using(Item { entry, .. }) = item {
-                            if entry.name == ".".into_array().unwrap()
-                                || entry.name == "..".into_array().unwrap()
-                            {
-                                continue;
+                        let entry = match item {
+                            Ok(DirEntryIterItem::Using(Item { entry, .. })) => entry,
+                            Ok(DirEntryIterItem::Deleted(_)) => continue,
+                            Err(e) => {
+                                println!("{}: {}", path, e);
+                                break;
                             }
-                            names.push(str(&entry.name).to_string());
+                        };
+                        if entry.name == ".".into_array().unwrap()
+                            || entry.name == "..".into_array().unwrap()
+                        {
+                            continue;
                         }
+                        names.push(str(&entry.name).to_string());
                     }
                 }
-                Err(e) => println!("{}: {}", path, e.to_string()),
+                Err(e) => println!("{}: {}", path, e),
             },
-            Err(e) => println!("{}: {}", path, e.to_string()),
+            Err(e) => println!("{}: {}", path, e),
         }
 
         for to_delete in names {
@@ -62,7 +68,7 @@ impl Cmd for Rm {
     fn run(&self, crate::shell::Shell { fs, .. }: &mut crate::shell::Shell, argv: &[&str]) {
         let dir = fs.pwd();
 
-        if argv.len() == 0 {
+        if argv.is_empty() {
             return;
         }
 
@@ -104,18 +110,15 @@ impl Cmd for Rm {
             // 首先尝试检查是否为符号链接
             if !follow_links {
                 // 尝试删除符号链接本身
-                match fs.path_parse_with_options(arg, false) {
-                    Ok(path_res) => {
-                        let symlink_type: u8 = FileType::Symlink.into();
-                        if path_res.dir_entry.file_type == symlink_type {
-                            // 是符号链接，删除链接本身
-                            if let Err(e) = fs.rm_symlink(arg) {
-                                println!("{}: {}", arg, e);
-                            }
-                            continue;
+                if let Ok(path_res) = fs.path_parse_with_options(arg, false) {
+                    let symlink_type: u8 = FileType::Symlink.into();
+                    if path_res.dir_entry.file_type == symlink_type {
+                        // 是符号链接，删除链接本身
+                        if let Err(e) = fs.rm_symlink(arg) {
+                            println!("{}: {}", arg, e);
                         }
-                    },
-                    Err(_) => {}
+                        continue;
+                    }
                 }
             }
             
@@ -124,7 +127,7 @@ impl Cmd for Rm {
             match fs.open(arg) {
                 Ok(fd) => {
                     if let Err(e) = fs.rm(fd) {
-                        println!("{}", e.to_string())
+                        println!("{}", e)
                     }
                 }
                 Err(e) => match e.kind() {
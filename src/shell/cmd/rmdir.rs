@@ -18,7 +18,7 @@ impl Cmd for RmDir {
         let mut start_index = 0;
 
         // 检查是否有 -r 参数
-        if argv.len() > 0 && argv[0] == "-r" {
+        if !argv.is_empty() && argv[0] == "-r" {
             recursive = true;
             start_index = 1;
         }
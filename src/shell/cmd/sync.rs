@@ -0,0 +1,27 @@
+use super::Cmd;
+
+pub struct SyncFs;
+
+impl Cmd for SyncFs {
+    fn description(&self) -> String {
+        "Write cached blocks back to disk".into()
+    }
+
+    fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
+        match argv.first() {
+            Some(&"-h") | Some(&"--help") => println!("{}", self.help()),
+            _ => {
+                if let Err(e) = shell.fs.sync() {
+                    println!("sync: {}", e);
+                }
+            }
+        }
+    }
+
+    fn help(&self) -> String {
+        self.description() + r#"
+
+sync
+Write all modified blocks in the block cache back to the disk image."#
+    }
+}
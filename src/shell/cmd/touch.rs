@@ -1,18 +1,131 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
 pub struct Touch;
 
+impl Touch {
+    /// 时间戳参数所在的时区：东八区，和 ls 显示的时间一致
+    fn china_tz() -> FixedOffset {
+        FixedOffset::east_opt(8 * 3600).unwrap()
+    }
+
+    /// 解析 `-t` 的参数，格式为 `[[CC]YY]MMDDhhmm[.ss]`
+    fn parse_stamp(stamp: &str) -> Option<DateTime<Utc>> {
+        let (digits, sec) = match stamp.split_once('.') {
+            Some((digits, sec)) if sec.len() == 2 => (digits, sec.parse().ok()?),
+            Some(_) => return None,
+            None => (stamp, 0),
+        };
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        // 省略世纪时 69~99 表示 1969~1999，00~68 表示 2000~2068；省略年份时使用今年
+        let (year, rest) = match digits.len() {
+            8 => (Utc::now().with_timezone(&Self::china_tz()).year(), digits),
+            10 => {
+                let yy: i32 = digits[..2].parse().ok()?;
+                (if yy >= 69 { 1900 + yy } else { 2000 + yy }, &digits[2..])
+            }
+            12 => (digits[..4].parse().ok()?, &digits[4..]),
+            _ => return None,
+        };
+        let field = |i: usize| rest[i..i + 2].parse::<u32>().ok();
+        let date = NaiveDate::from_ymd_opt(year, field(0)?, field(2)?)?;
+        let time = date.and_hms_opt(field(4)?, field(6)?, sec)?;
+        Some(Self::china_tz().from_local_datetime(&time).single()?.to_utc())
+    }
+
+    /// 解析 `-d` 的参数：`YYYY-MM-DD`、`YYYY-MM-DDThh:mm[:ss]` 或者 `@秒数`
+    fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+        if let Some(secs) = date.strip_prefix('@') {
+            return DateTime::from_timestamp(secs.parse().ok()?, 0);
+        }
+        let time = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+            .or_else(|| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+        Some(Self::china_tz().from_local_datetime(&time).single()?.to_utc())
+    }
+}
+
 impl super::Cmd for Touch {
     fn description(&self) -> String {
-        "Create a new file".to_string()
+        "Create a new file or change file timestamps".to_string()
     }
 
     fn run(&self, crate::shell::Shell { fs, .. }: &mut crate::shell::Shell, argv: &[&str]) {
-        for arg in argv {
-            if let Err(e) = fs.create(arg) {
-                if let std::io::ErrorKind::AlreadyExists = e.kind() {
-                } else {
-                    println!("{}: {}", arg, e.to_string())
+        // 只修改访问时间、只修改修改时间、文件不存在时不创建
+        let (mut access_only, mut modify_only, mut no_create) = (false, false, false);
+        // 要设置的时间，默认为当前时间
+        let mut time = None;
+        let mut files = Vec::new();
+
+        let mut args = argv.iter();
+        while let Some(&arg) = args.next() {
+            match arg {
+                "-a" => access_only = true,
+                "-m" => modify_only = true,
+                "-c" => no_create = true,
+                "-t" | "-d" => {
+                    let Some(&value) = args.next() else {
+                        println!("touch: option requires an argument -- '{}'", &arg[1..]);
+                        return;
+                    };
+                    let parsed = match arg {
+                        "-t" => Self::parse_stamp(value),
+                        _ => Self::parse_date(value),
+                    };
+                    match parsed {
+                        Some(parsed) => time = Some(parsed),
+                        None => {
+                            println!("touch: invalid date format '{}'", value);
+                            return;
+                        }
+                    }
+                }
+                "--help" => {
+                    println!("{}", self.help());
+                    return;
+                }
+                _ => files.push(arg),
+            }
+        }
+
+        for file in files {
+            if fs.stat(file).is_err() {
+                if no_create {
+                    continue;
                 }
+                if let Err(e) = fs.create(file) {
+                    println!("{}: {}", file, e);
+                    continue;
+                }
+                // 新建的文件的时间已经是当前时间
+                if time.is_none() {
+                    continue;
+                }
+            }
+
+            // -a 和 -m 都没有指定时两个时间都修改
+            let res = fs.stat(file).and_then(|meta| {
+                let time = time.unwrap_or_else(Utc::now);
+                let atime = if modify_only && !access_only { meta.atime } else { time };
+                let mtime = if access_only && !modify_only { meta.mtime } else { time };
+                fs.set_times(file, atime, mtime)
+            });
+            if let Err(e) = res {
+                println!("{}: {}", file, e)
             }
         }
     }
+
+    fn help(&self) -> String {
+        self.description()
+            + "\n touch [-a] [-m] [-c] [-t STAMP | -d DATE] [files...]"
+            + "\n -a change only the access time."
+            + "\n -m change only the modification time."
+            + "\n -c do not create any files."
+            + "\n -t use [[CC]YY]MMDDhhmm[.ss] (UTC+8) instead of current time."
+            + "\n -d use YYYY-MM-DD, YYYY-MM-DDThh:mm[:ss] (UTC+8) or @SECONDS instead of current time."
+    }
 }
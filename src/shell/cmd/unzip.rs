@@ -1,4 +1,4 @@
-use crate::{shell::Shell, fs::Result};
+use crate::{shell::Shell, fs::{OpenOptions, Result}};
 use super::Cmd;
 
 pub struct Unzip;
@@ -405,7 +405,7 @@ impl Unzip {
             }
             
             // 写入文件内容
-            let fd = match shell.fs.open(&full_path) {
+            let fd = match shell.fs.open_with(&full_path, OpenOptions::new().write(true)) {
                 Ok(fd) => fd,
                 Err(e) => {
                     println!("Warning: Failed to open file {}: {}", full_path, e);
@@ -606,10 +606,9 @@ impl Cmd for Unzip {
                     argv[1].to_string()
                 } else {
                     // 移除.zip扩展名作为输出文件名
-                    if archive_file.ends_with(".zip") {
-                        archive_file[..archive_file.len()-4].to_string()
-                    } else {
-                        format!("{}.out", archive_file)
+                    match archive_file.strip_suffix(".zip") {
+                        Some(stem) => stem.to_string(),
+                        None => format!("{}.out", archive_file),
                     }
                 };
                 
@@ -619,7 +618,7 @@ impl Cmd for Unzip {
                     return;
                 }
                 
-                let fd_dest = match shell.fs.open(&output_file) {
+                let fd_dest = match shell.fs.open_with(&output_file, OpenOptions::new().write(true)) {
                     Ok(fd) => fd,
                     Err(e) => {
                         println!("Error opening output file {}: {}", output_file, e);
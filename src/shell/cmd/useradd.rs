@@ -8,18 +8,29 @@ impl Cmd for Useradd {
     }
 
     fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
+        // -k 指定骨架目录
+        let (skel, argv) = match argv {
+            ["-k", skel, rest @ ..] => (Some(*skel), rest),
+            _ => (None, argv),
+        };
         if argv.len() != 2 {
             println!("Need tow arguments. username and password");
             return;
         }
 
-        match shell.fs.useradd(argv[0], argv[1]) {
+        let res = match skel {
+            Some(skel) => shell.fs.useradd_with_skel(argv[0], argv[1], skel),
+            None => shell.fs.useradd(argv[0], argv[1]),
+        };
+        match res {
             Err(e) => println!("{}", e),
-            Ok(_) => println!("Added user {}, password: {}", argv[0], argv[1]),
+            Ok(_) => println!("Added user {}", argv[0]),
         }
     }
 
     fn help(&self) -> String {
-        self.description() + "\n  useradd [username] [password]"
+        self.description()
+            + "\n  useradd [-k SKEL_DIR] [username] [password]"
+            + "\n  Create /home/<username> owned by the new user, copying SKEL_DIR (default /etc/skel if it exists) into it."
     }
 }
@@ -8,17 +8,28 @@ impl Cmd for UserDel {
     }
 
     fn run(&self, shell: &mut crate::shell::Shell, argv: &[&str]) {
+        // -r 同时删除主目录
+        let (remove_home, argv) = match argv {
+            ["-r", rest @ ..] => (true, rest),
+            _ => (false, argv),
+        };
         if argv.len() != 1 {
             println!("Need one argument as username");
             return;
         }
 
-        if let Err(e) = shell.fs.userdel(argv[0]) {
+        let res = match remove_home {
+            true => shell.fs.userdel_remove_home(argv[0]),
+            false => shell.fs.userdel(argv[0]),
+        };
+        if let Err(e) = res {
             println!("{}", e);
         }
     }
 
     fn help(&self) -> String {
-        self.description() + "\n userdel [username]"
+        self.description()
+            + "\n userdel [-r] [username]"
+            + "\n -r remove the user's home directory too"
     }
 }
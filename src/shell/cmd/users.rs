@@ -1,20 +1,25 @@
 use super::*;
-use crate::utils;
 
 pub struct Users;
 
 impl Cmd for Users {
     fn description(&self) -> String {
-        "Show users name and password".into()
+        "Show users".into()
     }
 
     fn run(&self, shell: &mut Shell, _argv: &[&str]) {
-        println!("{:16} {:16}", "User Name", "Password");
-        for user in shell.fs.fs_desc().users.iter().filter(|u| u.name[0] != 0) {
+        let users = match shell.fs.users() {
+            Ok(users) => users,
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        };
+        println!("{:16} {:>5} {:>5}  {:16} Shell", "User Name", "UID", "GID", "Home");
+        for user in users {
             println!(
-                "{:16} {:16}",
-                utils::str(&user.name),
-                utils::str(&user.password)
+                "{:16} {:>5} {:>5}  {:16} {}",
+                user.name, user.uid, user.gid, user.home, user.shell
             );
         }
     }
@@ -1,5 +1,3 @@
-use crate::utils::str;
-
 use super::*;
 
 pub struct Whoami;
@@ -10,8 +8,10 @@ impl Cmd for Whoami {
     }
 
     fn run(&self, shell: &mut Shell, _argv: &[&str]) {
-        let fs_desc = shell.fs.fs_desc();
-        let name = fs_desc.users[shell.fs.current_user()].name;
-        println!("{}", str(&name))
+        let uid = shell.fs.current_user();
+        match shell.fs.find_user_by_uid(uid) {
+            Ok(user) => println!("{}", user.name),
+            Err(e) => println!("whoami: cannot find name for user ID {}: {}", uid, e),
+        }
     }
 }
@@ -5,21 +5,13 @@ pub struct Write; // 定义Write结构体，用于实现向文件写入内容的
 impl Write {
     // 主逻辑函数，用于向指定路径的文件写入内容
     fn main(fs: &mut Fs, path: &str) -> Result<()> {
-        // 打开指定路径的文件
-        let fd = fs.open(path)?;
-
-        // 将文件指针移动到文件开头
-        let res = fs.cut(fd, 0);
-        if res.is_err() {
-            // 如果移动失败，关闭文件并返回错误
-            fs.close(fd)?;
-            return res;
-        }
+        // 以写模式打开指定路径的文件，并清空原有内容
+        let fd = fs.open_with(path, OpenOptions::new().write(true).truncate(true))?;
 
         // 提示用户输入文件内容
         println!("Input content of file now, Press Ctrl+D will save the file.");
         println!("-----------------------------------------------------------");
-        println!("");
+        println!();
 
         // 循环读取用户输入
         loop {
@@ -36,7 +28,7 @@ impl Write {
         fs.close(fd)?;
 
         // 提示用户文件已保存
-        println!("");
+        println!();
         println!("-----------------------------------------------------------");
         println!("{} Saved.", path);
         Ok(())
@@ -1,4 +1,4 @@
-use crate::{shell::Shell, fs::Result, fs::{FileType, DirEntryIterItem, Item}};
+use crate::{shell::Shell, fs::Result, fs::{FileType, DirEntryIterItem, Item, OpenOptions}};
 use super::Cmd;
 
 pub struct Zip;
@@ -104,7 +104,7 @@ impl Zip {
             // 收集目录中的所有条目信息
             let mut entries = Vec::new();
             for item in path_info.dir_entry.iter(&shell.fs)? {
-                if let DirEntryIterItem::Using(Item { entry, .. }) = item {
+                if let DirEntryIterItem::Using(Item { entry, .. }) = item? {
                     let entry_name = crate::fs::utils::str(&entry.name);
                     
                     // 跳过 "." 和 ".." 目录
@@ -349,7 +349,7 @@ impl Cmd for Zip {
             return;
         }
 
-        let fd_dest = match shell.fs.open(compressed_file) {
+        let fd_dest = match shell.fs.open_with(compressed_file, OpenOptions::new().write(true)) {
             Ok(fd) => fd,
             Err(e) => {
                 println!("Error opening compressed file {}: {}", compressed_file, e);
@@ -1,5 +1,6 @@
 pub mod cmd;
 
+use std::collections::BTreeMap;
 use std::io::Write;
 use rustyline::error::ReadlineError;
 use rustyline::{Editor, Config};
@@ -10,7 +11,7 @@ use rustyline::validate::Validator;
 use rustyline::Helper;
 
 use self::cmd::Cmds;
-use super::fs::Fs;
+use super::fs::{FormatOptions, Fs, MountError, MountOptions, DISK_PATH};
 use super::fs::{DirEntryIterItem, Item};
 use super::fs::utils;
 use crossterm::style::Stylize;
@@ -33,7 +34,7 @@ impl SimpleCompleter {
         // 使用当前目录解析当前路径
         if let Ok(parsed_path) = fs.path_parse("") {
             if let Ok(dir_entries) = parsed_path.dir_entry.iter(fs) {
-                for item in dir_entries {
+                for item in dir_entries.flatten() {
                     if let DirEntryIterItem::Using(Item { entry, .. }) = item {
                         let filename = utils::str(&entry.name).to_string();
                         // 跳过 "." 和 ".." 条目
@@ -141,12 +142,37 @@ pub struct Shell {
     pub fs: Fs,
     pub cmds: Cmds,
     pub history: Vec<String>,
+    /// 会话变量，登录之后有 `HOME`、`USER` 和 `PWD`，命令参数中的 `$NAME` 会被替换成变量的值，见 [`Shell::parse_args`]
+    pub env: BTreeMap<String, String>,
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Shell {
     pub fn new() -> Self {
         let fs = match Fs::init() {
             Ok(fs) => fs,
+            Err(err) if MountError::of(&err) == Some(MountError::NotCleanlyUnmounted) => {
+                println!("Warn: {}", err.to_string().yellow());
+                println!("Mount anyway? [y/n]");
+
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input).unwrap();
+                match input.trim() {
+                    "Y" | "y" => {
+                        let options = MountOptions {
+                            force: true,
+                            ..Default::default()
+                        };
+                        Fs::open_image_with(DISK_PATH, &options).unwrap()
+                    }
+                    _ => std::process::exit(1),
+                }
+            }
             Err(err) => {
                 println!("Err: {}", err.to_string().red());
                 println!("Format disk? [y/n]");
@@ -154,7 +180,16 @@ impl Shell {
                 let mut input = String::new();
                 std::io::stdin().read_line(&mut input).unwrap();
                 match input.trim() {
-                    "Y" | "y" => Fs::format().unwrap(),
+                    "Y" | "y" => {
+                        let Some(root_password) = cmd::format::Format::read_root_password() else {
+                            std::process::exit(1)
+                        };
+                        let options = FormatOptions {
+                            root_password,
+                            ..Default::default()
+                        };
+                        Fs::format(&options).unwrap()
+                    }
                     _ => std::process::exit(1),
                 }
             }
@@ -164,6 +199,7 @@ impl Shell {
             fs,
             cmds: cmd::cmds(),
             history: Vec::new(),
+            env: BTreeMap::new(),
         }
     }
 
@@ -193,14 +229,135 @@ impl Shell {
 
     pub fn init_cmds(&mut self) {}
 
+    /// 读取会话变量
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.env.get(name).map(String::as_str)
+    }
+
+    /// 设置会话变量
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        self.env.insert(name.into(), value.into());
+    }
+
+    /// 登录成功之后根据当前用户设置 `HOME`、`USER` 和 `PWD`
+    pub fn start_session(&mut self) {
+        let uid = self.fs.current_user();
+        let (user, home) = match self.fs.find_user_by_uid(uid) {
+            Ok(user) => (user.name, user.home),
+            Err(_) => (uid.to_string(), "/".into()),
+        };
+        self.set_var("USER", &user);
+        self.set_var("HOME", &home);
+        self.update_pwd();
+    }
+
+    /// 切换当前目录，同时更新 `PWD`
+    pub fn chdir(&mut self, path: &str) -> std::io::Result<()> {
+        self.fs.chdir(path)?;
+        self.update_pwd();
+        Ok(())
+    }
+
+    /// 让 `PWD` 和文件系统的当前目录保持一致，移动或者删除目录之后当前目录的路径可能会变
+    fn update_pwd(&mut self) {
+        let pwd = self.fs.pwd();
+        self.set_var("PWD", &pwd);
+    }
+
+    /// 展开参数中的变量：开头的 `~` 替换成 `HOME`，`$NAME` 和 `${NAME}` 替换成变量的值，不存在的变量替换成空串
+    pub fn expand(&self, arg: &str) -> String {
+        let home = self.var("HOME").unwrap_or("/");
+        match arg {
+            "~" => home.to_string(),
+            _ => match arg.strip_prefix("~/") {
+                Some(rest) => self.expand_vars(&format!("{}/{}", home.trim_end_matches('/'), rest)),
+                None => self.expand_vars(arg),
+            },
+        }
+    }
+
+    /// 把 `$NAME` 和 `${NAME}` 替换成变量的值，不存在的变量替换成空串
+    fn expand_vars(&self, arg: &str) -> String {
+        let mut res = String::new();
+        let mut rest = arg;
+        while let Some(i) = rest.find('$') {
+            res.push_str(&rest[..i]);
+            rest = &rest[i + 1..];
+            let (name, len) = match rest.strip_prefix('{').and_then(|r| r.split_once('}')) {
+                Some((name, _)) => (name, name.len() + 2),
+                None => {
+                    let len = rest
+                        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                        .unwrap_or(rest.len());
+                    (&rest[..len], len)
+                }
+            };
+            match name.is_empty() {
+                // 单独的 `$` 原样保留
+                true => res.push('$'),
+                false => res.push_str(self.var(name).unwrap_or_default()),
+            }
+            rest = &rest[len..];
+        }
+        res.push_str(rest);
+        res
+    }
+
+    /// 把一行输入拆分成参数，并展开其中的变量
+    ///
+    /// 参数之间用空白分隔。单引号中的内容原样保留，不展开变量，也可以包含空白；
+    /// `\$`、`\~`、`\'` 和 `\\` 表示这个字符本身。其余部分用 [`Shell::expand`] 展开
+    pub fn parse_args(&self, input: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        // 当前参数中已经处理好的部分，以及还没有展开的部分
+        let mut arg: Option<String> = None;
+        let mut plain = String::new();
+
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' => {
+                    self.push_expanded(&mut arg, &mut plain);
+                    let arg = arg.get_or_insert_with(String::new);
+                    arg.extend(chars.by_ref().take_while(|&c| c != '\''));
+                }
+                '\\' if matches!(chars.peek(), Some('$' | '~' | '\'' | '\\')) => {
+                    self.push_expanded(&mut arg, &mut plain);
+                    arg.get_or_insert_with(String::new).extend(chars.next());
+                }
+                c if c.is_whitespace() => {
+                    self.push_expanded(&mut arg, &mut plain);
+                    args.extend(arg.take());
+                }
+                c => plain.push(c),
+            }
+        }
+        self.push_expanded(&mut arg, &mut plain);
+        args.extend(arg);
+        args
+    }
+
+    /// 展开 `plain` 并追加到 `arg` 后面，只有在参数开头时才展开 `~`
+    fn push_expanded(&self, arg: &mut Option<String>, plain: &mut String) {
+        if plain.is_empty() {
+            return;
+        }
+        let expanded = match arg {
+            None => self.expand(plain),
+            Some(_) => self.expand_vars(plain),
+        };
+        arg.get_or_insert_with(String::new).push_str(&expanded);
+        plain.clear();
+    }
+
     pub fn run(&mut self) {
         println!(
             "\n {} ",
             " Welcome to simple unix like filesystem. ".on_white().black()
         );
-        println!("");
+        println!();
 
-        if !cmd::login::Login::login(&mut self.fs) {
+        if !cmd::login::Login::login(self) {
             return;
         }
 
@@ -234,7 +391,7 @@ impl Shell {
         let cmds = self.cmds.clone();
 
         println!("提示：使用上下方向键浏览历史命令，Tab键自动补全命令，Ctrl+C退出");
-        println!("");
+        println!();
 
         loop {
             let prompt = format!("[{}] ", self.fs.pwd().green());
@@ -257,11 +414,8 @@ impl Shell {
                     self.add_to_history(input.to_string());
 
                     // 解析命令
-                    let mut argv: Vec<&str> = input
-                        .split(' ')
-                        .map(|s| s.trim())
-                        .filter(|s| !s.is_empty())
-                        .collect();
+                    let args = self.parse_args(input);
+                    let mut argv: Vec<&str> = args.iter().map(String::as_str).collect();
 
                     if argv.is_empty() {
                         continue;
@@ -283,7 +437,10 @@ impl Shell {
                             match cmds.get(cmd_name) {
                                 Some(cmd) => match argv.contains(&"-h") {
                                     true => println!("{}", cmd.help()),
-                                    false => cmd.run(self, &argv),
+                                    false => {
+                                        cmd.run(self, &argv);
+                                        self.update_pwd();
+                                    }
                                 },
                                 None => println!(
                                     "{}",
@@ -318,6 +475,9 @@ impl Shell {
             println!("History saved to {}", history_file);
         }
 
+        if let Err(e) = self.fs.exit() {
+            eprintln!("Failed to unmount: {}", e);
+        }
         println!("Bye.");
     }
 
@@ -341,13 +501,10 @@ impl Shell {
             // 将命令添加到历史记录中
             self.add_to_history(input.to_string());
             
-            let mut argv: Vec<&str> = input
-                .split(' ')
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty())
-                .collect();
+            let args = self.parse_args(input);
+            let mut argv: Vec<&str> = args.iter().map(String::as_str).collect();
 
-            if argv.len() < 1 {
+            if argv.is_empty() {
                 continue;
             }
 
@@ -355,13 +512,19 @@ impl Shell {
 
             // 处理退出命令
             if cmd_name == "exit" {
+                if let Err(e) = self.fs.exit() {
+                    eprintln!("Failed to unmount: {}", e);
+                }
                 break;
             }
 
             match cmds.get(cmd_name) {
                 Some(cmd) => match argv.contains(&"-h") {
                     true => println!("{}", cmd.help()),
-                    false => cmd.run(self, &argv),
+                    false => {
+                        cmd.run(self, &argv);
+                        self.update_pwd();
+                    }
                 },
                 None => println!(
                     "{}",
@@ -372,3 +535,38 @@ impl Shell {
     }
 }
 
+
+#[test]
+fn test_parse_args() {
+    let options = FormatOptions::with_root_password("root");
+    let fs = Fs::format_on(super::fs::MemDevice::new(), &options).unwrap();
+    let mut shell = Shell {
+        fs,
+        cmds: cmd::cmds(),
+        history: Vec::new(),
+        env: BTreeMap::new(),
+    };
+    shell.start_session();
+
+    // 没有引号的部分照常展开变量和开头的 `~`
+    assert_eq!(shell.parse_args("cd  ~/docs ${USER}$NOTHING"), ["cd", "/root/docs", "root"]);
+    assert_eq!(shell.parse_args("ls x~ $ a'~'"), ["ls", "x~", "$", "a~"]);
+
+    // 单引号和反斜杠转义中的 `$` 原样保留，单引号中还可以有空格
+    assert_eq!(
+        shell.parse_args(r"grep \$HOME '$USER and ~' \~ a\\b\n"),
+        ["grep", "$HOME", "$USER and ~", "~", r"a\b\n"]
+    );
+
+    // 写入包含 `$` 的文件名和密码
+    let mut run = |line: &str| {
+        let args = shell.parse_args(line);
+        let argv: Vec<&str> = args.iter().map(String::as_str).collect();
+        let cmds = shell.cmds.clone();
+        cmds[argv[0]].run(&mut shell, &argv[1..]);
+    };
+    run("touch '/tmp$HOME'");
+    run(r"useradd bob pa\$\$word");
+    assert!(shell.fs.stat("/tmp$HOME").is_ok());
+    shell.fs.login("bob", "pa$$word").unwrap();
+}
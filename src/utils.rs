@@ -8,6 +8,6 @@ pub fn pretty_byte(size: u32) -> String {
     match size {
         a if a < 1024 => format!("{:.2} B", a),
         a if a < 1024 * 1024 => format!("{:.2} KB", a as f32 / 1024.),
-        a @ _ => format!("{:.2} MB", a as f32 / 1024. / 1024.),
+        a => format!("{:.2} MB", a as f32 / 1024. / 1024.),
     }
 }